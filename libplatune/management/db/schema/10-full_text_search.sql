@@ -20,6 +20,16 @@ VALUES(
         REPLACE(new.song_title, ' & ', ' and '),
         'song'
     );
+INSERT INTO search_index (
+        assoc_id,
+        entry_value,
+        entry_type
+    )
+SELECT new.song_id,
+    REPLACE(new.composer, ' & ', ' and '),
+    'composer'
+WHERE new.composer IS NOT NULL
+    AND new.composer != '';
 END;
 CREATE TRIGGER IF NOT EXISTS after_song_update
 UPDATE OF song_title ON song BEGIN
@@ -28,11 +38,27 @@ SET entry_value = REPLACE(new.song_title, ' & ', ' and ')
 WHERE assoc_id = old.song_id
     AND entry_type = 'song';
 END;
+CREATE TRIGGER IF NOT EXISTS after_song_composer_update
+UPDATE OF composer ON song BEGIN
+DELETE FROM search_index
+WHERE assoc_id = old.song_id
+    AND entry_type = 'composer';
+INSERT INTO search_index (
+        assoc_id,
+        entry_value,
+        entry_type
+    )
+SELECT new.song_id,
+    REPLACE(new.composer, ' & ', ' and '),
+    'composer'
+WHERE new.composer IS NOT NULL
+    AND new.composer != '';
+END;
 CREATE TRIGGER IF NOT EXISTS after_song_delete
 AFTER DELETE ON song BEGIN
 DELETE FROM search_index
 WHERE assoc_id = old.song_id
-    AND entry_type = 'song';
+    AND entry_type IN ('song', 'composer');
 END;
 -- Album
 CREATE TRIGGER IF NOT EXISTS after_album_insert
@@ -90,3 +116,32 @@ DELETE FROM search_index
 WHERE assoc_id = old.artist_id
     AND entry_type = 'artist';
 END;
+
+-- Genre
+CREATE TRIGGER IF NOT EXISTS after_genre_insert
+AFTER
+INSERT ON genre BEGIN
+INSERT INTO search_index (
+        assoc_id,
+        entry_value,
+        entry_type
+    )
+VALUES(
+        new.genre_id,
+        REPLACE(new.genre_name, ' & ', ' and '),
+        'genre'
+    );
+END;
+CREATE TRIGGER IF NOT EXISTS after_genre_update
+UPDATE OF genre_name ON genre BEGIN
+UPDATE search_index
+SET entry_value = REPLACE(new.genre_name, ' & ', ' and ')
+WHERE assoc_id = old.genre_id
+    AND entry_type = 'genre';
+END;
+CREATE TRIGGER IF NOT EXISTS after_genre_delete
+AFTER DELETE ON genre BEGIN
+DELETE FROM search_index
+WHERE assoc_id = old.genre_id
+    AND entry_type = 'genre';
+END;
\ No newline at end of file
@@ -2,6 +2,9 @@ CREATE TABLE IF NOT EXISTS album (
     album_id INTEGER PRIMARY KEY NOT NULL,
     album_name TEXT NOT NULL COLLATE NOCASE,
     artist_id INTEGER NOT NULL,
+    album_year INTEGER NULL,
+    musicbrainz_release_id TEXT NULL COLLATE NOCASE,
+    is_compilation BOOLEAN NOT NULL DEFAULT 0,
     created_date INTEGER NOT NULL,
-    UNIQUE(album_name, artist_id) FOREIGN KEY(artist_id) REFERENCES artist(artist_id)
+    FOREIGN KEY(artist_id) REFERENCES artist(artist_id)
 )
\ No newline at end of file
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS data_migration (
+    migration_name TEXT PRIMARY KEY NOT NULL,
+    applied_date INTEGER NOT NULL
+)
\ No newline at end of file
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS file_extension (
+    file_extension_id INTEGER PRIMARY KEY NOT NULL,
+    extension TEXT NOT NULL COLLATE NOCASE,
+    UNIQUE (extension COLLATE NOCASE)
+)
\ No newline at end of file
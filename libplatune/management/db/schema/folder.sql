@@ -1,5 +1,6 @@
 CREATE TABLE IF NOT EXISTS folder (
     folder_id INTEGER PRIMARY KEY NOT NULL,
     folder_path TEXT NOT NULL COLLATE NOCASE,
+    mount_name TEXT NULL,
     UNIQUE (folder_path COLLATE NOCASE)
 )
\ No newline at end of file
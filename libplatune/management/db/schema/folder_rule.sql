@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS folder_rule (
+    folder_rule_id INTEGER PRIMARY KEY NOT NULL,
+    folder_id INTEGER NOT NULL,
+    pattern TEXT NOT NULL,
+    is_exclude BOOLEAN NOT NULL,
+    FOREIGN KEY(folder_id) REFERENCES folder(folder_id)
+)
\ No newline at end of file
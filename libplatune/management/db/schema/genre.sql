@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS genre (
+    genre_id INTEGER PRIMARY KEY NOT NULL,
+    genre_name TEXT NOT NULL COLLATE NOCASE,
+    created_date INTEGER NOT NULL,
+    UNIQUE (genre_name COLLATE NOCASE)
+)
\ No newline at end of file
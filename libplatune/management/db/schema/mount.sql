@@ -1,6 +1,7 @@
 CREATE TABLE IF NOT EXISTS mount (
     mount_id INTEGER PRIMARY KEY NOT NULL,
     mount_uuid TEXT NOT NULL,
+    mount_name TEXT NOT NULL DEFAULT 'default',
     mount_path TEXT NOT NULL COLLATE NOCASE,
-    UNIQUE (mount_path COLLATE NOCASE)
+    UNIQUE (mount_name, mount_path COLLATE NOCASE)
 )
\ No newline at end of file
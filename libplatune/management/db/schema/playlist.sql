@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS playlist (
+    playlist_id INTEGER PRIMARY KEY NOT NULL,
+    playlist_name TEXT NOT NULL COLLATE NOCASE,
+    created_date INTEGER NOT NULL,
+    modified_date INTEGER NOT NULL,
+    UNIQUE (playlist_name COLLATE NOCASE)
+)
\ No newline at end of file
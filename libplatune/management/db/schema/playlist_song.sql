@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS playlist_song (
+    playlist_song_id INTEGER PRIMARY KEY NOT NULL,
+    playlist_id INTEGER NOT NULL,
+    song_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    FOREIGN KEY(playlist_id) REFERENCES playlist(playlist_id),
+    FOREIGN KEY(song_id) REFERENCES song(song_id)
+)
\ No newline at end of file
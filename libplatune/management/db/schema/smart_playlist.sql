@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS smart_playlist (
+    smart_playlist_id INTEGER PRIMARY KEY NOT NULL,
+    smart_playlist_name TEXT NOT NULL COLLATE NOCASE,
+    match_all BOOLEAN NOT NULL DEFAULT 1,
+    sort TEXT NOT NULL DEFAULT 'random',
+    descending BOOLEAN NOT NULL DEFAULT 0,
+    song_limit INTEGER NULL,
+    created_date INTEGER NOT NULL,
+    modified_date INTEGER NOT NULL,
+    evaluated_date INTEGER NULL,
+    UNIQUE (smart_playlist_name COLLATE NOCASE)
+)
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS smart_playlist_rule (
+    smart_playlist_rule_id INTEGER PRIMARY KEY NOT NULL,
+    smart_playlist_id INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    operator TEXT NOT NULL,
+    value TEXT NOT NULL,
+    FOREIGN KEY(smart_playlist_id) REFERENCES smart_playlist(smart_playlist_id)
+)
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS smart_playlist_song (
+    smart_playlist_song_id INTEGER PRIMARY KEY NOT NULL,
+    smart_playlist_id INTEGER NOT NULL,
+    song_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    FOREIGN KEY(smart_playlist_id) REFERENCES smart_playlist(smart_playlist_id),
+    FOREIGN KEY(song_id) REFERENCES song(song_id)
+)
\ No newline at end of file
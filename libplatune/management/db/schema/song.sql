@@ -11,6 +11,15 @@ CREATE TABLE IF NOT EXISTS song (
     song_year INTEGER NOT NULL,
     song_month INTEGER NOT NULL,
     song_day INTEGER NOT NULL,
+    release_date TEXT NULL,
+    original_year INTEGER NULL,
+    composer TEXT NULL COLLATE NOCASE,
+    comment TEXT NULL,
+    bpm INTEGER NULL,
+    musicbrainz_recording_id TEXT NULL,
+    musicbrainz_release_id TEXT NULL,
+    musicbrainz_artist_id TEXT NULL,
+    musicbrainz_release_group_id TEXT NULL,
     is_deleted BOOLEAN NOT NULL DEFAULT 0,
     duration INTEGER NOT NULL,
     sample_rate INTEGER NOT NULL,
@@ -18,7 +27,18 @@ CREATE TABLE IF NOT EXISTS song (
     file_size INTEGER NOT NULL,
     album_art_path TEXT NULL COLLATE NOCASE,
     fingerprint TEXT NOT NULL,
+    acoustic_fingerprint BLOB NULL,
+    track_gain REAL NULL,
+    track_peak REAL NULL,
+    album_gain REAL NULL,
+    album_peak REAL NULL,
+    loudness_analyzed BOOLEAN NOT NULL DEFAULT 0,
+    start_offset INTEGER NULL,
+    end_offset INTEGER NULL,
+    lyrics TEXT NULL,
+    synced_lyrics TEXT NULL,
     created_date INTEGER NOT NULL,
+    file_modified_date INTEGER NULL,
     modified_date INTEGER NOT NULL,
     FOREIGN KEY(artist_id) REFERENCES artist(artist_id),
     FOREIGN KEY(album_id) REFERENCES album(album_id),
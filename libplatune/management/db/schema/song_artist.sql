@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS song_artist (
+    song_artist_id INTEGER PRIMARY KEY NOT NULL,
+    song_id INTEGER NOT NULL,
+    artist_id INTEGER NOT NULL,
+    artist_role TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    FOREIGN KEY(song_id) REFERENCES song(song_id),
+    FOREIGN KEY(artist_id) REFERENCES artist(artist_id),
+    UNIQUE (song_id, artist_id, artist_role)
+)
\ No newline at end of file
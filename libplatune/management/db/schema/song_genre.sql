@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS song_genre (
+    song_genre_id INTEGER PRIMARY KEY NOT NULL,
+    song_id INTEGER NOT NULL,
+    genre_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    FOREIGN KEY(song_id) REFERENCES song(song_id),
+    FOREIGN KEY(genre_id) REFERENCES genre(genre_id),
+    UNIQUE (song_id, genre_id)
+)
\ No newline at end of file
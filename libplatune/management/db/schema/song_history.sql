@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS song_history (
+    song_history_id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    song_id INTEGER NOT NULL,
+    played_date INTEGER NOT NULL,
+    listened_millis INTEGER NOT NULL,
+    skipped BOOLEAN NOT NULL,
+    FOREIGN KEY(song_id) REFERENCES song(song_id)
+)
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS sync_report (
+    sync_report_id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    started_date INTEGER NOT NULL,
+    finished_date INTEGER NOT NULL,
+    full_rescan BOOLEAN NOT NULL,
+    cancelled BOOLEAN NOT NULL,
+    added_count INTEGER NOT NULL,
+    updated_count INTEGER NOT NULL,
+    deleted_count INTEGER NOT NULL,
+    failed_count INTEGER NOT NULL
+)
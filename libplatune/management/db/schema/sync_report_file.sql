@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS sync_report_file (
+    sync_report_file_id INTEGER PRIMARY KEY NOT NULL,
+    sync_report_id INTEGER NOT NULL,
+    file_path TEXT NOT NULL,
+    status TEXT NOT NULL,
+    error TEXT NULL,
+    FOREIGN KEY(sync_report_id) REFERENCES sync_report(sync_report_id)
+)
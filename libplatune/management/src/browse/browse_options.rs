@@ -0,0 +1,39 @@
+use strum::EnumString;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum BrowseSort {
+    #[default]
+    Name,
+    Year,
+    DateAdded,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BrowseFilter {
+    pub genre_ids: Vec<i64>,
+    pub year: Option<i64>,
+    /// First year of the decade, ex: 1990 for the 90s
+    pub decade: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BrowseOptions {
+    pub sort: BrowseSort,
+    pub descending: bool,
+    pub filter: BrowseFilter,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Default for BrowseOptions {
+    fn default() -> Self {
+        Self {
+            sort: BrowseSort::Name,
+            descending: false,
+            filter: BrowseFilter::default(),
+            offset: 0,
+            limit: 100,
+        }
+    }
+}
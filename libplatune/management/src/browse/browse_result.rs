@@ -0,0 +1,44 @@
+#[derive(Debug)]
+pub struct BrowsePage<T> {
+    pub entries: Vec<T>,
+    /// Number of entries matching the filter, ignoring the offset and limit
+    pub total: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ArtistSummary {
+    pub artist_id: i64,
+    pub artist: String,
+    pub album_count: i64,
+    pub song_count: i64,
+    /// Earliest release year of the artist's songs
+    pub year: Option<i64>,
+    pub created_date: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct AlbumSummary {
+    pub album_id: i64,
+    pub album: String,
+    pub album_artist_id: i64,
+    pub album_artist: String,
+    pub song_count: i64,
+    pub year: Option<i64>,
+    pub created_date: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct GenreSummary {
+    pub genre_id: i64,
+    pub genre: String,
+    pub album_count: i64,
+    pub song_count: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct YearSummary {
+    /// The release year, or the first year of the decade when grouping by decade
+    pub year: i64,
+    pub album_count: i64,
+    pub song_count: i64,
+}
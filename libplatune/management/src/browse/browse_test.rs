@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use itertools::Itertools;
+use pretty_assertions::assert_eq;
+use rstest::*;
+use tempfile::TempDir;
+
+use super::browse_options::{BrowseFilter, BrowseOptions, BrowseSort};
+use crate::entry_type::EntryType;
+use crate::manager::Manager;
+use crate::test_util::{TestSong, setup_library};
+
+const SONGS: [TestSong; 5] = [
+    TestSong {
+        title: "song1",
+        artist: "artist1",
+        album_artist: "artist1",
+        album: "b_album",
+        genre: "Rock",
+        year: 1994,
+    },
+    TestSong {
+        title: "song2",
+        artist: "artist2",
+        album_artist: "artist1",
+        album: "b_album",
+        genre: "Rock",
+        year: 1994,
+    },
+    TestSong {
+        title: "song3",
+        artist: "artist2",
+        album_artist: "artist2",
+        album: "a_album",
+        genre: "Jazz;Rock",
+        year: 2003,
+    },
+    TestSong {
+        title: "song4",
+        artist: "artist3",
+        album_artist: "artist3",
+        album: "c_album",
+        genre: "Rock",
+        year: 1989,
+    },
+    TestSong {
+        title: "song5",
+        artist: "artist3",
+        album_artist: "artist3",
+        album: "c_album",
+        genre: "",
+        year: 0,
+    },
+];
+
+#[rstest(
+    sort,
+    descending,
+    expected,
+    case(BrowseSort::Name, false, vec!["a_album", "b_album", "c_album"]),
+    case(BrowseSort::Name, true, vec!["c_album", "b_album", "a_album"]),
+    case(BrowseSort::Year, false, vec!["c_album", "b_album", "a_album"]),
+    case(BrowseSort::Year, true, vec!["a_album", "b_album", "c_album"])
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_browse_albums_sort(sort: BrowseSort, descending: bool, expected: Vec<&str>) {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let page = manager
+        .browse_albums(&BrowseOptions {
+            sort,
+            descending,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(3, page.total);
+    assert_eq!(
+        expected,
+        page.entries.iter().map(|e| e.album.as_str()).collect_vec()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_browse_paging() {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let page = manager
+        .browse_artists(&BrowseOptions {
+            offset: 1,
+            limit: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(3, page.total);
+    assert_eq!(1, page.entries.len());
+    let artist = &page.entries[0];
+    assert_eq!("artist2", artist.artist);
+    assert_eq!(2, artist.album_count);
+    assert_eq!(2, artist.song_count);
+    assert_eq!(Some(1994), artist.year);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_browse_filter() {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let genres = manager
+        .browse_genres(&BrowseFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![("Jazz", 1, 1), ("Rock", 3, 4)],
+        genres
+            .iter()
+            .map(|g| (g.genre.as_str(), g.album_count, g.song_count))
+            .collect_vec()
+    );
+    let rock = genres.iter().find(|g| g.genre == "Rock").unwrap();
+
+    // Songs with several genres are found through each of them and list all of their genres
+    let rock_songs = manager
+        .lookup(vec![rock.genre_id], EntryType::Genre)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            ("song1", Some("Rock")),
+            ("song2", Some("Rock")),
+            ("song3", Some("Jazz; Rock")),
+            ("song4", Some("Rock"))
+        ],
+        rock_songs
+            .iter()
+            .map(|s| (s.song.as_str(), s.genre.as_deref()))
+            .sorted()
+            .collect_vec()
+    );
+
+    let album_artists = manager
+        .browse_album_artists(&BrowseOptions {
+            filter: BrowseFilter {
+                genre_ids: vec![rock.genre_id],
+                decade: Some(1990),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(1, album_artists.total);
+    assert_eq!("artist1", album_artists.entries[0].artist);
+    assert_eq!(2, album_artists.entries[0].song_count);
+
+    let albums = manager
+        .browse_albums(&BrowseOptions {
+            filter: BrowseFilter {
+                year: Some(2003),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(1, albums.total);
+    assert_eq!("a_album", albums.entries[0].album);
+    assert_eq!("artist2", albums.entries[0].album_artist);
+}
+
+#[rstest(
+    by_decade,
+    expected,
+    case(false, vec![(1989, 1, 1), (1994, 1, 2), (2003, 1, 1)]),
+    case(true, vec![(1980, 1, 1), (1990, 1, 2), (2000, 1, 1)])
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_browse_years(by_decade: bool, expected: Vec<(i64, i64, i64)>) {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let years = manager
+        .browse_years(&BrowseFilter::default(), by_decade)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        expected,
+        years
+            .iter()
+            .map(|y| (y.year, y.album_count, y.song_count))
+            .collect_vec()
+    );
+}
+
+async fn setup(tempdir: &Path) -> Manager {
+    let (manager, _) = setup_library(tempdir, &SONGS).await;
+    manager
+}
@@ -0,0 +1,7 @@
+pub mod browse_options;
+pub mod browse_result;
+pub(crate) mod queries;
+
+#[cfg(test)]
+#[path = "./browse_test.rs"]
+mod browse_test;
@@ -0,0 +1,138 @@
+use super::browse_options::{BrowseFilter, BrowseOptions, BrowseSort};
+
+pub(crate) fn artists_query(filter: &BrowseFilter) -> String {
+    let filter_clause = filter_clause(filter);
+    format!(
+        "
+        SELECT ar.artist_id, ar.artist_name artist, COUNT(DISTINCT s.album_id) album_count,
+        COUNT(s.song_id) song_count, MIN(NULLIF(s.song_year, 0)) year, ar.created_date
+        FROM artist ar
+        INNER JOIN song s ON s.artist_id = ar.artist_id
+        {filter_clause}
+        GROUP BY ar.artist_id
+        "
+    )
+}
+
+pub(crate) fn album_artists_query(filter: &BrowseFilter) -> String {
+    let filter_clause = filter_clause(filter);
+    format!(
+        "
+        SELECT ar.artist_id, ar.artist_name artist, COUNT(DISTINCT al.album_id) album_count,
+        COUNT(s.song_id) song_count, MIN(NULLIF(s.song_year, 0)) year, ar.created_date
+        FROM artist ar
+        INNER JOIN album al ON al.artist_id = ar.artist_id
+        INNER JOIN song s ON s.album_id = al.album_id
+        {filter_clause}
+        GROUP BY ar.artist_id
+        "
+    )
+}
+
+pub(crate) fn albums_query(filter: &BrowseFilter) -> String {
+    let filter_clause = filter_clause(filter);
+    format!(
+        "
+        SELECT al.album_id, al.album_name album, aa.artist_id album_artist_id,
+        aa.artist_name album_artist, COUNT(s.song_id) song_count,
+        MAX(NULLIF(s.song_year, 0)) year, al.created_date
+        FROM album al
+        INNER JOIN artist aa ON aa.artist_id = al.artist_id
+        INNER JOIN song s ON s.album_id = al.album_id
+        {filter_clause}
+        GROUP BY al.album_id
+        "
+    )
+}
+
+pub(crate) fn genres_query(filter: &BrowseFilter) -> String {
+    let filter_clause = filter_clause(filter);
+    format!(
+        "
+        SELECT g.genre_id, g.genre_name genre, COUNT(DISTINCT s.album_id) album_count,
+        COUNT(s.song_id) song_count
+        FROM genre g
+        INNER JOIN song_genre sg ON sg.genre_id = g.genre_id
+        INNER JOIN song s ON s.song_id = sg.song_id
+        {filter_clause}
+        GROUP BY g.genre_id
+        ORDER BY g.genre_name COLLATE NOCASE
+        "
+    )
+}
+
+pub(crate) fn years_query(filter: &BrowseFilter, by_decade: bool) -> String {
+    let year_select = if by_decade {
+        "s.song_year / 10 * 10"
+    } else {
+        "s.song_year"
+    };
+    // Songs without a year are stored as 0
+    let filter_clause = match filter_clause(filter) {
+        clause if clause.is_empty() => "WHERE s.song_year > 0".to_owned(),
+        clause => format!("{clause} AND s.song_year > 0"),
+    };
+    format!(
+        "
+        SELECT {year_select} year, COUNT(DISTINCT s.album_id) album_count,
+        COUNT(s.song_id) song_count
+        FROM song s
+        {filter_clause}
+        GROUP BY {year_select}
+        ORDER BY year
+        "
+    )
+}
+
+/// Wraps one of the base queries to apply sorting and paging.
+/// Expects the filter values to be bound first, followed by the limit and offset.
+pub(crate) fn page_query(base_query: &str, name_column: &str, options: &BrowseOptions) -> String {
+    let direction = if options.descending { "DESC" } else { "ASC" };
+    let name_sort = format!("{name_column} COLLATE NOCASE {direction}");
+    let order_clause = match options.sort {
+        BrowseSort::Name => name_sort,
+        // Entries without a year always go last
+        BrowseSort::Year => format!("year IS NULL, year {direction}, {name_sort}"),
+        BrowseSort::DateAdded => format!("created_date {direction}, {name_sort}"),
+    };
+    format!("SELECT * FROM ({base_query}) ORDER BY {order_clause} LIMIT ? OFFSET ?")
+}
+
+pub(crate) fn count_query(base_query: &str) -> String {
+    format!("SELECT COUNT(*) FROM ({base_query})")
+}
+
+/// Values to bind for the parameters in the filter clause, in order
+pub(crate) fn filter_values(filter: &BrowseFilter) -> Vec<i64> {
+    filter
+        .genre_ids
+        .iter()
+        .copied()
+        .chain(filter.year)
+        .chain(filter.decade.into_iter().flat_map(|d| [d, d + 9]))
+        .collect()
+}
+
+fn filter_clause(filter: &BrowseFilter) -> String {
+    let mut clauses = vec![];
+    if !filter.genre_ids.is_empty() {
+        let in_list = vec!["?"; filter.genre_ids.len()].join(",");
+        // Songs with several genres match if any of them are selected
+        clauses.push(format!(
+            "EXISTS (SELECT 1 FROM song_genre fsg WHERE fsg.song_id = s.song_id \
+             AND fsg.genre_id IN ({in_list}))"
+        ));
+    }
+    if filter.year.is_some() {
+        clauses.push("s.song_year = ?".to_owned());
+    }
+    if filter.decade.is_some() {
+        clauses.push("s.song_year BETWEEN ? AND ?".to_owned());
+    }
+
+    if clauses.is_empty() {
+        "".to_owned()
+    } else {
+        format!("WHERE {}", clauses.join(" AND "))
+    }
+}
@@ -8,6 +8,7 @@ use uuid::Uuid;
 use super::Config;
 use super::config_error::ConfigError;
 
+// Named after the single drive ID that used to be stored so existing configs are still read
 static CONFIG_FILE: &str = "drive_id";
 
 #[derive(Clone)]
@@ -21,6 +22,12 @@ pub fn config_dir() -> Result<PathBuf, ConfigError> {
     Ok(proj_dirs.config_dir().to_path_buf())
 }
 
+pub fn cache_dir() -> Result<PathBuf, ConfigError> {
+    let proj_dirs =
+        directories::ProjectDirs::from("", "", "platune").ok_or(ConfigError::NoHomeDir)?;
+    Ok(proj_dirs.cache_dir().to_path_buf())
+}
+
 impl FileConfig {
     pub fn try_new() -> Result<Box<dyn Config + Send + Sync>, ConfigError> {
         FileConfig::new_from_path(config_dir()?.join(CONFIG_FILE))
@@ -57,21 +64,38 @@ impl FileConfig {
 }
 
 impl Config for FileConfig {
-    fn get_drive_id(&self) -> Option<Uuid> {
-        let mut file = File::open(&self.config_path).ok()?;
+    fn get_mount_ids(&self) -> Vec<Uuid> {
+        let Ok(mut file) = File::open(&self.config_path) else {
+            return Vec::new();
+        };
         let mut contents = String::new();
-        file.read_to_string(&mut contents).ok()?;
+        if file.read_to_string(&mut contents).is_err() {
+            return Vec::new();
+        }
 
-        contents.parse::<Uuid>().ok()
+        // Older versions stored a single ID so each one goes on its own line
+        contents
+            .lines()
+            .filter_map(|line| line.trim().parse::<Uuid>().ok())
+            .collect()
     }
 
-    fn set_drive_id(&self, id: Uuid) -> Result<()> {
+    fn add_mount_id(&self, id: Uuid) -> Result<()> {
+        let mut mount_ids = self.get_mount_ids();
+        if mount_ids.contains(&id) {
+            return Ok(());
+        }
+        mount_ids.push(id);
+
         let mut file =
             File::create(&self.config_path).wrap_err("Error opening file for writing")?;
 
-        write!(file, "{id:?}").wrap_err(format!(
-            "Error writing to config file {:?}",
-            self.config_path
-        ))
+        for mount_id in mount_ids {
+            writeln!(file, "{mount_id:?}").wrap_err(format!(
+                "Error writing to config file {:?}",
+                self.config_path
+            ))?;
+        }
+        Ok(())
     }
 }
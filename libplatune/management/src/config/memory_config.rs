@@ -1,4 +1,3 @@
-use std::cell::RefCell;
 use std::sync::Mutex;
 
 use eyre::Result;
@@ -7,24 +6,27 @@ use uuid::Uuid;
 use super::Config;
 
 pub struct MemoryConfig {
-    drive_id: Mutex<RefCell<Option<Uuid>>>,
+    mount_ids: Mutex<Vec<Uuid>>,
 }
 
 impl MemoryConfig {
     pub fn new_boxed() -> Box<dyn Config + Send + Sync> {
         Box::new(Self {
-            drive_id: Mutex::new(RefCell::new(None)),
+            mount_ids: Mutex::new(Vec::new()),
         })
     }
 }
 
 impl Config for MemoryConfig {
-    fn get_drive_id(&self) -> Option<Uuid> {
-        *self.drive_id.lock().unwrap().borrow()
+    fn get_mount_ids(&self) -> Vec<Uuid> {
+        self.mount_ids.lock().unwrap().clone()
     }
 
-    fn set_drive_id(&self, id: Uuid) -> Result<()> {
-        *self.drive_id.lock().unwrap().borrow_mut() = Some(id);
+    fn add_mount_id(&self, id: Uuid) -> Result<()> {
+        let mut mount_ids = self.mount_ids.lock().unwrap();
+        if !mount_ids.contains(&id) {
+            mount_ids.push(id);
+        }
         Ok(())
     }
 }
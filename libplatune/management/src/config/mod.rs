@@ -8,6 +8,7 @@ pub use memory_config::*;
 use uuid::Uuid;
 
 pub trait Config {
-    fn get_drive_id(&self) -> Option<Uuid>;
-    fn set_drive_id(&self, id: Uuid) -> Result<()>;
+    /// IDs of the mounts registered on this machine
+    fn get_mount_ids(&self) -> Vec<Uuid>;
+    fn add_mount_id(&self, id: Uuid) -> Result<()>;
 }
@@ -3,4 +3,14 @@ pub(crate) const MIN_LEN: usize = 20;
 
 pub(crate) const START_MATCH_TEXT: &str = "{startmatch}";
 pub(crate) const END_MATCH_TEXT: &str = "{endmatch}";
-pub(crate) const ALLOWED_FILE_EXTS: [&str; 7] = ["mp3", "m4a", "ogg", "opus", "wav", "flac", "aac"];
+// Used for syncing until a different set of extensions is configured
+pub(crate) const DEFAULT_FILE_EXTS: [&str; 10] = [
+    "mp3", "m4a", "ogg", "opus", "wav", "flac", "aac", "aiff", "ape", "wv",
+];
+// Formats that can't be decoded, so syncing them would only produce failed files
+pub(crate) const UNSUPPORTED_FILE_EXTS: [&str; 2] = ["wma", "dsf"];
+// A skipped track still counts as a play once it's been listened to for half its duration or for
+// this long, whichever comes first
+pub(crate) const PLAY_THRESHOLD_MILLIS: i64 = 4 * 60 * 1000;
+// Older sync reports are removed once this many have been stored
+pub(crate) const MAX_SYNC_REPORTS: i64 = 20;
@@ -2,26 +2,49 @@ use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use itertools::Itertools;
 use log::LevelFilter;
 use regex::Regex;
 use rust_embed::RustEmbed;
 use slite::{Connection, Migrator};
-use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
-use sqlx::{ConnectOptions, Pool, Sqlite, SqlitePool};
-use tokio::sync::Mutex;
-use tracing::info;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteRow};
+use sqlx::{ConnectOptions, Pool, Sqlite, SqliteExecutor, SqlitePool, Transaction};
+use tokio::sync::{Mutex, watch};
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::browse::browse_options::{BrowseFilter, BrowseOptions};
+use crate::browse::browse_result::{
+    AlbumSummary, ArtistSummary, BrowsePage, GenreSummary, YearSummary,
+};
+use crate::browse::queries;
+use crate::consts::{DEFAULT_FILE_EXTS, PLAY_THRESHOLD_MILLIS};
 use crate::db_error::DbError;
+use crate::duplicates::duplicate_group::{DuplicateGroup, FingerprintRow};
+use crate::duplicates::{matcher, queries as duplicate_queries};
 use crate::entry_type::EntryType;
+use crate::lyrics::lrc::parse_lrc;
+use crate::lyrics::song_lyrics::Lyrics;
+use crate::mount::Mount;
 use crate::path_util::PathMut;
 use crate::search::search_engine::SearchEngine;
+use crate::search::search_error::SearchError;
 use crate::search::search_options::SearchOptions;
 use crate::search::search_result::SearchResult;
+use crate::smart_playlist::queries::{self as smart_playlist_queries, BindValue};
+use crate::smart_playlist::smart_playlist_definition::{
+    SmartPlaylist, SmartPlaylistDefinition, SmartPlaylistRow, SmartPlaylistRuleRow,
+};
+use crate::smart_playlist::smart_playlist_error::SmartPlaylistError;
+use crate::sync::artist_credit::{SongArtist, SongArtistRow};
+use crate::sync::file_filter::{FileFilter, FolderRules};
 use crate::sync::progress_stream::ProgressStream;
 use crate::sync::sync_controller::SyncController;
+use crate::sync::sync_dal::SyncDAL;
+use crate::sync::sync_report::{SyncFileStatus, SyncReport, SyncReportFile, SyncReportFileRow};
+use crate::sync::sync_state::SyncState;
 
 #[derive(RustEmbed)]
 #[folder = "db/schema"]
@@ -33,10 +56,12 @@ pub struct Database {
     read_pool: Pool<Sqlite>,
     search_engine: SearchEngine,
     sync_controller: Arc<Mutex<SyncController>>,
+    art_cache_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct LookupEntry {
+    pub song_id: i64,
     pub artist: String,
     pub album_artist: String,
     pub album: String,
@@ -44,6 +69,23 @@ pub struct LookupEntry {
     pub path: String,
     pub track_number: i64,
     pub duration_millis: i64,
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+    /// File name of the cached album art
+    pub album_art: Option<String>,
+    /// Every genre of the song separated by semicolons, starting with the main genre
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub comment: Option<String>,
+    pub bpm: Option<i64>,
+    pub release_date: Option<String>,
+    pub original_year: Option<i64>,
+    pub musicbrainz_recording_id: Option<String>,
+    pub musicbrainz_release_id: Option<String>,
+    pub musicbrainz_artist_id: Option<String>,
+    pub musicbrainz_release_group_id: Option<String>,
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -52,6 +94,16 @@ pub struct Album {
     pub album_id: i64,
     pub album_artist: String,
     pub album_artist_id: i64,
+    /// Release year, used to tell apart albums with the same name
+    pub year: Option<i64>,
+    pub is_compilation: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Playlist {
+    pub playlist_id: i64,
+    pub playlist_name: String,
+    pub song_count: i64,
 }
 
 #[derive(Debug)]
@@ -66,6 +118,66 @@ pub struct DeletedEntry {
     pub song_path: String,
 }
 
+/// Entries removed from the library along with a set of folders
+#[derive(Debug, Default)]
+pub struct RemovedFolders {
+    /// Folders that were registered. Unknown folders are ignored.
+    pub folders: Vec<String>,
+    /// Songs that were marked as deleted, or removed entirely if the folders were purged
+    pub songs: Vec<DeletedEntry>,
+    /// Albums that no longer had any songs
+    pub albums: Vec<String>,
+    /// Artists that no longer had any songs or albums
+    pub artists: Vec<String>,
+}
+
+/// Albums and artists removed because they were no longer referenced
+#[derive(Debug, Default)]
+pub(crate) struct EmptyEntries {
+    pub(crate) albums: Vec<String>,
+    pub(crate) artists: Vec<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct HistoryEntry {
+    pub song_history_id: i64,
+    pub song_id: i64,
+    pub played_date: i64,
+    pub listened_millis: i64,
+    pub skipped: bool,
+    pub song: String,
+    pub artist: String,
+    pub album: String,
+    pub path: String,
+}
+
+impl PathMut for HistoryEntry {
+    fn get_path(&self) -> String {
+        self.path.to_owned()
+    }
+
+    fn update_path(&mut self, path: String) {
+        self.path = path
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct PlayStat {
+    pub id: i64,
+    pub name: String,
+    pub artist: Option<String>,
+    pub play_count: i64,
+    pub listened_millis: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct SongPlayCount {
+    pub song_id: i64,
+    pub play_count: i64,
+    pub skip_count: i64,
+    pub last_played: Option<i64>,
+}
+
 impl PathMut for DeletedEntry {
     fn get_path(&self) -> String {
         self.song_path.to_owned()
@@ -114,6 +226,7 @@ impl Database {
             sync_controller: Arc::new(Mutex::new(SyncController::new(write_pool.clone()))),
             read_pool,
             write_pool,
+            art_cache_dir: None,
         })
     }
 
@@ -131,9 +244,21 @@ impl Database {
             sync_controller: Arc::new(Mutex::new(SyncController::new(pool.clone()))),
             read_pool: pool.clone(),
             write_pool: pool,
+            art_cache_dir: None,
         })
     }
 
+    /// Album art found during sync will be cached in this directory. Art is skipped if this isn't
+    /// set.
+    pub fn with_art_cache_dir(mut self, art_cache_dir: impl Into<PathBuf>) -> Self {
+        self.art_cache_dir = Some(art_cache_dir.into());
+        self
+    }
+
+    pub fn art_cache_dir(&self) -> Option<&Path> {
+        self.art_cache_dir.as_deref()
+    }
+
     fn get_spellfix_lib() -> String {
         match std::env::var("SPELLFIX_LIB") {
             Ok(res) => res,
@@ -165,42 +290,60 @@ impl Database {
             })
             .collect();
 
-        let mut conn = self
-            .write_pool
-            .acquire()
-            .await
-            .map_err(|e| DbError::MigrateError(e.to_string()))?;
-        let mut handle = conn
-            .lock_handle()
-            .await
-            .map_err(|e| DbError::MigrateError(e.to_string()))?;
-        let conn = unsafe {
-            Connection::from_handle(handle.as_raw_handle().as_ptr())
-                .map_err(|e| DbError::MigrateError(e.to_string()))?
-        };
+        {
+            // The connection has to be released before the data migrations can start a transaction
+            let mut conn = self
+                .write_pool
+                .acquire()
+                .await
+                .map_err(|e| DbError::MigrateError(e.to_string()))?;
+            let mut handle = conn
+                .lock_handle()
+                .await
+                .map_err(|e| DbError::MigrateError(e.to_string()))?;
+            let conn = unsafe {
+                Connection::from_handle(handle.as_raw_handle().as_ptr())
+                    .map_err(|e| DbError::MigrateError(e.to_string()))?
+            };
 
-        let migrator = Migrator::new(
-            &schemas,
-            conn,
-            slite::Config {
-                extensions: vec![PathBuf::from(Self::get_spellfix_lib())],
-                ignore: Some(Regex::new("(search_spellfix_vocab.*)|(search_index_.*)").unwrap()),
-                ..Default::default()
-            },
-            slite::Options {
-                allow_deletions: true,
-                dry_run: false,
-            },
-        )
-        .map_err(|e| DbError::MigrateError(e.to_string()))?;
-        migrator
-            .migrate()
+            let migrator = Migrator::new(
+                &schemas,
+                conn,
+                slite::Config {
+                    extensions: vec![PathBuf::from(Self::get_spellfix_lib())],
+                    ignore: Some(
+                        Regex::new("(search_spellfix_vocab.*)|(search_index_.*)").unwrap(),
+                    ),
+                    ..Default::default()
+                },
+                slite::Options {
+                    allow_deletions: true,
+                    dry_run: false,
+                },
+            )
             .map_err(|e| DbError::MigrateError(e.to_string()))?;
+            migrator
+                .migrate()
+                .map_err(|e| DbError::MigrateError(e.to_string()))?;
+        }
+        self.run_data_migrations().await?;
         info!("Finished database sync");
 
         Ok(())
     }
 
+    /// Updates existing data that the schema changes alone can't fill in. Each migration only runs
+    /// once.
+    async fn run_data_migrations(&self) -> Result<(), DbError> {
+        let mut dal = SyncDAL::try_new(self.write_pool.clone()).await?;
+        if dal.apply_migration("album_releases").await? {
+            info!("Splitting existing albums by release");
+            dal.regroup_all_albums().await?;
+            dal.remove_empty_entries().await?;
+        }
+        dal.commit().await
+    }
+
     // TODO: make sure this gets called on shutdown
     pub async fn close(&self) {
         self.write_pool.close().await;
@@ -211,14 +354,16 @@ impl Database {
         &self,
         query: &str,
         options: SearchOptions<'_>,
-    ) -> Result<Vec<SearchResult>, DbError> {
+    ) -> Result<Vec<SearchResult>, SearchError> {
         self.search_engine.search(query, options).await
     }
 
     pub(crate) async fn sync(
         &mut self,
         folders: Vec<String>,
-        mount: Option<String>,
+        mounts: Vec<String>,
+        full_rescan: bool,
+        filter: FileFilter,
         finished_callback: Pin<Box<dyn Future<Output = ()> + Send>>,
     ) -> ProgressStream {
         let search_engine = self.search_engine.clone();
@@ -227,7 +372,10 @@ impl Database {
             .await
             .sync(
                 folders,
-                mount,
+                mounts,
+                self.art_cache_dir.clone(),
+                full_rescan,
+                filter,
                 Box::pin(async move {
                     search_engine.clear_cache();
                     finished_callback.await;
@@ -236,7 +384,76 @@ impl Database {
             .await
     }
 
+    /// Returns `false` if there's no running sync
+    pub(crate) async fn pause_sync(&self) -> bool {
+        self.sync_controller.lock().await.pause()
+    }
+
+    /// Returns `false` if there's no paused sync
+    pub(crate) async fn resume_sync(&self) -> bool {
+        self.sync_controller.lock().await.resume()
+    }
+
+    /// Returns `false` if there's no sync in progress
+    pub(crate) async fn cancel_sync(&self) -> bool {
+        self.sync_controller.lock().await.cancel()
+    }
+
+    pub(crate) async fn subscribe_sync_state(&self) -> watch::Receiver<SyncState> {
+        self.sync_controller.lock().await.subscribe_state()
+    }
+
     pub(crate) async fn rename_path(&self, from: &str, to: &str) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        // If the new path already exists, the song update below will be ignored, so point any
+        // playlist entries and play history at the existing song instead
+        sqlx::query!(
+            "
+            UPDATE playlist_song
+            SET song_id = (
+                SELECT s2.song_id FROM song s1
+                INNER JOIN song s2 ON s2.song_path = REPLACE(s1.song_path, $1, $2)
+                WHERE s1.song_id = playlist_song.song_id
+            )
+            WHERE EXISTS (
+                SELECT 1 FROM song s1
+                INNER JOIN song s2 ON s2.song_path = REPLACE(s1.song_path, $1, $2)
+                WHERE s1.song_id = playlist_song.song_id AND s2.song_id != s1.song_id
+            );
+            ",
+            from,
+            to
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        sqlx::query!(
+            "
+            UPDATE song_history
+            SET song_id = (
+                SELECT s2.song_id FROM song s1
+                INNER JOIN song s2 ON s2.song_path = REPLACE(s1.song_path, $1, $2)
+                WHERE s1.song_id = song_history.song_id
+            )
+            WHERE EXISTS (
+                SELECT 1 FROM song s1
+                INNER JOIN song s2 ON s2.song_path = REPLACE(s1.song_path, $1, $2)
+                WHERE s1.song_id = song_history.song_id AND s2.song_id != s1.song_id
+            );
+            ",
+            from,
+            to
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
         // Update could cause duplicate paths so just ignore if that happens
         sqlx::query!(
             "
@@ -246,11 +463,13 @@ impl Database {
             from,
             to
         )
-        .execute(&self.write_pool)
+        .execute(&mut *tran)
         .await
         .map_err(|e| DbError::DbError(format!("{e:?}")))?;
 
-        Ok(())
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
     pub(crate) async fn lookup(
@@ -262,6 +481,8 @@ impl Database {
             EntryType::Album => self.all_by_albums(correlation_ids).await,
             EntryType::Song => self.all_by_ids(correlation_ids).await,
             EntryType::Artist => self.all_by_artists(correlation_ids).await,
+            EntryType::Genre => self.all_by_genres(correlation_ids).await,
+            EntryType::Composer => self.all_by_composers(correlation_ids).await,
         }
     }
 
@@ -272,9 +493,16 @@ impl Database {
         sqlx::query_as!(
             LookupEntry,
             "
-            SELECT ar.artist_name artist, s.song_title song, s.song_path path, s.duration \
-             duration_millis,
-            al.album_name album, aa.artist_name album_artist, s.track_number track_number
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
             FROM song s
             INNER JOIN artist ar ON ar.artist_id = s.artist_id
             INNER JOIN album al ON al.album_id = s.album_id
@@ -289,18 +517,123 @@ impl Database {
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
+    pub(crate) async fn get_lyrics(&self, song_id: i64) -> Result<Option<Lyrics>, DbError> {
+        let lyrics = sqlx::query!(
+            "SELECT lyrics, synced_lyrics FROM song WHERE song_id = ?;",
+            song_id
+        )
+        .fetch_optional(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(lyrics.map(|row| Lyrics {
+            plain: row.lyrics,
+            synced: row
+                .synced_lyrics
+                .map(|synced| parse_lrc(&synced))
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// Returns every artist credited on the song. Primary artists come first, followed by featured
+    /// artists, album artists, and composers.
+    pub(crate) async fn get_song_artists(&self, song_id: i64) -> Result<Vec<SongArtist>, DbError> {
+        sqlx::query_as!(
+            SongArtistRow,
+            "
+            SELECT sa.artist_id, a.artist_name, sa.artist_role FROM song_artist sa
+            INNER JOIN artist a ON a.artist_id = sa.artist_id
+            WHERE sa.song_id = ?
+            ORDER BY sa.song_artist_id;
+            ",
+            song_id
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        .into_iter()
+        .map(SongArtist::try_from)
+        .collect()
+    }
+
+    /// Returns the most recent report if no ID is given. All files are included if no statuses
+    /// are given.
+    pub(crate) async fn get_sync_report(
+        &self,
+        sync_report_id: Option<i64>,
+        statuses: &[SyncFileStatus],
+    ) -> Result<Option<SyncReport>, DbError> {
+        let Some(row) = sqlx::query!(
+            "
+            SELECT sync_report_id, started_date, finished_date, full_rescan, cancelled,
+            added_count, updated_count, deleted_count, failed_count
+            FROM sync_report
+            WHERE sync_report_id = COALESCE(?, (SELECT MAX(sync_report_id) FROM sync_report));
+            ",
+            sync_report_id
+        )
+        .fetch_optional(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        else {
+            return Ok(None);
+        };
+
+        let files: Vec<SyncReportFile> = sqlx::query_as!(
+            SyncReportFileRow,
+            "
+            SELECT file_path, status, error FROM sync_report_file
+            WHERE sync_report_id = ?
+            ORDER BY file_path;
+            ",
+            row.sync_report_id
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        .into_iter()
+        .map(SyncReportFile::try_from)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|f| statuses.is_empty() || statuses.contains(&f.status))
+        .collect();
+
+        Ok(Some(SyncReport {
+            sync_report_id: row.sync_report_id,
+            started_date: row.started_date,
+            finished_date: row.finished_date,
+            full_rescan: row.full_rescan,
+            cancelled: row.cancelled,
+            added_count: row.added_count,
+            updated_count: row.updated_count,
+            deleted_count: row.deleted_count,
+            failed_count: row.failed_count,
+            files,
+        }))
+    }
+
     async fn all_by_artists(&self, artist_ids: Vec<i64>) -> Result<Vec<LookupEntry>, DbError> {
         sqlx::query_as!(
             LookupEntry,
             "
-            SELECT ar.artist_name artist, s.song_title song, s.song_path path, s.duration \
-             duration_millis,
-            al.album_name album, aa.artist_name album_artist, s.track_number track_number
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
             FROM artist ar
             INNER JOIN song s ON s.artist_id = ar.artist_id
             INNER JOIN album al ON al.album_id = s.album_id
             INNER JOIN artist aa ON aa.artist_id = al.artist_id
             WHERE ar.artist_id = $1 OR aa.artist_id = $1
+            OR EXISTS (
+                SELECT 1 FROM song_artist sa WHERE sa.song_id = s.song_id AND sa.artist_id = $1
+            )
             ORDER BY aa.artist_id, al.album_id, s.disc_number, s.track_number;
             ",
             artist_ids[0]
@@ -314,9 +647,16 @@ impl Database {
         sqlx::query_as!(
             LookupEntry,
             "
-            SELECT ar.artist_name artist, s.song_title song, s.song_path path, s.duration \
-             duration_millis,
-            al.album_name album, aa.artist_name album_artist, s.track_number track_number
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
             FROM album al
             INNER JOIN artist aa ON aa.artist_id = al.artist_id
             INNER JOIN song s ON s.album_id = al.album_id
@@ -335,9 +675,16 @@ impl Database {
         sqlx::query_as!(
             LookupEntry,
             "
-            SELECT ar.artist_name artist, s.song_title song, s.song_path path, s.duration \
-             duration_millis,
-            al.album_name album, aa.artist_name album_artist, s.track_number track_number
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
             FROM song s
             INNER JOIN artist ar ON ar.artist_id = s.artist_id
             INNER JOIN album al ON al.album_id = s.album_id
@@ -352,6 +699,64 @@ impl Database {
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
+    async fn all_by_genres(&self, genre_ids: Vec<i64>) -> Result<Vec<LookupEntry>, DbError> {
+        sqlx::query_as!(
+            LookupEntry,
+            "
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
+            FROM song s
+            INNER JOIN artist ar ON ar.artist_id = s.artist_id
+            INNER JOIN album al ON al.album_id = s.album_id
+            INNER JOIN artist aa ON aa.artist_id = al.artist_id
+            WHERE EXISTS (
+                SELECT 1 FROM song_genre sg WHERE sg.song_id = s.song_id AND sg.genre_id = ?
+            )
+            ORDER BY aa.artist_id, al.album_id, s.disc_number, s.track_number;
+            ",
+            genre_ids[0]
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    async fn all_by_composers(&self, song_ids: Vec<i64>) -> Result<Vec<LookupEntry>, DbError> {
+        sqlx::query_as!(
+            LookupEntry,
+            "
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
+            FROM song s
+            INNER JOIN artist ar ON ar.artist_id = s.artist_id
+            INNER JOIN album al ON al.album_id = s.album_id
+            INNER JOIN artist aa ON aa.artist_id = al.artist_id
+            WHERE s.composer = (SELECT composer FROM song WHERE song_id = ?)
+            ORDER BY aa.artist_id, al.album_id, s.disc_number, s.track_number;
+            ",
+            song_ids[0]
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
     pub async fn albums_by_album_artists(
         &self,
         artist_ids: Vec<i64>,
@@ -360,10 +765,11 @@ impl Database {
             Album,
             "
             SELECT al.album_name album, al.album_id, aa.artist_name album_artist, aa.artist_id \
-             album_artist_id
+             album_artist_id, al.album_year year, al.is_compilation
             FROM album al
             INNER JOIN artist aa ON aa.artist_id = al.artist_id
             WHERE aa.artist_id = ?
+            ORDER BY al.album_name, al.album_year, al.album_id
             ",
             artist_ids[0]
         )
@@ -372,6 +778,101 @@ impl Database {
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
+    pub(crate) async fn browse_artists(
+        &self,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<ArtistSummary>, DbError> {
+        self.browse_page(queries::artists_query(&options.filter), "artist", options)
+            .await
+    }
+
+    pub(crate) async fn browse_album_artists(
+        &self,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<ArtistSummary>, DbError> {
+        self.browse_page(
+            queries::album_artists_query(&options.filter),
+            "artist",
+            options,
+        )
+        .await
+    }
+
+    pub(crate) async fn browse_albums(
+        &self,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<AlbumSummary>, DbError> {
+        self.browse_page(queries::albums_query(&options.filter), "album", options)
+            .await
+    }
+
+    pub(crate) async fn browse_genres(
+        &self,
+        filter: &BrowseFilter,
+    ) -> Result<Vec<GenreSummary>, DbError> {
+        let query = queries::genres_query(filter);
+        let mut sql_query = sqlx::query_as::<_, GenreSummary>(&query);
+        for value in queries::filter_values(filter) {
+            sql_query = sql_query.bind(value);
+        }
+        sql_query
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn browse_years(
+        &self,
+        filter: &BrowseFilter,
+        by_decade: bool,
+    ) -> Result<Vec<YearSummary>, DbError> {
+        let query = queries::years_query(filter, by_decade);
+        let mut sql_query = sqlx::query_as::<_, YearSummary>(&query);
+        for value in queries::filter_values(filter) {
+            sql_query = sql_query.bind(value);
+        }
+        sql_query
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    async fn browse_page<T>(
+        &self,
+        base_query: String,
+        name_column: &str,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<T>, DbError>
+    where
+        T: for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+    {
+        let filter_values = queries::filter_values(&options.filter);
+
+        let count_query = queries::count_query(&base_query);
+        let mut sql_count_query = sqlx::query_scalar::<_, i64>(&count_query);
+        for value in &filter_values {
+            sql_count_query = sql_count_query.bind(value);
+        }
+        let total = sql_count_query
+            .fetch_one(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let page_query = queries::page_query(&base_query, name_column, options);
+        let mut sql_query = sqlx::query_as::<_, T>(&page_query);
+        for value in &filter_values {
+            sql_query = sql_query.bind(value);
+        }
+        let entries = sql_query
+            .bind(options.limit)
+            .bind(options.offset)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(BrowsePage { entries, total })
+    }
+
     pub(crate) async fn get_deleted_songs(&self) -> Result<Vec<DeletedEntry>, DbError> {
         sqlx::query_as!(
             DeletedEntry,
@@ -391,34 +892,32 @@ impl Database {
             .begin()
             .await
             .map_err(|e| DbError::DbError(format!("{e:?}")))?;
-        for id in ids {
-            sqlx::query!("DELETE FROM deleted_song WHERE song_id = ?;", id)
-                .execute(&mut *tran)
-                .await
-                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
-
-            sqlx::query!("DELETE FROM song WHERE song_id = ?;", id)
-                .execute(&mut *tran)
-                .await
-                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
-        }
+        Self::delete_songs(&mut tran, &ids).await?;
 
         tran.commit()
             .await
             .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
-    pub(crate) async fn add_folders(&self, paths: Vec<String>) -> Result<(), DbError> {
+    /// Adds the folders along with the name of the mount they're relative to
+    pub(crate) async fn add_folders(
+        &self,
+        paths: Vec<(String, Option<String>)>,
+    ) -> Result<(), DbError> {
         let mut tran = self
             .write_pool
             .begin()
             .await
             .map_err(|e| DbError::DbError(format!("{e:?}")))?;
-        for path in paths {
-            sqlx::query!("INSERT OR IGNORE INTO folder(folder_path) VALUES(?);", path)
-                .execute(&mut *tran)
-                .await
-                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        for (path, mount_name) in paths {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO folder(folder_path, mount_name) VALUES(?, ?);",
+                path,
+                mount_name
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
         }
         tran.commit()
             .await
@@ -429,10 +928,12 @@ impl Database {
         &self,
         old_path: &str,
         new_path: &str,
+        mount_name: &str,
     ) -> Result<(), DbError> {
         sqlx::query!(
-            "UPDATE folder SET folder_path = ? WHERE folder_path = ?;",
+            "UPDATE folder SET folder_path = ?, mount_name = ? WHERE folder_path = ?;",
             new_path,
+            mount_name,
             old_path
         )
         .execute(&self.write_pool)
@@ -442,6 +943,90 @@ impl Database {
         Ok(())
     }
 
+    pub(crate) async fn remove_folders(
+        &self,
+        paths: Vec<String>,
+        purge: bool,
+    ) -> Result<RemovedFolders, DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let mut removed = RemovedFolders::default();
+        for path in paths {
+            sqlx::query!(
+                "
+                DELETE FROM folder_rule
+                WHERE folder_id IN (SELECT folder_id FROM folder WHERE folder_path = ?);
+                ",
+                path
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            let res = sqlx::query!("DELETE FROM folder WHERE folder_path = ?;", path)
+                .execute(&mut *tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            if res.rows_affected() > 0 {
+                removed.folders.push(path);
+            }
+        }
+
+        for folder in &removed.folders {
+            // Make sure we add a trailing slash so we don't get false matches off of word prefixes
+            // i.e. /folder/app and /folder/apple
+            let folder = format!("{}/%", folder.trim_end_matches('/'));
+            // Songs that are still covered by another folder need to stay in the library
+            let songs = sqlx::query_as!(
+                DeletedEntry,
+                "
+                SELECT song_id, song_path FROM song s
+                WHERE song_path LIKE $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM folder f WHERE s.song_path LIKE RTRIM(f.folder_path, '/') || '/%'
+                )
+                AND ($2 OR NOT EXISTS (SELECT 1 FROM deleted_song ds WHERE ds.song_id = s.song_id));
+                ",
+                folder,
+                purge
+            )
+            .fetch_all(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            removed.songs.extend(songs);
+        }
+
+        let ids: Vec<_> = removed.songs.iter().map(|s| s.song_id).collect();
+        if purge {
+            Self::delete_songs(&mut tran, &ids).await?;
+            let empty = Self::remove_empty_entries(&mut tran).await?;
+            removed.albums = empty.albums;
+            removed.artists = empty.artists;
+        } else {
+            // Marked songs can be removed later with delete_tracks or restored by adding the
+            // folder back and syncing it again
+            for id in ids {
+                sqlx::query!(
+                    "INSERT INTO deleted_song(song_id) VALUES(?) ON CONFLICT DO NOTHING;",
+                    id
+                )
+                .execute(&mut *tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            }
+        }
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(removed)
+    }
+
     pub(crate) async fn get_all_folders(&self) -> Result<Vec<String>, DbError> {
         Ok(sqlx::query!("SELECT folder_path FROM folder;")
             .fetch_all(&self.read_pool)
@@ -452,35 +1037,178 @@ impl Database {
             .collect())
     }
 
-    pub(crate) async fn get_mount(&self, mount_uuid: Uuid) -> Option<String> {
-        let mount_uuid = mount_uuid.to_string();
-        match sqlx::query!(
-            "SELECT mount_path FROM mount WHERE mount_uuid = ?;",
-            mount_uuid
-        )
-        .fetch_one(&self.read_pool)
-        .await
-        {
-            Ok(res) => Some(res.mount_path),
-            Err(_) => None,
-        }
-    }
-
-    pub(crate) async fn add_mount(&self, path: &str) -> Result<Uuid, DbError> {
+    /// Returns each folder with the name of the mount it's relative to
+    pub(crate) async fn get_folder_mounts(&self) -> Result<Vec<(String, Option<String>)>, DbError> {
+        Ok(sqlx::query!("SELECT folder_path, mount_name FROM folder;")
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?
+            .into_iter()
+            .map(|r| (r.folder_path, r.mount_name))
+            .collect())
+    }
+
+    pub(crate) async fn get_folder_rules(&self) -> Result<Vec<(String, FolderRules)>, DbError> {
+        let rows = sqlx::query!(
+            "
+            SELECT f.folder_path, r.pattern, r.is_exclude FROM folder f
+            INNER JOIN folder_rule r ON r.folder_id = f.folder_id
+            ORDER BY f.folder_id, r.folder_rule_id;
+            "
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let mut folders: Vec<(String, FolderRules)> = Vec::new();
+        for row in rows {
+            if folders
+                .last()
+                .is_none_or(|(path, _)| *path != row.folder_path)
+            {
+                folders.push((row.folder_path.clone(), FolderRules::default()));
+            }
+            let (_, rules) = folders.last_mut().unwrap();
+            if row.is_exclude {
+                rules.exclude.push(row.pattern);
+            } else {
+                rules.include.push(row.pattern);
+            }
+        }
+        Ok(folders)
+    }
+
+    /// Replaces the rules for the folder. Returns `false` if the folder isn't registered.
+    pub(crate) async fn set_folder_rules(
+        &self,
+        path: &str,
+        rules: &FolderRules,
+    ) -> Result<bool, DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let Some(folder_id) =
+            sqlx::query_scalar!("SELECT folder_id FROM folder WHERE folder_path = ?;", path)
+                .fetch_optional(&mut *tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        else {
+            return Ok(false);
+        };
+
+        sqlx::query!("DELETE FROM folder_rule WHERE folder_id = ?;", folder_id)
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let patterns = rules
+            .include
+            .iter()
+            .map(|pattern| (pattern, false))
+            .chain(rules.exclude.iter().map(|pattern| (pattern, true)));
+        for (pattern, is_exclude) in patterns {
+            sqlx::query!(
+                "INSERT INTO folder_rule(folder_id, pattern, is_exclude) VALUES(?, ?, ?);",
+                folder_id,
+                pattern,
+                is_exclude
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(true)
+    }
+
+    pub(crate) async fn get_file_extensions(&self) -> Result<Vec<String>, DbError> {
+        let extensions =
+            sqlx::query_scalar!("SELECT extension FROM file_extension ORDER BY extension;")
+                .fetch_all(&self.read_pool)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        if extensions.is_empty() {
+            return Ok(DEFAULT_FILE_EXTS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect());
+        }
+        Ok(extensions)
+    }
+
+    /// Replaces the extensions used for syncing. The defaults are used if the list is empty.
+    pub(crate) async fn set_file_extensions(&self, extensions: Vec<String>) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        sqlx::query!("DELETE FROM file_extension;")
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        for extension in extensions {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO file_extension(extension) VALUES(?);",
+                extension
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_mounts(&self) -> Result<Vec<Mount>, DbError> {
+        sqlx::query!("SELECT mount_uuid, mount_name, mount_path FROM mount;")
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?
+            .into_iter()
+            .map(|r| {
+                Ok(Mount {
+                    mount_id: Uuid::from_str(&r.mount_uuid)
+                        .map_err(|e| DbError::DbError(format!("{e:?}")))?,
+                    name: r.mount_name,
+                    path: r.mount_path,
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) async fn add_mount(&self, name: &str, path: &str) -> Result<Uuid, DbError> {
         let new_id = Uuid::new_v4().to_string();
         sqlx::query!(
-            r"INSERT OR IGNORE INTO mount(mount_uuid, mount_path) VALUES(?, ?);",
+            r"INSERT OR IGNORE INTO mount(mount_uuid, mount_name, mount_path) VALUES(?, ?, ?);",
             new_id,
+            name,
             path
         )
         .execute(&self.write_pool)
         .await
         .map_err(|e| DbError::DbError(format!("{e:?}")))?;
 
-        let res = sqlx::query!(r"SELECT mount_uuid FROM mount WHERE mount_path = ?;", path)
-            .fetch_one(&self.read_pool)
-            .await
-            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        let res = sqlx::query!(
+            r"SELECT mount_uuid FROM mount WHERE mount_name = ? AND mount_path = ?;",
+            name,
+            path
+        )
+        .fetch_one(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
 
         Ok(Uuid::from_str(&res.mount_uuid).unwrap())
     }
@@ -498,4 +1226,950 @@ impl Database {
 
         Ok(res.rows_affected())
     }
+
+    pub(crate) async fn create_playlist(&self, name: &str) -> Result<i64, DbError> {
+        let timestamp = current_timestamp();
+        let res = sqlx::query!(
+            "INSERT INTO playlist(playlist_name, created_date, modified_date) VALUES(?, ?, ?);",
+            name,
+            timestamp,
+            timestamp
+        )
+        .execute(&self.write_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(res.last_insert_rowid())
+    }
+
+    pub(crate) async fn rename_playlist(
+        &self,
+        playlist_id: i64,
+        name: &str,
+    ) -> Result<(), DbError> {
+        let timestamp = current_timestamp();
+        let res = sqlx::query!(
+            "UPDATE playlist SET playlist_name = ?, modified_date = ? WHERE playlist_id = ?;",
+            name,
+            timestamp,
+            playlist_id
+        )
+        .execute(&self.write_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound(format!("Playlist {playlist_id}")));
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn delete_playlists(&self, playlist_ids: Vec<i64>) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        for id in playlist_ids {
+            sqlx::query!("DELETE FROM playlist_song WHERE playlist_id = ?;", id)
+                .execute(&mut *tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            let res = sqlx::query!("DELETE FROM playlist WHERE playlist_id = ?;", id)
+                .execute(&mut *tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            if res.rows_affected() == 0 {
+                return Err(DbError::NotFound(format!("Playlist {id}")));
+            }
+        }
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn append_to_playlist(
+        &self,
+        playlist_id: i64,
+        song_ids: Vec<i64>,
+    ) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Self::check_playlist_exists(&mut *tran, playlist_id).await?;
+
+        for song_id in song_ids {
+            // Foreign keys aren't enforced so missing songs need to be checked here to avoid
+            // leaving orphaned entries in the playlist
+            let res = sqlx::query!(
+                "
+                INSERT INTO playlist_song(playlist_id, song_id, position)
+                SELECT
+                    $1,
+                    s.song_id,
+                    (SELECT COALESCE(MAX(position) + 1, 0) FROM playlist_song WHERE playlist_id = $1)
+                FROM song s WHERE s.song_id = $2;
+                ",
+                playlist_id,
+                song_id
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            if res.rows_affected() == 0 {
+                return Err(DbError::NotFound(format!("Song {song_id}")));
+            }
+        }
+        Self::touch_playlist(&mut tran, playlist_id).await?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn reorder_playlist(
+        &self,
+        playlist_id: i64,
+        from_position: i64,
+        to_position: i64,
+    ) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Self::check_playlist_exists(&mut *tran, playlist_id).await?;
+
+        let song_count = sqlx::query!(
+            r#"SELECT COUNT(1) as "count!: i64" FROM playlist_song WHERE playlist_id = ?;"#,
+            playlist_id
+        )
+        .fetch_one(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        .count;
+
+        if !(0..song_count).contains(&from_position) || !(0..song_count).contains(&to_position) {
+            return Err(DbError::DbError(format!(
+                "Invalid playlist position. Playlist {playlist_id} contains {song_count} songs"
+            )));
+        }
+
+        // Move the entry out of the way first so it doesn't get shifted with the others
+        sqlx::query!(
+            "UPDATE playlist_song SET position = -1 WHERE playlist_id = ? AND position = ?;",
+            playlist_id,
+            from_position
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        if from_position < to_position {
+            sqlx::query!(
+                "
+                UPDATE playlist_song SET position = position - 1
+                WHERE playlist_id = ? AND position > ? AND position <= ?;
+                ",
+                playlist_id,
+                from_position,
+                to_position
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        } else {
+            sqlx::query!(
+                "
+                UPDATE playlist_song SET position = position + 1
+                WHERE playlist_id = ? AND position >= ? AND position < ?;
+                ",
+                playlist_id,
+                to_position,
+                from_position
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        sqlx::query!(
+            "UPDATE playlist_song SET position = ? WHERE playlist_id = ? AND position = -1;",
+            to_position,
+            playlist_id
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Self::touch_playlist(&mut tran, playlist_id).await?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn remove_from_playlist(
+        &self,
+        playlist_id: i64,
+        positions: Vec<i64>,
+    ) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Self::check_playlist_exists(&mut *tran, playlist_id).await?;
+        for position in positions {
+            sqlx::query!(
+                "DELETE FROM playlist_song WHERE playlist_id = ? AND position = ?;",
+                playlist_id,
+                position
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+        Self::normalize_playlist_positions(&mut tran, playlist_id).await?;
+        Self::touch_playlist(&mut tran, playlist_id).await?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub async fn get_all_playlists(&self) -> Result<Vec<Playlist>, DbError> {
+        sqlx::query_as!(
+            Playlist,
+            r#"
+            SELECT p.playlist_id, p.playlist_name, COUNT(ps.playlist_song_id) as "song_count!: i64"
+            FROM playlist p
+            LEFT OUTER JOIN playlist_song ps ON ps.playlist_id = p.playlist_id
+            GROUP BY p.playlist_id, p.playlist_name
+            ORDER BY p.playlist_name;
+            "#
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_playlist_songs(
+        &self,
+        playlist_id: i64,
+    ) -> Result<Vec<LookupEntry>, DbError> {
+        Self::check_playlist_exists(&self.read_pool, playlist_id).await?;
+        sqlx::query_as!(
+            LookupEntry,
+            "
+            SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path, \
+             s.duration duration_millis,
+            al.album_name album, aa.artist_name album_artist, s.track_number track_number,
+            s.track_gain, s.track_peak, s.album_gain, s.album_peak, s.album_art_path album_art,
+            (
+                SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+                INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+            ) \"genre?\", s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+            s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+            s.musicbrainz_release_group_id
+            FROM playlist_song ps
+            INNER JOIN song s ON s.song_id = ps.song_id
+            INNER JOIN artist ar ON ar.artist_id = s.artist_id
+            INNER JOIN album al ON al.album_id = s.album_id
+            INNER JOIN artist aa ON aa.artist_id = al.artist_id
+            WHERE ps.playlist_id = ?
+            ORDER BY ps.position;
+            ",
+            playlist_id
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn record_play(
+        &self,
+        path: String,
+        played_date: i64,
+        listened_millis: i64,
+        skipped: bool,
+    ) -> Result<bool, DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let Some(song) = sqlx::query!(
+            "SELECT song_id, duration FROM song WHERE song_path = ?;",
+            path
+        )
+        .fetch_optional(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        else {
+            return Ok(false);
+        };
+
+        let threshold = (song.duration / 2).min(PLAY_THRESHOLD_MILLIS);
+        if skipped && listened_millis < threshold {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "
+            INSERT INTO song_history(song_id, played_date, listened_millis, skipped)
+            VALUES(?, ?, ?, ?);
+            ",
+            song.song_id,
+            played_date,
+            listened_millis,
+            skipped
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        sqlx::query!(
+            "UPDATE song SET play_count = play_count + 1 WHERE song_id = ?;",
+            song.song_id
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(true)
+    }
+
+    pub(crate) async fn get_history(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<HistoryEntry>, DbError> {
+        sqlx::query_as!(
+            HistoryEntry,
+            "
+            SELECT h.song_history_id, h.song_id, h.played_date, h.listened_millis, h.skipped,
+            s.song_title song, ar.artist_name artist, al.album_name album, s.song_path path
+            FROM song_history h
+            INNER JOIN song s ON s.song_id = h.song_id
+            INNER JOIN artist ar ON ar.artist_id = s.artist_id
+            INNER JOIN album al ON al.album_id = s.album_id
+            WHERE h.played_date BETWEEN ? AND ?
+            ORDER BY h.played_date DESC, h.song_history_id DESC
+            LIMIT ?;
+            ",
+            start,
+            end,
+            limit
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_top_songs(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayStat>, DbError> {
+        sqlx::query_as!(
+            PlayStat,
+            r#"
+            SELECT s.song_id id, s.song_title name, ar.artist_name "artist?: String",
+            COUNT(1) "play_count!: i64", SUM(h.listened_millis) "listened_millis!: i64"
+            FROM song_history h
+            INNER JOIN song s ON s.song_id = h.song_id
+            INNER JOIN artist ar ON ar.artist_id = s.artist_id
+            WHERE h.played_date BETWEEN ? AND ?
+            GROUP BY s.song_id, s.song_title, ar.artist_name
+            ORDER BY 4 DESC, 5 DESC, s.song_title
+            LIMIT ?;
+            "#,
+            start,
+            end,
+            limit
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_top_albums(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayStat>, DbError> {
+        sqlx::query_as!(
+            PlayStat,
+            r#"
+            SELECT al.album_id id, al.album_name name, aa.artist_name "artist?: String",
+            COUNT(1) "play_count!: i64", SUM(h.listened_millis) "listened_millis!: i64"
+            FROM song_history h
+            INNER JOIN song s ON s.song_id = h.song_id
+            INNER JOIN album al ON al.album_id = s.album_id
+            INNER JOIN artist aa ON aa.artist_id = al.artist_id
+            WHERE h.played_date BETWEEN ? AND ?
+            GROUP BY al.album_id, al.album_name, aa.artist_name
+            ORDER BY 4 DESC, 5 DESC, al.album_name
+            LIMIT ?;
+            "#,
+            start,
+            end,
+            limit
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_top_artists(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayStat>, DbError> {
+        sqlx::query_as!(
+            PlayStat,
+            r#"
+            SELECT ar.artist_id id, ar.artist_name name, NULL "artist?: String",
+            COUNT(1) "play_count!: i64", SUM(h.listened_millis) "listened_millis!: i64"
+            FROM song_history h
+            INNER JOIN song s ON s.song_id = h.song_id
+            INNER JOIN artist ar ON ar.artist_id = s.artist_id
+            WHERE h.played_date BETWEEN ? AND ?
+            GROUP BY ar.artist_id, ar.artist_name
+            ORDER BY 4 DESC, 5 DESC, ar.artist_name
+            LIMIT ?;
+            "#,
+            start,
+            end,
+            limit
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_play_counts(
+        &self,
+        song_ids: Vec<i64>,
+    ) -> Result<Vec<SongPlayCount>, DbError> {
+        // Pass the IDs as a JSON array so they can all be looked up in one query
+        let song_ids = format!("[{}]", song_ids.iter().join(","));
+        sqlx::query_as!(
+            SongPlayCount,
+            r#"
+            SELECT s.song_id, s.play_count,
+            COUNT(h.song_history_id) FILTER (WHERE h.skipped) "skip_count!: i64",
+            MAX(h.played_date) "last_played?: i64"
+            FROM json_each(?) ids
+            INNER JOIN song s ON s.song_id = ids.value
+            LEFT OUTER JOIN song_history h ON h.song_id = s.song_id
+            GROUP BY ids.key, s.song_id, s.play_count
+            ORDER BY ids.key;
+            "#,
+            song_ids
+        )
+        .fetch_all(&self.read_pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn create_smart_playlist(
+        &self,
+        definition: &SmartPlaylistDefinition,
+    ) -> Result<i64, SmartPlaylistError> {
+        definition.validate()?;
+        let timestamp = current_timestamp();
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let sort = definition.sort.to_string();
+        let res = sqlx::query!(
+            "
+            INSERT INTO smart_playlist(smart_playlist_name, match_all, sort, descending, song_limit,
+            created_date, modified_date)
+            VALUES(?, ?, ?, ?, ?, ?, ?);
+            ",
+            definition.name,
+            definition.match_all,
+            sort,
+            definition.descending,
+            definition.limit,
+            timestamp,
+            timestamp
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        let smart_playlist_id = res.last_insert_rowid();
+        Self::insert_smart_playlist_rules(&mut tran, smart_playlist_id, definition).await?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(smart_playlist_id)
+    }
+
+    pub(crate) async fn update_smart_playlist(
+        &self,
+        smart_playlist_id: i64,
+        definition: &SmartPlaylistDefinition,
+    ) -> Result<(), SmartPlaylistError> {
+        definition.validate()?;
+        let timestamp = current_timestamp();
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let sort = definition.sort.to_string();
+        let res = sqlx::query!(
+            "
+            UPDATE smart_playlist
+            SET smart_playlist_name = ?, match_all = ?, sort = ?, descending = ?, song_limit = ?,
+            modified_date = ?
+            WHERE smart_playlist_id = ?;
+            ",
+            definition.name,
+            definition.match_all,
+            sort,
+            definition.descending,
+            definition.limit,
+            timestamp,
+            smart_playlist_id
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        if res.rows_affected() == 0 {
+            return Err(SmartPlaylistError::NotFound(smart_playlist_id));
+        }
+
+        sqlx::query!(
+            "DELETE FROM smart_playlist_rule WHERE smart_playlist_id = ?;",
+            smart_playlist_id
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Self::insert_smart_playlist_rules(&mut tran, smart_playlist_id, definition).await?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_smart_playlists(
+        &self,
+        smart_playlist_ids: Vec<i64>,
+    ) -> Result<(), DbError> {
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        for id in smart_playlist_ids {
+            sqlx::query!(
+                "DELETE FROM smart_playlist_song WHERE smart_playlist_id = ?;",
+                id
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!(
+                "DELETE FROM smart_playlist_rule WHERE smart_playlist_id = ?;",
+                id
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!(
+                "DELETE FROM smart_playlist WHERE smart_playlist_id = ?;",
+                id
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub async fn get_all_smart_playlists(&self) -> Result<Vec<SmartPlaylist>, DbError> {
+        let query = smart_playlist_queries::smart_playlists_query(false);
+        let playlists = sqlx::query_as::<_, SmartPlaylistRow>(&query)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let rules_query = smart_playlist_queries::smart_playlist_rules_query(false);
+        let rules = sqlx::query_as::<_, SmartPlaylistRuleRow>(&rules_query)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        playlists
+            .into_iter()
+            .map(|p| p.into_smart_playlist(&rules))
+            .collect()
+    }
+
+    pub(crate) async fn get_smart_playlist(
+        &self,
+        smart_playlist_id: i64,
+    ) -> Result<Option<SmartPlaylist>, DbError> {
+        let query = smart_playlist_queries::smart_playlists_query(true);
+        let Some(playlist) = sqlx::query_as::<_, SmartPlaylistRow>(&query)
+            .bind(smart_playlist_id)
+            .fetch_optional(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        else {
+            return Ok(None);
+        };
+
+        let rules_query = smart_playlist_queries::smart_playlist_rules_query(true);
+        let rules = sqlx::query_as::<_, SmartPlaylistRuleRow>(&rules_query)
+            .bind(smart_playlist_id)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        playlist.into_smart_playlist(&rules).map(Some)
+    }
+
+    /// Runs the playlist's rules against the library and stores the matching songs
+    pub(crate) async fn evaluate_smart_playlist(
+        &self,
+        smart_playlist_id: i64,
+    ) -> Result<(), SmartPlaylistError> {
+        let playlist = self
+            .get_smart_playlist(smart_playlist_id)
+            .await?
+            .ok_or(SmartPlaylistError::NotFound(smart_playlist_id))?;
+        let timestamp = current_timestamp();
+        let (query, values) =
+            smart_playlist_queries::evaluate_query(&playlist.definition, timestamp)?;
+
+        let mut tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let mut sql_query = sqlx::query_scalar::<_, i64>(&query);
+        for value in values {
+            sql_query = match value {
+                BindValue::Text(value) => sql_query.bind(value),
+                BindValue::Int(value) => sql_query.bind(value),
+            };
+        }
+        let song_ids = sql_query
+            .fetch_all(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        sqlx::query!(
+            "DELETE FROM smart_playlist_song WHERE smart_playlist_id = ?;",
+            smart_playlist_id
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        for (position, song_id) in song_ids.into_iter().enumerate() {
+            let position = position as i64;
+            sqlx::query!(
+                "
+                INSERT INTO smart_playlist_song(smart_playlist_id, song_id, position)
+                VALUES(?, ?, ?);
+                ",
+                smart_playlist_id,
+                song_id,
+                position
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        sqlx::query!(
+            "UPDATE smart_playlist SET evaluated_date = ? WHERE smart_playlist_id = ?;",
+            timestamp,
+            smart_playlist_id
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn refresh_smart_playlists(&self) -> Result<(), SmartPlaylistError> {
+        let ids = sqlx::query_scalar!("SELECT smart_playlist_id FROM smart_playlist;")
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        for id in ids {
+            // One broken playlist shouldn't keep the rest from being updated
+            if let Err(e) = self.evaluate_smart_playlist(id).await {
+                warn!("Error refreshing smart playlist {id}: {e:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn get_smart_playlist_songs(
+        &self,
+        smart_playlist_id: i64,
+    ) -> Result<Vec<LookupEntry>, DbError> {
+        sqlx::query_as::<_, LookupEntry>(smart_playlist_queries::smart_playlist_songs_query())
+            .bind(smart_playlist_id)
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_duplicates(&self) -> Result<Vec<DuplicateGroup>, DbError> {
+        let rows = sqlx::query_as::<_, FingerprintRow>(duplicate_queries::duplicates_query())
+            .fetch_all(&self.read_pool)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        // Comparing fingerprints is CPU-bound for large libraries
+        tokio::task::spawn_blocking(move || matcher::find_duplicates(rows))
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    async fn insert_smart_playlist_rules(
+        tran: &mut Transaction<'_, Sqlite>,
+        smart_playlist_id: i64,
+        definition: &SmartPlaylistDefinition,
+    ) -> Result<(), DbError> {
+        for rule in &definition.rules {
+            let field = rule.field.to_string();
+            let operator = rule.operator.to_string();
+            sqlx::query!(
+                "
+                INSERT INTO smart_playlist_rule(smart_playlist_id, field, operator, value)
+                VALUES(?, ?, ?, ?);
+                ",
+                smart_playlist_id,
+                field,
+                operator,
+                rule.value
+            )
+            .execute(&mut **tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        Ok(())
+    }
+
+    async fn check_playlist_exists<'e>(
+        executor: impl SqliteExecutor<'e>,
+        playlist_id: i64,
+    ) -> Result<(), DbError> {
+        sqlx::query_scalar!("SELECT 1 FROM playlist WHERE playlist_id = ?;", playlist_id)
+            .fetch_optional(executor)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?
+            .ok_or_else(|| DbError::NotFound(format!("Playlist {playlist_id}")))?;
+
+        Ok(())
+    }
+
+    async fn touch_playlist(
+        tran: &mut Transaction<'_, Sqlite>,
+        playlist_id: i64,
+    ) -> Result<(), DbError> {
+        let timestamp = current_timestamp();
+        sqlx::query!(
+            "UPDATE playlist SET modified_date = ? WHERE playlist_id = ?;",
+            timestamp,
+            playlist_id
+        )
+        .execute(&mut **tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(())
+    }
+
+    async fn delete_songs(tran: &mut Transaction<'_, Sqlite>, ids: &[i64]) -> Result<(), DbError> {
+        for &id in ids {
+            sqlx::query!("DELETE FROM deleted_song WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!("DELETE FROM playlist_song WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!("DELETE FROM song_history WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!("DELETE FROM smart_playlist_song WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!("DELETE FROM song_artist WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!("DELETE FROM song_genre WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            sqlx::query!("DELETE FROM song WHERE song_id = ?;", id)
+                .execute(&mut **tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+        Self::normalize_all_playlist_positions(tran).await
+    }
+
+    /// Removes albums, artists, and genres that are no longer referenced by any songs
+    pub(crate) async fn remove_empty_entries(
+        tran: &mut Transaction<'_, Sqlite>,
+    ) -> Result<EmptyEntries, DbError> {
+        let albums = sqlx::query_scalar!(
+            r#"
+            WITH albums_to_delete AS (
+                SELECT album_id FROM album a
+                WHERE NOT EXISTS (select 1 FROM song s WHERE s.album_id = a.album_id)
+            )
+            DELETE FROM album WHERE album_id IN (SELECT album_id FROM albums_to_delete)
+            RETURNING album_name
+            "#
+        )
+        .fetch_all(&mut **tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let artists = sqlx::query_scalar!(
+            r#"
+            WITH artists_to_delete AS (
+                SELECT artist_id FROM artist ar
+                WHERE NOT EXISTS (SELECT 1 FROM album al WHERE al.artist_id = ar.artist_id)
+                AND NOT EXISTS (SELECT 1 FROM song s WHERE s.artist_id = ar.artist_id)
+                AND NOT EXISTS (SELECT 1 FROM song_artist sa WHERE sa.artist_id = ar.artist_id)
+            )
+            DELETE FROM artist WHERE artist_id IN (SELECT artist_id FROM artists_to_delete)
+            RETURNING artist_name
+            "#
+        )
+        .fetch_all(&mut **tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM genre
+            WHERE NOT EXISTS (SELECT 1 FROM song_genre sg WHERE sg.genre_id = genre.genre_id)
+            "#
+        )
+        .execute(&mut **tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(EmptyEntries { albums, artists })
+    }
+
+    /// Closes any gaps left behind by removed entries so positions stay contiguous
+    async fn normalize_playlist_positions(
+        tran: &mut Transaction<'_, Sqlite>,
+        playlist_id: i64,
+    ) -> Result<(), DbError> {
+        sqlx::query!(
+            "
+            UPDATE playlist_song
+            SET position = ranked.new_position
+            FROM (
+                SELECT playlist_song_id,
+                ROW_NUMBER() OVER (ORDER BY position, playlist_song_id) - 1 new_position
+                FROM playlist_song
+                WHERE playlist_id = $1
+            ) ranked
+            WHERE playlist_song.playlist_song_id = ranked.playlist_song_id
+            AND playlist_song.position != ranked.new_position;
+            ",
+            playlist_id
+        )
+        .execute(&mut **tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(())
+    }
+
+    /// Same as [`Database::normalize_playlist_positions`] for every playlist, used when songs are
+    /// removed from the library
+    async fn normalize_all_playlist_positions(
+        tran: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), DbError> {
+        sqlx::query!(
+            "
+            UPDATE playlist_song
+            SET position = ranked.new_position
+            FROM (
+                SELECT playlist_song_id,
+                ROW_NUMBER() OVER (PARTITION BY playlist_id ORDER BY position, playlist_song_id) - 1 \
+             new_position
+                FROM playlist_song
+            ) ranked
+            WHERE playlist_song.playlist_song_id = ranked.playlist_song_id
+            AND playlist_song.position != ranked.new_position;
+            "
+        )
+        .execute(&mut **tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(())
+    }
+}
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
 }
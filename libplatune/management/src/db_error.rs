@@ -8,4 +8,6 @@ pub enum DbError {
     MigrateError(String),
     #[error("Error loading spellfix: {0}")]
     SpellfixLoadError(String),
+    #[error("{0} not found")]
+    NotFound(String),
 }
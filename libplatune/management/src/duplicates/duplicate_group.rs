@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::path_util::PathMut;
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Songs that are likely the same recording, ordered from highest to lowest quality
+    pub songs: Vec<DuplicateEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateEntry {
+    pub song_id: i64,
+    pub path: String,
+    pub song: String,
+    pub artist: String,
+    pub album: String,
+    pub duration_millis: i64,
+    pub bit_rate: i64,
+    pub sample_rate: i64,
+    pub file_size: i64,
+    /// Lowercase file extension
+    pub format: String,
+}
+
+impl PathMut for DuplicateEntry {
+    fn get_path(&self) -> String {
+        self.path.to_owned()
+    }
+
+    fn update_path(&mut self, path: String) {
+        self.path = path
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct FingerprintRow {
+    pub(crate) song_id: i64,
+    pub(crate) path: String,
+    pub(crate) song: String,
+    pub(crate) artist: String,
+    pub(crate) album: String,
+    pub(crate) duration_millis: i64,
+    pub(crate) bit_rate: i64,
+    pub(crate) sample_rate: i64,
+    pub(crate) file_size: i64,
+    pub(crate) acoustic_fingerprint: Vec<u8>,
+}
+
+impl FingerprintRow {
+    pub(crate) fn into_entry(self) -> DuplicateEntry {
+        let format = Path::new(&self.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        DuplicateEntry {
+            song_id: self.song_id,
+            path: self.path,
+            song: self.song,
+            artist: self.artist,
+            album: self.album,
+            duration_millis: self.duration_millis,
+            bit_rate: self.bit_rate,
+            sample_rate: self.sample_rate,
+            file_size: self.file_size,
+            format,
+        }
+    }
+}
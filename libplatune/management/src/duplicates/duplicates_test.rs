@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use lofty::config::WriteOptions;
+use lofty::file::TaggedFileExt;
+use lofty::probe::Probe;
+use lofty::tag::{Accessor, TagExt};
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+use crate::test_util::{setup_files, sync};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_duplicates_across_folders() {
+    let tempdir = TempDir::new().unwrap();
+    let (mut manager, paths) = setup_files(
+        tempdir.path(),
+        &[
+            ("folder1/test.mp3", "test.mp3"),
+            ("folder1/test2.mp3", "test2.mp3"),
+            ("folder2/copy.mp3", "test.mp3"),
+        ],
+    )
+    .await;
+    // Tags shouldn't affect matching
+    let mut t = Probe::open(&paths[2]).unwrap().read().unwrap();
+    t.primary_tag_mut()
+        .unwrap()
+        .set_title("renamed copy".to_owned());
+    t.save_to_path(&paths[2], WriteOptions::new()).unwrap();
+    sync(&mut manager).await;
+
+    let groups = manager.get_duplicates().await.unwrap();
+    assert_eq!(1, groups.len());
+    let group_paths = groups[0]
+        .songs
+        .iter()
+        .map(|s| PathBuf::from(&s.path))
+        .sorted()
+        .collect_vec();
+    assert_eq!(vec![paths[0].clone(), paths[2].clone()], group_paths);
+
+    for song in &groups[0].songs {
+        assert_eq!("mp3", song.format);
+        assert_eq!(22050, song.sample_rate);
+        assert!(song.bit_rate > 0);
+        assert!(song.file_size > 0);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_duplicates_ignore_deleted() {
+    let tempdir = TempDir::new().unwrap();
+    let (manager, paths) = setup_files(
+        tempdir.path(),
+        &[
+            ("folder1/test.mp3", "test.mp3"),
+            ("folder2/test.mp3", "test.mp3"),
+        ],
+    )
+    .await;
+    assert_eq!(1, manager.get_duplicates().await.unwrap().len());
+
+    let song = manager.get_song_by_path(&paths[1]).await.unwrap().unwrap();
+    manager.delete_tracks(vec![song.song_id]).await.unwrap();
+    assert!(manager.get_duplicates().await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_no_duplicates() {
+    let tempdir = TempDir::new().unwrap();
+    let (manager, _) = setup_files(
+        tempdir.path(),
+        &[
+            ("folder1/test.mp3", "test.mp3"),
+            ("folder1/test2.mp3", "test2.mp3"),
+            ("folder2/test3.mp3", "test3.mp3"),
+        ],
+    )
+    .await;
+
+    assert!(manager.get_duplicates().await.unwrap().is_empty());
+}
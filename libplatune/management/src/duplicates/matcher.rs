@@ -0,0 +1,117 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::duplicate_group::{DuplicateGroup, FingerprintRow};
+use crate::sync::fingerprint;
+
+// Copies of the same recording can differ slightly in length due to encoder padding or
+// trimmed silence, but anything beyond this is likely a different version of the song
+const MAX_DURATION_DIFF_MILLIS: i64 = 5000;
+// Each fingerprint item covers roughly 0.12 seconds of audio
+const MAX_ITEM_OFFSET: isize = 10;
+// Unrelated audio matches about half of the bits
+const MIN_SIMILARITY: f64 = 0.8;
+
+/// Groups songs whose fingerprints are similar enough to be the same recording.
+/// Rows must be sorted by duration.
+pub(crate) fn find_duplicates(rows: Vec<FingerprintRow>) -> Vec<DuplicateGroup> {
+    let fingerprints = rows
+        .iter()
+        .map(|r| fingerprint::from_bytes(&r.acoustic_fingerprint))
+        .collect_vec();
+    let mut parents = (0..rows.len()).collect_vec();
+
+    for i in 0..rows.len() {
+        for j in (i + 1)..rows.len() {
+            if rows[j].duration_millis - rows[i].duration_millis > MAX_DURATION_DIFF_MILLIS {
+                break;
+            }
+            if similarity(&fingerprints[i], &fingerprints[j]) >= MIN_SIMILARITY {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[root_j] = root_i;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..rows.len() {
+        let root = find_root(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+    let duplicate_indexes: HashMap<usize, usize> = groups
+        .into_values()
+        .filter(|g| g.len() > 1)
+        .enumerate()
+        .flat_map(|(group, indexes)| indexes.into_iter().map(move |i| (i, group)))
+        .collect();
+
+    let mut groups: HashMap<usize, Vec<_>> = HashMap::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        if let Some(group) = duplicate_indexes.get(&i) {
+            groups.entry(*group).or_default().push(row.into_entry());
+        }
+    }
+
+    groups
+        .into_values()
+        .map(|mut songs| {
+            songs.sort_by_key(|s| {
+                (
+                    Reverse(s.sample_rate),
+                    Reverse(s.bit_rate),
+                    Reverse(s.file_size),
+                    s.song_id,
+                )
+            });
+            DuplicateGroup { songs }
+        })
+        .sorted_by_cached_key(|g| {
+            (
+                g.songs[0].artist.to_lowercase(),
+                g.songs[0].song.to_lowercase(),
+                g.songs[0].song_id,
+            )
+        })
+        .collect()
+}
+
+/// Fraction of matching bits at the best alignment of the two fingerprints
+fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let min_overlap = (a.len().min(b.len()) / 2).max(1);
+    (-MAX_ITEM_OFFSET..=MAX_ITEM_OFFSET)
+        .filter_map(|offset| {
+            let (a, b) = if offset >= 0 {
+                (a.get(offset as usize..)?, b)
+            } else {
+                (a, b.get(offset.unsigned_abs()..)?)
+            };
+            let overlap = a.len().min(b.len());
+            if overlap < min_overlap {
+                return None;
+            }
+            let matching_bits: u32 = a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| 32 - (a ^ b).count_ones())
+                .sum();
+            Some(matching_bits as f64 / (overlap * 32) as f64)
+        })
+        .fold(0.0, f64::max)
+}
+
+fn find_root(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    // Compress the path so later lookups are faster
+    let mut current = i;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
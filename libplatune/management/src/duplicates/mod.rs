@@ -0,0 +1,7 @@
+pub mod duplicate_group;
+pub(crate) mod matcher;
+pub(crate) mod queries;
+
+#[cfg(test)]
+#[path = "./duplicates_test.rs"]
+mod duplicates_test;
@@ -0,0 +1,13 @@
+pub(crate) fn duplicates_query() -> &'static str {
+    "
+    SELECT s.song_id, s.song_path path, s.song_title song, ar.artist_name artist,
+    al.album_name album, s.duration duration_millis, s.bit_rate, s.sample_rate, s.file_size,
+    s.acoustic_fingerprint
+    FROM song s
+    INNER JOIN artist ar ON ar.artist_id = s.artist_id
+    INNER JOIN album al ON al.album_id = s.album_id
+    WHERE LENGTH(s.acoustic_fingerprint) > 0
+    AND NOT EXISTS (SELECT 1 FROM deleted_song ds WHERE ds.song_id = s.song_id)
+    ORDER BY s.duration
+    "
+}
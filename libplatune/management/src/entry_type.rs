@@ -6,4 +6,6 @@ pub enum EntryType {
     Song,
     Artist,
     Album,
+    Genre,
+    Composer,
 }
@@ -10,19 +10,20 @@ use notify::event::{EventKind, ModifyKind, RenameMode};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use tap::TapFallible;
 use thiserror::Error;
-use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc, watch};
 use tracing::{error, info, warn};
 
-use crate::consts::ALLOWED_FILE_EXTS;
+use crate::database::RemovedFolders;
 use crate::db_error::DbError;
 use crate::manager::{Manager, ManagerError};
 use crate::sync::progress_stream::ProgressStream;
+use crate::sync::sync_state::SyncState;
 
 #[derive(Debug)]
 pub(crate) enum SyncMessage {
     Path(PathBuf),
     Rename(PathBuf, PathBuf),
-    All,
+    All { full_rescan: bool },
 }
 
 #[derive(Clone, Debug)]
@@ -30,6 +31,9 @@ pub struct Progress {
     pub job: String,
     pub percentage: f32,
     pub finished: bool,
+    pub paused: bool,
+    /// The sync was stopped before it finished
+    pub cancelled: bool,
 }
 
 #[derive(Error, Debug)]
@@ -156,6 +160,9 @@ impl FileWatchManager {
                 }
             }
         });
+        // Smart playlists, the sync state, and the file filter only need database access, so use a
+        // separate handle to avoid waiting on the lock while a sync is being started
+        let smart_playlist_manager = manager.clone();
         let manager = Arc::new(RwLock::new(manager));
         let manager_ = manager.clone();
 
@@ -168,15 +175,26 @@ impl FileWatchManager {
                     .tap_ok(|event| info!("Processing watch event: {event:?}"));
 
                 match watch_event {
-                    Ok(Some(SyncMessage::All)) => {
-                        if let Ok(rx) = manager_
-                            .write()
-                            .await
-                            .sync(None, finished_callback())
-                            .await
-                            .tap_err(|e| error!("Error syncing: {e:?}"))
-                        {
-                            Self::send_progress(running.clone(), progress_tx_.clone(), rx);
+                    Ok(Some(SyncMessage::All { full_rescan })) => {
+                        let callback = Self::with_smart_playlist_refresh(
+                            smart_playlist_manager.clone(),
+                            finished_callback(),
+                        );
+                        let mut manager = manager_.write().await;
+                        let result = if full_rescan {
+                            manager.full_rescan(None, callback).await
+                        } else {
+                            manager.sync(None, callback).await
+                        };
+                        drop(manager);
+                        if let Ok(rx) = result.tap_err(|e| error!("Error syncing: {e:?}")) {
+                            let state_rx = smart_playlist_manager.subscribe_sync_state().await;
+                            Self::send_progress(
+                                running.clone(),
+                                progress_tx_.clone(),
+                                rx,
+                                state_rx,
+                            );
                         }
                     }
                     Ok(Some(SyncMessage::Path(new_path))) => {
@@ -186,9 +204,11 @@ impl FileWatchManager {
                         // Some metadata tagging programs may rename the existing file and create a
                         // new one. If this happens, we don't want to
                         // include the intermediate temporary file.
-                        if let Some(ext) = to.extension()
-                            && let Some(ext) = ext.to_str()
-                            && ALLOWED_FILE_EXTS.contains(&ext)
+                        if smart_playlist_manager
+                            .get_file_filter()
+                            .await
+                            .tap_err(|e| error!("Error loading file filter: {e:?}"))
+                            .is_ok_and(|filter| filter.is_allowed(&to))
                         {
                             let _ = manager_
                                 .write()
@@ -215,6 +235,17 @@ impl FileWatchManager {
                             info!("Sync already running, will start sync on next debounce timeout");
                             continue;
                         }
+                        if let Ok(filter) = smart_playlist_manager
+                            .get_file_filter()
+                            .await
+                            .tap_err(|e| error!("Error loading file filter: {e:?}"))
+                        {
+                            paths.retain(|p| !filter.is_excluded(p, p.is_dir()));
+                            if paths.is_empty() {
+                                info!("All changed paths are excluded from syncing");
+                                continue;
+                            }
+                        }
 
                         let folders = if cfg!(target_os = "macos") {
                             info!("Syncing all folders");
@@ -233,11 +264,23 @@ impl FileWatchManager {
                         if let Ok(rx) = manager_
                             .write()
                             .await
-                            .sync(folders, finished_callback())
+                            .sync(
+                                folders,
+                                Self::with_smart_playlist_refresh(
+                                    smart_playlist_manager.clone(),
+                                    finished_callback(),
+                                ),
+                            )
                             .await
                             .tap_err(|e| error!("Error syncing: {e:?}"))
                         {
-                            Self::send_progress(running.clone(), progress_tx_.clone(), rx);
+                            let state_rx = smart_playlist_manager.subscribe_sync_state().await;
+                            Self::send_progress(
+                                running.clone(),
+                                progress_tx_.clone(),
+                                rx,
+                                state_rx,
+                            );
                         }
 
                         paths.clear();
@@ -254,29 +297,61 @@ impl FileWatchManager {
         })
     }
 
+    fn with_smart_playlist_refresh(
+        manager: Manager,
+        finished_callback: Pin<Box<dyn Future<Output = ()> + Send>>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async move {
+            let _ = manager
+                .refresh_smart_playlists()
+                .await
+                .tap_err(|e| error!("Error refreshing smart playlists: {e:?}"));
+            finished_callback.await;
+        })
+    }
+
     fn send_progress(
         running: Arc<AtomicBool>,
         progress_tx: broadcast::Sender<Progress>,
         mut rx: ProgressStream,
+        mut state_rx: watch::Receiver<SyncState>,
     ) {
         tokio::spawn(async move {
             running.store(true, Ordering::SeqCst);
-            while let Some(m) = rx.next().await {
+            let mut percentage = 0.0;
+            loop {
+                tokio::select! {
+                    m = rx.next() => match m {
+                        Some(m) => {
+                            percentage = m
+                                .tap_err(|e| error!("Error getting progress: {e:?}"))
+                                .unwrap_or(0.0);
+                        }
+                        None => break,
+                    },
+                    // Let clients know when the sync is paused or resumed since no progress is
+                    // reported in the meantime
+                    Ok(()) = state_rx.changed() => {}
+                }
+                let state = *state_rx.borrow_and_update();
                 progress_tx
                     .send(Progress {
                         job: "sync".to_string(),
-                        percentage: m
-                            .tap_err(|e| error!("Error getting progress: {e:?}"))
-                            .unwrap_or(0.0),
+                        percentage,
                         finished: false,
+                        paused: state == SyncState::Paused,
+                        cancelled: state == SyncState::Cancelled,
                     })
                     .unwrap_or_default();
             }
+            let cancelled = *state_rx.borrow() == SyncState::Cancelled;
             progress_tx
                 .send(Progress {
                     job: "sync".to_string(),
-                    percentage: 1.0,
+                    percentage: if cancelled { percentage } else { 1.0 },
                     finished: true,
+                    paused: false,
+                    cancelled,
                 })
                 .unwrap_or_default();
             running.store(false, Ordering::SeqCst);
@@ -308,7 +383,14 @@ impl FileWatchManager {
 
     pub async fn start_sync_all(&self) -> Result<(), FileWatchError> {
         self.sync_tx
-            .send(SyncMessage::All)
+            .send(SyncMessage::All { full_rescan: false })
+            .await
+            .map_err(|e| FileWatchError::ThreadCommError(e.to_string()))
+    }
+
+    pub async fn start_full_rescan(&self) -> Result<(), FileWatchError> {
+        self.sync_tx
+            .send(SyncMessage::All { full_rescan: true })
             .await
             .map_err(|e| FileWatchError::ThreadCommError(e.to_string()))
     }
@@ -336,6 +418,28 @@ impl FileWatchManager {
         }
         Ok(())
     }
+
+    pub async fn remove_folders(
+        &self,
+        paths: Vec<&str>,
+        purge: bool,
+    ) -> Result<RemovedFolders, FileWatchError> {
+        let removed = self
+            .manager
+            .read()
+            .await
+            .remove_folders(paths, purge)
+            .await?;
+
+        let mut watcher = self.watcher.lock().await;
+        for path in &removed.folders {
+            // The folder may not have been watched if it didn't exist when it was added
+            let _ = watcher
+                .unwatch(Path::new(path))
+                .tap_err(|e| warn!("Error unwatching path {path}: {e:?}"));
+        }
+        Ok(removed)
+    }
 }
 
 #[cfg(test)]
@@ -13,7 +13,8 @@ use tokio::time::timeout;
 use super::{FileWatchManager, Progress};
 use crate::config::MemoryConfig;
 use crate::database::Database;
-use crate::manager::Manager;
+use crate::manager::{FolderRules, Manager};
+use crate::smart_playlist::smart_playlist_definition::SmartPlaylistDefinition;
 
 #[rstest]
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
@@ -279,6 +280,140 @@ async fn test_sync_all(sync_twice: bool) {
     }
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_sync_refreshes_smart_playlists() {
+    let (_tempdir, temp_path) = create_tempdir();
+    let (_, manager) = setup().await;
+
+    let music_dir = temp_path.join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let all_songs = manager
+        .create_smart_playlist(&SmartPlaylistDefinition::new("all"))
+        .await
+        .unwrap();
+    let limited = manager
+        .create_smart_playlist(&SmartPlaylistDefinition {
+            limit: Some(1),
+            ..SmartPlaylistDefinition::new("limited")
+        })
+        .await
+        .unwrap();
+
+    let (finished_tx, mut finished_rx) = mpsc::channel(10);
+    let file_watch_manager =
+        FileWatchManager::new(manager, Duration::from_millis(100), move || {
+            let finished_tx = finished_tx.clone();
+            Box::pin(async move {
+                finished_tx.send(()).await.unwrap_or_default();
+            })
+        })
+        .await
+        .unwrap();
+
+    let paths = vec![
+        music_dir.join("test.mp3"),
+        music_dir.join("test2.mp3"),
+        music_dir.join("test3.mp3"),
+    ];
+    fs::copy("../test_assets/test.mp3", &paths[0]).unwrap();
+    fs::copy("../test_assets/test2.mp3", &paths[1]).unwrap();
+    fs::copy("../test_assets/test3.mp3", &paths[2]).unwrap();
+
+    // The files may be picked up by more than one sync
+    let song_counts = timeout(Duration::from_secs(10), async {
+        loop {
+            finished_rx.recv().await.unwrap();
+            let manager = file_watch_manager.read().await;
+            let all_count = manager
+                .get_smart_playlist_songs(all_songs)
+                .await
+                .unwrap()
+                .len();
+            let limited_count = manager
+                .get_smart_playlist_songs(limited)
+                .await
+                .unwrap()
+                .len();
+            if all_count == paths.len() {
+                return (all_count, limited_count);
+            }
+        }
+    })
+    .await
+    .expect("smart playlists were not refreshed after the sync");
+
+    assert_eq!((paths.len(), 1), song_counts);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_file_sync_folder_rules() {
+    let (_tempdir, temp_path) = create_tempdir();
+    let (_, manager) = setup().await;
+
+    let music_dir = temp_path.join("configdir");
+    let podcast_dir = music_dir.join("Podcasts");
+    create_dir_all(&podcast_dir).unwrap();
+    let folder = music_dir.to_str().unwrap();
+    manager.add_folder(folder).await.unwrap();
+    manager
+        .set_folder_rules(
+            folder,
+            FolderRules {
+                include: vec![],
+                exclude: vec!["**/Podcasts/**".to_owned(), "*.sample.mp3".to_owned()],
+            },
+        )
+        .await
+        .unwrap();
+
+    let (finished_tx, mut finished_rx) = mpsc::channel(10);
+    let file_watch_manager =
+        FileWatchManager::new(manager, Duration::from_millis(100), move || {
+            let finished_tx = finished_tx.clone();
+            Box::pin(async move {
+                finished_tx.send(()).await.unwrap_or_default();
+            })
+        })
+        .await
+        .unwrap();
+
+    // Copy the excluded files first so their changes are seen before the sync finishes
+    let excluded_paths = vec![
+        podcast_dir.join("test2.mp3"),
+        music_dir.join("test3.sample.mp3"),
+    ];
+    fs::copy("../test_assets/test2.mp3", &excluded_paths[0]).unwrap();
+    fs::copy("../test_assets/test3.mp3", &excluded_paths[1]).unwrap();
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+
+    timeout(Duration::from_secs(10), async {
+        loop {
+            finished_rx.recv().await.unwrap();
+            let manager = file_watch_manager.read().await;
+            if manager
+                .get_song_by_path(&song_path)
+                .await
+                .unwrap()
+                .is_some()
+            {
+                return;
+            }
+        }
+    })
+    .await
+    .expect("included file was not synced");
+
+    let manager = file_watch_manager.read().await;
+    for path in excluded_paths {
+        assert!(manager.get_song_by_path(&path).await.unwrap().is_none());
+    }
+}
+
 #[rstest(paths, new_path, expected,
     case(vec![], "/test/path/1", vec!["/test/path/1"]),
     case(vec!["/test/path/1"], "/test/path/2", vec!["/test/path/1", "/test/path/2"]),
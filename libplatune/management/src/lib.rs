@@ -1,11 +1,19 @@
+pub mod browse;
 pub mod config;
 mod consts;
 pub mod database;
 pub mod db_error;
+pub mod duplicates;
 pub mod entry_type;
 pub mod file_watch_manager;
+pub mod lyrics;
 pub mod manager;
+pub mod mount;
 mod path_util;
+pub mod playlist_file;
 pub mod search;
+pub mod smart_playlist;
 mod sql_util;
 pub mod sync;
+#[cfg(test)]
+mod test_util;
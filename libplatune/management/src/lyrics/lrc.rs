@@ -0,0 +1,106 @@
+use itertools::Itertools;
+
+use super::song_lyrics::LyricLine;
+
+/// Parses lyrics in the LRC format. Lines without a timestamp are skipped.
+pub(crate) fn parse_lrc(content: &str) -> Vec<LyricLine> {
+    let mut offset = 0;
+    let mut lines = Vec::new();
+    for line in content.lines() {
+        let mut rest = line.trim();
+        let mut timestamps = Vec::new();
+        while let Some((tag, remaining)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            rest = remaining.trim_start();
+            match parse_timestamp(tag) {
+                Some(millis) => timestamps.push(millis),
+                None => {
+                    // A positive offset means the lyrics should be shown earlier
+                    if let Some(value) = tag.strip_prefix("offset:") {
+                        offset = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+        }
+        let text = strip_word_timestamps(rest);
+        lines.extend(timestamps.into_iter().map(|start_millis| LyricLine {
+            start_millis,
+            text: text.clone(),
+        }));
+    }
+
+    for line in &mut lines {
+        line.start_millis = (line.start_millis - offset).max(0);
+    }
+    // Lines with multiple timestamps are listed once, so they need to be put in order
+    lines.sort_by_key(|line| line.start_millis);
+    lines
+}
+
+pub(crate) fn is_lrc(content: &str) -> bool {
+    !parse_lrc(content).is_empty()
+}
+
+pub(crate) fn to_lrc(lines: &[LyricLine]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            let millis = line.start_millis.max(0);
+            format!(
+                "[{:02}:{:02}.{:03}]{}",
+                millis / 60000,
+                millis / 1000 % 60,
+                millis % 1000,
+                line.text
+            )
+        })
+        .join("\n")
+}
+
+fn parse_timestamp(tag: &str) -> Option<i64> {
+    let (minutes, rest) = tag.split_once(':')?;
+    // Some files use a colon instead of a period before the fraction
+    let (seconds, fraction) = rest
+        .split_once(['.', ':'])
+        .map(|(s, f)| (s, Some(f)))
+        .unwrap_or((rest, None));
+    let minutes = parse_digits(minutes)?;
+    let seconds = parse_digits(seconds)?;
+    let fraction_millis = match fraction {
+        Some(fraction) if !fraction.is_empty() => {
+            parse_digits(fraction)?;
+            // Pad or truncate to three digits, e.g. "5" is 500 ms and "25" is 250 ms
+            let digits = format!("{fraction:0<3}");
+            digits[..3].parse().ok()?
+        }
+        _ => 0,
+    };
+    Some((minutes * 60 + seconds) * 1000 + fraction_millis)
+}
+
+fn parse_digits(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn strip_word_timestamps(text: &str) -> String {
+    // Enhanced LRC files include per-word timestamps like <00:12.34>
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start + 1..].split_once('>') {
+            Some((tag, remaining)) if parse_timestamp(tag).is_some() => {
+                result.push_str(&rest[..start]);
+                rest = remaining;
+            }
+            _ => {
+                result.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result.split_whitespace().join(" ")
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+
+use lofty::config::WriteOptions;
+use lofty::file::TaggedFileExt;
+use lofty::probe::Probe;
+use lofty::tag::{ItemKey, TagExt};
+use pretty_assertions::assert_eq;
+use rstest::*;
+use tempfile::TempDir;
+
+use super::lrc::{parse_lrc, to_lrc};
+use super::song_lyrics::{LyricLine, Lyrics};
+use crate::manager::Manager;
+use crate::test_util::{copy_files, music_dir, sync, sync_library};
+
+fn line(start_millis: i64, text: &str) -> LyricLine {
+    LyricLine {
+        start_millis,
+        text: text.to_owned(),
+    }
+}
+
+#[rstest(
+    content,
+    expected,
+    case("[00:01.50]first\n[00:03.00]second", vec![line(1500, "first"), line(3000, "second")]),
+    case(
+        "[ar:artist]\n[ti:title]\n\n[01:02.345] words ",
+        vec![line(62345, "words")]
+    ),
+    case(
+        "[00:05.00][00:01.00]chorus\n[00:03:20]verse",
+        vec![line(1000, "chorus"), line(3200, "verse"), line(5000, "chorus")]
+    ),
+    case(
+        "[offset:+500]\n[00:01.00]early\n[00:00.20]clamped",
+        vec![line(0, "clamped"), line(500, "early")]
+    ),
+    case(
+        "[00:01.00]<00:01.00>word <00:01.50>by <00:02.00>word",
+        vec![line(1000, "word by word")]
+    ),
+    case("[00:02]", vec![line(2000, "")]),
+    case("plain lyrics\nwithout timestamps", vec![])
+)]
+fn test_parse_lrc(content: &str, expected: Vec<LyricLine>) {
+    assert_eq!(expected, parse_lrc(content));
+}
+
+#[test]
+fn test_lrc_round_trip() {
+    let lines = vec![line(0, "first"), line(61005, "second"), line(3600000, "")];
+    assert_eq!(
+        "[00:00.000]first\n[01:01.005]second\n[60:00.000]",
+        to_lrc(&lines)
+    );
+    assert_eq!(lines, parse_lrc(&to_lrc(&lines)));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_lyrics() {
+    let tempdir = TempDir::new().unwrap();
+    let music_dir = music_dir(tempdir.path());
+    let [plain_path, embedded_lrc_path, sidecar_path, no_lyrics_path] = copy_files(
+        tempdir.path(),
+        &[
+            ("plain.mp3", "test.mp3"),
+            ("embedded_lrc.mp3", "test2.mp3"),
+            ("sidecar.mp3", "test3.mp3"),
+            ("none.mp3", "test_44100.mp3"),
+        ],
+    )
+    .try_into()
+    .unwrap();
+
+    // Embedded plain lyrics
+    set_lyrics_tag(&plain_path, "embedded\nlyrics");
+
+    // Embedded LRC text
+    set_lyrics_tag(&embedded_lrc_path, "[00:01.00]embedded line");
+
+    // Sidecar file overrides the embedded lyrics
+    set_lyrics_tag(&sidecar_path, "[00:01.00]old line");
+    fs::write(
+        music_dir.join("sidecar.lrc"),
+        "\u{FEFF}[ti:sidecar]\n[00:00.50]one\n[00:02.25]two\n",
+    )
+    .unwrap();
+
+    let mut manager = sync_library(tempdir.path()).await;
+
+    assert_eq!(
+        Lyrics {
+            plain: Some("embedded\nlyrics".to_owned()),
+            synced: vec![],
+        },
+        get_lyrics(&manager, &plain_path).await
+    );
+    assert_eq!(
+        Lyrics {
+            plain: None,
+            synced: vec![line(1000, "embedded line")],
+        },
+        get_lyrics(&manager, &embedded_lrc_path).await
+    );
+    assert_eq!(
+        Lyrics {
+            plain: None,
+            synced: vec![line(500, "one"), line(2250, "two")],
+        },
+        get_lyrics(&manager, &sidecar_path).await
+    );
+    assert_eq!(
+        Lyrics::default(),
+        get_lyrics(&manager, &no_lyrics_path).await
+    );
+
+    // Changing the sidecar file should update the lyrics on the next sync
+    fs::write(music_dir.join("sidecar.lrc"), "[00:03.00]three").unwrap();
+    sync(&mut manager).await;
+    assert_eq!(
+        vec![line(3000, "three")],
+        get_lyrics(&manager, &sidecar_path).await.synced
+    );
+
+    assert_eq!(None, manager.get_lyrics(-1).await.unwrap());
+}
+
+fn set_lyrics_tag(path: &Path, lyrics: &str) {
+    let mut track = Probe::open(path).unwrap().read().unwrap();
+    let tag = track.primary_tag_mut().unwrap();
+    tag.insert_text(ItemKey::Lyrics, lyrics.to_owned());
+    tag.save_to_path(path, WriteOptions::new()).unwrap();
+}
+
+async fn get_lyrics(manager: &Manager, path: &Path) -> Lyrics {
+    let song = manager.get_song_by_path(path).await.unwrap().unwrap();
+    manager.get_lyrics(song.song_id).await.unwrap().unwrap()
+}
@@ -0,0 +1,7 @@
+pub(crate) mod lrc;
+pub(crate) mod reader;
+pub mod song_lyrics;
+
+#[cfg(test)]
+#[path = "./lyrics_test.rs"]
+mod lyrics_test;
@@ -0,0 +1,104 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use lofty::config::ParseOptions;
+use lofty::file::AudioFile;
+use lofty::id3::v2::{Frame, SynchronizedTextFrame, TimestampFormat};
+use lofty::mpeg::MpegFile;
+use tap::TapFallible;
+use tracing::warn;
+
+use super::lrc::{is_lrc, to_lrc};
+use super::song_lyrics::LyricLine;
+
+const LRC_EXT: &str = "lrc";
+
+/// Lyrics found for a file. Synced lyrics are stored in the LRC format.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct FileLyrics {
+    pub(crate) plain: Option<String>,
+    pub(crate) synced: Option<String>,
+}
+
+/// Collects the lyrics for a file from the embedded lyrics tag, synchronized lyrics frames, and a
+/// sidecar .lrc file with the same name. The sidecar file takes precedence since it's usually
+/// the most recent.
+pub(crate) fn read_lyrics(file_path: &Path, embedded: Option<String>) -> FileLyrics {
+    let mut lyrics = FileLyrics::default();
+    // Some taggers store LRC formatted text in the regular lyrics tag
+    match embedded {
+        Some(embedded) if is_lrc(&embedded) => lyrics.synced = Some(embedded),
+        embedded => lyrics.plain = embedded,
+    }
+    if let Some(synced) = read_synced_frame(file_path) {
+        lyrics.synced = Some(synced);
+    }
+
+    let sidecar_path = sidecar_path(file_path);
+    if sidecar_path.is_file()
+        && let Ok(content) = fs::read(&sidecar_path)
+            .tap_err(|e| warn!("Error reading lyrics file {sidecar_path:?}: {e:?}"))
+    {
+        let content = decode(&content);
+        let content = content.trim();
+        if is_lrc(content) {
+            lyrics.synced = Some(content.to_owned());
+        } else if !content.is_empty() {
+            lyrics.plain = Some(content.to_owned());
+        }
+    }
+    lyrics
+}
+
+pub(crate) fn sidecar_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension(LRC_EXT)
+}
+
+fn read_synced_frame(file_path: &Path) -> Option<String> {
+    // Synchronized lyrics frames only exist in ID3v2 tags
+    if !file_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"))
+    {
+        return None;
+    }
+    let mut file = File::open(file_path).ok()?;
+    let mpeg_file = MpegFile::read_from(&mut file, ParseOptions::new())
+        .tap_err(|e| warn!("Error reading ID3v2 tag from {file_path:?}: {e:?}"))
+        .ok()?;
+    mpeg_file.id3v2()?.into_iter().find_map(|frame| {
+        let Frame::Binary(binary) = frame else {
+            return None;
+        };
+        if frame.id_str() != "SYLT" {
+            return None;
+        }
+        let sync_text = SynchronizedTextFrame::parse(&binary.data, frame.flags())
+            .tap_err(|e| warn!("Error parsing synchronized lyrics in {file_path:?}: {e:?}"))
+            .ok()?;
+        // MPEG frame timestamps would require decoding the file to convert
+        if sync_text.timestamp_format != TimestampFormat::MS {
+            return None;
+        }
+        let lines = sync_text
+            .content
+            .into_iter()
+            .map(|(start_millis, text)| LyricLine {
+                start_millis: start_millis as i64,
+                text: text.trim().to_owned(),
+            })
+            .sorted_by_key(|line| line.start_millis)
+            .collect_vec();
+        (!lines.is_empty()).then(|| to_lrc(&lines))
+    })
+}
+
+fn decode(content: &[u8]) -> String {
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    match std::str::from_utf8(content) {
+        Ok(content) => content.to_owned(),
+        // Older lyrics files are commonly Latin-1
+        Err(_) => content.iter().map(|&b| b as char).collect(),
+    }
+}
@@ -0,0 +1,13 @@
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lyrics {
+    /// Unsynchronized lyrics
+    pub plain: Option<String>,
+    /// Timed lines ordered by start time
+    pub synced: Vec<LyricLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LyricLine {
+    pub start_millis: i64,
+    pub text: String,
+}
@@ -1,23 +1,64 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 
+use itertools::Itertools;
 use normpath::PathExt;
+use tap::TapFallible;
 use thiserror::Error;
+use tokio::sync::watch;
+use tracing::warn;
 
+pub use crate::browse::browse_options::{BrowseFilter, BrowseOptions, BrowseSort};
+pub use crate::browse::browse_result::{
+    AlbumSummary, ArtistSummary, BrowsePage, GenreSummary, YearSummary,
+};
 use crate::config::Config;
-use crate::database::{Database, DeletedEntry, LookupEntry};
-use crate::db_error::DbError;
+use crate::consts::UNSUPPORTED_FILE_EXTS;
+use crate::database::{
+    Database, DeletedEntry, HistoryEntry, LookupEntry, PlayStat, RemovedFolders, SongPlayCount,
+};
+pub use crate::db_error::DbError;
+pub use crate::duplicates::duplicate_group::{DuplicateEntry, DuplicateGroup};
 pub use crate::entry_type::EntryType;
+pub use crate::lyrics::song_lyrics::{LyricLine, Lyrics};
+pub use crate::mount::{DEFAULT_MOUNT, Mount};
+use crate::mount::{MountPaths, folders_overlap, strip_prefix_ignore_case};
 use crate::path_util::{PathMut, clean_file_path, update_path};
+use crate::playlist_file::parser::parse_playlist;
+pub use crate::playlist_file::playlist_file_error::PlaylistFileError;
+pub use crate::playlist_file::playlist_format::PlaylistFormat;
+pub use crate::playlist_file::playlist_import::PlaylistImport;
+use crate::playlist_file::uri::{file_uri_to_path, is_absolute_path, is_remote_uri};
+use crate::playlist_file::writer::{PlaylistFileEntry, write_playlist};
+pub use crate::search::search_error::{QueryError, SearchError};
 pub use crate::search::search_options::SearchOptions;
 pub use crate::search::search_result::SearchResult;
+pub use crate::smart_playlist::smart_playlist_definition::{
+    RuleField, RuleOperator, SmartPlaylist, SmartPlaylistDefinition, SmartPlaylistRule,
+    SmartPlaylistSort,
+};
+pub use crate::smart_playlist::smart_playlist_error::SmartPlaylistError;
+pub use crate::sync::artist_credit::{ArtistRole, SongArtist};
+use crate::sync::file_filter::FileFilter;
+pub use crate::sync::file_filter::FolderRules;
 use crate::sync::progress_stream::ProgressStream;
+pub use crate::sync::sync_report::{SyncFileStatus, SyncReport, SyncReportFile};
+pub use crate::sync::sync_state::SyncState;
 
 #[derive(Error, Debug)]
 pub enum ManagerError {
     #[error("{0} is not a valid path")]
     InvalidPath(PathBuf),
+    #[error("{0} is not a registered folder")]
+    FolderNotFound(String),
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("{0} is already registered under a different mount")]
+    MountConflict(String),
+    #[error("{0} files are not supported")]
+    UnsupportedExtension(String),
     #[error("Error writing file: {0}")]
     WriteError(String),
     #[error(transparent)]
@@ -48,7 +89,14 @@ impl Manager {
         }
     }
 
-    pub async fn register_drive<P: AsRef<Path>>(&self, path: P) -> Result<(), ManagerError> {
+    /// Registers the root path of a mount on this machine. Folders under the path are stored
+    /// relative to it, so other machines that share the database can register the same mount
+    /// at a different path. An empty name registers the default mount.
+    pub async fn register_mount<P: AsRef<Path>>(
+        &self,
+        name: &str,
+        path: P,
+    ) -> Result<(), ManagerError> {
         let path = path.as_ref();
         if !path.exists() {
             return Err(ManagerError::InvalidPath(path.to_owned()));
@@ -57,41 +105,50 @@ impl Manager {
         let path = self
             .clean_path(&*path.to_string_lossy())
             .map_err(|_| ManagerError::InvalidPath(path.to_path_buf()))?;
+        let name = match name.trim() {
+            "" => DEFAULT_MOUNT,
+            name => name,
+        };
 
-        match self.config.get_drive_id() {
-            Some(drive_id) => {
+        let mounts = self
+            .get_registered_mounts()
+            .await
+            .map_err(ManagerError::DbError)?;
+        match mounts.into_iter().find(|m| m.name == name) {
+            Some(mount) => {
                 let rows = self
                     .db
-                    .update_mount(drive_id, &path)
+                    .update_mount(mount.mount_id, &path)
                     .await
                     .map_err(ManagerError::DbError)?;
                 if rows == 0 {
-                    self.set_drive(&path).await?;
+                    self.add_mount(name, &path).await?;
                 }
             }
             None => {
-                self.set_drive(&path).await?;
+                self.add_mount(name, &path).await?;
             }
         };
 
         let folders = self
             .db
-            .get_all_folders()
+            .get_folder_mounts()
             .await
             .map_err(ManagerError::DbError)?;
 
-        for folder in folders {
-            let new_folder = folder.replacen(&path, "", 1);
+        for (folder, _) in folders {
+            // Relative folders already belong to a mount
+            let Some(new_folder) = strip_prefix_ignore_case(&folder, &path) else {
+                continue;
+            };
             self.db
-                .update_folder(&folder, &new_folder)
+                .update_folder(&folder, new_folder, name)
                 .await
                 .map_err(ManagerError::DbError)?;
 
-            let mut folder = folder.clone();
-            self.update_path(&mut folder).await;
             // Update song paths to remove drive prefix
             self.db
-                .rename_path(&folder, &new_folder)
+                .rename_path(&folder, new_folder)
                 .await
                 .map_err(ManagerError::DbError)?;
         }
@@ -99,71 +156,273 @@ impl Manager {
         Ok(())
     }
 
+    /// Returns the mounts registered on this machine
+    pub async fn get_registered_mounts(&self) -> Result<Vec<Mount>, DbError> {
+        let mount_ids = self.config.get_mount_ids();
+        Ok(self
+            .db
+            .get_mounts()
+            .await?
+            .into_iter()
+            .filter(|m| mount_ids.contains(&m.mount_id))
+            .collect())
+    }
+
+    /// Returns the path of the default mount
+    pub async fn get_registered_mount(&self) -> Option<String> {
+        self.get_mount_paths()
+            .await
+            .default_root()
+            .map(|root| root.to_owned())
+    }
+
     pub async fn add_folder(&self, path: &str) -> Result<(), ManagerError> {
         self.add_folders(vec![path]).await
     }
 
     pub async fn add_folders(&self, paths: Vec<&str>) -> Result<(), ManagerError> {
-        let new_paths = self.replace_prefix(paths).await?;
+        let mount_paths = self.get_mount_paths().await;
+        let existing = self
+            .db
+            .get_folder_mounts()
+            .await
+            .map_err(ManagerError::DbError)?;
+
+        let mut new_folders = Vec::new();
+        for path in paths {
+            let (folder, mount_name) = mount_paths.split(&self.clean_path(path)?);
+            // Song paths are stored relative to their mount, so the same relative folder can't
+            // belong to two different mounts. Nested folders would also be ambiguous since songs
+            // are resolved using the most specific folder.
+            let mount = mount_name.as_deref().unwrap_or(DEFAULT_MOUNT);
+            let conflict = existing
+                .iter()
+                .chain(&new_folders)
+                .any(|(other, other_mount)| {
+                    folders_overlap(other, &folder)
+                        && other_mount.as_deref().unwrap_or(DEFAULT_MOUNT) != mount
+                });
+            if conflict {
+                return Err(ManagerError::MountConflict(path.to_owned()));
+            }
+            new_folders.push((folder, mount_name));
+        }
+
         self.db
-            .add_folders(new_paths)
+            .add_folders(new_folders)
             .await
             .map_err(ManagerError::DbError)
     }
 
+    /// Unregisters the folders and removes their songs from the library. Songs are marked as
+    /// deleted unless `purge` is set, in which case they're removed along with any albums and
+    /// artists that are left empty.
+    pub async fn remove_folders(
+        &self,
+        paths: Vec<&str>,
+        purge: bool,
+    ) -> Result<RemovedFolders, ManagerError> {
+        let paths = self.replace_folder_prefix(paths).await;
+        // The mounts need to be resolved before the folders are removed
+        let mount_paths = self.get_mount_paths().await;
+        let mut removed = self
+            .db
+            .remove_folders(paths, purge)
+            .await
+            .map_err(ManagerError::DbError)?;
+        removed.folders = self.expand_with(&mount_paths, removed.folders);
+        Self::update_paths_with(&mount_paths, &mut removed.songs);
+        Ok(removed)
+    }
+
     pub async fn get_all_folders(&self) -> Result<Vec<String>, DbError> {
         let folders = self.db.get_all_folders().await?;
         Ok(self.expand_paths(folders).await)
     }
 
+    pub async fn get_folder_rules(&self, path: &str) -> Result<FolderRules, ManagerError> {
+        let folder = self.replace_folder_prefix(vec![path]).await.remove(0);
+        let folders = self
+            .db
+            .get_all_folders()
+            .await
+            .map_err(ManagerError::DbError)?;
+        if !folders.iter().any(|f| f.eq_ignore_ascii_case(&folder)) {
+            return Err(ManagerError::FolderNotFound(path.to_owned()));
+        }
+        let rules = self
+            .db
+            .get_folder_rules()
+            .await
+            .map_err(ManagerError::DbError)?
+            .into_iter()
+            .find(|(f, _)| f.eq_ignore_ascii_case(&folder))
+            .map(|(_, rules)| rules)
+            .unwrap_or_default();
+        Ok(rules)
+    }
+
+    /// Replaces the include and exclude patterns for the folder. The rules are applied on the
+    /// next sync.
+    pub async fn set_folder_rules(
+        &self,
+        path: &str,
+        rules: FolderRules,
+    ) -> Result<(), ManagerError> {
+        rules
+            .build(Path::new(path))
+            .map_err(|e| ManagerError::InvalidPattern(e.to_string()))?;
+        let folder = self.replace_folder_prefix(vec![path]).await.remove(0);
+        let found = self
+            .db
+            .set_folder_rules(&folder, &rules)
+            .await
+            .map_err(ManagerError::DbError)?;
+        if !found {
+            return Err(ManagerError::FolderNotFound(path.to_owned()));
+        }
+        Ok(())
+    }
+
+    pub async fn get_file_extensions(&self) -> Result<Vec<String>, DbError> {
+        self.db.get_file_extensions().await
+    }
+
+    /// Replaces the file extensions that are included in the sync. Passing an empty list restores
+    /// the defaults. Formats that can't be played, such as wma and dsf, are rejected.
+    pub async fn set_file_extensions(&self, extensions: Vec<&str>) -> Result<(), ManagerError> {
+        let extensions: Vec<_> = extensions
+            .into_iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        if let Some(ext) = extensions
+            .iter()
+            .find(|ext| UNSUPPORTED_FILE_EXTS.contains(&ext.as_str()))
+        {
+            return Err(ManagerError::UnsupportedExtension(ext.clone()));
+        }
+        self.db
+            .set_file_extensions(extensions)
+            .await
+            .map_err(ManagerError::DbError)
+    }
+
+    pub(crate) async fn get_file_filter(&self) -> Result<FileFilter, DbError> {
+        let extensions = self.db.get_file_extensions().await?;
+        let mut folders = Vec::new();
+        for (folder, rules) in self.db.get_folder_rules().await? {
+            let folder = self.expand_paths(vec![folder]).await.remove(0);
+            folders.push((folder, rules));
+        }
+        Ok(FileFilter::new(extensions, folders))
+    }
+
     pub async fn sync(
         &mut self,
         paths: Option<Vec<String>>,
         finished_callback: Pin<Box<dyn Future<Output = ()> + Send>>,
+    ) -> Result<ProgressStream, DbError> {
+        self.start_sync(paths, false, finished_callback).await
+    }
+
+    /// Syncs without skipping files that haven't changed since the last scan
+    pub async fn full_rescan(
+        &mut self,
+        paths: Option<Vec<String>>,
+        finished_callback: Pin<Box<dyn Future<Output = ()> + Send>>,
+    ) -> Result<ProgressStream, DbError> {
+        self.start_sync(paths, true, finished_callback).await
+    }
+
+    /// Pauses the current sync and commits the changes made so far. Returns `false` if there's
+    /// no running sync.
+    pub async fn pause_sync(&self) -> bool {
+        self.db.pause_sync().await
+    }
+
+    /// Returns `false` if there's no paused sync
+    pub async fn resume_sync(&self) -> bool {
+        self.db.resume_sync().await
+    }
+
+    /// Stops the current sync after committing the changes made so far. Returns `false` if
+    /// there's no sync in progress.
+    pub async fn cancel_sync(&self) -> bool {
+        self.db.cancel_sync().await
+    }
+
+    pub async fn subscribe_sync_state(&self) -> watch::Receiver<SyncState> {
+        self.db.subscribe_sync_state().await
+    }
+
+    async fn start_sync(
+        &mut self,
+        paths: Option<Vec<String>>,
+        full_rescan: bool,
+        finished_callback: Pin<Box<dyn Future<Output = ()> + Send>>,
     ) -> Result<ProgressStream, DbError> {
         let folders = match paths {
             Some(paths) => paths,
             None => self.get_all_folders().await?,
         };
 
-        let mount = self.get_registered_mount().await;
-        Ok(self.db.sync(folders, mount, finished_callback).await)
+        let filter = self.get_file_filter().await?;
+        let mounts = self.get_mount_paths().await.roots();
+        Ok(self
+            .db
+            .sync(folders, mounts, full_rescan, filter, finished_callback)
+            .await)
     }
 
-    async fn replace_prefix(&self, paths: Vec<&str>) -> Result<Vec<String>, ManagerError> {
-        let paths: Result<Vec<String>, ManagerError> = paths
+    /// Strips the mount from the paths of library folders. Folders that were deleted from the
+    /// disk can't be normalized but should still be manageable.
+    async fn replace_folder_prefix(&self, paths: Vec<&str>) -> Vec<String> {
+        let paths = paths
             .into_iter()
-            .map(|new_path| self.clean_path(new_path))
+            .map(|path| {
+                self.clean_path(path)
+                    .unwrap_or_else(|_| self.format_path(path.to_owned()))
+            })
             .collect();
-        let paths = paths?;
+        self.strip_mount(paths).await
+    }
 
-        Ok(match self.get_registered_mount().await {
-            Some(mount) => paths
-                .iter()
-                .map(|path| match path.find(&mount[..]) {
-                    Some(0) => path.replacen(&mount[..], "", 1),
-                    _ => path.to_owned(),
-                })
-                .collect::<Vec<_>>(),
-            None => paths,
-        })
+    async fn strip_mount(&self, paths: Vec<String>) -> Vec<String> {
+        let mount_paths = self.get_mount_paths().await;
+        paths.iter().map(|path| mount_paths.strip(path)).collect()
     }
 
     pub async fn expand_paths(&self, folders: Vec<String>) -> Vec<String> {
-        let res = match self.get_registered_mount().await {
-            Some(mount) => folders.into_iter().map(|f| format!("{mount}{f}")).collect(),
-            None => folders,
-        };
-        res.into_iter()
+        let mount_paths = self.get_mount_paths().await;
+        self.expand_with(&mount_paths, folders)
+    }
+
+    fn expand_with(&self, mount_paths: &MountPaths, folders: Vec<String>) -> Vec<String> {
+        folders
+            .into_iter()
+            .map(|f| match mount_paths.root(&f) {
+                Some(root) => format!("{root}{f}"),
+                None => f,
+            })
             .map(|r| r.replace('/', self.delim))
             .collect()
     }
 
-    pub async fn get_registered_mount(&self) -> Option<String> {
-        match self.config.get_drive_id() {
-            Some(drive_id) => self.db.get_mount(drive_id).await,
-            None => None,
-        }
+    async fn get_mount_paths(&self) -> MountPaths {
+        // Paths are left as they are if the mounts can't be loaded
+        let mounts = self
+            .get_registered_mounts()
+            .await
+            .tap_err(|e| warn!("Error loading mounts: {e:?}"))
+            .unwrap_or_default();
+        let folders = self
+            .db
+            .get_folder_mounts()
+            .await
+            .tap_err(|e| warn!("Error loading folders: {e:?}"))
+            .unwrap_or_default();
+        MountPaths::new(mounts, folders)
     }
 
     pub async fn lookup(
@@ -181,9 +440,9 @@ impl Manager {
     where
         P: AsRef<Path>,
     {
-        let mount = self.get_registered_mount().await;
-        let from = clean_file_path(&from, &mount).map_err(|e| DbError::DbError(e.to_string()))?;
-        let to = clean_file_path(&to, &mount).map_err(|e| DbError::DbError(e.to_string()))?;
+        let mounts = self.get_mount_paths().await.roots();
+        let from = clean_file_path(&from, &mounts).map_err(|e| DbError::DbError(e.to_string()))?;
+        let to = clean_file_path(&to, &mounts).map_err(|e| DbError::DbError(e.to_string()))?;
         self.db.rename_path(&from, &to).await?;
         Ok(())
     }
@@ -192,8 +451,8 @@ impl Manager {
     where
         P: AsRef<Path>,
     {
-        let mount = self.get_registered_mount().await;
-        let path = clean_file_path(&path, &mount).map_err(|e| DbError::DbError(e.to_string()))?;
+        let mounts = self.get_mount_paths().await.roots();
+        let path = clean_file_path(&path, &mounts).map_err(|e| DbError::DbError(e.to_string()))?;
 
         let res = self.db.get_song_by_path(path).await?;
         match res {
@@ -209,19 +468,25 @@ impl Manager {
     where
         T: PathMut,
     {
-        if let Some(mount) = self.get_registered_mount().await {
-            for entry in paths.iter_mut() {
-                update_path(entry, &mount);
-            }
-        }
+        let mount_paths = self.get_mount_paths().await;
+        Self::update_paths_with(&mount_paths, paths);
     }
 
     async fn update_path<T>(&self, path: &mut T)
     where
         T: PathMut,
     {
-        if let Some(mount) = self.get_registered_mount().await {
-            update_path(path, &mount);
+        self.update_paths(std::slice::from_mut(path)).await;
+    }
+
+    fn update_paths_with<T>(mount_paths: &MountPaths, paths: &mut [T])
+    where
+        T: PathMut,
+    {
+        for entry in paths.iter_mut() {
+            if let Some(root) = mount_paths.root(&entry.get_path()) {
+                update_path(entry, &root);
+            }
         }
     }
 
@@ -229,7 +494,7 @@ impl Manager {
         &self,
         query: &str,
         options: SearchOptions<'_>,
-    ) -> Result<Vec<SearchResult>, DbError> {
+    ) -> Result<Vec<SearchResult>, SearchError> {
         self.db.search(query, options).await
     }
 
@@ -243,6 +508,424 @@ impl Manager {
         self.db.delete_tracks(ids).await
     }
 
+    pub async fn create_playlist(&self, name: &str) -> Result<i64, DbError> {
+        self.db.create_playlist(name).await
+    }
+
+    pub async fn rename_playlist(&self, playlist_id: i64, name: &str) -> Result<(), DbError> {
+        self.db.rename_playlist(playlist_id, name).await
+    }
+
+    pub async fn delete_playlists(&self, playlist_ids: Vec<i64>) -> Result<(), DbError> {
+        self.db.delete_playlists(playlist_ids).await
+    }
+
+    pub async fn append_to_playlist(
+        &self,
+        playlist_id: i64,
+        song_ids: Vec<i64>,
+    ) -> Result<(), DbError> {
+        self.db.append_to_playlist(playlist_id, song_ids).await
+    }
+
+    pub async fn reorder_playlist(
+        &self,
+        playlist_id: i64,
+        from_position: i64,
+        to_position: i64,
+    ) -> Result<(), DbError> {
+        self.db
+            .reorder_playlist(playlist_id, from_position, to_position)
+            .await
+    }
+
+    pub async fn remove_from_playlist(
+        &self,
+        playlist_id: i64,
+        positions: Vec<i64>,
+    ) -> Result<(), DbError> {
+        self.db.remove_from_playlist(playlist_id, positions).await
+    }
+
+    pub async fn get_playlist_songs(&self, playlist_id: i64) -> Result<Vec<LookupEntry>, DbError> {
+        let mut songs = self.db.get_playlist_songs(playlist_id).await?;
+        self.update_paths(&mut songs).await;
+        Ok(songs)
+    }
+
+    /// Imports a playlist file into a new playlist. Entries are matched against songs in the
+    /// library and any that can't be found are reported back instead of failing the import.
+    pub async fn import_playlist(
+        &self,
+        path: impl AsRef<Path>,
+        name: Option<&str>,
+    ) -> Result<PlaylistImport, PlaylistFileError> {
+        let path = path.as_ref();
+        let format = PlaylistFormat::from_path(path)
+            .ok_or_else(|| PlaylistFileError::UnsupportedFile(path.to_path_buf()))?;
+        let content = tokio::fs::read(path)
+            .await
+            .map_err(|e| PlaylistFileError::ReadError(format!("{path:?}: {e:?}")))?;
+        let parsed = parse_playlist(&content, format)?;
+
+        let name = name
+            .map(|n| n.to_owned())
+            .or(parsed.name)
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| {
+                path.file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+        let name = self.unique_playlist_name(&name).await?;
+
+        let playlist_dir = path.parent().unwrap_or(Path::new(""));
+        let folders = self.get_all_folders().await?;
+        let mut song_ids = vec![];
+        let mut unresolved = vec![];
+        for entry in parsed.entries {
+            match self
+                .resolve_playlist_entry(&entry, playlist_dir, &folders)
+                .await?
+            {
+                Some(song_id) => song_ids.push(song_id),
+                None => unresolved.push(entry),
+            }
+        }
+
+        let playlist_id = self.db.create_playlist(&name).await?;
+        let imported_count = song_ids.len();
+        if !song_ids.is_empty() {
+            self.db.append_to_playlist(playlist_id, song_ids).await?;
+        }
+
+        Ok(PlaylistImport {
+            playlist_id,
+            playlist_name: name,
+            imported_count,
+            unresolved,
+        })
+    }
+
+    /// Writes the playlist's songs in the given format.
+    /// Paths are made relative to `relative_to` if they're inside of it.
+    pub async fn export_playlist(
+        &self,
+        playlist_id: i64,
+        format: PlaylistFormat,
+        relative_to: Option<&Path>,
+    ) -> Result<String, PlaylistFileError> {
+        let playlist = self
+            .db
+            .get_all_playlists()
+            .await?
+            .into_iter()
+            .find(|p| p.playlist_id == playlist_id)
+            .ok_or(PlaylistFileError::NotFound(playlist_id))?;
+        let songs = self.get_playlist_songs(playlist_id).await?;
+        let entries = songs.into_iter().map(playlist_file_entry).collect_vec();
+
+        export_entries(&playlist.playlist_name, entries, format, relative_to)
+    }
+
+    /// Writes a selection of songs from the library in the given format
+    pub async fn export_songs(
+        &self,
+        name: &str,
+        song_ids: Vec<i64>,
+        format: PlaylistFormat,
+        relative_to: Option<&Path>,
+    ) -> Result<String, PlaylistFileError> {
+        let mut entries = vec![];
+        for song_id in song_ids {
+            if let Some(song) = self
+                .lookup(vec![song_id], EntryType::Song)
+                .await?
+                .into_iter()
+                .next()
+            {
+                entries.push(playlist_file_entry(song));
+            }
+        }
+
+        export_entries(name, entries, format, relative_to)
+    }
+
+    /// Writes a list of paths, such as a play queue, in the given format.
+    /// Paths that aren't in the library are written without any metadata.
+    pub async fn export_paths(
+        &self,
+        name: &str,
+        paths: Vec<String>,
+        format: PlaylistFormat,
+        relative_to: Option<&Path>,
+    ) -> Result<String, PlaylistFileError> {
+        let mut entries = vec![];
+        for path in paths {
+            // Queues from local clients store paths as file URIs
+            let path = file_uri_to_path(&path).unwrap_or(path);
+            let song = if is_remote_uri(&path) {
+                None
+            } else {
+                self.get_song_by_path(&path).await?
+            };
+            entries.push(match song {
+                Some(song) => playlist_file_entry(song),
+                None => PlaylistFileEntry {
+                    path,
+                    title: None,
+                    artist: None,
+                    album: None,
+                    duration_millis: None,
+                },
+            });
+        }
+
+        export_entries(name, entries, format, relative_to)
+    }
+
+    async fn resolve_playlist_entry(
+        &self,
+        entry: &str,
+        playlist_dir: &Path,
+        folders: &[String],
+    ) -> Result<Option<i64>, DbError> {
+        if is_remote_uri(entry) {
+            return Ok(None);
+        }
+        let mut path = file_uri_to_path(entry).unwrap_or_else(|| entry.to_owned());
+        if !cfg!(windows) {
+            path = path.replace('\\', "/");
+        }
+
+        if !is_absolute_path(&path) {
+            let bases = std::iter::once(playlist_dir).chain(folders.iter().map(Path::new));
+            for base in bases {
+                if let Some(song) = self.get_song_by_path(base.join(&path)).await? {
+                    return Ok(Some(song.song_id));
+                }
+            }
+            return Ok(None);
+        }
+
+        if let Some(song) = self.get_song_by_path(&path).await? {
+            return Ok(Some(song.song_id));
+        }
+        // The playlist may have been created on another machine or before the library was
+        // moved, so try to find the longest trailing part of the path in one of the folders
+        let components = path.split('/').filter(|c| !c.is_empty()).collect_vec();
+        for start in 1..components.len() {
+            let relative_path = components[start..].join("/");
+            for folder in folders {
+                if let Some(song) = self
+                    .get_song_by_path(Path::new(folder).join(&relative_path))
+                    .await?
+                {
+                    return Ok(Some(song.song_id));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn unique_playlist_name(&self, name: &str) -> Result<String, DbError> {
+        let existing = self
+            .db
+            .get_all_playlists()
+            .await?
+            .into_iter()
+            .map(|p| p.playlist_name.to_lowercase())
+            .collect::<HashSet<_>>();
+        let mut unique_name = name.to_owned();
+        let mut i = 2;
+        while existing.contains(&unique_name.to_lowercase()) {
+            unique_name = format!("{name} ({i})");
+            i += 1;
+        }
+        Ok(unique_name)
+    }
+
+    pub async fn create_smart_playlist(
+        &self,
+        definition: &SmartPlaylistDefinition,
+    ) -> Result<i64, SmartPlaylistError> {
+        let smart_playlist_id = self.db.create_smart_playlist(definition).await?;
+        self.db.evaluate_smart_playlist(smart_playlist_id).await?;
+        Ok(smart_playlist_id)
+    }
+
+    pub async fn update_smart_playlist(
+        &self,
+        smart_playlist_id: i64,
+        definition: &SmartPlaylistDefinition,
+    ) -> Result<(), SmartPlaylistError> {
+        self.db
+            .update_smart_playlist(smart_playlist_id, definition)
+            .await?;
+        self.db.evaluate_smart_playlist(smart_playlist_id).await
+    }
+
+    pub async fn delete_smart_playlists(
+        &self,
+        smart_playlist_ids: Vec<i64>,
+    ) -> Result<(), DbError> {
+        self.db.delete_smart_playlists(smart_playlist_ids).await
+    }
+
+    pub async fn get_smart_playlist(
+        &self,
+        smart_playlist_id: i64,
+    ) -> Result<Option<SmartPlaylist>, DbError> {
+        self.db.get_smart_playlist(smart_playlist_id).await
+    }
+
+    /// Re-runs the playlist's rules and returns the new list of songs
+    pub async fn evaluate_smart_playlist(
+        &self,
+        smart_playlist_id: i64,
+    ) -> Result<Vec<LookupEntry>, SmartPlaylistError> {
+        self.db.evaluate_smart_playlist(smart_playlist_id).await?;
+        Ok(self.get_smart_playlist_songs(smart_playlist_id).await?)
+    }
+
+    /// Returns the songs from the last time the playlist was evaluated
+    pub async fn get_smart_playlist_songs(
+        &self,
+        smart_playlist_id: i64,
+    ) -> Result<Vec<LookupEntry>, DbError> {
+        let mut songs = self.db.get_smart_playlist_songs(smart_playlist_id).await?;
+        self.update_paths(&mut songs).await;
+        Ok(songs)
+    }
+
+    pub async fn refresh_smart_playlists(&self) -> Result<(), SmartPlaylistError> {
+        self.db.refresh_smart_playlists().await
+    }
+
+    pub async fn record_play<P>(
+        &self,
+        path: P,
+        played_date: i64,
+        listened_millis: i64,
+        skipped: bool,
+    ) -> Result<bool, DbError>
+    where
+        P: AsRef<Path>,
+    {
+        let mounts = self.get_mount_paths().await.roots();
+        let path = clean_file_path(&path, &mounts).map_err(|e| DbError::DbError(e.to_string()))?;
+
+        self.db
+            .record_play(path, played_date, listened_millis, skipped)
+            .await
+    }
+
+    pub async fn get_duplicates(&self) -> Result<Vec<DuplicateGroup>, DbError> {
+        let mut groups = self.db.get_duplicates().await?;
+        for group in &mut groups {
+            self.update_paths(&mut group.songs).await;
+        }
+        Ok(groups)
+    }
+
+    /// Returns the most recent report if no ID is given. Only files with the given statuses are
+    /// included unless the list is empty.
+    pub async fn get_sync_report(
+        &self,
+        sync_report_id: Option<i64>,
+        statuses: &[SyncFileStatus],
+    ) -> Result<Option<SyncReport>, DbError> {
+        let mut report = self.db.get_sync_report(sync_report_id, statuses).await?;
+        if let Some(report) = &mut report {
+            self.update_paths(&mut report.files).await;
+        }
+        Ok(report)
+    }
+
+    pub async fn get_song_artists(&self, song_id: i64) -> Result<Vec<SongArtist>, DbError> {
+        self.db.get_song_artists(song_id).await
+    }
+
+    /// Returns `None` if the song doesn't exist
+    pub async fn get_lyrics(&self, song_id: i64) -> Result<Option<Lyrics>, DbError> {
+        self.db.get_lyrics(song_id).await
+    }
+
+    pub async fn get_history(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<HistoryEntry>, DbError> {
+        let mut history = self.db.get_history(start, end, limit).await?;
+        self.update_paths(&mut history).await;
+        Ok(history)
+    }
+
+    pub async fn get_top_songs(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayStat>, DbError> {
+        self.db.get_top_songs(start, end, limit).await
+    }
+
+    pub async fn get_top_albums(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayStat>, DbError> {
+        self.db.get_top_albums(start, end, limit).await
+    }
+
+    pub async fn get_top_artists(
+        &self,
+        start: i64,
+        end: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayStat>, DbError> {
+        self.db.get_top_artists(start, end, limit).await
+    }
+
+    pub async fn get_play_counts(&self, song_ids: Vec<i64>) -> Result<Vec<SongPlayCount>, DbError> {
+        self.db.get_play_counts(song_ids).await
+    }
+
+    pub async fn browse_artists(
+        &self,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<ArtistSummary>, DbError> {
+        self.db.browse_artists(options).await
+    }
+
+    pub async fn browse_album_artists(
+        &self,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<ArtistSummary>, DbError> {
+        self.db.browse_album_artists(options).await
+    }
+
+    pub async fn browse_albums(
+        &self,
+        options: &BrowseOptions,
+    ) -> Result<BrowsePage<AlbumSummary>, DbError> {
+        self.db.browse_albums(options).await
+    }
+
+    pub async fn browse_genres(&self, filter: &BrowseFilter) -> Result<Vec<GenreSummary>, DbError> {
+        self.db.browse_genres(filter).await
+    }
+
+    pub async fn browse_years(
+        &self,
+        filter: &BrowseFilter,
+        by_decade: bool,
+    ) -> Result<Vec<YearSummary>, DbError> {
+        self.db.browse_years(filter, by_decade).await
+    }
+
     fn clean_path(&self, path: impl AsRef<Path>) -> Result<String, ManagerError> {
         let path = path
             .as_ref()
@@ -252,26 +935,56 @@ impl Manager {
             .to_string_lossy()
             .to_string();
 
+        Ok(self.format_path(path))
+    }
+
+    fn format_path(&self, path: String) -> String {
         let mut path = path.replace(self.delim, "/");
         if !path.ends_with('/') {
             path += "/";
         }
-        Ok(path)
+        path
     }
 
-    async fn set_drive(&self, path: &str) -> Result<(), ManagerError> {
+    async fn add_mount(&self, name: &str, path: &str) -> Result<(), ManagerError> {
         let id = self
             .db
-            .add_mount(path)
+            .add_mount(name, path)
             .await
             .map_err(ManagerError::DbError)?;
 
         self.config
-            .set_drive_id(id)
+            .add_mount_id(id)
             .map_err(|e| ManagerError::WriteError(format!("{e:?}")))
     }
 }
 
+fn playlist_file_entry(song: LookupEntry) -> PlaylistFileEntry {
+    PlaylistFileEntry {
+        path: song.path,
+        title: Some(song.song),
+        artist: Some(song.artist),
+        album: Some(song.album),
+        duration_millis: Some(song.duration_millis),
+    }
+}
+
+fn export_entries(
+    name: &str,
+    mut entries: Vec<PlaylistFileEntry>,
+    format: PlaylistFormat,
+    relative_to: Option<&Path>,
+) -> Result<String, PlaylistFileError> {
+    if let Some(relative_to) = relative_to {
+        for entry in &mut entries {
+            if let Ok(relative_path) = Path::new(&entry.path).strip_prefix(relative_to) {
+                entry.path = relative_path.to_string_lossy().replace('\\', "/");
+            }
+        }
+    }
+    write_playlist(name, &entries, format)
+}
+
 #[cfg(test)]
 #[path = "./manager_test.rs"]
 mod manager_test;
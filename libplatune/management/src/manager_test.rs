@@ -2,11 +2,12 @@ use std::fs;
 use std::path::{MAIN_SEPARATOR, PathBuf};
 use std::sync::Arc;
 
+use futures::StreamExt;
 use normpath::PathExt;
 use pretty_assertions::assert_eq;
 use tempfile::{TempDir, tempdir};
 
-use super::Manager;
+use super::{DEFAULT_MOUNT, DbError, Manager};
 use crate::config::MemoryConfig;
 use crate::database::Database;
 
@@ -58,7 +59,10 @@ pub async fn test_change_mount() {
     let test = temp.path().join("test");
     fs::create_dir_all(test).unwrap();
 
-    manager.register_drive(&temp_str).await.unwrap();
+    manager
+        .register_mount(DEFAULT_MOUNT, &temp_str)
+        .await
+        .unwrap();
     manager
         .add_folder(&format!(r"{temp_str}{MAIN_SEPARATOR}test"))
         .await
@@ -73,7 +77,10 @@ pub async fn test_change_mount() {
     fs::create_dir_all(temp2.path().join("test")).unwrap();
     let temp_str2 = temp2.path().to_string_lossy().to_string();
 
-    manager.register_drive(&temp_str2).await.unwrap();
+    manager
+        .register_mount(DEFAULT_MOUNT, &temp_str2)
+        .await
+        .unwrap();
     let folders2 = manager.get_all_folders().await.unwrap();
 
     assert_normalized(
@@ -103,14 +110,20 @@ pub async fn test_change_mount_after() {
         .add_folder(&format!(r"{temp_str}{MAIN_SEPARATOR}test{MAIN_SEPARATOR}"))
         .await
         .unwrap();
-    manager.register_drive(&temp_str).await.unwrap();
+    manager
+        .register_mount(DEFAULT_MOUNT, &temp_str)
+        .await
+        .unwrap();
     let folders1 = manager.get_all_folders().await.unwrap();
 
     let temp2 = tempdir().unwrap();
     fs::create_dir_all(temp2.path().join("test")).unwrap();
     let temp_str2 = temp2.path().to_string_lossy().to_string();
 
-    manager.register_drive(&temp_str2).await.unwrap();
+    manager
+        .register_mount(DEFAULT_MOUNT, &temp_str2)
+        .await
+        .unwrap();
     let folders2 = manager.get_all_folders().await.unwrap();
 
     assert_normalized(
@@ -143,14 +156,20 @@ pub async fn test_multiple_mounts() {
         .add_folder(&format!(r"{temp_str}{MAIN_SEPARATOR}test{MAIN_SEPARATOR}"))
         .await
         .unwrap();
-    manager.register_drive(&temp_str).await.unwrap();
+    manager
+        .register_mount(DEFAULT_MOUNT, &temp_str)
+        .await
+        .unwrap();
     let folders1 = manager.get_all_folders().await.unwrap();
 
     let temp2 = tempdir().unwrap();
     let temp_str2 = temp2.path().to_string_lossy().to_string();
     fs::create_dir_all(temp2.path().join("test")).unwrap();
 
-    manager2.register_drive(&temp_str2).await.unwrap();
+    manager2
+        .register_mount(DEFAULT_MOUNT, &temp_str2)
+        .await
+        .unwrap();
     let folders2 = manager2.get_all_folders().await.unwrap();
 
     assert_normalized(
@@ -181,7 +200,10 @@ pub async fn test_reset_drive_id_if_missing() {
         .add_folder(&format!(r"{temp_str}{MAIN_SEPARATOR}test"))
         .await
         .unwrap();
-    manager.register_drive(&temp_str).await.unwrap();
+    manager
+        .register_mount(DEFAULT_MOUNT, &temp_str)
+        .await
+        .unwrap();
 
     let tempdir2 = TempDir::new().unwrap();
     let sql_path2 = tempdir2.path().join("platune.db");
@@ -194,7 +216,10 @@ pub async fn test_reset_drive_id_if_missing() {
         .add_folder(&format!(r"{temp_str}{MAIN_SEPARATOR}test"))
         .await
         .unwrap();
-    manager2.register_drive(&temp_str).await.unwrap();
+    manager2
+        .register_mount(DEFAULT_MOUNT, &temp_str)
+        .await
+        .unwrap();
 
     let folders = manager2.get_all_folders().await.unwrap();
 
@@ -208,11 +233,267 @@ pub async fn test_reset_drive_id_if_missing() {
 pub async fn test_validate_path() {
     let (_, manager) = setup().await;
 
-    let res = manager.register_drive(r"/some/invalid/path").await;
+    let res = manager
+        .register_mount(DEFAULT_MOUNT, r"/some/invalid/path")
+        .await;
 
     assert!(res.is_err());
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_playlists() {
+    let (_, mut manager) = setup().await;
+
+    let temp = tempdir().unwrap();
+    let music_dir = temp.path().join("music");
+    fs::create_dir_all(&music_dir).unwrap();
+    let paths = vec![
+        music_dir.join("test.mp3"),
+        music_dir.join("test2.mp3"),
+        music_dir.join("test3.mp3"),
+    ];
+    fs::copy("../test_assets/test.mp3", &paths[0]).unwrap();
+    fs::copy("../test_assets/test2.mp3", &paths[1]).unwrap();
+    fs::copy("../test_assets/test3.mp3", &paths[2]).unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while let Some(msg) = receiver.next().await {
+        msg.unwrap();
+    }
+
+    let mut ids = vec![];
+    for path in &paths {
+        ids.push(
+            manager
+                .get_song_by_path(path)
+                .await
+                .unwrap()
+                .unwrap()
+                .song_id,
+        );
+    }
+
+    let playlist_id = manager.create_playlist("playlist").await.unwrap();
+    manager
+        .append_to_playlist(playlist_id, vec![ids[2], ids[0], ids[1]])
+        .await
+        .unwrap();
+    manager.reorder_playlist(playlist_id, 0, 2).await.unwrap();
+    assert_eq!(
+        vec![ids[0], ids[1], ids[2]],
+        playlist_song_ids(&manager, playlist_id).await
+    );
+    assert!(manager.reorder_playlist(playlist_id, 0, 3).await.is_err());
+
+    manager
+        .remove_from_playlist(playlist_id, vec![1])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![ids[0], ids[2]],
+        playlist_song_ids(&manager, playlist_id).await
+    );
+
+    // Renaming onto an existing song should move the playlist entry to that song
+    manager.rename_path(&paths[2], &paths[1]).await.unwrap();
+    assert_eq!(
+        vec![ids[0], ids[1]],
+        playlist_song_ids(&manager, playlist_id).await
+    );
+
+    manager.delete_tracks(vec![ids[0]]).await.unwrap();
+    assert_eq!(vec![ids[1]], playlist_song_ids(&manager, playlist_id).await);
+
+    manager
+        .rename_playlist(playlist_id, "renamed")
+        .await
+        .unwrap();
+    let playlists = manager.get_all_playlists().await.unwrap();
+    assert_eq!(1, playlists.len());
+    assert_eq!("renamed", playlists[0].playlist_name);
+    assert_eq!(1, playlists[0].song_count);
+
+    // Missing playlists and songs shouldn't leave orphaned entries behind
+    assert!(matches!(
+        manager.append_to_playlist(playlist_id, vec![-1]).await,
+        Err(DbError::NotFound(_))
+    ));
+    assert!(matches!(
+        manager.append_to_playlist(-1, vec![ids[1]]).await,
+        Err(DbError::NotFound(_))
+    ));
+    assert!(matches!(
+        manager.rename_playlist(-1, "missing").await,
+        Err(DbError::NotFound(_))
+    ));
+    assert!(matches!(
+        manager.reorder_playlist(-1, 0, 0).await,
+        Err(DbError::NotFound(_))
+    ));
+    assert!(matches!(
+        manager.remove_from_playlist(-1, vec![0]).await,
+        Err(DbError::NotFound(_))
+    ));
+    assert!(matches!(
+        manager.get_playlist_songs(-1).await,
+        Err(DbError::NotFound(_))
+    ));
+    assert!(matches!(
+        manager.delete_playlists(vec![playlist_id, -1]).await,
+        Err(DbError::NotFound(_))
+    ));
+    assert_eq!(vec![ids[1]], playlist_song_ids(&manager, playlist_id).await);
+
+    manager.delete_playlists(vec![playlist_id]).await.unwrap();
+    assert!(manager.get_all_playlists().await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_play_history() {
+    let (_, manager) = setup().await;
+
+    let temp = tempdir().unwrap();
+    let music_dir = temp.path().join("music");
+    fs::create_dir_all(&music_dir).unwrap();
+    let paths = vec![music_dir.join("test.mp3"), music_dir.join("test2.mp3")];
+    fs::copy("../test_assets/test.mp3", &paths[0]).unwrap();
+    fs::copy("../test_assets/test2.mp3", &paths[1]).unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while let Some(msg) = receiver.next().await {
+        msg.unwrap();
+    }
+
+    let song1 = manager.get_song_by_path(&paths[0]).await.unwrap().unwrap();
+    let song2 = manager.get_song_by_path(&paths[1]).await.unwrap().unwrap();
+
+    assert!(
+        manager
+            .record_play(&paths[0], 100, song1.duration_millis, false)
+            .await
+            .unwrap()
+    );
+    assert!(
+        manager
+            .record_play(&paths[0], 200, song1.duration_millis / 2, true)
+            .await
+            .unwrap()
+    );
+    // Skipped before reaching the threshold so it shouldn't count
+    assert!(
+        !manager
+            .record_play(&paths[1], 300, song2.duration_millis / 4, true)
+            .await
+            .unwrap()
+    );
+    assert!(
+        manager
+            .record_play(&paths[1], 400, song2.duration_millis, false)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !manager
+            .record_play(music_dir.join("missing.mp3"), 500, 1000, false)
+            .await
+            .unwrap()
+    );
+
+    let history = manager.get_history(0, i64::MAX, 10).await.unwrap();
+    assert_eq!(
+        vec![
+            (song2.song_id, 400, false),
+            (song1.song_id, 200, true),
+            (song1.song_id, 100, false)
+        ],
+        history
+            .iter()
+            .map(|h| (h.song_id, h.played_date, h.skipped))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        normalize(&paths[1].to_string_lossy().to_string()),
+        normalize(&history[0].path)
+    );
+
+    let history = manager.get_history(150, 350, 10).await.unwrap();
+    assert_eq!(1, history.len());
+
+    let top_songs = manager.get_top_songs(0, i64::MAX, 10).await.unwrap();
+    assert_eq!(
+        vec![(song1.song_id, 2), (song2.song_id, 1)],
+        top_songs
+            .iter()
+            .map(|s| (s.id, s.play_count))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        song1.duration_millis + song1.duration_millis / 2,
+        top_songs[0].listened_millis
+    );
+
+    let top_songs = manager.get_top_songs(300, i64::MAX, 10).await.unwrap();
+    assert_eq!(
+        vec![song2.song_id],
+        top_songs.iter().map(|s| s.id).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        1,
+        manager.get_top_songs(0, i64::MAX, 1).await.unwrap().len()
+    );
+    assert!(
+        !manager
+            .get_top_albums(0, i64::MAX, 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        !manager
+            .get_top_artists(0, i64::MAX, 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let counts = manager
+        .get_play_counts(vec![song1.song_id, song2.song_id])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            (song1.song_id, 2, 1, Some(200)),
+            (song2.song_id, 1, 0, Some(400))
+        ],
+        counts
+            .iter()
+            .map(|c| (c.song_id, c.play_count, c.skip_count, c.last_played))
+            .collect::<Vec<_>>()
+    );
+
+    manager.delete_tracks(vec![song1.song_id]).await.unwrap();
+    let history = manager.get_history(0, i64::MAX, 10).await.unwrap();
+    assert_eq!(1, history.len());
+}
+
+async fn playlist_song_ids(manager: &Manager, playlist_id: i64) -> Vec<i64> {
+    manager
+        .get_playlist_songs(playlist_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| s.song_id)
+        .collect()
+}
+
 async fn setup() -> (Database, Manager) {
     let db = Database::connect_in_memory().await.unwrap();
     db.sync_database().await.unwrap();
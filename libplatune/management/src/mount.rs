@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::path_util::sort_nested_folders;
+
+/// Name used for mounts registered without one
+pub const DEFAULT_MOUNT: &str = "default";
+
+/// Root path that library folders are stored relative to. Mounts are registered per machine, so
+/// the same name can point to a different path on each machine that shares the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub mount_id: Uuid,
+    pub name: String,
+    pub path: String,
+}
+
+/// Resolves stored paths to the mount of the folder they belong to
+#[derive(Debug, Default)]
+pub(crate) struct MountPaths {
+    mounts: Vec<Mount>,
+    /// Relative folder paths with the path of their mount
+    folders: Vec<(String, String)>,
+}
+
+impl MountPaths {
+    pub(crate) fn new(mounts: Vec<Mount>, folders: Vec<(String, Option<String>)>) -> Self {
+        let mut folders = folders
+            .into_iter()
+            .filter_map(|(folder, mount_name)| {
+                let mount = mounts
+                    .iter()
+                    .find(|m| Some(&m.name) == mount_name.as_ref())?;
+                Some((folder, mount.path.to_owned()))
+            })
+            .collect::<Vec<_>>();
+        sort_nested_folders(&mut folders, |(folder, _)| Path::new(folder));
+
+        Self { mounts, folders }
+    }
+
+    pub(crate) fn roots(&self) -> Vec<String> {
+        self.mounts.iter().map(|m| m.path.to_owned()).collect()
+    }
+
+    pub(crate) fn default_root(&self) -> Option<&str> {
+        self.mounts
+            .iter()
+            .find(|m| m.name == DEFAULT_MOUNT)
+            .map(|m| m.path.as_str())
+    }
+
+    /// Mount path that the stored path is relative to. Relative paths that don't belong to a
+    /// folder with a mount were stored before named mounts existed, so they use the default one.
+    pub(crate) fn root(&self, path: &str) -> Option<&str> {
+        if Path::new(path).is_absolute() {
+            return None;
+        }
+        self.folders
+            .iter()
+            .find(|(folder, _)| starts_with_ignore_case(path, folder))
+            .map(|(_, root)| root.as_str())
+            .or_else(|| self.default_root())
+    }
+
+    /// Splits an absolute path into the path relative to the mount that contains it and the
+    /// name of the mount. The most specific mount is used if mounts are nested.
+    pub(crate) fn split(&self, path: &str) -> (String, Option<String>) {
+        match self
+            .mounts
+            .iter()
+            .filter(|m| starts_with_ignore_case(path, &m.path))
+            .max_by_key(|m| m.path.len())
+        {
+            Some(mount) => (
+                path[mount.path.len()..].to_owned(),
+                Some(mount.name.to_owned()),
+            ),
+            None => (path.to_owned(), None),
+        }
+    }
+
+    pub(crate) fn strip(&self, path: &str) -> String {
+        strip_mount(path, &self.roots())
+    }
+}
+
+/// Removes the longest matching mount prefix from the path
+pub(crate) fn strip_mount(path: &str, roots: &[String]) -> String {
+    roots
+        .iter()
+        .filter(|root| starts_with_ignore_case(path, root))
+        .max_by_key(|root| root.len())
+        .map(|root| path[root.len()..].to_owned())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Removes the prefix from the path, ignoring case like the rest of the path comparisons
+pub(crate) fn strip_prefix_ignore_case<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    starts_with_ignore_case(path, prefix).then(|| &path[prefix.len()..])
+}
+
+/// Checks if either folder contains the other one
+pub(crate) fn folders_overlap(folder: &str, other: &str) -> bool {
+    let contains = |parent: &str, child: &str| {
+        strip_prefix_ignore_case(child, parent).is_some_and(|rest| {
+            rest.is_empty()
+                || parent.is_empty()
+                || parent.ends_with(std::path::is_separator)
+                || rest.starts_with(std::path::is_separator)
+        })
+    };
+    contains(folder, other) || contains(other, folder)
+}
+
+fn starts_with_ignore_case(path: &str, prefix: &str) -> bool {
+    path.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
@@ -4,6 +4,7 @@ use std::path::Path;
 use normpath::PathExt;
 
 use crate::database::LookupEntry;
+use crate::mount::strip_mount;
 
 pub(crate) trait PathMut {
     fn get_path(&self) -> String;
@@ -29,7 +30,12 @@ impl PathMut for String {
     }
 }
 
-pub(crate) fn clean_file_path<P>(file_path: &P, mount: &Option<String>) -> io::Result<String>
+/// Sorts the folders so the most specific one is checked first in case the folders are nested
+pub(crate) fn sort_nested_folders<T>(folders: &mut [T], folder: impl Fn(&T) -> &Path) {
+    folders.sort_by_key(|f| std::cmp::Reverse(folder(f).components().count()));
+}
+
+pub(crate) fn clean_file_path<P>(file_path: &P, mounts: &[String]) -> io::Result<String>
 where
     P: AsRef<Path>,
 {
@@ -56,13 +62,7 @@ where
         file_path_str = file_path_str.replace('\\', "/");
     }
 
-    if let Some(mount) = mount
-        && file_path_str.starts_with(&mount[..])
-    {
-        file_path_str = file_path_str.replace(&mount[..], "");
-    }
-
-    Ok(file_path_str)
+    Ok(strip_mount(&file_path_str, mounts))
 }
 
 pub(crate) fn update_path<T, P>(entry: &mut T, mount: &P)
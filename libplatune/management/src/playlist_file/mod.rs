@@ -0,0 +1,10 @@
+pub(crate) mod parser;
+pub mod playlist_file_error;
+pub mod playlist_format;
+pub mod playlist_import;
+pub(crate) mod uri;
+pub(crate) mod writer;
+
+#[cfg(test)]
+#[path = "./playlist_file_test.rs"]
+mod playlist_file_test;
@@ -0,0 +1,107 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use super::playlist_file_error::PlaylistFileError;
+use super::playlist_format::PlaylistFormat;
+use super::uri::{is_file_uri, is_remote_uri, percent_decode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParsedPlaylist {
+    pub(crate) name: Option<String>,
+    /// Paths or URIs in the order they appear in the file
+    pub(crate) entries: Vec<String>,
+}
+
+pub(crate) fn parse_playlist(
+    content: &[u8],
+    format: PlaylistFormat,
+) -> Result<ParsedPlaylist, PlaylistFileError> {
+    let content = decode(content);
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => Ok(parse_m3u(&content)),
+        PlaylistFormat::Pls => parse_pls(&content),
+        PlaylistFormat::Xspf => parse_xspf(&content),
+    }
+}
+
+fn decode(content: &[u8]) -> Cow<'_, str> {
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    match std::str::from_utf8(content) {
+        Ok(content) => Cow::Borrowed(content),
+        // Plain M3U and PLS files written by older players are usually Latin-1
+        Err(_) => Cow::Owned(content.iter().map(|&b| b as char).collect()),
+    }
+}
+
+fn parse_m3u(content: &str) -> ParsedPlaylist {
+    let mut name = None;
+    let mut entries = vec![];
+    for line in content.lines().map(str::trim) {
+        if let Some(playlist_name) = line.strip_prefix("#PLAYLIST:") {
+            name = Some(playlist_name.trim().to_owned());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(line.to_owned());
+        }
+    }
+    ParsedPlaylist { name, entries }
+}
+
+fn parse_pls(content: &str) -> Result<ParsedPlaylist, PlaylistFileError> {
+    let elements = pls::parse(&mut content.as_bytes())
+        .map_err(|e| PlaylistFileError::ParseError(format!("{e:?}")))?;
+    Ok(ParsedPlaylist {
+        name: None,
+        entries: elements.into_iter().map(|e| e.path).collect(),
+    })
+}
+
+fn parse_xspf(content: &str) -> Result<ParsedPlaylist, PlaylistFileError> {
+    let playlist: XspfPlaylist = quick_xml::de::from_str(content)
+        .map_err(|e| PlaylistFileError::ParseError(format!("Invalid XSPF playlist: {e}")))?;
+
+    let name = playlist
+        .title
+        .map(|title| title.trim().to_owned())
+        .filter(|title| !title.is_empty());
+    let entries = playlist
+        .track_list
+        .tracks
+        .into_iter()
+        .filter_map(|track| {
+            track.locations.into_iter().next().map(|location| {
+                let location = location.trim();
+                // Relative locations are URI references so they still need to be decoded
+                if is_file_uri(location) || is_remote_uri(location) {
+                    location.to_owned()
+                } else {
+                    percent_decode(location)
+                }
+            })
+        })
+        .collect();
+
+    Ok(ParsedPlaylist { name, entries })
+}
+
+// Only the parts of the XSPF format that are needed for importing.
+// Other elements, including extensions from other namespaces, are skipped.
+#[derive(Deserialize)]
+struct XspfPlaylist {
+    title: Option<String>,
+    #[serde(rename = "trackList")]
+    track_list: XspfTrackList,
+}
+
+#[derive(Deserialize)]
+struct XspfTrackList {
+    #[serde(rename = "track", default)]
+    tracks: Vec<XspfTrack>,
+}
+
+#[derive(Deserialize)]
+struct XspfTrack {
+    // A track can list several locations for the same resource, the first one is preferred
+    #[serde(rename = "location", default)]
+    locations: Vec<String>,
+}
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use super::playlist_format::PlaylistFormat;
+use crate::db_error::DbError;
+
+#[derive(Error, Debug)]
+pub enum PlaylistFileError {
+    #[error("{0:?} is not a supported playlist file")]
+    UnsupportedFile(PathBuf),
+    #[error("Exporting to {0} is not supported")]
+    UnsupportedExportFormat(PlaylistFormat),
+    #[error("Error reading playlist: {0}")]
+    ReadError(String),
+    #[error("Error parsing playlist: {0}")]
+    ParseError(String),
+    #[error("Playlist {0} not found")]
+    NotFound(i64),
+    #[error(transparent)]
+    DbError(#[from] DbError),
+}
@@ -0,0 +1,261 @@
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+
+use pretty_assertions::assert_eq;
+use rstest::*;
+use tempfile::TempDir;
+
+use super::parser::{ParsedPlaylist, parse_playlist};
+use super::playlist_file_error::PlaylistFileError;
+use super::playlist_format::PlaylistFormat;
+use crate::manager::Manager;
+use crate::test_util::{music_dir, setup_files};
+
+#[rstest(
+    content,
+    format,
+    expected_name,
+    case(
+        "#EXTM3U\n#PLAYLIST:mix\n#EXTINF:3,artist - song\n/music/a.mp3\n\n  b c.mp3  \n",
+        PlaylistFormat::M3u8,
+        Some("mix")
+    ),
+    case(
+        "[playlist]\nFile1=/music/a.mp3\nTitle1=song\nFile2=b c.mp3\nNumberOfEntries=2\nVersion=2\n",
+        PlaylistFormat::Pls,
+        None
+    ),
+    case(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>mix</title>
+  <trackList>
+    <track><title>song</title><location>file:///music/a.mp3</location></track>
+    <track><location>b%20c.mp3</location></track>
+  </trackList>
+</playlist>"#,
+        PlaylistFormat::Xspf,
+        Some("mix")
+    )
+)]
+fn test_parse_playlist(content: &str, format: PlaylistFormat, expected_name: Option<&str>) {
+    let parsed = parse_playlist(content.as_bytes(), format).unwrap();
+    let expected_first = if format == PlaylistFormat::Xspf {
+        "file:///music/a.mp3"
+    } else {
+        "/music/a.mp3"
+    };
+    assert_eq!(
+        ParsedPlaylist {
+            name: expected_name.map(|n| n.to_owned()),
+            entries: vec![expected_first.to_owned(), "b c.mp3".to_owned()],
+        },
+        parsed
+    );
+}
+
+#[test]
+fn test_parse_xspf_escapes() {
+    let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/" xmlns:vlc="http://www.videolan.org/vlc/playlist/ns/0/">
+  <title>R&amp;B &#8211; mix</title>
+  <!-- <trackList><track><location>commented.mp3</location></track></trackList> -->
+  <trackList>
+    <track>
+      <location><![CDATA[file:///music/a&b.mp3]]></location>
+      <location>file:///music/fallback.mp3</location>
+      <extension application="http://www.videolan.org/vlc/playlist/0">
+        <vlc:id>0</vlc:id>
+        <vlc:option>start-time=10</vlc:option>
+      </extension>
+    </track>
+    <track><title>no location</title></track>
+    <track><location>caf&#xE9;%20&#233;.mp3</location></track>
+  </trackList>
+</playlist>"#;
+    let parsed = parse_playlist(content.as_bytes(), PlaylistFormat::Xspf).unwrap();
+    assert_eq!(
+        ParsedPlaylist {
+            name: Some("R&B \u{2013} mix".to_owned()),
+            entries: vec!["file:///music/a&b.mp3".to_owned(), "café é.mp3".to_owned()],
+        },
+        parsed
+    );
+}
+
+#[test]
+fn test_parse_xspf_missing_track_list() {
+    let content =
+        r#"<playlist version="1" xmlns="http://xspf.org/ns/0/"><title>mix</title></playlist>"#;
+    let res = parse_playlist(content.as_bytes(), PlaylistFormat::Xspf);
+    assert!(matches!(res, Err(PlaylistFileError::ParseError(_))));
+}
+
+#[test]
+fn test_parse_latin1_m3u() {
+    let parsed = parse_playlist(b"caf\xe9.mp3\n", PlaylistFormat::M3u).unwrap();
+    assert_eq!(vec!["café.mp3".to_owned()], parsed.entries);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_import_playlist() {
+    let tempdir = TempDir::new().unwrap();
+    let (manager, paths) = setup(tempdir.path()).await;
+    let music_dir = music_dir(tempdir.path());
+
+    let playlist_path = music_dir.join("playlists/imported.m3u8");
+    create_dir_all(playlist_path.parent().unwrap()).unwrap();
+    let content = [
+        "#EXTM3U".to_owned(),
+        // Relative to the playlist
+        "../folder/song2.mp3".to_owned(),
+        // Relative to the configured folder
+        "folder/song 3.mp3".to_owned(),
+        paths[0].to_string_lossy().to_string(),
+        // Absolute path from another machine
+        r"D:\Music\folder\song2.mp3".to_owned(),
+        "file://".to_owned() + &paths[2].to_string_lossy().replace(' ', "%20"),
+        "folder/missing.mp3".to_owned(),
+        "http://radio.example/stream".to_owned(),
+    ]
+    .join("\n");
+    fs::write(&playlist_path, content).unwrap();
+
+    let res = manager.import_playlist(&playlist_path, None).await.unwrap();
+    assert_eq!("imported", res.playlist_name);
+    assert_eq!(5, res.imported_count);
+    assert_eq!(
+        vec![
+            "folder/missing.mp3".to_owned(),
+            "http://radio.example/stream".to_owned()
+        ],
+        res.unresolved
+    );
+    assert_eq!(
+        vec![
+            paths[1].clone(),
+            paths[2].clone(),
+            paths[0].clone(),
+            paths[1].clone(),
+            paths[2].clone()
+        ],
+        playlist_paths(&manager, res.playlist_id).await
+    );
+
+    // Importing again shouldn't conflict with the existing playlist
+    let res = manager.import_playlist(&playlist_path, None).await.unwrap();
+    assert_eq!("imported (2)", res.playlist_name);
+}
+
+#[rstest(format, case(PlaylistFormat::M3u8), case(PlaylistFormat::Xspf))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_export_playlist(format: PlaylistFormat) {
+    let tempdir = TempDir::new().unwrap();
+    let (manager, paths) = setup(tempdir.path()).await;
+    let music_dir = music_dir(tempdir.path());
+
+    let mut song_ids = vec![];
+    for path in [&paths[2], &paths[0]] {
+        song_ids.push(
+            manager
+                .get_song_by_path(path)
+                .await
+                .unwrap()
+                .unwrap()
+                .song_id,
+        );
+    }
+    let playlist_id = manager.create_playlist("exported").await.unwrap();
+    manager
+        .append_to_playlist(playlist_id, song_ids.clone())
+        .await
+        .unwrap();
+
+    let absolute = manager
+        .export_playlist(playlist_id, format, None)
+        .await
+        .unwrap();
+    let relative = manager
+        .export_playlist(playlist_id, format, Some(&music_dir))
+        .await
+        .unwrap();
+    assert!(!relative.contains(&music_dir.to_string_lossy().to_string()));
+    assert_eq!(
+        absolute,
+        manager
+            .export_songs("exported", song_ids, format, None)
+            .await
+            .unwrap()
+    );
+
+    for (name, content) in [("absolute", absolute), ("relative", relative)] {
+        let export_path = music_dir.join(format!("{name}.{format}"));
+        fs::write(&export_path, content).unwrap();
+        let res = manager.import_playlist(&export_path, None).await.unwrap();
+        assert!(res.unresolved.is_empty());
+        assert_eq!(
+            vec![paths[2].clone(), paths[0].clone()],
+            playlist_paths(&manager, res.playlist_id).await
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_export_paths() {
+    let tempdir = TempDir::new().unwrap();
+    let (manager, paths) = setup(tempdir.path()).await;
+
+    let content = manager
+        .export_paths(
+            "queue",
+            vec![
+                paths[1].to_string_lossy().to_string(),
+                "http://radio.example/stream".to_owned(),
+            ],
+            PlaylistFormat::M3u8,
+            None,
+        )
+        .await
+        .unwrap();
+    let parsed = parse_playlist(content.as_bytes(), PlaylistFormat::M3u8).unwrap();
+    assert_eq!(Some("queue".to_owned()), parsed.name);
+    assert_eq!(
+        vec![
+            paths[1].to_string_lossy().to_string(),
+            "http://radio.example/stream".to_owned()
+        ],
+        parsed.entries
+    );
+
+    let res = manager
+        .export_paths("queue", vec![], PlaylistFormat::Pls, None)
+        .await;
+    assert!(matches!(
+        res,
+        Err(PlaylistFileError::UnsupportedExportFormat(
+            PlaylistFormat::Pls
+        ))
+    ));
+}
+
+async fn playlist_paths(manager: &Manager, playlist_id: i64) -> Vec<PathBuf> {
+    manager
+        .get_playlist_songs(playlist_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| PathBuf::from(s.path))
+        .collect()
+}
+
+async fn setup(tempdir: &Path) -> (Manager, Vec<PathBuf>) {
+    setup_files(
+        tempdir,
+        &[
+            ("folder/song1.mp3", "test.mp3"),
+            ("folder/song2.mp3", "test2.mp3"),
+            ("folder/song 3.mp3", "test3.mp3"),
+        ],
+    )
+    .await
+}
@@ -0,0 +1,26 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum PlaylistFormat {
+    M3u,
+    M3u8,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    /// Detects the format from the file extension
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        path.as_ref()
+            .extension()
+            .and_then(|e| Self::from_str(&e.to_string_lossy()).ok())
+    }
+
+    pub fn can_export(&self) -> bool {
+        matches!(self, Self::M3u | Self::M3u8 | Self::Xspf)
+    }
+}
@@ -0,0 +1,9 @@
+#[derive(Debug, Clone)]
+pub struct PlaylistImport {
+    pub playlist_id: i64,
+    pub playlist_name: String,
+    /// Number of entries that were added to the playlist
+    pub imported_count: usize,
+    /// Entries that could not be matched to a song in the library
+    pub unresolved: Vec<String>,
+}
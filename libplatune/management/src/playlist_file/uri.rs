@@ -0,0 +1,79 @@
+const FILE_SCHEME: &str = "file://";
+
+pub(crate) fn is_file_uri(value: &str) -> bool {
+    value.len() >= FILE_SCHEME.len() && value[..FILE_SCHEME.len()].eq_ignore_ascii_case(FILE_SCHEME)
+}
+
+pub(crate) fn is_remote_uri(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Converts a `file://` URI into a local path.
+/// Returns `None` if the value isn't a file URI.
+pub(crate) fn file_uri_to_path(uri: &str) -> Option<String> {
+    if !is_file_uri(uri) {
+        return None;
+    }
+    let path = &uri[FILE_SCHEME.len()..];
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    let path = percent_decode(path);
+    // Windows paths are written as file:///C:/path
+    match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => Some(path[1..].to_owned()),
+        _ => Some(path),
+    }
+}
+
+/// Checks for absolute paths from any platform since playlists may have been written on a
+/// different OS than the one we're running on
+pub(crate) fn is_absolute_path(path: &str) -> bool {
+    match path.as_bytes() {
+        [b'/' | b'\\', ..] => true,
+        [drive, b':', b'/' | b'\\', ..] => drive.is_ascii_alphabetic(),
+        _ => false,
+    }
+}
+
+/// Converts a path into a URI. Relative paths are kept relative.
+pub(crate) fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    if !is_absolute_path(&path) {
+        return percent_encode(&path);
+    }
+    let separator = if path.starts_with('/') { "" } else { "/" };
+    format!("{FILE_SCHEME}{separator}{}", percent_encode(&path))
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
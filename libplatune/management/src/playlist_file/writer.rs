@@ -0,0 +1,83 @@
+use super::playlist_file_error::PlaylistFileError;
+use super::playlist_format::PlaylistFormat;
+use super::uri::{is_remote_uri, path_to_uri};
+
+#[derive(Debug, Clone)]
+pub(crate) struct PlaylistFileEntry {
+    pub(crate) path: String,
+    pub(crate) title: Option<String>,
+    pub(crate) artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) duration_millis: Option<i64>,
+}
+
+pub(crate) fn write_playlist(
+    name: &str,
+    entries: &[PlaylistFileEntry],
+    format: PlaylistFormat,
+) -> Result<String, PlaylistFileError> {
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => Ok(write_m3u(name, entries)),
+        PlaylistFormat::Xspf => Ok(write_xspf(name, entries)),
+        PlaylistFormat::Pls => Err(PlaylistFileError::UnsupportedExportFormat(format)),
+    }
+}
+
+fn write_m3u(name: &str, entries: &[PlaylistFileEntry]) -> String {
+    let mut content = format!("#EXTM3U\n#PLAYLIST:{name}\n");
+    for entry in entries {
+        let duration = entry.duration_millis.map(|d| d / 1000).unwrap_or(-1);
+        let title = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => format!("{artist} - {title}"),
+            (None, Some(title)) => title.to_owned(),
+            _ => String::new(),
+        };
+        content += &format!("#EXTINF:{duration},{title}\n{}\n", entry.path);
+    }
+    content
+}
+
+fn write_xspf(name: &str, entries: &[PlaylistFileEntry]) -> String {
+    let mut content = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  \
+         <title>{}</title>\n  <trackList>\n",
+        escape_xml(name)
+    );
+    for entry in entries {
+        let location = if is_remote_uri(&entry.path) {
+            entry.path.to_owned()
+        } else {
+            path_to_uri(&entry.path)
+        };
+        content += &format!(
+            "    <track>\n      <location>{}</location>\n",
+            escape_xml(&location)
+        );
+        let fields = [
+            ("title", &entry.title),
+            ("creator", &entry.artist),
+            ("album", &entry.album),
+        ];
+        for (tag, value) in fields {
+            if let Some(value) = value {
+                content += &format!("      <{tag}>{}</{tag}>\n", escape_xml(value));
+            }
+        }
+        if let Some(duration) = entry.duration_millis {
+            content += &format!("      <duration>{duration}</duration>\n");
+        }
+        content += "    </track>\n";
+    }
+    content += "  </trackList>\n</playlist>\n";
+    content
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
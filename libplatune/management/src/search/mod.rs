@@ -1,11 +1,17 @@
 pub(crate) mod queries;
+mod query_parser;
 mod result_score;
 pub(crate) mod search_engine;
 mod search_entry;
+pub mod search_error;
 pub mod search_options;
 pub(crate) mod search_result;
 mod spellfix_result;
 
+#[cfg(test)]
+#[path = "./query_parser_test.rs"]
+mod query_parser_test;
+
 #[cfg(test)]
 #[path = "./search_test.rs"]
 mod search_test;
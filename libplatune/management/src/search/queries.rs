@@ -5,32 +5,132 @@ use super::spellfix_result::SpellfixResult;
 use crate::consts::{END_MATCH_TEXT, START_MATCH_TEXT};
 use crate::sql_util::generate_parameterized_bindings;
 
-pub(crate) fn get_search_query(artist_filter: &[String], allowed_entry_types: &[&str]) -> String {
+#[derive(Debug, Default)]
+pub(crate) struct SearchFilters {
+    pub(crate) artists: Vec<String>,
+    pub(crate) excluded_artists: Vec<String>,
+    pub(crate) song_filters: Vec<SongFilter>,
+    /// Additional FTS5 expression for required phrases and excluded terms
+    pub(crate) match_suffix: String,
+}
+
+/// Restricts results to entries with at least one song matching the condition
+#[derive(Debug)]
+pub(crate) struct SongFilter {
+    pub(crate) condition: SongCondition,
+    pub(crate) negated: bool,
+}
+
+#[derive(Debug)]
+pub(crate) enum SongCondition {
+    Albums(Vec<i64>),
+    Genres(Vec<i64>),
+    Years(Vec<(i64, i64)>),
+}
+
+impl SongFilter {
+    /// Values to bind for the parameters in the condition, in order
+    pub(crate) fn values(&self) -> Vec<i64> {
+        match &self.condition {
+            SongCondition::Albums(ids) | SongCondition::Genres(ids) => ids.clone(),
+            SongCondition::Years(ranges) => ranges.iter().flat_map(|(s, e)| [*s, *e]).collect(),
+        }
+    }
+
+    fn clause(&self, start: usize) -> String {
+        let condition = match &self.condition {
+            SongCondition::Albums(ids) => format!(
+                "fs.album_id IN ({})",
+                generate_parameterized_bindings(start, ids.len())
+            ),
+            SongCondition::Genres(ids) => format!(
+                "EXISTS (SELECT 1 FROM song_genre fsg WHERE fsg.song_id = fs.song_id AND \
+                 fsg.genre_id IN ({}))",
+                generate_parameterized_bindings(start, ids.len())
+            ),
+            SongCondition::Years(ranges) => {
+                let ranges = (0..ranges.len())
+                    .map(|i| {
+                        let i = start + i * 2;
+                        format!("fs.song_year BETWEEN ${i} AND ${}", i + 1)
+                    })
+                    .join(" OR ");
+                format!("({ranges})")
+            }
+        };
+        let negate = if self.negated { "NOT " } else { "" };
+
+        format!(
+            "{negate}CASE entry_type
+            WHEN 'song' THEN EXISTS (SELECT 1 FROM song fs WHERE fs.song_id = assoc_id AND \
+             {condition})
+            WHEN 'composer' THEN EXISTS (SELECT 1 FROM song fs WHERE fs.song_id = assoc_id AND \
+             {condition})
+            WHEN 'album' THEN EXISTS (SELECT 1 FROM song fs WHERE fs.album_id = assoc_id AND \
+             {condition})
+            WHEN 'artist' THEN EXISTS (SELECT 1 FROM song fs INNER JOIN album fa ON fa.album_id = \
+             fs.album_id WHERE (fs.artist_id = assoc_id OR fa.artist_id = assoc_id OR EXISTS (SELECT \
+             1 FROM song_artist fsa WHERE fsa.song_id = fs.song_id AND fsa.artist_id = assoc_id)) \
+             AND {condition})
+            WHEN 'genre' THEN EXISTS (SELECT 1 FROM song fs INNER JOIN song_genre fsg ON \
+             fsg.song_id = fs.song_id WHERE fsg.genre_id = assoc_id AND {condition})
+            ELSE 0 END"
+        )
+    }
+}
+
+pub(crate) fn get_search_query(filters: &SearchFilters, allowed_entry_types: &[&str]) -> String {
     let num_base_args = 5;
-    let num_artists = artist_filter.len();
+    let num_artists = filters.artists.len();
+    let num_excluded_artists = filters.excluded_artists.len();
     let artist_select = "CASE entry_type WHEN 'song' THEN ar.artist_name WHEN 'album' THEN \
                          aa.artist_name ELSE NULL END";
 
-    let artist_filter_clause = if artist_filter.is_empty() {
-        "".to_owned()
-    } else {
+    let mut where_clauses = vec![];
+    if num_artists > 0 {
         //  WHERE clause with parameterized bindings for each artist in the list
         let start = num_base_args + 1;
         let artist_list = generate_parameterized_bindings(start, num_artists);
 
-        format!("WHERE {artist_select} in ({artist_list})")
-    };
+        where_clauses.push(format!(
+            "({artist_select} in ({artist_list}) OR {})",
+            credited_artist_clause(&artist_list)
+        ));
+    }
+    if num_excluded_artists > 0 {
+        let start = num_base_args + num_artists + 1;
+        let artist_list = generate_parameterized_bindings(start, num_excluded_artists);
+
+        // Entries without an artist can't be excluded by one
+        where_clauses.push(format!(
+            "(COALESCE({artist_select}, '') not in ({artist_list}) AND NOT {})",
+            credited_artist_clause(&artist_list)
+        ));
+    }
 
     let type_filter = if allowed_entry_types.is_empty() {
         "".to_owned()
     } else {
         // AND clause for the search_index search if allowed_entry_types was supplied
-        let start = num_base_args + num_artists + 1;
+        let start = num_base_args + num_artists + num_excluded_artists + 1;
         let in_list = generate_parameterized_bindings(start, allowed_entry_types.len());
 
         format!("AND entry_type in ({in_list})")
     };
 
+    let mut start =
+        num_base_args + num_artists + num_excluded_artists + allowed_entry_types.len() + 1;
+    for song_filter in &filters.song_filters {
+        where_clauses.push(song_filter.clause(start));
+        start += song_filter.values().len();
+    }
+
+    let filter_clause = if where_clauses.is_empty() {
+        "".to_owned()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+
     let full_query = format!(
         "
     WITH CTE AS (
@@ -45,7 +145,8 @@ pub(crate) fn get_search_query(artist_filter: &[String], allowed_entry_types: &[
         ROW_NUMBER() OVER (PARTITION BY
             entry_value,
             {artist_select},
-            CASE entry_type WHEN 'song' THEN 1 WHEN 'album' THEN 2 WHEN 'tag' THEN 3 ELSE 4 END,
+            CASE entry_type WHEN 'song' THEN 1 WHEN 'album' THEN 2 WHEN 'tag' THEN 3 WHEN 'genre' \
+         THEN 5 WHEN 'composer' THEN 6 ELSE 4 END,
             CASE entry_type WHEN 'song' THEN s.song_title + s.album_id WHEN 'album' THEN \
          al.album_name WHEN 'artist' THEN ar2.artist_name END
             ORDER BY entry_type DESC) row_num
@@ -58,7 +159,7 @@ pub(crate) fn get_search_query(artist_filter: &[String], allowed_entry_types: &[
         LEFT OUTER JOIN album al2 on al2.album_id = s.album_id
         LEFT OUTER JOIN artist aa on aa.artist_id = al.artist_id
         LEFT OUTER JOIN artist ar2 on ar2.artist_id = assoc_id
-        {artist_filter_clause}
+        {filter_clause}
         ORDER BY rank
         LIMIT $4
     )
@@ -72,6 +173,15 @@ pub(crate) fn get_search_query(artist_filter: &[String], allowed_entry_types: &[
     full_query
 }
 
+/// Matches songs that credit one of the artists, such as a featured artist
+fn credited_artist_clause(artist_list: &str) -> String {
+    format!(
+        "(entry_type = 'song' AND EXISTS (SELECT 1 FROM song_artist sa INNER JOIN artist sar ON \
+         sar.artist_id = sa.artist_id WHERE sa.song_id = assoc_id AND sar.artist_name IN \
+         ({artist_list})))"
+    )
+}
+
 pub(crate) fn get_full_spellfix_query(terms: &[&str]) -> String {
     // Union all queries together to avoid multiple trips to the database
 
@@ -111,6 +221,25 @@ pub(crate) fn combine_spellfix_results(spellfix_results: Vec<SpellfixResult>) ->
     combined_query
 }
 
+pub(crate) fn get_match_suffix(phrases: &[String], excluded: &[String]) -> String {
+    let quote = |value: &String| {
+        let value = replace_special_chars(&replace_ampersand(value)).replace('*', "");
+        (!value.is_empty()).then(|| format!("\"{value}\""))
+    };
+
+    phrases
+        .iter()
+        .filter_map(quote)
+        .map(|phrase| format!(" AND {phrase}"))
+        .chain(
+            excluded
+                .iter()
+                .filter_map(quote)
+                .map(|term| format!(" NOT {term}")),
+        )
+        .collect()
+}
+
 pub(crate) fn clean_query(query: &str) -> String {
     let query = replace_special_chars(query);
     if query.is_empty() || query.ends_with('*') {
@@ -0,0 +1,296 @@
+use super::search_error::QueryError;
+
+const MIN_YEAR: i64 = 1;
+const MAX_YEAR: i64 = 9999;
+const FIELDS: [&str; 4] = ["artist", "album", "genre", "year"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FieldFilter {
+    Artist(String),
+    Album(String),
+    Genre(String),
+    Year { start: i64, end: i64 },
+}
+
+impl FieldFilter {
+    /// Search index entry types that can be used to resolve the filter value
+    pub(crate) fn entry_types(&self) -> Option<Vec<&'static str>> {
+        match self {
+            Self::Artist(_) => Some(vec!["artist", "album_artist"]),
+            Self::Album(_) => Some(vec!["album"]),
+            Self::Genre(_) => Some(vec!["genre"]),
+            Self::Year { .. } => None,
+        }
+    }
+
+    pub(crate) fn text_value(&self) -> Option<&str> {
+        match self {
+            Self::Artist(value) | Self::Album(value) | Self::Genre(value) => Some(value),
+            Self::Year { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Filter {
+    pub(crate) field: FieldFilter,
+    pub(crate) negated: bool,
+}
+
+/// A group of search terms that must all match.
+/// Clauses separated by OR are searched independently.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct QueryClause {
+    pub(crate) terms: Vec<String>,
+    pub(crate) phrases: Vec<String>,
+    pub(crate) excluded: Vec<String>,
+    pub(crate) filters: Vec<Filter>,
+}
+
+impl QueryClause {
+    fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+            && self.phrases.is_empty()
+            && self.excluded.is_empty()
+            && self.filters.is_empty()
+    }
+}
+
+pub(crate) fn parse_query(query: &str) -> Result<Vec<QueryClause>, QueryError> {
+    let mut parser = Parser {
+        chars: query.chars().collect(),
+        pos: 0,
+    };
+    let mut clauses = vec![];
+    let mut clause = QueryClause::default();
+    let mut pending_operator = None;
+
+    loop {
+        parser.skip_whitespace();
+        let Some(c) = parser.peek() else {
+            break;
+        };
+        let start = parser.pos;
+        if let Some(operator) = parser.take_operator() {
+            if clause.is_empty() {
+                return Err(QueryError::MissingOperand {
+                    operator,
+                    position: start,
+                });
+            }
+            clauses.push(std::mem::take(&mut clause));
+            pending_operator = Some((operator, start));
+            continue;
+        }
+        pending_operator = None;
+
+        let negated = c == '-' && parser.peek_at(1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            parser.pos += 1;
+        }
+        parser.parse_term(&mut clause, negated)?;
+    }
+
+    if let Some((operator, position)) = pending_operator {
+        return Err(QueryError::MissingOperand { operator, position });
+    }
+    if !clause.is_empty() {
+        clauses.push(clause);
+    }
+    Ok(clauses)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_operator(&mut self) -> Option<String> {
+        // Only the uppercase form is an operator so titles containing "or" still work
+        for operator in ["OR", "|"] {
+            let len = operator.len();
+            let matches = self
+                .chars
+                .get(self.pos..self.pos + len)
+                .is_some_and(|c| c.iter().copied().eq(operator.chars()));
+            if matches && self.peek_at(len).is_none_or(char::is_whitespace) {
+                self.pos += len;
+                return Some(operator.to_owned());
+            }
+        }
+        None
+    }
+
+    fn parse_term(&mut self, clause: &mut QueryClause, negated: bool) -> Result<(), QueryError> {
+        let start = self.pos;
+        if self.peek() == Some('"') {
+            let phrase = self.parse_quoted()?;
+            if !phrase.is_empty() {
+                if negated {
+                    clause.excluded.push(phrase);
+                } else {
+                    clause.phrases.push(phrase);
+                }
+            }
+            return Ok(());
+        }
+
+        let word = self.read_word();
+        match split_field(&word) {
+            Some((field, value)) => {
+                let value = if value.is_empty() && self.peek() == Some('"') {
+                    self.parse_quoted()?
+                } else if !value.is_empty() && !field.eq_ignore_ascii_case("year") {
+                    self.read_field_value(value.to_owned()).to_lowercase()
+                } else {
+                    value.to_lowercase()
+                };
+                if value.is_empty() {
+                    return Err(QueryError::MissingValue {
+                        field: field.to_owned(),
+                        position: start,
+                    });
+                }
+                let field = parse_field(field, value, start)?;
+                clause.filters.push(Filter { field, negated });
+            }
+            _ => {
+                let word = word.to_lowercase();
+                if negated {
+                    clause.excluded.push(word);
+                } else {
+                    clause.terms.push(word);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '"' {
+                break;
+            }
+            word.push(c);
+            self.pos += 1;
+        }
+        word
+    }
+
+    /// Unquoted text values keep going until the next filter, operator, or quote so multi-word
+    /// names can be used without quotes at the end of the query
+    fn read_field_value(&mut self, mut value: String) -> String {
+        loop {
+            let end = self.pos;
+            self.skip_whitespace();
+            let stop = self.pos == end
+                || self.peek().is_none_or(|c| c == '"')
+                || (self.peek() == Some('-')
+                    && self.peek_at(1).is_some_and(|c| !c.is_whitespace()))
+                || self.take_operator().is_some();
+            if stop {
+                self.pos = end;
+                return value;
+            }
+            let word = self.read_word();
+            if split_field(&word).is_some() {
+                self.pos = end;
+                return value;
+            }
+            value.push(' ');
+            value.push_str(&word);
+        }
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        // Skip the opening quote
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(value.trim().to_lowercase());
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(QueryError::UnterminatedQuote { position: start }),
+            }
+        }
+    }
+}
+
+/// Splits a word like "artist:name" into the field and its value. Any other word with a colon,
+/// like "Interlude:" or "re:stacks", is searched as plain text.
+fn split_field(word: &str) -> Option<(&str, &str)> {
+    word.split_once(':')
+        .filter(|(field, _)| FIELDS.iter().any(|f| f.eq_ignore_ascii_case(field)))
+}
+
+fn parse_field(field: &str, value: String, position: usize) -> Result<FieldFilter, QueryError> {
+    match field.to_lowercase().as_str() {
+        "artist" => Ok(FieldFilter::Artist(value)),
+        "album" => Ok(FieldFilter::Album(value)),
+        "genre" => Ok(FieldFilter::Genre(value)),
+        // split_field only returns known fields
+        _ => parse_year_range(&value, position),
+    }
+}
+
+fn parse_year_range(value: &str, position: usize) -> Result<FieldFilter, QueryError> {
+    let invalid = || QueryError::InvalidYear {
+        value: value.to_owned(),
+        position,
+    };
+    let parse_year = |year: &str| {
+        year.parse::<i64>()
+            .ok()
+            .filter(|y| (MIN_YEAR..=MAX_YEAR).contains(y))
+            .ok_or_else(invalid)
+    };
+
+    let (start, end) = match value.split_once("..") {
+        // Either side of the range can be left open, but not both
+        Some(("", "")) => return Err(invalid()),
+        Some((start, end)) => (
+            if start.is_empty() {
+                MIN_YEAR
+            } else {
+                parse_year(start)?
+            },
+            if end.is_empty() {
+                MAX_YEAR
+            } else {
+                parse_year(end)?
+            },
+        ),
+        None => {
+            let year = parse_year(value)?;
+            (year, year)
+        }
+    };
+
+    if start > end {
+        return Err(invalid());
+    }
+    Ok(FieldFilter::Year { start, end })
+}
@@ -0,0 +1,117 @@
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use super::query_parser::{FieldFilter, Filter, QueryClause, parse_query};
+use super::search_error::QueryError;
+
+fn terms(terms: &[&str]) -> QueryClause {
+    QueryClause {
+        terms: terms.iter().map(|t| t.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[rstest(
+    query,
+    expected,
+    case("", vec![]),
+    case("Red Hot", vec![terms(&["red", "hot"])]),
+    case("war or peace", vec![terms(&["war", "or", "peace"])]),
+    case("red OR blue", vec![terms(&["red"]), terms(&["blue"])]),
+    case("red | blue green", vec![terms(&["red"]), terms(&["blue", "green"])]),
+    case("10:30", vec![terms(&["10:30"])]),
+    case("Interlude: Outro", vec![terms(&["interlude:", "outro"])]),
+    case("song mood:happy re:stacks", vec![terms(&["song", "mood:happy", "re:stacks"])]),
+    case(
+        "Mission:Impossible artist:abc",
+        vec![QueryClause {
+            terms: vec!["mission:impossible".to_owned()],
+            filters: vec![Filter {
+                field: FieldFilter::Artist("abc".to_owned()),
+                negated: false,
+            }],
+            ..Default::default()
+        }]
+    ),
+    case("a - b", vec![terms(&["a", "-", "b"])]),
+    case(
+        "\"Hot Chili\" pep -live -\"bad mix\"",
+        vec![QueryClause {
+            terms: vec!["pep".to_owned()],
+            phrases: vec!["hot chili".to_owned()],
+            excluded: vec!["live".to_owned(), "bad mix".to_owned()],
+            ..Default::default()
+        }]
+    ),
+    case(
+        "untitled artist:\"Red Hot\" -album:live genre:rock",
+        vec![QueryClause {
+            terms: vec!["untitled".to_owned()],
+            filters: vec![
+                Filter { field: FieldFilter::Artist("red hot".to_owned()), negated: false },
+                Filter { field: FieldFilter::Album("live".to_owned()), negated: true },
+                Filter { field: FieldFilter::Genre("rock".to_owned()), negated: false },
+            ],
+            ..Default::default()
+        }]
+    ),
+    case(
+        "live artist:Red Hot Chili  Peppers",
+        vec![QueryClause {
+            terms: vec!["live".to_owned()],
+            filters: vec![Filter {
+                field: FieldFilter::Artist("red hot chili peppers".to_owned()),
+                negated: false,
+            }],
+            ..Default::default()
+        }]
+    ),
+    case(
+        "artist:red hot album:by the way -genre:funk rock OR blue",
+        vec![
+            QueryClause {
+                filters: vec![
+                    Filter { field: FieldFilter::Artist("red hot".to_owned()), negated: false },
+                    Filter { field: FieldFilter::Album("by the way".to_owned()), negated: false },
+                    Filter { field: FieldFilter::Genre("funk rock".to_owned()), negated: true },
+                ],
+                ..Default::default()
+            },
+            terms(&["blue"]),
+        ]
+    ),
+    case(
+        "year:1990..1999 year:2001 YEAR:..1980 year:2010..",
+        vec![QueryClause {
+            filters: vec![
+                Filter { field: FieldFilter::Year { start: 1990, end: 1999 }, negated: false },
+                Filter { field: FieldFilter::Year { start: 2001, end: 2001 }, negated: false },
+                Filter { field: FieldFilter::Year { start: 1, end: 1980 }, negated: false },
+                Filter { field: FieldFilter::Year { start: 2010, end: 9999 }, negated: false },
+            ],
+            ..Default::default()
+        }]
+    )
+)]
+fn test_parse_query(query: &str, expected: Vec<QueryClause>) {
+    assert_eq!(expected, parse_query(query).unwrap());
+}
+
+#[rstest(
+    query,
+    expected,
+    case("artist:", QueryError::MissingValue { field: "artist".to_owned(), position: 0 }),
+    case("album:\"\"", QueryError::MissingValue { field: "album".to_owned(), position: 0 }),
+    case("year:19x0", QueryError::InvalidYear { value: "19x0".to_owned(), position: 0 }),
+    case("year:..", QueryError::InvalidYear { value: "..".to_owned(), position: 0 }),
+    case("year:2000..1990", QueryError::InvalidYear { value: "2000..1990".to_owned(), position: 0 }),
+    case("a \"open", QueryError::UnterminatedQuote { position: 2 }),
+    case("OR a", QueryError::MissingOperand { operator: "OR".to_owned(), position: 0 }),
+    case("a OR", QueryError::MissingOperand { operator: "OR".to_owned(), position: 2 }),
+    case("a | | b", QueryError::MissingOperand { operator: "|".to_owned(), position: 4 })
+)]
+fn test_parse_query_error(query: &str, expected: QueryError) {
+    let err = parse_query(query).unwrap_err();
+    assert_eq!(expected.position(), err.position());
+    assert_eq!(expected, err);
+}
@@ -11,7 +11,12 @@ use sqlx::{Pool, Row, Sqlite};
 use tap::Tap;
 use tracing::{info, warn};
 
-use super::queries::{clean_query, combine_spellfix_results, get_search_query, replace_ampersand};
+use super::queries::{
+    SearchFilters, SongCondition, SongFilter, clean_query, combine_spellfix_results,
+    get_match_suffix, get_search_query, replace_ampersand,
+};
+use super::query_parser::{FieldFilter, Filter, QueryClause, parse_query};
+use super::search_error::SearchError;
 use super::search_options::SearchOptions;
 use super::search_result::SearchResult;
 use crate::consts::{END_MATCH_TEXT, START_MATCH_TEXT};
@@ -40,21 +45,35 @@ impl SearchEngine {
         &self,
         query: &str,
         options: SearchOptions<'_>,
-    ) -> Result<Vec<SearchResult>, DbError> {
-        let query = query.trim().to_lowercase();
-        let res = match self.cache.read().get(&query) {
+    ) -> Result<Vec<SearchResult>, SearchError> {
+        // Operators are case sensitive so the query can't be normalized before parsing
+        let query = query.trim();
+        let res = match self.cache.read().get(query) {
             Some(val) => {
                 info!("Using cache for search {}", query);
                 val.to_owned()
             }
             None => {
                 let start = Instant::now();
-                // Parse out artist filter if it was supplied
-                let (adj_query, artist_filter) = self.split_artist_filter(&query).await?;
+                let clauses = parse_query(query)?;
 
+                let mut entries = vec![];
+                for clause in clauses {
+                    entries.extend(self.search_clause(clause, &options).await?);
+                }
+                // Results from separate clauses are ranked together. If they overlap, only the
+                // best match is kept.
+                let entries = entries
+                    .tap_mut(|e| e.sort())
+                    .into_iter()
+                    .unique_by(entry_key)
+                    .collect_vec();
                 let res = self
-                    .search_helper(&adj_query, &adj_query, options, artist_filter)
-                    .await?;
+                    .convert_entries(entries)
+                    .into_iter()
+                    .take(options.limit as usize)
+                    .collect_vec();
+
                 let time_taken = start.elapsed();
                 if time_taken > Duration::from_millis(50) {
                     warn!("Search for {query} was slow: {time_taken:?}. Caching result");
@@ -78,30 +97,121 @@ impl SearchEngine {
         write_tx.commit();
     }
 
-    async fn split_artist_filter(&self, query: &str) -> Result<(String, Vec<String>), DbError> {
-        let artist_split = query.split("artist:").collect_vec();
-
-        match *artist_split.as_slice() {
-            [] => Ok(("".to_owned(), vec![])),
-            [query] => Ok((query.to_owned(), vec![])),
-            [query, artist, ..] => {
-                let artist_filter = self
-                    .search_helper(
-                        artist,
-                        query,
-                        SearchOptions {
-                            valid_entry_types: vec!["artist", "album_artist"],
-                            ..Default::default()
-                        },
-                        vec![],
-                    )
-                    .await?
-                    .into_iter()
-                    .map(|r| r.entry)
-                    .collect_vec();
-                Ok((query.to_owned(), artist_filter))
+    async fn search_clause(
+        &self,
+        mut clause: QueryClause,
+        options: &SearchOptions<'_>,
+    ) -> Result<Vec<SearchEntry>, DbError> {
+        let mut options = options.clone();
+        if clause.terms.is_empty() && clause.phrases.is_empty() {
+            // A clause with only field filters searches for the filter value itself
+            let Some(index) = clause
+                .filters
+                .iter()
+                .position(|f| !f.negated && f.field.text_value().is_some())
+            else {
+                return Ok(vec![]);
+            };
+            let filter = clause.filters.remove(index);
+            if options.valid_entry_types.is_empty() {
+                options.valid_entry_types = filter.field.entry_types().unwrap_or_default();
             }
+            clause
+                .terms
+                .push(filter.field.text_value().unwrap_or_default().to_owned());
         }
+
+        // Phrase words are included in the fuzzy search so they can be highlighted,
+        // the match suffix ensures the full phrase is present
+        let query = clause.terms.iter().chain(&clause.phrases).join(" ");
+        let Some(mut filters) = self.resolve_filters(&query, &clause.filters).await? else {
+            return Ok(vec![]);
+        };
+        filters.match_suffix = get_match_suffix(&clause.phrases, &clause.excluded);
+
+        self.search_entries(&query, &query, options, &filters).await
+    }
+
+    /// Converts field filters into SQL filters.
+    /// Returns None if a required filter can't match anything.
+    async fn resolve_filters(
+        &self,
+        query: &str,
+        filters: &[Filter],
+    ) -> Result<Option<SearchFilters>, DbError> {
+        let mut resolved = SearchFilters::default();
+        let mut included = FieldConditions::default();
+        let mut excluded = FieldConditions::default();
+
+        for filter in filters {
+            let conditions = if filter.negated {
+                &mut excluded
+            } else {
+                &mut included
+            };
+            match &filter.field {
+                FieldFilter::Year { start, end } => conditions.years.push((*start, *end)),
+                FieldFilter::Artist(value) => {
+                    let Some(results) = self.resolve_filter_value(value, query, filter).await?
+                    else {
+                        return Ok(None);
+                    };
+                    let artists = results.into_iter().map(|r| r.entry);
+                    if filter.negated {
+                        resolved.excluded_artists.extend(artists);
+                    } else {
+                        resolved.artists.extend(artists);
+                    }
+                }
+                FieldFilter::Album(value) => {
+                    let Some(results) = self.resolve_filter_value(value, query, filter).await?
+                    else {
+                        return Ok(None);
+                    };
+                    conditions
+                        .albums
+                        .extend(results.into_iter().flat_map(|r| r.correlation_ids));
+                }
+                FieldFilter::Genre(value) => {
+                    let Some(results) = self.resolve_filter_value(value, query, filter).await?
+                    else {
+                        return Ok(None);
+                    };
+                    conditions
+                        .genres
+                        .extend(results.into_iter().flat_map(|r| r.correlation_ids));
+                }
+            }
+        }
+
+        resolved.song_filters = included
+            .into_filters(false)
+            .chain(excluded.into_filters(true))
+            .collect();
+        Ok(Some(resolved))
+    }
+
+    /// Looks up the library entries matching the filter value.
+    /// Returns None if the filter is required and nothing matches.
+    async fn resolve_filter_value(
+        &self,
+        value: &str,
+        query: &str,
+        filter: &Filter,
+    ) -> Result<Option<Vec<SearchResult>>, DbError> {
+        let results = self
+            .search_helper(
+                value,
+                query,
+                SearchOptions {
+                    valid_entry_types: filter.field.entry_types().unwrap_or_default(),
+                    ..Default::default()
+                },
+                &SearchFilters::default(),
+            )
+            .await?;
+        // Nothing can match a required filter that doesn't exist in the library
+        Ok((!results.is_empty() || filter.negated).then_some(results))
     }
 
     fn restrict_num_terms(&self, spellfix_results: Vec<SpellfixResult>) -> Vec<SpellfixResult> {
@@ -153,8 +263,21 @@ impl SearchEngine {
         query: &str,
         original_query: &str,
         options: SearchOptions<'_>,
-        artist_filter: Vec<String>,
+        filters: &SearchFilters,
     ) -> Result<Vec<SearchResult>, DbError> {
+        let search_entries = self
+            .search_entries(query, original_query, options, filters)
+            .await?;
+        Ok(self.convert_entries(search_entries))
+    }
+
+    async fn search_entries(
+        &self,
+        query: &str,
+        original_query: &str,
+        options: SearchOptions<'_>,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchEntry>, DbError> {
         let query = clean_query(query);
         if query.is_empty() {
             return Ok(vec![]);
@@ -171,14 +294,14 @@ impl SearchEngine {
                 original_query,
                 HashMap::new(),
                 &options,
-                &artist_filter,
+                filters,
                 &mut conn,
             )
             .await?;
 
         // Already generated enough results, don't need to attempt spellfix
         if search_entries.len() == options.limit as usize {
-            return Ok(self.convert_entries(search_entries));
+            return Ok(search_entries);
         }
 
         let re = Regex::new(r"\s+").unwrap();
@@ -205,7 +328,7 @@ impl SearchEngine {
                 original_query,
                 weights.clone(),
                 &options,
-                &artist_filter,
+                filters,
                 &mut conn,
             )
             .await?;
@@ -214,21 +337,11 @@ impl SearchEngine {
             r.weights.clone_from(&weights)
         }
         search_entries.extend(rest);
-        let search_entries = search_entries
+        Ok(search_entries
             .into_iter()
-            .unique_by(|r| {
-                r.entry
-                    .clone()
-                    .replace(START_MATCH_TEXT, "")
-                    .replace(END_MATCH_TEXT, "")
-                    + "-"
-                    + &r.entry_type
-                    + &r.correlation_id.to_string()
-            })
+            .unique_by(entry_key)
             .take(options.limit as usize)
-            .collect_vec();
-
-        Ok(self.convert_entries(search_entries))
+            .collect_vec())
     }
 
     async fn run_search(
@@ -237,24 +350,32 @@ impl SearchEngine {
         original_query: &str,
         weights: HashMap<String, f32>,
         options: &SearchOptions<'_>,
-        artist_filter: &[String],
+        filters: &SearchFilters,
         con: &mut PoolConnection<Sqlite>,
     ) -> Result<Vec<SearchEntry>, DbError> {
-        let full_query = get_search_query(artist_filter, &options.valid_entry_types);
+        let full_query = get_search_query(filters, &options.valid_entry_types);
+        let match_query = if filters.match_suffix.is_empty() {
+            query.to_owned()
+        } else {
+            format!("({query}){}", filters.match_suffix)
+        };
 
         let mut sql_query = sqlx::query(&full_query)
             .bind(options.start_highlight)
             .bind(options.end_highlight)
-            .bind(query.to_owned())
+            .bind(match_query)
             .bind(options.limit * 2)
             .bind(options.limit);
 
-        for artist in artist_filter {
+        for artist in filters.artists.iter().chain(&filters.excluded_artists) {
             sql_query = sql_query.bind(artist.to_owned());
         }
         for entry_type in &options.valid_entry_types {
             sql_query = sql_query.bind(entry_type.to_owned());
         }
+        for value in filters.song_filters.iter().flat_map(SongFilter::values) {
+            sql_query = sql_query.bind(value);
+        }
 
         sql_query
             .map(|row: <sqlx::Sqlite as sqlx::Database>::Row| SearchEntry {
@@ -293,3 +414,35 @@ impl SearchEngine {
             .collect_vec()
     }
 }
+
+/// Identifies the same library entry across separate searches, ignoring highlighting
+fn entry_key(entry: &SearchEntry) -> String {
+    entry
+        .entry
+        .replace(START_MATCH_TEXT, "")
+        .replace(END_MATCH_TEXT, "")
+        + "-"
+        + &entry.entry_type
+        + &entry.correlation_id.to_string()
+}
+
+/// Filter values grouped by field. Values for the same field are combined with OR.
+#[derive(Default)]
+struct FieldConditions {
+    albums: Vec<i64>,
+    genres: Vec<i64>,
+    years: Vec<(i64, i64)>,
+}
+
+impl FieldConditions {
+    fn into_filters(self, negated: bool) -> impl Iterator<Item = SongFilter> {
+        [
+            (!self.albums.is_empty()).then_some(SongCondition::Albums(self.albums)),
+            (!self.genres.is_empty()).then_some(SongCondition::Genres(self.genres)),
+            (!self.years.is_empty()).then_some(SongCondition::Years(self.years)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(move |condition| SongFilter { condition, negated })
+    }
+}
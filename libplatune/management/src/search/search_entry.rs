@@ -84,6 +84,8 @@ impl SearchEntry {
             "album" => format!("Album by {}", self.artist.to_owned().unwrap_or_default()),
             "artist" => "Artist".to_owned(),
             "album_artist" => "Album Artist".to_owned(),
+            "genre" => "Genre".to_owned(),
+            "composer" => "Composer".to_owned(),
             _ => "".to_owned(),
         }
     }
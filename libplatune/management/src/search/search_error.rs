@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+use crate::db_error::DbError;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    #[error("Missing value for field '{field}' at position {position}")]
+    MissingValue { field: String, position: usize },
+    #[error("Invalid year '{value}' at position {position}")]
+    InvalidYear { value: String, position: usize },
+    #[error("Unterminated quote at position {position}")]
+    UnterminatedQuote { position: usize },
+    #[error("Operator '{operator}' at position {position} is missing an operand")]
+    MissingOperand { operator: String, position: usize },
+}
+
+impl QueryError {
+    /// Character offset in the query where the error was found
+    pub fn position(&self) -> usize {
+        match self {
+            Self::MissingValue { position, .. }
+            | Self::InvalidYear { position, .. }
+            | Self::UnterminatedQuote { position }
+            | Self::MissingOperand { position, .. } => *position,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum SearchError {
+    #[error("Invalid query: {0}")]
+    InvalidQuery(#[from] QueryError),
+    #[error(transparent)]
+    DbError(#[from] DbError),
+}
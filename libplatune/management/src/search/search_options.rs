@@ -1,3 +1,4 @@
+#[derive(Clone)]
 pub struct SearchOptions<'a> {
     pub start_highlight: &'a str,
     pub end_highlight: &'a str,
@@ -2,6 +2,7 @@ use std::fs::{self, create_dir_all};
 use std::sync::Arc;
 
 use futures::StreamExt;
+use itertools::Itertools;
 use lofty::config::WriteOptions;
 use lofty::file::TaggedFileExt;
 use lofty::probe::Probe;
@@ -14,6 +15,7 @@ use tracing::info;
 use crate::config::MemoryConfig;
 use crate::database::Database;
 use crate::manager::Manager;
+use crate::search::search_error::{QueryError, SearchError};
 
 #[derive(Default)]
 pub struct SongTest {
@@ -363,6 +365,27 @@ pub struct SearchResultTest {
             SearchResultTest {entry: "untitled 2", correlation_ids: vec![2]},
         ],
         "untitled artist:rhcp"),
+    case(vec![
+        SongTest {
+            artist: Some("red hot chili peppers"),
+            title: Some("untitled"),
+            ..Default::default()
+        },
+        SongTest {
+            artist: Some("red hot chili peppers"),
+            title: Some("untitled 2"),
+            ..Default::default()
+        },
+        SongTest {
+            artist: Some("bag"),
+            title: Some("untitled"),
+            ..Default::default()
+        }],
+        vec![
+            SearchResultTest {entry: "untitled", correlation_ids: vec![3]},
+            SearchResultTest {entry: "untitled 2", correlation_ids: vec![2]},
+        ],
+        "untitled artist:red hot chili peppers"),
     case(vec![
         SongTest {
             artist: Some("abc test"),
@@ -516,6 +539,9 @@ pub async fn test_search(songs: Vec<SongTest>, results: Vec<SearchResultTest>, s
         fs::copy("../test_assets/test.mp3", song_path.clone()).unwrap();
         let mut t = Probe::open(&song_path).unwrap().read().unwrap();
         let tag = t.primary_tag_mut().unwrap();
+        // The test file has a genre and composer which would show up in the results
+        tag.remove_genre();
+        tag.remove_key(ItemKey::Composer);
         if let Some(title) = song.title {
             tag.set_title(title.to_owned());
         }
@@ -565,3 +591,120 @@ async fn setup() -> (Database, Manager) {
     let manager = Manager::new(&db, config);
     (db, manager)
 }
+
+#[rstest(
+    query,
+    expected,
+    case("rain", vec!["rain song", "rain dance", "rain man"]),
+    case("rain album:storms", vec!["rain song", "rain dance"]),
+    case("rain -album:storms", vec!["rain man"]),
+    case("rain genre:jazz", vec!["rain dance", "rain man"]),
+    case("rain year:1990..1999", vec!["rain song"]),
+    case("rain year:..1999 OR man", vec!["rain song", "rain man"]),
+    case("\"rain dance\"", vec!["rain dance"]),
+    case("rain -dance -song", vec!["rain man"]),
+    case("rain: dance", vec!["rain dance"]),
+    case("genre:jazz", vec!["jazz"]),
+    case("rain album:missing", vec![])
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_search_query_syntax(query: &str, expected: Vec<&str>) {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let songs = [
+        ("rain song", "storms", "rock", "1994"),
+        ("rain dance", "storms", "jazz", "2004"),
+        ("rain man", "clouds", "jazz", "2010"),
+    ];
+    for (i, (title, album, genre, year)) in songs.into_iter().enumerate() {
+        let song_path = music_dir.join(format!("test{i}.mp3"));
+        fs::copy("../test_assets/test.mp3", song_path.clone()).unwrap();
+        let mut t = Probe::open(&song_path).unwrap().read().unwrap();
+        let tag = t.primary_tag_mut().unwrap();
+        tag.remove_key(ItemKey::Composer);
+        tag.set_title(title.to_owned());
+        tag.set_artist("artist".to_owned());
+        tag.set_album(album.to_owned());
+        tag.set_genre(genre.to_owned());
+        tag.remove_key(ItemKey::Year);
+        tag.insert_text(ItemKey::RecordingDate, year.to_owned());
+        tag.save_to_path(song_path, WriteOptions::new()).unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let res = manager.search(query, Default::default()).await.unwrap();
+    assert_eq!(
+        expected.into_iter().sorted().collect_vec(),
+        res.iter().map(|r| r.entry.as_str()).sorted().collect_vec()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_search_invalid_query() {
+    let (_, manager) = setup().await;
+
+    let res = manager.search("song year:19x0", Default::default()).await;
+    assert!(matches!(
+        res,
+        Err(SearchError::InvalidQuery(QueryError::InvalidYear {
+            position: 5,
+            ..
+        }))
+    ));
+}
+
+#[rstest(
+    query,
+    expected,
+    case("rain artist:guest", vec!["rain song", "rain dance"]),
+    case("rain artist:artist", vec!["rain song", "rain man"]),
+    case("rain -artist:guest", vec!["rain man"])
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_search_credited_artists(query: &str, expected: Vec<&str>) {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    // Featured artists should match the artist filter even though they aren't the song's artist
+    let songs = [
+        ("rain song", "artist feat. guest"),
+        ("rain dance", "guest"),
+        ("rain man", "artist"),
+    ];
+    for (i, (title, artist)) in songs.into_iter().enumerate() {
+        let song_path = music_dir.join(format!("test{i}.mp3"));
+        fs::copy("../test_assets/test.mp3", song_path.clone()).unwrap();
+        let mut t = Probe::open(&song_path).unwrap().read().unwrap();
+        let tag = t.primary_tag_mut().unwrap();
+        tag.remove_genre();
+        tag.remove_key(ItemKey::Composer);
+        tag.set_title(title.to_owned());
+        tag.set_artist(artist.to_owned());
+        tag.set_album("album".to_owned());
+        tag.save_to_path(song_path, WriteOptions::new()).unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let res = manager.search(query, Default::default()).await.unwrap();
+    assert_eq!(
+        expected.into_iter().sorted().collect_vec(),
+        res.iter().map(|r| r.entry.as_str()).sorted().collect_vec()
+    );
+}
@@ -0,0 +1,7 @@
+pub(crate) mod queries;
+pub mod smart_playlist_definition;
+pub mod smart_playlist_error;
+
+#[cfg(test)]
+#[path = "./smart_playlist_test.rs"]
+mod smart_playlist_test;
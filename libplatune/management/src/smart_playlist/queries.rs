@@ -0,0 +1,244 @@
+use super::smart_playlist_definition::{
+    FieldKind, RuleField, RuleOperator, SmartPlaylistDefinition, SmartPlaylistRule,
+    SmartPlaylistSort,
+};
+use super::smart_playlist_error::SmartPlaylistError;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BindValue {
+    Text(String),
+    Int(i64),
+}
+
+pub(crate) fn smart_playlists_query(filter_id: bool) -> String {
+    let filter_clause = if filter_id {
+        "WHERE sp.smart_playlist_id = ?"
+    } else {
+        ""
+    };
+    format!(
+        "
+        SELECT sp.smart_playlist_id, sp.smart_playlist_name, sp.match_all, sp.sort, sp.descending,
+        sp.song_limit, sp.evaluated_date, COUNT(sps.smart_playlist_song_id) song_count
+        FROM smart_playlist sp
+        LEFT OUTER JOIN smart_playlist_song sps ON sps.smart_playlist_id = sp.smart_playlist_id
+        {filter_clause}
+        GROUP BY sp.smart_playlist_id
+        ORDER BY sp.smart_playlist_name
+        "
+    )
+}
+
+pub(crate) fn smart_playlist_rules_query(filter_id: bool) -> String {
+    let filter_clause = if filter_id {
+        "WHERE smart_playlist_id = ?"
+    } else {
+        ""
+    };
+    format!(
+        "
+        SELECT smart_playlist_id, field, operator, value
+        FROM smart_playlist_rule
+        {filter_clause}
+        ORDER BY smart_playlist_id, smart_playlist_rule_id
+        "
+    )
+}
+
+pub(crate) fn smart_playlist_songs_query() -> &'static str {
+    "
+    SELECT s.song_id, ar.artist_name artist, s.song_title song, s.song_path path,
+    s.duration duration_millis, al.album_name album, aa.artist_name album_artist,
+    s.track_number track_number, s.track_gain, s.track_peak, s.album_gain, s.album_peak,
+    s.album_art_path album_art, (
+        SELECT GROUP_CONCAT(g.genre_name, '; ' ORDER BY sg.position) FROM song_genre sg
+        INNER JOIN genre g ON g.genre_id = sg.genre_id WHERE sg.song_id = s.song_id
+    ) genre, s.composer, s.comment, s.bpm, s.release_date, s.original_year,
+    s.musicbrainz_recording_id, s.musicbrainz_release_id, s.musicbrainz_artist_id,
+    s.musicbrainz_release_group_id
+    FROM smart_playlist_song sps
+    INNER JOIN song s ON s.song_id = sps.song_id
+    INNER JOIN artist ar ON ar.artist_id = s.artist_id
+    INNER JOIN album al ON al.album_id = s.album_id
+    INNER JOIN artist aa ON aa.artist_id = al.artist_id
+    WHERE sps.smart_playlist_id = ?
+    ORDER BY sps.position
+    "
+}
+
+/// Builds the query that selects the ids of all songs matching the playlist's rules.
+/// `now` is the unix timestamp used as the reference point for relative date rules.
+pub(crate) fn evaluate_query(
+    definition: &SmartPlaylistDefinition,
+    now: i64,
+) -> Result<(String, Vec<BindValue>), SmartPlaylistError> {
+    definition.validate()?;
+
+    let mut values = vec![];
+    let mut conditions = vec![];
+    for rule in &definition.rules {
+        let (condition, value) = rule_condition(rule, now)?;
+        conditions.push(condition);
+        values.push(value);
+    }
+
+    let rule_clause = if conditions.is_empty() {
+        "".to_owned()
+    } else {
+        let separator = if definition.match_all {
+            " AND "
+        } else {
+            " OR "
+        };
+        format!("AND ({})", conditions.join(separator))
+    };
+
+    let limit_clause = match definition.limit {
+        Some(limit) => {
+            values.push(BindValue::Int(limit));
+            "LIMIT ?"
+        }
+        None => "",
+    };
+    let order_clause = order_clause(definition.sort, definition.descending);
+
+    let query = format!(
+        "
+        SELECT s.song_id
+        FROM song s
+        INNER JOIN artist ar ON ar.artist_id = s.artist_id
+        INNER JOIN album al ON al.album_id = s.album_id
+        INNER JOIN artist aa ON aa.artist_id = al.artist_id
+        LEFT JOIN (
+            SELECT song_id, MAX(played_date) last_played,
+            COUNT(1) FILTER (WHERE skipped) skip_count
+            FROM song_history
+            GROUP BY song_id
+        ) h ON h.song_id = s.song_id
+        WHERE NOT EXISTS (SELECT 1 FROM deleted_song ds WHERE ds.song_id = s.song_id)
+        {rule_clause}
+        ORDER BY {order_clause}
+        {limit_clause}
+        "
+    );
+
+    Ok((query, values))
+}
+
+fn field_column(field: RuleField) -> &'static str {
+    match field {
+        RuleField::Title => "s.song_title",
+        RuleField::Artist => "ar.artist_name",
+        RuleField::AlbumArtist => "aa.artist_name",
+        RuleField::Album => "al.album_name",
+        RuleField::Genre => "rg.genre_name",
+        RuleField::Composer => "s.composer",
+        RuleField::Comment => "s.comment",
+        RuleField::Path => "s.song_path",
+        // Songs without a year are stored as 0
+        RuleField::Year => "NULLIF(s.song_year, 0)",
+        RuleField::Bpm => "s.bpm",
+        RuleField::Duration => "s.duration / 1000",
+        RuleField::PlayCount => "s.play_count",
+        RuleField::SkipCount => "COALESCE(h.skip_count, 0)",
+        RuleField::LastPlayed => "h.last_played",
+        RuleField::DateAdded => "s.created_date",
+    }
+}
+
+fn rule_condition(
+    rule: &SmartPlaylistRule,
+    now: i64,
+) -> Result<(String, BindValue), SmartPlaylistError> {
+    let column = field_column(rule.field);
+    let value = match rule.field.kind() {
+        FieldKind::Text => BindValue::Text(match rule.operator {
+            RuleOperator::Contains | RuleOperator::NotContains => {
+                format!("%{}%", escape_like(&rule.value))
+            }
+            RuleOperator::StartsWith => format!("{}%", escape_like(&rule.value)),
+            RuleOperator::EndsWith => format!("%{}", escape_like(&rule.value)),
+            _ => rule.value.clone(),
+        }),
+        FieldKind::Number => BindValue::Int(rule.int_value()?),
+        FieldKind::Date => BindValue::Int(now - rule.int_value()? * SECONDS_PER_DAY),
+    };
+
+    let collate = if rule.field.kind() == FieldKind::Text {
+        " COLLATE NOCASE"
+    } else {
+        ""
+    };
+    // Songs can have several genres, so the rule matches if any of them does and a negative
+    // rule only matches if none of them do
+    if rule.field == RuleField::Genre {
+        let (negate, operator) = match rule.operator {
+            RuleOperator::IsNot => ("NOT ", RuleOperator::Is),
+            RuleOperator::NotContains => ("NOT ", RuleOperator::Contains),
+            operator => ("", operator),
+        };
+        let condition = operator_condition(column, operator, collate);
+        return Ok((
+            format!(
+                "{negate}EXISTS (SELECT 1 FROM song_genre rsg INNER JOIN genre rg ON rg.genre_id = \
+                 rsg.genre_id WHERE rsg.song_id = s.song_id AND {condition})"
+            ),
+            value,
+        ));
+    }
+
+    Ok((operator_condition(column, rule.operator, collate), value))
+}
+
+fn operator_condition(column: &str, operator: RuleOperator, collate: &str) -> String {
+    // Negative conditions should also match empty values
+    match operator {
+        RuleOperator::Is => format!("{column} = ?{collate}"),
+        RuleOperator::IsNot => format!("({column} IS NULL OR {column} != ?{collate})"),
+        RuleOperator::Contains | RuleOperator::StartsWith | RuleOperator::EndsWith => {
+            format!("{column} LIKE ? ESCAPE '\\'")
+        }
+        RuleOperator::NotContains => {
+            format!("({column} IS NULL OR {column} NOT LIKE ? ESCAPE '\\')")
+        }
+        RuleOperator::GreaterThan => format!("{column} > ?"),
+        RuleOperator::LessThan => format!("{column} < ?"),
+        RuleOperator::InLast => format!("{column} >= ?"),
+        RuleOperator::NotInLast => format!("({column} IS NULL OR {column} < ?)"),
+    }
+}
+
+fn order_clause(sort: SmartPlaylistSort, descending: bool) -> String {
+    let direction = if descending { "DESC" } else { "ASC" };
+    let album_order = "al.album_name COLLATE NOCASE, s.disc_number, s.track_number";
+    match sort {
+        SmartPlaylistSort::Random => "RANDOM()".to_owned(),
+        SmartPlaylistSort::Title => format!("s.song_title COLLATE NOCASE {direction}, s.song_id"),
+        SmartPlaylistSort::Artist => {
+            format!("ar.artist_name COLLATE NOCASE {direction}, {album_order}")
+        }
+        SmartPlaylistSort::Album => format!(
+            "al.album_name COLLATE NOCASE {direction}, aa.artist_name COLLATE NOCASE, \
+             s.disc_number, s.track_number"
+        ),
+        // Songs without a year always go last
+        SmartPlaylistSort::Year => {
+            format!("s.song_year = 0, s.song_year {direction}, {album_order}")
+        }
+        SmartPlaylistSort::DateAdded => format!("s.created_date {direction}, {album_order}"),
+        SmartPlaylistSort::PlayCount => format!("s.play_count {direction}, s.song_id"),
+        // Songs that have never been played always go last
+        SmartPlaylistSort::LastPlayed => {
+            format!("h.last_played IS NULL, h.last_played {direction}, s.song_id")
+        }
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
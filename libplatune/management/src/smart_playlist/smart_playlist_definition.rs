@@ -0,0 +1,237 @@
+use std::str::FromStr;
+
+use strum::{Display, EnumString};
+
+use super::smart_playlist_error::SmartPlaylistError;
+use crate::db_error::DbError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FieldKind {
+    Text,
+    Number,
+    Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum RuleField {
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    Genre,
+    Composer,
+    Comment,
+    Path,
+    Year,
+    Bpm,
+    /// Track length in seconds
+    Duration,
+    PlayCount,
+    SkipCount,
+    LastPlayed,
+    DateAdded,
+}
+
+impl RuleField {
+    pub(crate) fn kind(&self) -> FieldKind {
+        match self {
+            Self::Title
+            | Self::Artist
+            | Self::AlbumArtist
+            | Self::Album
+            | Self::Genre
+            | Self::Composer
+            | Self::Comment
+            | Self::Path => FieldKind::Text,
+            Self::Year | Self::Bpm | Self::Duration | Self::PlayCount | Self::SkipCount => {
+                FieldKind::Number
+            }
+            Self::LastPlayed | Self::DateAdded => FieldKind::Date,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum RuleOperator {
+    Is,
+    IsNot,
+    Contains,
+    NotContains,
+    StartsWith,
+    EndsWith,
+    GreaterThan,
+    LessThan,
+    /// Value is a number of days
+    InLast,
+    /// Value is a number of days. Also matches songs that have never been played.
+    NotInLast,
+}
+
+impl RuleOperator {
+    pub(crate) fn supports(&self, kind: FieldKind) -> bool {
+        match self {
+            Self::Is | Self::IsNot => kind != FieldKind::Date,
+            Self::Contains | Self::NotContains | Self::StartsWith | Self::EndsWith => {
+                kind == FieldKind::Text
+            }
+            Self::GreaterThan | Self::LessThan => kind == FieldKind::Number,
+            Self::InLast | Self::NotInLast => kind == FieldKind::Date,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum SmartPlaylistSort {
+    #[default]
+    Random,
+    Title,
+    Artist,
+    Album,
+    Year,
+    DateAdded,
+    PlayCount,
+    LastPlayed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartPlaylistRule {
+    pub field: RuleField,
+    pub operator: RuleOperator,
+    pub value: String,
+}
+
+impl SmartPlaylistRule {
+    pub fn new(field: RuleField, operator: RuleOperator, value: impl Into<String>) -> Self {
+        Self {
+            field,
+            operator,
+            value: value.into(),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), SmartPlaylistError> {
+        if !self.operator.supports(self.field.kind()) {
+            return Err(SmartPlaylistError::UnsupportedOperator {
+                field: self.field,
+                operator: self.operator,
+            });
+        }
+        if self.field.kind() != FieldKind::Text {
+            self.int_value()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn int_value(&self) -> Result<i64, SmartPlaylistError> {
+        self.value
+            .trim()
+            .parse()
+            .map_err(|_| SmartPlaylistError::InvalidValue {
+                field: self.field,
+                value: self.value.clone(),
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartPlaylistDefinition {
+    pub name: String,
+    pub rules: Vec<SmartPlaylistRule>,
+    /// Songs must match every rule if true, otherwise any rule
+    pub match_all: bool,
+    pub sort: SmartPlaylistSort,
+    pub descending: bool,
+    pub limit: Option<i64>,
+}
+
+impl SmartPlaylistDefinition {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            rules: vec![],
+            match_all: true,
+            sort: SmartPlaylistSort::Random,
+            descending: false,
+            limit: None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), SmartPlaylistError> {
+        if let Some(limit) = self.limit
+            && limit <= 0
+        {
+            return Err(SmartPlaylistError::InvalidLimit(limit));
+        }
+        self.rules.iter().try_for_each(|r| r.validate())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmartPlaylist {
+    pub smart_playlist_id: i64,
+    pub definition: SmartPlaylistDefinition,
+    /// Number of songs from the last evaluation
+    pub song_count: i64,
+    pub evaluated_date: Option<i64>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct SmartPlaylistRow {
+    pub(crate) smart_playlist_id: i64,
+    pub(crate) smart_playlist_name: String,
+    pub(crate) match_all: bool,
+    pub(crate) sort: String,
+    pub(crate) descending: bool,
+    pub(crate) song_limit: Option<i64>,
+    pub(crate) evaluated_date: Option<i64>,
+    pub(crate) song_count: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct SmartPlaylistRuleRow {
+    pub(crate) smart_playlist_id: i64,
+    pub(crate) field: String,
+    pub(crate) operator: String,
+    pub(crate) value: String,
+}
+
+impl SmartPlaylistRow {
+    pub(crate) fn into_smart_playlist(
+        self,
+        rules: &[SmartPlaylistRuleRow],
+    ) -> Result<SmartPlaylist, DbError> {
+        let rules: Result<Vec<_>, _> = rules
+            .iter()
+            .filter(|r| r.smart_playlist_id == self.smart_playlist_id)
+            .map(|r| {
+                Ok(SmartPlaylistRule {
+                    field: parse_column(&r.field)?,
+                    operator: parse_column(&r.operator)?,
+                    value: r.value.clone(),
+                })
+            })
+            .collect();
+
+        Ok(SmartPlaylist {
+            smart_playlist_id: self.smart_playlist_id,
+            definition: SmartPlaylistDefinition {
+                name: self.smart_playlist_name,
+                rules: rules?,
+                match_all: self.match_all,
+                sort: parse_column(&self.sort)?,
+                descending: self.descending,
+                limit: self.song_limit,
+            },
+            song_count: self.song_count,
+            evaluated_date: self.evaluated_date,
+        })
+    }
+}
+
+fn parse_column<T: FromStr>(value: &str) -> Result<T, DbError> {
+    T::from_str(value)
+        .map_err(|_| DbError::DbError(format!("Invalid smart playlist value '{value}'")))
+}
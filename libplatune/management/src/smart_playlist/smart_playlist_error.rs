@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+use super::smart_playlist_definition::{RuleField, RuleOperator};
+use crate::db_error::DbError;
+
+#[derive(Error, Debug, Clone)]
+pub enum SmartPlaylistError {
+    #[error("Operator '{operator}' can't be used with field '{field}'")]
+    UnsupportedOperator {
+        field: RuleField,
+        operator: RuleOperator,
+    },
+    #[error("Invalid value '{value}' for field '{field}'")]
+    InvalidValue { field: RuleField, value: String },
+    #[error("Invalid song limit {0}")]
+    InvalidLimit(i64),
+    #[error("Smart playlist {0} does not exist")]
+    NotFound(i64),
+    #[error(transparent)]
+    DbError(#[from] DbError),
+}
@@ -0,0 +1,262 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use itertools::Itertools;
+use pretty_assertions::assert_eq;
+use rstest::*;
+use tempfile::TempDir;
+
+use super::smart_playlist_definition::{
+    RuleField, RuleOperator, SmartPlaylistDefinition, SmartPlaylistRule, SmartPlaylistSort,
+};
+use super::smart_playlist_error::SmartPlaylistError;
+use crate::manager::Manager;
+use crate::test_util::{TestSong, setup_library};
+
+// Tags for each song along with the number of times it was played
+const SONGS: [(TestSong, usize); 4] = [
+    (
+        TestSong {
+            title: "song1",
+            artist: "artist1",
+            album_artist: "artist1",
+            album: "artist1_album",
+            genre: "Jazz",
+            year: 1959,
+        },
+        0,
+    ),
+    (
+        TestSong {
+            title: "song2",
+            artist: "artist1",
+            album_artist: "artist1",
+            album: "artist1_album",
+            genre: "Jazz",
+            year: 1964,
+        },
+        5,
+    ),
+    (
+        TestSong {
+            title: "song3",
+            artist: "artist2",
+            album_artist: "artist2",
+            album: "artist2_album",
+            genre: "Rock",
+            year: 1994,
+        },
+        1,
+    ),
+    (
+        TestSong {
+            title: "song4",
+            artist: "artist_2",
+            album_artist: "artist_2",
+            album: "artist_2_album",
+            genre: "",
+            year: 0,
+        },
+        2,
+    ),
+];
+
+#[rstest(
+    rules,
+    match_all,
+    expected,
+    case(vec![], true, vec!["song1", "song2", "song3", "song4"]),
+    case(
+        vec![
+            SmartPlaylistRule::new(RuleField::Genre, RuleOperator::Is, "jazz"),
+            SmartPlaylistRule::new(RuleField::PlayCount, RuleOperator::LessThan, "3"),
+            SmartPlaylistRule::new(RuleField::DateAdded, RuleOperator::InLast, "30"),
+        ],
+        true,
+        vec!["song1"]
+    ),
+    case(
+        vec![
+            SmartPlaylistRule::new(RuleField::Genre, RuleOperator::Is, "rock"),
+            SmartPlaylistRule::new(RuleField::Year, RuleOperator::LessThan, "1960"),
+        ],
+        false,
+        vec!["song1", "song3"]
+    ),
+    case(
+        vec![SmartPlaylistRule::new(RuleField::Genre, RuleOperator::IsNot, "jazz")],
+        true,
+        vec!["song3", "song4"]
+    ),
+    case(
+        vec![SmartPlaylistRule::new(RuleField::Artist, RuleOperator::Contains, "_")],
+        true,
+        vec!["song4"]
+    ),
+    case(
+        vec![SmartPlaylistRule::new(RuleField::LastPlayed, RuleOperator::NotInLast, "1")],
+        true,
+        vec!["song1"]
+    )
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_smart_playlist_rules(
+    rules: Vec<SmartPlaylistRule>,
+    match_all: bool,
+    expected: Vec<&str>,
+) {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let smart_playlist_id = manager
+        .create_smart_playlist(&SmartPlaylistDefinition {
+            rules,
+            match_all,
+            sort: SmartPlaylistSort::Title,
+            ..SmartPlaylistDefinition::new("smart")
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(expected, song_titles(&manager, smart_playlist_id).await);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_smart_playlist_sort_and_limit() {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let smart_playlist_id = manager
+        .create_smart_playlist(&SmartPlaylistDefinition {
+            sort: SmartPlaylistSort::PlayCount,
+            descending: true,
+            limit: Some(2),
+            ..SmartPlaylistDefinition::new("most played")
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["song2", "song4"],
+        song_titles(&manager, smart_playlist_id).await
+    );
+
+    let random_id = manager
+        .create_smart_playlist(&SmartPlaylistDefinition {
+            limit: Some(3),
+            ..SmartPlaylistDefinition::new("random")
+        })
+        .await
+        .unwrap();
+    let songs = manager.get_smart_playlist_songs(random_id).await.unwrap();
+    assert_eq!(3, songs.len());
+    assert_eq!(3, songs.iter().map(|s| s.song_id).unique().count());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_smart_playlist_update_and_delete() {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let mut definition = SmartPlaylistDefinition {
+        rules: vec![SmartPlaylistRule::new(
+            RuleField::Genre,
+            RuleOperator::Is,
+            "Jazz",
+        )],
+        sort: SmartPlaylistSort::Year,
+        descending: true,
+        ..SmartPlaylistDefinition::new("jazz")
+    };
+    let smart_playlist_id = manager.create_smart_playlist(&definition).await.unwrap();
+    assert_eq!(
+        vec!["song2", "song1"],
+        song_titles(&manager, smart_playlist_id).await
+    );
+
+    definition.name = "rock".to_owned();
+    definition.rules[0].value = "Rock".to_owned();
+    manager
+        .update_smart_playlist(smart_playlist_id, &definition)
+        .await
+        .unwrap();
+
+    let playlists = manager.get_all_smart_playlists().await.unwrap();
+    assert_eq!(1, playlists.len());
+    assert_eq!(definition, playlists[0].definition);
+    assert_eq!(1, playlists[0].song_count);
+    assert!(playlists[0].evaluated_date.is_some());
+
+    let songs = manager
+        .evaluate_smart_playlist(smart_playlist_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["song3"],
+        songs.iter().map(|s| s.song.as_str()).collect_vec()
+    );
+
+    // Deleted tracks should be removed from the stored results
+    manager.delete_tracks(vec![songs[0].song_id]).await.unwrap();
+    assert!(song_titles(&manager, smart_playlist_id).await.is_empty());
+
+    manager
+        .delete_smart_playlists(vec![smart_playlist_id])
+        .await
+        .unwrap();
+    assert!(manager.get_all_smart_playlists().await.unwrap().is_empty());
+}
+
+#[rstest(
+    rule,
+    case(SmartPlaylistRule::new(RuleField::PlayCount, RuleOperator::Contains, "1")),
+    case(SmartPlaylistRule::new(RuleField::Title, RuleOperator::InLast, "1")),
+    case(SmartPlaylistRule::new(RuleField::Year, RuleOperator::Is, "the nineties"))
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_smart_playlist_invalid_rule(rule: SmartPlaylistRule) {
+    let tempdir = TempDir::new().unwrap();
+    let manager = setup(tempdir.path()).await;
+
+    let res = manager
+        .create_smart_playlist(&SmartPlaylistDefinition {
+            rules: vec![rule],
+            ..SmartPlaylistDefinition::new("invalid")
+        })
+        .await;
+
+    assert!(matches!(
+        res,
+        Err(SmartPlaylistError::UnsupportedOperator { .. }
+            | SmartPlaylistError::InvalidValue { .. })
+    ));
+    assert!(manager.get_all_smart_playlists().await.unwrap().is_empty());
+}
+
+async fn song_titles(manager: &Manager, smart_playlist_id: i64) -> Vec<String> {
+    manager
+        .get_smart_playlist_songs(smart_playlist_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| s.song)
+        .collect()
+}
+
+async fn setup(tempdir: &Path) -> Manager {
+    let (manager, song_paths) = setup_library(tempdir, &SONGS.map(|(song, _)| song)).await;
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    for ((_, plays), path) in SONGS.iter().zip(song_paths) {
+        for _ in 0..*plays {
+            manager
+                .record_play(&path, now, 60 * 1000, false)
+                .await
+                .unwrap();
+        }
+    }
+
+    manager
+}
@@ -0,0 +1,116 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use image::ImageFormat;
+use lofty::file::{TaggedFile, TaggedFileExt};
+use lofty::picture::PictureType;
+
+use super::sync_engine::SyncError;
+
+pub const THUMBNAIL_DIR: &str = "thumbnails";
+const THUMBNAIL_SIZE: u32 = 300;
+// Checked in order of preference
+const FOLDER_IMAGE_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const FOLDER_IMAGE_EXTS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+pub(crate) fn embedded_art(tagged_file: &TaggedFile) -> Option<Vec<u8>> {
+    let pictures = tagged_file.tags().iter().flat_map(|tag| tag.pictures());
+    // Prefer the front cover, but any picture is better than nothing
+    pictures
+        .clone()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.clone().next())
+        .map(|picture| picture.data().to_vec())
+}
+
+pub(crate) fn folder_art(dir: &Path) -> Result<Option<Vec<u8>>, SyncError> {
+    match folder_art_path(dir)? {
+        Some(path) => fs::read(&path)
+            .map(Some)
+            .map_err(|e| SyncError::IOError(format!("Error reading image {path:?}: {e:?}"))),
+        None => Ok(None),
+    }
+}
+
+/// Finds the image file that would be used as the album art for songs in the folder
+pub(crate) fn folder_art_path(dir: &Path) -> Result<Option<PathBuf>, SyncError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| SyncError::IOError(format!("Error reading directory {dir:?}: {e:?}")))?;
+    let found = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_lowercase();
+            let ext = path.extension()?.to_str()?.to_lowercase();
+            let rank = FOLDER_IMAGE_NAMES.iter().position(|name| *name == stem)?;
+            FOLDER_IMAGE_EXTS
+                .contains(&&ext[..])
+                .then_some((rank, path))
+        })
+        .min_by_key(|(rank, _)| *rank);
+    Ok(found.map(|(_, path)| path))
+}
+
+/// Writes the image and a thumbnail to the cache if they don't exist yet and returns the name of
+/// the cached file. Images are named by a hash of their contents so each image is only stored
+/// once regardless of how many songs reference it.
+pub(crate) fn cache_art(cache_dir: &Path, data: &[u8]) -> Result<String, SyncError> {
+    let format = image::guess_format(data)
+        .map_err(|e| SyncError::ImageError(format!("Unrecognized image format: {e:?}")))?;
+    let ext = match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Png => "png",
+        _ => {
+            return Err(SyncError::ImageError(format!(
+                "Unsupported image format {format:?}"
+            )));
+        }
+    };
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    let hash = format!("{:016x}", hasher.finish());
+    let file_name = format!("{hash}.{ext}");
+
+    let image_path = cache_dir.join(&file_name);
+    if !image_path.exists() {
+        write_atomic(&image_path, data)?;
+    }
+
+    let thumbnail_path = cache_dir
+        .join(THUMBNAIL_DIR)
+        .join(thumbnail_name(&file_name));
+    if !thumbnail_path.exists() {
+        let thumbnail = image::load_from_memory_with_format(data, format)
+            .map_err(|e| SyncError::ImageError(format!("Error decoding image: {e:?}")))?
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .into_rgb8();
+        let mut bytes = Vec::new();
+        thumbnail
+            .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .map_err(|e| SyncError::ImageError(format!("Error encoding thumbnail: {e:?}")))?;
+        write_atomic(&thumbnail_path, &bytes)?;
+    }
+
+    Ok(file_name)
+}
+
+/// Thumbnails are always stored as jpegs
+pub fn thumbnail_name(file_name: &str) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file_name);
+    format!("{stem}.jpg")
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), SyncError> {
+    let map_err = |e| SyncError::IOError(format!("Error writing {path:?}: {e:?}"));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(map_err)?;
+    }
+    // Multiple sync threads may be writing the same image so write to a unique temp file first
+    let temp_path = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+    fs::write(&temp_path, data).map_err(map_err)?;
+    fs::rename(&temp_path, path).map_err(map_err)
+}
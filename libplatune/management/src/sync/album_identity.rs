@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use itertools::Itertools;
+use regex::Regex;
+
+/// Album artist that compilations are grouped under
+pub(crate) const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Disc numbers that some taggers add to the album name, like "Album (Disc 2)" or "Album CD2"
+static DISC_SUFFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*-?\s*[(\[]?\s*\b(?:disc|disk|cd)\s*(\d+)\s*[)\]]?\s*$").unwrap()
+});
+
+/// Splits the disc number off of the album name so every disc of the album is grouped together
+pub(crate) fn split_disc(album: &str) -> (String, Option<u32>) {
+    let Some(captures) = DISC_SUFFIX.captures(album) else {
+        return (album.to_owned(), None);
+    };
+    let name = album[..captures.get(0).unwrap().start()].trim();
+    // The disc number might be the whole name
+    if name.is_empty() {
+        return (album.to_owned(), None);
+    }
+    (name.to_owned(), captures[1].parse().ok())
+}
+
+pub(crate) fn is_various_artists(artist: &str) -> bool {
+    ["various artists", "various", "va", "v.a.", "v/a"]
+        .contains(&artist.trim().to_lowercase().as_str())
+}
+
+/// Groups the album under "Various Artists" if the album artist is one of the ways of spelling
+/// it, or if it's flagged as a compilation without an album artist tag. Otherwise, those
+/// compilations would be split up into a separate album for each track artist. Compilations
+/// with an album artist, like a DJ mix, keep it.
+pub(crate) fn compilation_artists(
+    album_artists: Vec<String>,
+    compilation: bool,
+) -> (Vec<String>, bool) {
+    if album_artists.iter().any(|a| is_various_artists(a))
+        || (compilation && album_artists.is_empty())
+    {
+        (vec![VARIOUS_ARTISTS.to_owned()], true)
+    } else {
+        (album_artists, compilation)
+    }
+}
+
+/// Tags that tell apart different releases of an album with the same name and artist
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct ReleaseKey {
+    pub(crate) release_id: Option<String>,
+    pub(crate) year: Option<u32>,
+}
+
+/// Decides which release each track of an album belongs to. Every track is considered at once so
+/// the result doesn't depend on the order the tracks were scanned in.
+///
+/// A release ID always decides the release. Tracks without one join the only release that has a
+/// compatible year, otherwise they're grouped by year. Tracks without a year join the only year
+/// that the other tracks without a release ID have.
+pub(crate) fn assign_releases(tracks: &[ReleaseKey]) -> Vec<ReleaseKey> {
+    // Earliest year of each release, in case the tracks don't agree
+    let mut release_years: BTreeMap<String, Option<u32>> = BTreeMap::new();
+    for track in tracks {
+        if let Some(release_id) = &track.release_id {
+            let year = release_years
+                .entry(release_id.to_lowercase())
+                .or_insert(track.year);
+            *year = year.iter().copied().chain(track.year).min();
+        }
+    }
+    let untagged_years = tracks
+        .iter()
+        .filter(|t| t.release_id.is_none())
+        .filter_map(|t| t.year)
+        .unique()
+        .collect_vec();
+
+    tracks
+        .iter()
+        .map(|track| {
+            if let Some(release_id) = &track.release_id {
+                let release_id = release_id.to_lowercase();
+                let year = release_years[&release_id];
+                return ReleaseKey {
+                    release_id: Some(release_id),
+                    year,
+                };
+            }
+            let candidates = release_years
+                .iter()
+                .filter(|(_, year)| track.year.is_none() || year.is_none() || **year == track.year)
+                .collect_vec();
+            if let [(release_id, year)] = candidates[..] {
+                return ReleaseKey {
+                    release_id: Some(release_id.clone()),
+                    year: *year,
+                };
+            }
+            let year = match (track.year, &untagged_years[..]) {
+                (None, [year]) => Some(*year),
+                (year, _) => year,
+            };
+            ReleaseKey {
+                release_id: None,
+                year,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,101 @@
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use super::album_identity::{
+    ReleaseKey, VARIOUS_ARTISTS, assign_releases, compilation_artists, split_disc,
+};
+
+#[rstest(
+    album,
+    expected_name,
+    expected_disc,
+    case("Album", "Album", None),
+    case("Album (Disc 2)", "Album", Some(2)),
+    case("Album [CD 1]", "Album", Some(1)),
+    case("Album - Disc 3", "Album", Some(3)),
+    case("Album CD2", "Album", Some(2)),
+    case("Album (disk 10)", "Album", Some(10)),
+    case("Abcd2", "Abcd2", None),
+    case("Disc 1", "Disc 1", None),
+    case("Discovery", "Discovery", None)
+)]
+fn test_split_disc(album: &str, expected_name: &str, expected_disc: Option<u32>) {
+    assert_eq!((expected_name.to_owned(), expected_disc), split_disc(album));
+}
+
+#[rstest(
+    album_artists,
+    compilation,
+    expected,
+    expected_compilation,
+    case(vec!["A"], false, vec!["A"], false),
+    case(vec!["A"], true, vec!["A"], true),
+    case(vec![], true, vec![VARIOUS_ARTISTS], true),
+    case(vec!["VA"], false, vec![VARIOUS_ARTISTS], true),
+    case(vec!["various artists"], false, vec![VARIOUS_ARTISTS], true)
+)]
+fn test_compilation_artists(
+    album_artists: Vec<&str>,
+    compilation: bool,
+    expected: Vec<&str>,
+    expected_compilation: bool,
+) {
+    let album_artists = album_artists.into_iter().map(ToOwned::to_owned).collect();
+    assert_eq!(
+        (
+            expected.into_iter().map(ToOwned::to_owned).collect(),
+            expected_compilation
+        ),
+        compilation_artists(album_artists, compilation)
+    );
+}
+
+fn release(release_id: Option<&str>, year: Option<u32>) -> ReleaseKey {
+    ReleaseKey {
+        release_id: release_id.map(ToOwned::to_owned),
+        year,
+    }
+}
+
+#[rstest(
+    tracks,
+    expected,
+    case(
+        vec![(Some("a"), Some(2000)), (Some("A"), Some(1999))],
+        vec![(Some("a"), Some(1999)), (Some("a"), Some(1999))]
+    ),
+    case(
+        vec![(Some("a"), Some(2000)), (None, Some(2000)), (None, None)],
+        vec![(Some("a"), Some(2000)), (Some("a"), Some(2000)), (Some("a"), Some(2000))]
+    ),
+    case(
+        vec![(Some("a"), Some(2000)), (None, Some(2010))],
+        vec![(Some("a"), Some(2000)), (None, Some(2010))]
+    ),
+    case(
+        vec![(Some("a"), Some(2000)), (Some("b"), Some(2010)), (None, None)],
+        vec![(Some("a"), Some(2000)), (Some("b"), Some(2010)), (None, None)]
+    ),
+    case(
+        vec![(None, Some(2000)), (None, None), (None, Some(2000))],
+        vec![(None, Some(2000)), (None, Some(2000)), (None, Some(2000))]
+    ),
+    case(
+        vec![(None, Some(2000)), (None, None), (None, Some(2010))],
+        vec![(None, Some(2000)), (None, None), (None, Some(2010))]
+    )
+)]
+fn test_assign_releases(
+    tracks: Vec<(Option<&str>, Option<u32>)>,
+    expected: Vec<(Option<&str>, Option<u32>)>,
+) {
+    let tracks: Vec<_> = tracks.into_iter().map(|(r, y)| release(r, y)).collect();
+    let expected: Vec<_> = expected.into_iter().map(|(r, y)| release(r, y)).collect();
+    assert_eq!(expected, assign_releases(&tracks));
+    // Scan order doesn't matter
+    let reversed: Vec<_> = tracks.into_iter().rev().collect();
+    assert_eq!(
+        expected.into_iter().rev().collect::<Vec<_>>(),
+        assign_releases(&reversed)
+    );
+}
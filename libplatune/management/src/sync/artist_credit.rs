@@ -0,0 +1,157 @@
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use itertools::Itertools;
+use regex::Regex;
+use strum::{Display, EnumString};
+
+use crate::db_error::DbError;
+
+/// Featured artists in parentheses or brackets, like "Title (feat. B & C)"
+static BRACKETED_FEATURE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*[(\[]\s*(?:feat\.?|ft\.?|featuring)\s+([^)\]]+)[)\]]").unwrap()
+});
+/// Featured artists at the end of the value, like "A feat. B". The abbreviations need a period
+/// here so titles like "10 ft Tall" aren't mistaken for a credit.
+static TRAILING_FEATURE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s+(?:feat\.|ft\.|featuring)\s+(.+)$").unwrap());
+static FEATURE_SEPARATOR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s*,\s*|\s+(?:&|and)\s+").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ArtistRole {
+    Primary,
+    Featured,
+    AlbumArtist,
+    Composer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SongArtist {
+    pub artist_id: i64,
+    pub artist_name: String,
+    pub role: ArtistRole,
+}
+
+#[derive(Debug)]
+pub(crate) struct SongArtistRow {
+    pub(crate) artist_id: i64,
+    pub(crate) artist_name: String,
+    pub(crate) artist_role: String,
+}
+
+impl TryFrom<SongArtistRow> for SongArtist {
+    type Error = DbError;
+
+    fn try_from(row: SongArtistRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            artist_id: row.artist_id,
+            artist_name: row.artist_name,
+            role: ArtistRole::from_str(&row.artist_role)
+                .map_err(|e| DbError::DbError(format!("Invalid artist role {e:?}")))?,
+        })
+    }
+}
+
+/// Artists performing on a song
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct ArtistCredits {
+    pub(crate) primary: Vec<String>,
+    pub(crate) featured: Vec<String>,
+}
+
+impl ArtistCredits {
+    /// Parses artist tag values, moving any "feat." credits into the featured artists. Known
+    /// names from other tags are kept together even if they contain a separator.
+    pub(crate) fn parse<'a>(values: impl IntoIterator<Item = &'a str>, known: &[String]) -> Self {
+        let mut credits = Self::default();
+        for artist in split_artists(values) {
+            let (main, featured) = split_featured(&artist, known);
+            if !main.is_empty() {
+                credits.primary.push(main);
+            }
+            credits.featured.extend(featured);
+        }
+        credits.dedup();
+        credits
+    }
+
+    /// Adds the artists credited in the title, like "Title (feat. B)"
+    pub(crate) fn with_title(mut self, title: &str, known: &[String]) -> Self {
+        let (_, featured) = split_featured(title, known);
+        self.featured.extend(featured);
+        self.dedup();
+        self
+    }
+
+    fn dedup(&mut self) {
+        self.primary = self
+            .primary
+            .drain(..)
+            .unique_by(|a| a.to_lowercase())
+            .collect();
+        let primary = self.primary.iter().map(|a| a.to_lowercase()).collect_vec();
+        self.featured = self
+            .featured
+            .drain(..)
+            .unique_by(|a| a.to_lowercase())
+            .filter(|a| !primary.contains(&a.to_lowercase()))
+            .collect();
+    }
+}
+
+/// Splits tag values into individual artists. Multi-value tags already have one artist per
+/// value, but some taggers put every artist into a single value. Slashes are only treated as a
+/// separator when they're surrounded by spaces since they're part of names like AC/DC.
+pub(crate) fn split_artists<'a>(values: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    values
+        .into_iter()
+        .flat_map(|value| value.split([';', '\0']).flat_map(|v| v.split(" / ")))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(ToOwned::to_owned)
+        .unique_by(|v| v.to_lowercase())
+        .collect()
+}
+
+fn split_featured(value: &str, known: &[String]) -> (String, Vec<String>) {
+    let Some(captures) = BRACKETED_FEATURE
+        .captures(value)
+        .or_else(|| TRAILING_FEATURE.captures(value))
+    else {
+        return (value.trim().to_owned(), Vec::new());
+    };
+    let credit = captures.get(0).unwrap();
+    let main = format!("{}{}", &value[..credit.start()], &value[credit.end()..]);
+    (main.trim().to_owned(), split_credit(&captures[1], known))
+}
+
+/// Splits a list of featured artists. Names like "Earth, Wind & Fire" look like a list too, so
+/// any run of names that matches a known artist is kept together.
+fn split_credit(credit: &str, known: &[String]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut start = 0;
+    for separator in FEATURE_SEPARATOR.find_iter(credit) {
+        names.push(start..separator.start());
+        start = separator.end();
+    }
+    names.push(start..credit.len());
+
+    let mut artists = Vec::new();
+    let mut i = 0;
+    while i < names.len() {
+        // Prefer the longest match
+        let end = (i + 1..names.len())
+            .rev()
+            .find(|&j| {
+                let name = credit[names[i].start..names[j].end].trim();
+                known.iter().any(|k| k.trim().eq_ignore_ascii_case(name))
+            })
+            .unwrap_or(i);
+        artists.push(credit[names[i].start..names[end].end].trim().to_owned());
+        i = end + 1;
+    }
+    artists.retain(|a| !a.is_empty());
+    artists
+}
@@ -0,0 +1,81 @@
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use super::artist_credit::{ArtistCredits, split_artists};
+
+fn credits(primary: &[&str], featured: &[&str]) -> ArtistCredits {
+    ArtistCredits {
+        primary: primary.iter().map(|a| a.to_string()).collect(),
+        featured: featured.iter().map(|a| a.to_string()).collect(),
+    }
+}
+
+#[rstest(
+    values,
+    expected,
+    case(vec!["A", "B"], vec!["A", "B"]),
+    case(vec!["A; B"], vec!["A", "B"]),
+    case(vec!["A / B"], vec!["A", "B"]),
+    case(vec!["AC/DC"], vec!["AC/DC"]),
+    case(vec!["A", "a", " "], vec!["A"])
+)]
+fn test_split_artists(values: Vec<&str>, expected: Vec<&str>) {
+    assert_eq!(expected, split_artists(values));
+}
+
+#[rstest(
+    values,
+    title,
+    expected,
+    case(vec!["A feat. B"], "Song", credits(&["A"], &["B"])),
+    case(vec!["A ft. B, C & D"], "Song", credits(&["A"], &["B", "C", "D"])),
+    case(vec!["A (featuring B and C)"], "Song", credits(&["A"], &["B", "C"])),
+    case(vec!["A", "B"], "Song (feat. C)", credits(&["A", "B"], &["C"])),
+    case(vec!["A"], "Song [ft. A & B]", credits(&["A"], &["B"])),
+    case(vec!["A"], "10 ft Tall", credits(&["A"], &[])),
+    case(vec!["A"], "A Feat of Strength", credits(&["A"], &[])),
+    case(vec![], "Song", credits(&[], &[]))
+)]
+fn test_parse_credits(values: Vec<&str>, title: &str, expected: ArtistCredits) {
+    assert_eq!(
+        expected,
+        ArtistCredits::parse(values, &[]).with_title(title, &[])
+    );
+}
+
+#[rstest(
+    values,
+    title,
+    known,
+    expected,
+    case(
+        vec!["A feat. Earth, Wind & Fire"],
+        "Song",
+        vec!["A", "Earth, Wind & Fire"],
+        credits(&["A"], &["Earth, Wind & Fire"])
+    ),
+    case(
+        vec!["A"],
+        "Song (ft. B, earth, wind & fire and C)",
+        vec!["Earth, Wind & Fire"],
+        credits(&["A"], &["B", "earth, wind & fire", "C"])
+    ),
+    case(
+        vec!["A feat. Simon & Garfunkel"],
+        "Song",
+        vec!["Simon"],
+        credits(&["A"], &["Simon", "Garfunkel"])
+    )
+)]
+fn test_parse_credits_known_artists(
+    values: Vec<&str>,
+    title: &str,
+    known: Vec<&str>,
+    expected: ArtistCredits,
+) {
+    let known = known.into_iter().map(ToOwned::to_owned).collect::<Vec<_>>();
+    assert_eq!(
+        expected,
+        ArtistCredits::parse(values, &known).with_title(title, &known)
+    );
+}
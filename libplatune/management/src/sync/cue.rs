@@ -0,0 +1,404 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+
+use super::album_identity::{compilation_artists, split_disc};
+use super::artist_credit::{ArtistCredits, split_artists};
+use super::file_filter::FileFilter;
+use super::gain_value::parse_gain_value;
+use super::sync_engine::SyncError;
+use super::tag::{MusicBrainzIds, ReplayGain, Tag, split_genres};
+
+pub(crate) const CUE_EXT: &str = "cue";
+// CUE sheet timestamps are in minutes, seconds, and frames
+const FRAMES_PER_SECOND: i64 = 75;
+
+/// The part of an audio file that belongs to a single track, in milliseconds
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) struct AudioRange {
+    pub(crate) start_millis: i64,
+    pub(crate) end_millis: i64,
+}
+
+impl AudioRange {
+    /// Media fragment appended to the file path so each track gets its own path and the player
+    /// knows which part of the file to play
+    pub(crate) fn fragment(&self) -> String {
+        format!(
+            "#t={},{}",
+            format_seconds(self.start_millis),
+            format_seconds(self.end_millis)
+        )
+    }
+
+    fn frames(&self, sample_rate: u32) -> (u64, u64) {
+        let to_frames = |millis: i64| millis.max(0) as u64 * sample_rate as u64 / 1000;
+        (to_frames(self.start_millis), to_frames(self.end_millis))
+    }
+}
+
+fn format_seconds(millis: i64) -> String {
+    format!("{}.{:03}", millis / 1000, millis % 1000)
+}
+
+/// Keeps track of the decoding position so only the samples inside of a range are processed
+pub(crate) struct RangeFilter {
+    range: Option<AudioRange>,
+    position: u64,
+}
+
+impl RangeFilter {
+    pub(crate) fn new(range: Option<AudioRange>) -> Self {
+        Self { range, position: 0 }
+    }
+
+    /// Returns the part of the interleaved samples that's inside of the range
+    pub(crate) fn filter<'a, T>(
+        &mut self,
+        samples: &'a [T],
+        channels: usize,
+        sample_rate: u32,
+    ) -> &'a [T] {
+        let channels = channels.max(1);
+        let start = self.position;
+        let end = start + (samples.len() / channels) as u64;
+        self.position = end;
+        let Some(range) = self.range else {
+            return samples;
+        };
+        let (range_start, range_end) = range.frames(sample_rate);
+        let from = (range_start.clamp(start, end) - start) as usize;
+        let to = (range_end.clamp(start, end) - start) as usize;
+        &samples[from * channels..to * channels]
+    }
+
+    pub(crate) fn is_finished(&self, sample_rate: u32) -> bool {
+        self.range
+            .is_some_and(|range| self.position >= range.frames(sample_rate).1)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct CueSheet {
+    pub(crate) title: Option<String>,
+    pub(crate) performer: Option<String>,
+    pub(crate) songwriter: Option<String>,
+    pub(crate) genre: Option<String>,
+    pub(crate) year: Option<u32>,
+    pub(crate) disc_number: Option<u32>,
+    pub(crate) album_gain: Option<f64>,
+    pub(crate) album_peak: Option<f64>,
+    pub(crate) files: Vec<CueFile>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct CueFile {
+    pub(crate) name: String,
+    pub(crate) tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct CueTrack {
+    pub(crate) number: u32,
+    pub(crate) title: Option<String>,
+    pub(crate) performer: Option<String>,
+    pub(crate) songwriter: Option<String>,
+    pub(crate) track_gain: Option<f64>,
+    pub(crate) track_peak: Option<f64>,
+    /// Start of the track from INDEX 01, or INDEX 00 if that's all there is
+    pub(crate) start_millis: i64,
+}
+
+impl CueSheet {
+    pub(crate) fn read(path: &Path) -> Result<Self, SyncError> {
+        let content = fs::read(path)
+            .map_err(|e| SyncError::IOError(format!("Error reading cue sheet {path:?}: {e:?}")))?;
+        Self::parse(&content)
+            .map_err(|e| SyncError::TagReadError(format!("Error parsing {path:?}: {e}")))
+    }
+
+    pub(crate) fn parse(content: &[u8]) -> Result<Self, String> {
+        let mut sheet = CueSheet::default();
+        let mut pregap_start = None;
+        let mut in_data_track = false;
+        for line in decode(content).lines() {
+            let line = line.trim();
+            let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let command = command.to_uppercase();
+            let args = args.trim();
+            match command.as_str() {
+                "FILE" => {
+                    let name = match args.strip_prefix('"') {
+                        Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                        // Unquoted file names are followed by the file type
+                        None => args.rsplit_once(' ').map(|(n, _)| n).unwrap_or(args),
+                    };
+                    sheet.files.push(CueFile {
+                        name: name.to_owned(),
+                        tracks: vec![],
+                    });
+                    in_data_track = false;
+                }
+                "TRACK" => {
+                    let (number, track_type) = args.split_once(' ').unwrap_or((args, ""));
+                    let file = sheet
+                        .files
+                        .last_mut()
+                        .ok_or_else(|| "TRACK found before FILE".to_owned())?;
+                    // Data tracks on enhanced CDs aren't playable
+                    in_data_track = !track_type.trim().eq_ignore_ascii_case("AUDIO");
+                    if !in_data_track {
+                        file.tracks.push(CueTrack {
+                            number: number
+                                .parse()
+                                .map_err(|_| format!("Invalid track {args}"))?,
+                            start_millis: -1,
+                            ..Default::default()
+                        });
+                        pregap_start = None;
+                    }
+                }
+                _ if in_data_track => {}
+                _ => {
+                    let track = sheet.files.last_mut().and_then(|f| f.tracks.last_mut());
+                    match (command.as_str(), track) {
+                        ("INDEX", Some(track)) => {
+                            let (index, time) = args.split_once(' ').unwrap_or((args, ""));
+                            let millis = parse_time(time.trim())
+                                .ok_or_else(|| format!("Invalid index time {time}"))?;
+                            match index.parse::<u32>() {
+                                Ok(0) => pregap_start = Some(millis),
+                                Ok(1) => track.start_millis = millis,
+                                _ => {}
+                            }
+                            if track.start_millis < 0
+                                && let Some(pregap_start) = pregap_start
+                            {
+                                track.start_millis = pregap_start;
+                            }
+                        }
+                        ("TITLE", Some(track)) => track.title = non_empty(args),
+                        ("TITLE", None) => sheet.title = non_empty(args),
+                        ("PERFORMER", Some(track)) => track.performer = non_empty(args),
+                        ("PERFORMER", None) => sheet.performer = non_empty(args),
+                        ("SONGWRITER", Some(track)) => track.songwriter = non_empty(args),
+                        ("SONGWRITER", None) => sheet.songwriter = non_empty(args),
+                        ("REM", track) => {
+                            let (key, value) =
+                                args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                            let value = non_empty(value);
+                            match (key.to_uppercase().as_str(), track) {
+                                ("GENRE", None) => sheet.genre = value,
+                                ("DATE", None) => {
+                                    sheet.year = value.and_then(|v| v.get(..4)?.parse().ok())
+                                }
+                                ("DISCNUMBER", None) => {
+                                    sheet.disc_number = value.and_then(|v| v.parse().ok())
+                                }
+                                ("REPLAYGAIN_ALBUM_GAIN", None) => {
+                                    sheet.album_gain = value.as_deref().and_then(parse_gain_value)
+                                }
+                                ("REPLAYGAIN_ALBUM_PEAK", None) => {
+                                    sheet.album_peak = value.as_deref().and_then(parse_gain_value)
+                                }
+                                ("REPLAYGAIN_TRACK_GAIN", Some(track)) => {
+                                    track.track_gain = value.as_deref().and_then(parse_gain_value)
+                                }
+                                ("REPLAYGAIN_TRACK_PEAK", Some(track)) => {
+                                    track.track_peak = value.as_deref().and_then(parse_gain_value)
+                                }
+                                _ => {}
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        for file in &mut sheet.files {
+            file.tracks.retain(|t| t.start_millis >= 0);
+        }
+        sheet.files.retain(|f| !f.tracks.is_empty());
+        Ok(sheet)
+    }
+
+    /// Finds the audio file referenced by the sheet. The referenced extension is commonly wrong
+    /// after an album gets converted to a different format, so other audio files with the same
+    /// name are checked as well.
+    pub(crate) fn resolve_file(cue_dir: &Path, name: &str, filter: &FileFilter) -> Option<PathBuf> {
+        let path = cue_dir.join(name.replace('\\', "/"));
+        if path.is_file() {
+            return Some(path);
+        }
+        filter
+            .extensions()
+            .sorted()
+            .map(|ext| path.with_extension(ext))
+            .find(|p| p.is_file())
+    }
+
+    /// Names of the audio files in the folder that are split up by a cue sheet
+    pub(crate) fn referenced_files(dir: &Path, filter: &FileFilter) -> HashSet<OsString> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return HashSet::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(CUE_EXT))
+            })
+            .filter_map(|path| Self::read(&path).ok())
+            .flat_map(|sheet| sheet.files)
+            .filter_map(|file| {
+                Self::resolve_file(dir, &file.name, filter)?
+                    .file_name()
+                    .map(Into::into)
+            })
+            .collect()
+    }
+
+    /// Creates the tags for each track in the file using the tags from the file itself for
+    /// anything that isn't in the sheet
+    pub(crate) fn track_tags(&self, file: &CueFile, file_tag: &Tag) -> Vec<Tag> {
+        let album_replay_gain = ReplayGain {
+            // Any gain stored in the file itself applies to the whole album
+            album_gain: self
+                .album_gain
+                .or(file_tag.replay_gain.album_gain)
+                .or(file_tag.replay_gain.track_gain),
+            album_peak: self
+                .album_peak
+                .or(file_tag.replay_gain.album_peak)
+                .or(file_tag.replay_gain.track_peak),
+            ..Default::default()
+        };
+        let year = match file_tag.year {
+            0 => self.year.unwrap_or(0),
+            year => year,
+        };
+        let (album, album_disc) = match &self.title {
+            Some(title) => split_disc(title),
+            None => (file_tag.album.clone(), None),
+        };
+        let (album_artists, compilation) = match &self.performer {
+            Some(performer) => {
+                compilation_artists(split_artists([performer.as_str()]), file_tag.compilation)
+            }
+            None => (file_tag.album_artists.clone(), file_tag.compilation),
+        };
+        // Full artist names from the file and the sheet, used to keep featured artists like
+        // "Earth, Wind & Fire" together
+        let known_artists = file_tag
+            .artists
+            .iter()
+            .chain(&file_tag.featured_artists)
+            .chain(&file_tag.album_artists)
+            .chain(&self.performer)
+            .cloned()
+            .collect_vec();
+
+        file.tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, track)| {
+                let end_millis = file
+                    .tracks
+                    .get(i + 1)
+                    .map(|next| next.start_millis)
+                    .unwrap_or(file_tag.duration);
+                if end_millis <= track.start_millis {
+                    return None;
+                }
+                let range = AudioRange {
+                    start_millis: track.start_millis,
+                    end_millis,
+                };
+                let title = track
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("Track {:02}", track.number));
+                let credits = match track.performer.as_ref().or(self.performer.as_ref()) {
+                    Some(performer) => ArtistCredits::parse([performer.as_str()], &known_artists),
+                    None => ArtistCredits {
+                        primary: file_tag.artists.clone(),
+                        featured: file_tag.featured_artists.clone(),
+                    },
+                }
+                .with_title(&title, &known_artists);
+                Some(Tag {
+                    title,
+                    artists: credits.primary,
+                    featured_artists: credits.featured,
+                    album_artists: album_artists.clone(),
+                    album: album.clone(),
+                    compilation,
+                    track_number: track.number,
+                    disc_number: self
+                        .disc_number
+                        .or(album_disc)
+                        .unwrap_or(file_tag.disc_number),
+                    year,
+                    release_date: file_tag
+                        .release_date
+                        .clone()
+                        .or_else(|| (year > 0).then(|| format!("{year:04}"))),
+                    genres: self
+                        .genre
+                        .as_deref()
+                        .map(|genre| split_genres([genre]))
+                        .unwrap_or_else(|| file_tag.genres.clone()),
+                    composer: track
+                        .songwriter
+                        .clone()
+                        .or_else(|| self.songwriter.clone())
+                        .or_else(|| file_tag.composer.clone()),
+                    musicbrainz: MusicBrainzIds {
+                        recording_id: None,
+                        ..file_tag.musicbrainz.clone()
+                    },
+                    duration: range.end_millis - range.start_millis,
+                    replay_gain: ReplayGain {
+                        track_gain: track.track_gain,
+                        track_peak: track.track_peak,
+                        ..album_replay_gain
+                    },
+                    // Lyrics for the whole file can't be split up by track
+                    lyrics: None,
+                    synced_lyrics: None,
+                    range: Some(range),
+                    ..file_tag.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+fn decode(content: &[u8]) -> Cow<'_, str> {
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    match std::str::from_utf8(content) {
+        Ok(content) => Cow::Borrowed(content),
+        // Cue sheets from older rippers are usually Latin-1
+        Err(_) => Cow::Owned(content.iter().map(|&b| b as char).collect()),
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+fn parse_time(time: &str) -> Option<i64> {
+    let mut parts = time.split(':').map(|p| p.parse::<i64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some((minutes * 60 + seconds) * 1000 + frames * 1000 / FRAMES_PER_SECOND)
+}
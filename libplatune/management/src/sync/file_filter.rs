@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::{Override, OverrideBuilder};
+use tap::TapFallible;
+use tracing::error;
+
+use crate::path_util::sort_nested_folders;
+
+/// Glob patterns that control which files are synced from a library folder. Patterns use the
+/// gitignore syntax and are relative to the folder, so `*.sample.flac` matches in any subfolder
+/// and `**/Podcasts/**` skips everything under a folder named Podcasts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FolderRules {
+    /// Only files matching one of these patterns are synced. Every file is included if this is
+    /// empty.
+    pub include: Vec<String>,
+    /// Files matching any of these patterns are skipped, even if they match an include pattern
+    pub exclude: Vec<String>,
+}
+
+impl FolderRules {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub(crate) fn build(&self, folder: &Path) -> Result<Override, ignore::Error> {
+        let mut builder = OverrideBuilder::new(folder);
+        for pattern in &self.include {
+            builder.add(pattern)?;
+        }
+        // Later patterns take precedence so excludes need to be added last
+        for pattern in &self.exclude {
+            builder.add(&format!("!{pattern}"))?;
+        }
+        builder.build()
+    }
+}
+
+/// Decides which files are synced based on the configured extensions and folder rules
+#[derive(Clone)]
+pub(crate) struct FileFilter {
+    extensions: HashSet<String>,
+    folders: Vec<(PathBuf, Override)>,
+}
+
+impl FileFilter {
+    pub(crate) fn new(extensions: Vec<String>, folders: Vec<(String, FolderRules)>) -> Self {
+        let mut folders = folders
+            .into_iter()
+            .filter(|(_, rules)| !rules.is_empty())
+            .filter_map(|(folder, rules)| {
+                let folder = PathBuf::from(folder);
+                // Rules are validated before they're saved so this shouldn't happen
+                let rules = rules
+                    .build(&folder)
+                    .tap_err(|e| error!("Error building rules for {folder:?}: {e:?}"))
+                    .ok()?;
+                Some((folder, rules))
+            })
+            .collect::<Vec<_>>();
+        sort_nested_folders(&mut folders, |(folder, _)| folder.as_path());
+
+        Self {
+            extensions: extensions.into_iter().collect(),
+            folders,
+        }
+    }
+
+    pub(crate) fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.extensions.iter().map(String::as_str)
+    }
+
+    pub(crate) fn has_allowed_ext(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.contains(&ext.to_lowercase()))
+    }
+
+    /// Checks the path against the rules for the folder it belongs to
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.folders
+            .iter()
+            .find(|(folder, _)| path.starts_with(folder))
+            .is_some_and(|(_, rules)| rules.matched(path, is_dir).is_ignore())
+    }
+
+    /// Audio file that should be synced
+    pub(crate) fn is_allowed(&self, path: &Path) -> bool {
+        self.has_allowed_ext(path) && !self.is_excluded(path, false)
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::path::Path;
+
+use rusty_chromaprint::{Configuration, Fingerprinter};
+use symphonia::core::codecs::audio::AudioDecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::probe::Hint;
+use symphonia::core::formats::{FormatOptions, TrackType};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+
+use super::cue::{AudioRange, RangeFilter};
+use super::sync_engine::SyncError;
+
+// Same as the reference Chromaprint implementation. The beginning of the track is enough to
+// identify it and decoding the whole file would slow down syncing large libraries.
+const MAX_FINGERPRINT_SECONDS: u64 = 120;
+
+/// Computes an acoustic fingerprint from the decoded audio so the same recording can be
+/// identified regardless of format, bitrate, or tags. Only the given range is used if the file
+/// contains multiple tracks.
+pub(crate) fn fingerprint_file(
+    file_path: &Path,
+    range: Option<AudioRange>,
+) -> Result<Vec<u32>, SyncError> {
+    let map_err = |e: SymphoniaError| {
+        SyncError::TagReadError(format!("Error decoding file {file_path:?}: {e:?}"))
+    };
+    let file = File::open(file_path)
+        .map_err(|e| SyncError::IOError(format!("Error opening file {file_path:?}: {e:?}")))?;
+    let mut hint = Hint::new();
+    if let Some(extension) = file_path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut format = symphonia::default::get_probe()
+        .probe(
+            &hint,
+            MediaSourceStream::new(Box::new(file), Default::default()),
+            FormatOptions::default(),
+            MetadataOptions::default(),
+        )
+        .map_err(map_err)?;
+    let track = format
+        .default_track(TrackType::Audio)
+        .ok_or_else(|| SyncError::TagReadError(format!("No audio track found in {file_path:?}")))?;
+    let track_id = track.id;
+    let codec_params = track
+        .codec_params
+        .as_ref()
+        .and_then(|p| p.audio())
+        .ok_or_else(|| {
+            SyncError::TagReadError(format!("Missing codec parameters for {file_path:?}"))
+        })?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make_audio_decoder(codec_params, &AudioDecoderOptions::default())
+        .map_err(map_err)?;
+
+    let config = Configuration::preset_test2();
+    let mut printer = Fingerprinter::new(&config);
+    let mut filter = RangeFilter::new(range);
+    let mut started = false;
+    let mut remaining_samples = 0;
+    let mut samples: Vec<i16> = Vec::new();
+    while remaining_samples > 0 || !started {
+        let Some(packet) = format.next_packet().map_err(map_err)? else {
+            break;
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip over corrupted packets instead of failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(map_err(e)),
+        };
+        let spec = decoded.spec();
+        let (channels, rate) = (spec.channels().count() as u32, spec.rate());
+        if !started {
+            printer.start(rate, channels).map_err(|e| {
+                SyncError::TagReadError(format!(
+                    "Error starting fingerprint for {file_path:?}: {e:?}"
+                ))
+            })?;
+            remaining_samples = rate as u64 * channels as u64 * MAX_FINGERPRINT_SECONDS;
+            started = true;
+        }
+        decoded.copy_to_vec_interleaved(&mut samples);
+        let samples = filter.filter(&samples, channels as usize, rate);
+        let count = samples.len().min(remaining_samples as usize);
+        printer.consume(&samples[..count]);
+        remaining_samples -= count as u64;
+        if filter.is_finished(rate) {
+            break;
+        }
+    }
+
+    if !started {
+        return Err(SyncError::TagReadError(format!(
+            "No audio in {file_path:?}"
+        )));
+    }
+    printer.finish();
+    let fingerprint = printer.fingerprint().to_vec();
+    if fingerprint.is_empty() {
+        return Err(SyncError::TagReadError(format!(
+            "Audio in {file_path:?} is too short to fingerprint"
+        )));
+    }
+
+    Ok(fingerprint)
+}
+
+pub(crate) fn to_bytes(fingerprint: &[u32]) -> Vec<u8> {
+    fingerprint.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub(crate) fn from_bytes(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
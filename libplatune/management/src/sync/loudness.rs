@@ -0,0 +1,144 @@
+use std::fs::File;
+use std::path::Path;
+
+use ebur128::{EbuR128, Mode};
+use symphonia::core::codecs::audio::AudioDecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::probe::Hint;
+use symphonia::core::formats::{FormatOptions, TrackType};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+
+use super::cue::{AudioRange, RangeFilter};
+use super::sync_engine::SyncError;
+
+// ReplayGain 2.0 targets -18 LUFS
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Loudness {
+    pub(crate) gain: f64,
+    pub(crate) peak: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TrackLoudness {
+    pub(crate) gain: f64,
+    pub(crate) peak: f64,
+    pub(crate) duration: i64,
+}
+
+pub(crate) fn analyze_file(
+    file_path: &Path,
+    range: Option<AudioRange>,
+) -> Result<Loudness, SyncError> {
+    let map_err = |e: SymphoniaError| {
+        SyncError::TagReadError(format!("Error decoding file {file_path:?}: {e:?}"))
+    };
+    let file = File::open(file_path)
+        .map_err(|e| SyncError::IOError(format!("Error opening file {file_path:?}: {e:?}")))?;
+    let mut hint = Hint::new();
+    if let Some(extension) = file_path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut format = symphonia::default::get_probe()
+        .probe(
+            &hint,
+            MediaSourceStream::new(Box::new(file), Default::default()),
+            FormatOptions::default(),
+            MetadataOptions::default(),
+        )
+        .map_err(map_err)?;
+    let track = format
+        .default_track(TrackType::Audio)
+        .ok_or_else(|| SyncError::TagReadError(format!("No audio track found in {file_path:?}")))?;
+    let track_id = track.id;
+    let codec_params = track
+        .codec_params
+        .as_ref()
+        .and_then(|p| p.audio())
+        .ok_or_else(|| {
+            SyncError::TagReadError(format!("Missing codec parameters for {file_path:?}"))
+        })?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make_audio_decoder(codec_params, &AudioDecoderOptions::default())
+        .map_err(map_err)?;
+
+    let mut meter: Option<EbuR128> = None;
+    let mut filter = RangeFilter::new(range);
+    let mut samples = Vec::new();
+    while let Some(packet) = format.next_packet().map_err(map_err)? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip over corrupted packets instead of failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(map_err(e)),
+        };
+        let spec = decoded.spec();
+        let (channels, rate) = (spec.channels().count(), spec.rate());
+        if meter.is_none() {
+            meter = Some(
+                EbuR128::new(channels as u32, rate, Mode::I | Mode::SAMPLE_PEAK).map_err(|e| {
+                    SyncError::TagReadError(format!(
+                        "Error creating loudness meter for {file_path:?}: {e:?}"
+                    ))
+                })?,
+            );
+        }
+        decoded.copy_to_vec_interleaved(&mut samples);
+        if let Some(meter) = &mut meter {
+            meter
+                .add_frames_f32(filter.filter(&samples, channels, rate))
+                .map_err(|e| {
+                    SyncError::TagReadError(format!(
+                        "Error measuring loudness for {file_path:?}: {e:?}"
+                    ))
+                })?;
+        }
+        if filter.is_finished(rate) {
+            break;
+        }
+    }
+
+    let meter =
+        meter.ok_or_else(|| SyncError::TagReadError(format!("No audio in {file_path:?}")))?;
+    let loudness = meter
+        .loudness_global()
+        .map_err(|e| SyncError::TagReadError(format!("Error measuring loudness: {e:?}")))?;
+    if !loudness.is_finite() {
+        // Completely silent files have no measurable loudness
+        return Err(SyncError::TagReadError(format!(
+            "Unable to measure loudness for {file_path:?}"
+        )));
+    }
+    let peak = (0..meter.channels())
+        .filter_map(|channel| meter.sample_peak(channel).ok())
+        .fold(0.0, f64::max);
+
+    Ok(Loudness {
+        gain: REFERENCE_LOUDNESS - loudness,
+        peak,
+    })
+}
+
+pub(crate) fn album_loudness(tracks: &[TrackLoudness]) -> Option<Loudness> {
+    let total_duration: i64 = tracks.iter().map(|t| t.duration).sum();
+    if tracks.is_empty() || total_duration <= 0 {
+        return None;
+    }
+    // Average the track energy weighted by duration so longer tracks contribute more
+    let energy = tracks
+        .iter()
+        .map(|t| t.duration as f64 * 10f64.powf((REFERENCE_LOUDNESS - t.gain) / 10.0))
+        .sum::<f64>()
+        / total_duration as f64;
+    let loudness = 10.0 * energy.log10();
+
+    Some(Loudness {
+        gain: REFERENCE_LOUDNESS - loudness,
+        peak: tracks.iter().map(|t| t.peak).fold(0.0, f64::max),
+    })
+}
@@ -1,10 +1,29 @@
+pub mod album_art;
+pub(crate) mod album_identity;
+pub mod artist_credit;
+pub(crate) mod cue;
 mod dir_read;
+pub mod file_filter;
+pub(crate) mod fingerprint;
+#[path = "../../../player/src/gain_value.rs"]
+mod gain_value;
+mod loudness;
 pub mod progress_stream;
 pub(crate) mod sync_controller;
 pub(crate) mod sync_dal;
 pub mod sync_engine;
+pub mod sync_report;
+pub mod sync_state;
 pub(crate) mod tag;
 
+#[cfg(test)]
+#[path = "./album_identity_test.rs"]
+mod album_identity_test;
+
+#[cfg(test)]
+#[path = "./artist_credit_test.rs"]
+mod artist_credit_test;
+
 #[cfg(test)]
 #[path = "./sync_test.rs"]
 mod sync_test;
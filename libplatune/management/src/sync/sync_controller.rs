@@ -1,17 +1,22 @@
+use std::path::PathBuf;
 use std::pin::Pin;
 
 use sqlx::{Pool, Sqlite};
 use tap::TapFallible;
-use tokio::sync::{broadcast, oneshot};
+use tokio::sync::{broadcast, oneshot, watch};
 use tracing::{error, info, warn};
 
+use super::file_filter::FileFilter;
 use super::progress_stream::ProgressStream;
 use super::sync_engine::{SyncEngine, SyncError};
+use super::sync_state::{SyncSignal, SyncState};
 
 pub(crate) struct SyncController {
     write_pool: Pool<Sqlite>,
     progress_tx: Option<broadcast::Sender<Option<Result<f32, SyncError>>>>,
     finished_rx: Option<oneshot::Receiver<()>>,
+    full_rescan: bool,
+    state_tx: watch::Sender<SyncState>,
 }
 
 impl SyncController {
@@ -20,40 +25,81 @@ impl SyncController {
             write_pool,
             progress_tx: None,
             finished_rx: None,
+            full_rescan: false,
+            state_tx: watch::Sender::new(SyncState::Idle),
         }
     }
+
     pub(crate) async fn sync(
         &mut self,
         folders: Vec<String>,
-        mount: Option<String>,
+        mounts: Vec<String>,
+        art_cache_dir: Option<PathBuf>,
+        full_rescan: bool,
+        filter: FileFilter,
         finished_callback: Pin<Box<dyn Future<Output = ()> + Send>>,
     ) -> ProgressStream {
-        // If sync is currently running, subscribe to the current stream instead of starting another
-        // one
-        if let Some(finished_rx) = &mut self.finished_rx {
+        let mut previous_rx = None;
+        if let Some(mut finished_rx) = self.finished_rx.take() {
             // If the finished channel has a value, the last sync finished so we should restart
             // Otherwise, the sync is curently in progress
             if finished_rx.try_recv().is_err()
                 && let Some(tx) = &self.progress_tx
             {
-                info!("Subscribing to sync in progress");
-                return ProgressStream::new(tx.subscribe());
+                // If sync is currently running, subscribe to the current stream instead of
+                // starting another one unless it would skip files that the full rescan needs
+                if self.full_rescan || !full_rescan {
+                    info!("Subscribing to sync in progress");
+                    self.finished_rx = Some(finished_rx);
+                    return ProgressStream::new(tx.subscribe());
+                }
+                info!("Queueing full rescan until the sync in progress finishes");
+                previous_rx = Some(finished_rx);
             }
         }
         let (finished_tx, finished_rx) = oneshot::channel();
 
         let (tx, rx) = broadcast::channel(10000);
         self.finished_rx = Some(finished_rx);
+        self.full_rescan = full_rescan;
 
         self.progress_tx = Some(tx.clone());
         if !folders.is_empty() {
             let write_pool = self.write_pool.clone();
+            if previous_rx.is_none() {
+                self.state_tx.send_replace(SyncState::Running);
+            }
+            let state_tx = self.state_tx.clone();
 
             tokio::task::spawn(async move {
+                if let Some(previous_rx) = previous_rx {
+                    // The sender is dropped if the previous sync exits early so the result
+                    // doesn't matter here
+                    let _ = previous_rx.await;
+                    state_tx.send_replace(SyncState::Running);
+                }
+                let signal = SyncSignal::new(state_tx.subscribe());
                 info!("Starting new sync");
-                let mut engine = SyncEngine::new(folders, write_pool, mount, tx.clone());
+                let mut engine = SyncEngine::new(
+                    folders,
+                    write_pool,
+                    mounts,
+                    art_cache_dir,
+                    full_rescan,
+                    filter,
+                    signal,
+                    tx.clone(),
+                );
                 engine.start().await;
 
+                state_tx.send_if_modified(|state| {
+                    if *state == SyncState::Cancelled {
+                        return false;
+                    }
+                    *state = SyncState::Finished;
+                    true
+                });
+
                 finished_callback.await;
 
                 let _ = finished_tx
@@ -74,4 +120,38 @@ impl SyncController {
 
         ProgressStream::new(rx)
     }
+
+    pub(crate) fn pause(&self) -> bool {
+        self.state_tx.send_if_modified(|state| {
+            if *state != SyncState::Running {
+                return false;
+            }
+            *state = SyncState::Paused;
+            true
+        })
+    }
+
+    pub(crate) fn resume(&self) -> bool {
+        self.state_tx.send_if_modified(|state| {
+            if *state != SyncState::Paused {
+                return false;
+            }
+            *state = SyncState::Running;
+            true
+        })
+    }
+
+    pub(crate) fn cancel(&self) -> bool {
+        self.state_tx.send_if_modified(|state| {
+            if !matches!(state, SyncState::Running | SyncState::Paused) {
+                return false;
+            }
+            *state = SyncState::Cancelled;
+            true
+        })
+    }
+
+    pub(crate) fn subscribe_state(&self) -> watch::Receiver<SyncState> {
+        self.state_tx.subscribe()
+    }
 }
@@ -1,18 +1,68 @@
+use std::collections::{HashMap, HashSet};
 use std::time::SystemTime;
 
 use itertools::Itertools;
 use sqlx::sqlite::SqliteQueryResult;
 use sqlx::{Pool, Sqlite, Transaction};
 
+use super::album_identity::{ReleaseKey, assign_releases};
+use super::loudness::{Loudness, TrackLoudness};
+use super::sync_report::{SyncFileStatus, SyncReportFile};
 use super::tag::Tag;
-use crate::consts::MIN_LEN;
+use crate::consts::{MAX_SYNC_REPORTS, MIN_LEN};
+use crate::database::Database;
 use crate::db_error::DbError;
 
+/// File attributes recorded during the last scan, used to detect files that haven't changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    pub(crate) modified_date: i64,
+    pub(crate) file_size: i64,
+}
+
+/// Song along with the album it's currently in, used to decide which release it belongs to
+struct AlbumSong {
+    song_id: i64,
+    song_year: i64,
+    musicbrainz_release_id: Option<String>,
+    album_id: i64,
+    album_name: String,
+    artist_id: i64,
+    album_year: Option<i64>,
+    album_release_id: Option<String>,
+    is_compilation: bool,
+}
+
 pub(crate) struct SyncDAL<'a> {
+    write_pool: Pool<Sqlite>,
     tran: Transaction<'a, Sqlite>,
     timestamp: u32,
 }
 
+/// Sync that has committed its changes so far and released the write connection
+pub(crate) struct SuspendedSyncDAL {
+    write_pool: Pool<Sqlite>,
+    timestamp: u32,
+}
+
+impl SuspendedSyncDAL {
+    /// Continues the sync in a new transaction. The original scan timestamp is kept so songs
+    /// found before the sync was suspended aren't treated as missing.
+    pub(crate) async fn resume<'a>(self) -> Result<SyncDAL<'a>, DbError> {
+        let tran = self
+            .write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(SyncDAL {
+            write_pool: self.write_pool,
+            tran,
+            timestamp: self.timestamp,
+        })
+    }
+}
+
 impl<'a> SyncDAL<'a> {
     pub(crate) async fn try_new(write_pool: Pool<Sqlite>) -> Result<SyncDAL<'a>, DbError> {
         let tran = write_pool
@@ -25,7 +75,29 @@ impl<'a> SyncDAL<'a> {
             .unwrap()
             .as_secs() as u32;
 
-        Ok(Self { tran, timestamp })
+        Ok(Self {
+            write_pool,
+            tran,
+            timestamp,
+        })
+    }
+
+    /// Time the sync started, used to find songs that weren't seen during the scan
+    pub(crate) fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    /// Commits the changes made so far so other writers aren't blocked while the sync is paused
+    pub(crate) async fn suspend(self) -> Result<SuspendedSyncDAL, DbError> {
+        self.tran
+            .commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(SuspendedSyncDAL {
+            write_pool: self.write_pool,
+            timestamp: self.timestamp,
+        })
     }
 
     pub(crate) async fn add_artist(&mut self, artist: &str) -> Result<SqliteQueryResult, DbError> {
@@ -39,38 +111,401 @@ impl<'a> SyncDAL<'a> {
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
-    pub(crate) async fn add_album(
-        &mut self,
-        album: &str,
-        album_artist: &str,
-    ) -> Result<SqliteQueryResult, DbError> {
-        sqlx::query!(
+    /// Finds the album that the song belongs to, adding it if it doesn't exist yet. Albums with
+    /// the same name and album artist are kept apart by their MusicBrainz release ID, or by their
+    /// release year if the song doesn't have one. Partially tagged songs are moved to the rest of
+    /// their release once every file has been scanned, see [`SyncDAL::regroup_albums`].
+    pub(crate) async fn add_album(&mut self, metadata: &Tag) -> Result<i64, DbError> {
+        let album_artist = metadata.album_artist();
+        let release_id = metadata.musicbrainz.release_id.as_deref();
+        // Tracks on compilations are often tagged with the year of the original recording
+        let year = (metadata.year > 0 && !metadata.compilation).then_some(metadata.year);
+        let existing = sqlx::query_scalar!(
+            "
+            SELECT al.album_id FROM album al
+            INNER JOIN artist ar ON ar.artist_id = al.artist_id
+            WHERE al.album_name = $1 AND ar.artist_name = $2
+            AND al.musicbrainz_release_id IS $3 AND ($3 IS NOT NULL OR al.album_year IS $4)
+            ORDER BY al.album_id
+            LIMIT 1;
+            ",
+            metadata.album,
+            album_artist,
+            release_id,
+            year
+        )
+        .fetch_optional(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        if let Some(album_id) = existing {
+            sqlx::query!(
+                "UPDATE album SET is_compilation = MAX(is_compilation, ?) WHERE album_id = ?;",
+                metadata.compilation,
+                album_id
+            )
+            .execute(&mut *self.tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            return Ok(album_id);
+        }
+
+        sqlx::query_scalar!(
             "
-        INSERT OR IGNORE INTO album(album_name, artist_id, created_date)
-        values(?, (SELECT artist_id FROM artist WHERE artist_name = ?), ?);",
-            album,
+            INSERT INTO album(
+                album_name, artist_id, musicbrainz_release_id, album_year, is_compilation,
+                created_date
+            )
+            values(?, (SELECT artist_id FROM artist WHERE artist_name = ?), ?, ?, ?, ?)
+            RETURNING album_id;
+            ",
+            metadata.album,
             album_artist,
+            release_id,
+            year,
+            metadata.compilation,
             self.timestamp
         )
-        .execute(&mut *self.tran)
+        .fetch_one(&mut *self.tran)
         .await
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
+    /// Returns the album the song at this path is currently in
+    pub(crate) async fn get_song_album(&mut self, path: &str) -> Result<Option<i64>, DbError> {
+        sqlx::query_scalar!("SELECT album_id FROM song WHERE song_path = ?;", path)
+            .fetch_optional(&mut *self.tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    /// Moves songs between the albums that have the same name and album artist as any of the
+    /// given albums once every song has been synced. Deciding this from the tags of all of the
+    /// songs keeps the albums the same no matter which order the files were scanned in.
+    pub(crate) async fn regroup_albums(&mut self, album_ids: &HashSet<i64>) -> Result<(), DbError> {
+        let mut regrouped = HashSet::new();
+        for &album_id in album_ids {
+            if regrouped.contains(&album_id) {
+                continue;
+            }
+            let songs = sqlx::query_as!(
+                AlbumSong,
+                r#"
+                SELECT s.song_id, s.song_year, s.musicbrainz_release_id, al.album_id, al.album_name,
+                    al.artist_id, al.album_year, al.musicbrainz_release_id AS album_release_id,
+                    al.is_compilation
+                FROM song s
+                INNER JOIN album al ON al.album_id = s.album_id
+                INNER JOIN album touched
+                    ON touched.artist_id = al.artist_id AND touched.album_name = al.album_name
+                WHERE touched.album_id = ?
+                ORDER BY s.song_id;
+                "#,
+                album_id
+            )
+            .fetch_all(&mut *self.tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+            regrouped.insert(album_id);
+            regrouped.extend(songs.iter().map(|song| song.album_id));
+            self.regroup_album(songs).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Regroups every album in the library. This fills in the release ID and year of albums that
+    /// were added before those were tracked.
+    pub(crate) async fn regroup_all_albums(&mut self) -> Result<(), DbError> {
+        let songs = sqlx::query_as!(
+            AlbumSong,
+            r#"
+            SELECT s.song_id, s.song_year, s.musicbrainz_release_id, al.album_id, al.album_name,
+                al.artist_id, al.album_year, al.musicbrainz_release_id AS album_release_id,
+                al.is_compilation
+            FROM song s
+            INNER JOIN album al ON al.album_id = s.album_id
+            ORDER BY s.song_id;
+            "#
+        )
+        .fetch_all(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        // Album names are case insensitive
+        let albums =
+            songs.into_group_map_by(|song| (song.artist_id, song.album_name.to_ascii_lowercase()));
+        for songs in albums.into_values() {
+            self.regroup_album(songs).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the songs of albums with the same name and album artist into one album per release
+    async fn regroup_album(&mut self, songs: Vec<AlbumSong>) -> Result<(), DbError> {
+        let compilation = songs.iter().any(|song| song.is_compilation);
+        let tags = songs
+            .iter()
+            .map(|song| ReleaseKey {
+                release_id: song.musicbrainz_release_id.clone(),
+                // Compilation tracks are tagged with the year of each original recording
+                year: (song.song_year > 0 && !compilation).then_some(song.song_year as u32),
+            })
+            .collect_vec();
+        let releases = assign_releases(&tags);
+
+        let mut album_ids: HashMap<&ReleaseKey, i64> = HashMap::new();
+        for release in releases.iter().unique().sorted() {
+            // Keep the existing album IDs where possible, preferring the album that's already
+            // tagged with this release
+            let existing = songs
+                .iter()
+                .zip(&releases)
+                .filter(|(song, song_release)| {
+                    *song_release == release && !album_ids.values().contains(&song.album_id)
+                })
+                .map(|(song, _)| {
+                    let album_release = ReleaseKey {
+                        release_id: song.album_release_id.as_ref().map(|r| r.to_lowercase()),
+                        year: song.album_year.map(|y| y as u32),
+                    };
+                    let changed = &album_release != release || song.is_compilation != compilation;
+                    (changed, song.album_id)
+                })
+                .min();
+
+            let album_id = match existing {
+                Some((false, album_id)) => album_id,
+                Some((true, album_id)) => {
+                    sqlx::query!(
+                        "
+                        UPDATE album SET
+                            musicbrainz_release_id = ?, album_year = ?, is_compilation = ?
+                        WHERE album_id = ?;
+                        ",
+                        release.release_id,
+                        release.year,
+                        compilation,
+                        album_id
+                    )
+                    .execute(&mut *self.tran)
+                    .await
+                    .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+                    album_id
+                }
+                None => sqlx::query_scalar!(
+                    "
+                    INSERT INTO album(
+                        album_name, artist_id, musicbrainz_release_id, album_year,
+                        is_compilation, created_date
+                    )
+                    values(?, ?, ?, ?, ?, ?)
+                    RETURNING album_id;
+                    ",
+                    songs[0].album_name,
+                    songs[0].artist_id,
+                    release.release_id,
+                    release.year,
+                    compilation,
+                    self.timestamp
+                )
+                .fetch_one(&mut *self.tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?,
+            };
+            album_ids.insert(release, album_id);
+        }
+
+        for (song, release) in songs.iter().zip(&releases) {
+            let album_id = album_ids[release];
+            if song.album_id != album_id {
+                sqlx::query!(
+                    "UPDATE song SET album_id = ? WHERE song_id = ?;",
+                    album_id,
+                    song.song_id
+                )
+                .execute(&mut *self.tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn add_genre(&mut self, genre: &str) -> Result<(), DbError> {
+        if genre.is_empty() {
+            return Ok(());
+        }
+        sqlx::query!(
+            "INSERT OR IGNORE INTO genre(genre_name, created_date) values(?, ?);",
+            genre,
+            self.timestamp
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(())
+    }
+
     pub(crate) async fn sync_song(
         &mut self,
         path: &str,
         metadata: &Tag,
+        album_id: i64,
         file_size: i64,
+        modified_date: Option<i64>,
         fingerprint: &str,
-    ) -> Result<SqliteQueryResult, DbError> {
-        self.add_song(path, metadata, file_size, fingerprint)
+    ) -> Result<Option<SyncFileStatus>, DbError> {
+        let existing = sqlx::query_scalar!("SELECT song_id FROM song WHERE song_path = ?;", path)
+            .fetch_optional(&mut *self.tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        self.add_song(path, metadata, album_id, file_size, fingerprint)
             .await?;
-        self.update_song(path, metadata, file_size, fingerprint)
+        let updated = self
+            .update_song(path, metadata, album_id, file_size, fingerprint)
+            .await?
+            .rows_affected()
+            > 0;
+        // The modified date isn't part of the fingerprint so touching a file doesn't force the
+        // song to be analyzed again
+        sqlx::query!(
+            "UPDATE song SET file_modified_date = ? WHERE song_path = ?;",
+            modified_date,
+            path
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let status = match (existing, updated) {
+            (None, _) => Some(SyncFileStatus::Added),
+            (Some(_), true) => Some(SyncFileStatus::Updated),
+            (Some(_), false) => None,
+        };
+        // Always rewritten so songs that were synced before these were tracked get filled in
+        // when they're rescanned, even if the rest of the song didn't change
+        self.set_song_artists(path, metadata).await?;
+        self.set_song_genres(path, metadata).await?;
+        Ok(status)
+    }
+
+    async fn set_song_genres(&mut self, path: &str, metadata: &Tag) -> Result<(), DbError> {
+        sqlx::query!(
+            "DELETE FROM song_genre WHERE song_id = (SELECT song_id FROM song WHERE song_path = ?);",
+            path
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        for (position, genre) in metadata.genres.iter().enumerate() {
+            let position = position as i64;
+            sqlx::query!(
+                "
+                INSERT OR IGNORE INTO song_genre(song_id, genre_id, position)
+                SELECT s.song_id, g.genre_id, ? FROM song s, genre g
+                WHERE s.song_path = ? AND g.genre_name = ?;
+                ",
+                position,
+                path,
+                genre
+            )
+            .execute(&mut *self.tran)
             .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+        Ok(())
+    }
+
+    async fn set_song_artists(&mut self, path: &str, metadata: &Tag) -> Result<(), DbError> {
+        sqlx::query!(
+            "DELETE FROM song_artist WHERE song_id = (SELECT song_id FROM song WHERE song_path = ?);",
+            path
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        for (role, artists) in metadata.credits() {
+            let role = role.to_string();
+            for (position, artist) in artists.iter().enumerate() {
+                let position = position as i64;
+                sqlx::query!(
+                    "
+                    INSERT OR IGNORE INTO song_artist(song_id, artist_id, artist_role, position)
+                    SELECT s.song_id, a.artist_id, ?, ? FROM song s, artist a
+                    WHERE s.song_path = ? AND a.artist_name = ?;
+                    ",
+                    role,
+                    position,
+                    path,
+                    artist
+                )
+                .execute(&mut *self.tran)
+                .await
+                .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn mark_scanned(&mut self, path: &str) -> Result<SqliteQueryResult, DbError> {
+        sqlx::query!(
+            "UPDATE song SET last_scanned_date = ? WHERE song_path = ?;",
+            self.timestamp,
+            path
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_scanned_files(
+        pool: &Pool<Sqlite>,
+    ) -> Result<HashMap<String, FileStamp>, DbError> {
+        // Songs that still need to be analyzed or fingerprinted are left out so they get picked
+        // up again on the next scan, along with songs whose artist credits haven't been split up
+        // yet
+        let rows = sqlx::query!(
+            r#"
+            SELECT song_path, file_modified_date "file_modified_date!", file_size FROM song
+            WHERE file_modified_date IS NOT NULL
+            AND acoustic_fingerprint IS NOT NULL
+            AND (track_gain IS NOT NULL OR loudness_analyzed = 1)
+            AND (EXISTS (
+                SELECT 1 FROM song_artist sa WHERE sa.song_id = song.song_id
+            ) OR EXISTS (
+                SELECT 1 FROM artist a WHERE a.artist_id = song.artist_id AND a.artist_name = ''
+            ));
+            "#
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.song_path,
+                    FileStamp {
+                        modified_date: row.file_modified_date,
+                        file_size: row.file_size,
+                    },
+                )
+            })
+            .collect())
     }
 
-    pub(crate) async fn update_missing_songs(&mut self, path: String) -> Result<(), DbError> {
+    pub(crate) async fn update_missing_songs(
+        &mut self,
+        path: String,
+    ) -> Result<Vec<String>, DbError> {
         // Add songs not found in the last scan attempt to the list of deleted songs
         let mut path = path.clone();
         if !path.ends_with('/') {
@@ -80,6 +515,19 @@ impl<'a> SyncDAL<'a> {
         }
         path += "%";
 
+        let deleted = sqlx::query_scalar!(
+            "
+            SELECT song_path FROM song s WHERE last_scanned_date < ?
+            AND song_path like ?
+            AND NOT EXISTS(SELECT 1 FROM deleted_song ds WHERE ds.song_id = s.song_id);
+            ",
+            self.timestamp,
+            path
+        )
+        .fetch_all(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
         sqlx::query!(
             "
             INSERT INTO deleted_song(song_id)
@@ -108,7 +556,98 @@ impl<'a> SyncDAL<'a> {
         .await
         .map_err(|e| DbError::DbError(format!("{e:?}")))?;
 
-        Ok(())
+        Ok(deleted)
+    }
+
+    /// Saves the report in its own transaction so it's kept even if the sync's changes were
+    /// rolled back
+    pub(crate) async fn save_sync_report(
+        write_pool: &Pool<Sqlite>,
+        started_date: u32,
+        full_rescan: bool,
+        cancelled: bool,
+        files: &[SyncReportFile],
+    ) -> Result<(), DbError> {
+        let count = |status| files.iter().filter(|f| f.status == status).count() as i64;
+        let (added, updated, deleted, failed) = (
+            count(SyncFileStatus::Added),
+            count(SyncFileStatus::Updated),
+            count(SyncFileStatus::Deleted),
+            count(SyncFileStatus::Failed),
+        );
+        let finished_date = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let mut tran = write_pool
+            .begin()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        let sync_report_id = sqlx::query!(
+            "
+            INSERT INTO sync_report(started_date, finished_date, full_rescan, cancelled,
+                added_count, updated_count, deleted_count, failed_count)
+            VALUES(?, ?, ?, ?, ?, ?, ?, ?);
+            ",
+            started_date,
+            finished_date,
+            full_rescan,
+            cancelled,
+            added,
+            updated,
+            deleted,
+            failed
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?
+        .last_insert_rowid();
+
+        for file in files {
+            let status = file.status.to_string();
+            sqlx::query!(
+                "
+                INSERT INTO sync_report_file(sync_report_id, file_path, status, error)
+                VALUES(?, ?, ?, ?);
+                ",
+                sync_report_id,
+                file.path,
+                status,
+                file.error
+            )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        }
+
+        // Only keep the most recent reports around
+        sqlx::query!(
+            "
+            DELETE FROM sync_report_file WHERE sync_report_id NOT IN (
+                SELECT sync_report_id FROM sync_report ORDER BY sync_report_id DESC LIMIT ?
+            );
+            ",
+            MAX_SYNC_REPORTS
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        sqlx::query!(
+            "
+            DELETE FROM sync_report WHERE sync_report_id NOT IN (
+                SELECT sync_report_id FROM sync_report ORDER BY sync_report_id DESC LIMIT ?
+            );
+            ",
+            MAX_SYNC_REPORTS
+        )
+        .execute(&mut *tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        tran.commit()
+            .await
+            .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
     pub(crate) async fn sync_spellfix(&mut self) -> Result<(), DbError> {
@@ -164,33 +703,7 @@ impl<'a> SyncDAL<'a> {
     }
 
     pub(crate) async fn remove_empty_entries(&mut self) -> Result<(), DbError> {
-        sqlx::query!(
-            r#"
-            WITH albums_to_delete AS (
-                SELECT album_id FROM album a
-                WHERE NOT EXISTS (select 1 FROM song s WHERE s.album_id = a.album_id)
-            )
-            DELETE FROM album WHERE album_id IN (SELECT album_id FROM albums_to_delete)
-            "#
-        )
-        .execute(&mut *self.tran)
-        .await
-        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
-
-        sqlx::query!(
-            r#"
-            WITH artists_to_delete AS (
-                SELECT artist_id FROM artist ar
-                WHERE NOT EXISTS (SELECT 1 FROM album al WHERE al.artist_id = ar.artist_id)
-                AND NOT EXISTS (SELECT 1 FROM song s WHERE s.artist_id = ar.artist_id)
-            )
-            DELETE FROM artist WHERE artist_id IN (SELECT artist_id FROM artists_to_delete)
-            "#
-        )
-        .execute(&mut *self.tran)
-        .await
-        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
-
+        Database::remove_empty_entries(&mut self.tran).await?;
         Ok(())
     }
 
@@ -222,6 +735,20 @@ impl<'a> SyncDAL<'a> {
         Ok(())
     }
 
+    /// Records that the data migration ran and returns whether it hadn't run before
+    pub(crate) async fn apply_migration(&mut self, migration_name: &str) -> Result<bool, DbError> {
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO data_migration(migration_name, applied_date) values(?, ?);",
+            migration_name,
+            self.timestamp
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub(crate) async fn commit(self) -> Result<(), DbError> {
         self.tran
             .commit()
@@ -250,13 +777,145 @@ impl<'a> SyncDAL<'a> {
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 
+    pub(crate) async fn get_unanalyzed_song(&mut self, path: &str) -> Result<Option<i64>, DbError> {
+        // Songs that have gain tags don't need to be analyzed
+        let song_id = sqlx::query_scalar!(
+            "
+            SELECT song_id FROM song
+            WHERE song_path = ? AND track_gain IS NULL AND loudness_analyzed = 0
+            ",
+            path
+        )
+        .fetch_optional(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Ok(song_id)
+    }
+
+    pub(crate) async fn set_track_loudness(
+        &mut self,
+        song_id: i64,
+        loudness: Option<Loudness>,
+    ) -> Result<SqliteQueryResult, DbError> {
+        // Mark the song as analyzed even if it failed so we don't retry on every sync
+        let gain = loudness.map(|l| l.gain);
+        let peak = loudness.map(|l| l.peak);
+        sqlx::query!(
+            "
+            UPDATE song
+            SET track_gain = ?, track_peak = ?, loudness_analyzed = 1
+            WHERE song_id = ?
+            ",
+            gain,
+            peak,
+            song_id
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_unfingerprinted_song(
+        &mut self,
+        path: &str,
+    ) -> Result<Option<i64>, DbError> {
+        let song_id = sqlx::query_scalar!(
+            "
+            SELECT song_id FROM song
+            WHERE song_path = ? AND acoustic_fingerprint IS NULL
+            ",
+            path
+        )
+        .fetch_optional(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+        Ok(song_id)
+    }
+
+    pub(crate) async fn set_acoustic_fingerprint(
+        &mut self,
+        song_id: i64,
+        fingerprint: Option<Vec<u8>>,
+    ) -> Result<SqliteQueryResult, DbError> {
+        // Store an empty fingerprint if it failed so we don't retry on every sync
+        let fingerprint = fingerprint.unwrap_or_default();
+        sqlx::query!(
+            "UPDATE song SET acoustic_fingerprint = ? WHERE song_id = ?",
+            fingerprint,
+            song_id
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
+    pub(crate) async fn get_albums_missing_gain(
+        &mut self,
+    ) -> Result<Vec<(i64, Vec<TrackLoudness>)>, DbError> {
+        let rows = sqlx::query!(
+            "
+            SELECT album_id, track_gain, track_peak, duration FROM song
+            WHERE album_id IN (SELECT album_id FROM song WHERE album_gain IS NULL)
+            AND song_id NOT IN (SELECT song_id FROM deleted_song)
+            ORDER BY album_id
+            "
+        )
+        .fetch_all(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))?;
+
+        // Album gain can only be calculated once every track on the album has a track gain
+        Ok(rows
+            .into_iter()
+            .chunk_by(|row| row.album_id)
+            .into_iter()
+            .filter_map(|(album_id, rows)| {
+                let tracks: Option<Vec<_>> = rows
+                    .map(|row| {
+                        Some(TrackLoudness {
+                            gain: row.track_gain?,
+                            peak: row.track_peak.unwrap_or(1.0),
+                            duration: row.duration,
+                        })
+                    })
+                    .collect();
+                Some((album_id, tracks?))
+            })
+            .collect())
+    }
+
+    /// Replaces the album values for every song on the album since adding a track changes the
+    /// loudness of the whole album
+    pub(crate) async fn set_album_loudness(
+        &mut self,
+        album_id: i64,
+        loudness: Loudness,
+    ) -> Result<SqliteQueryResult, DbError> {
+        sqlx::query!(
+            "
+            UPDATE song
+            SET album_gain = ?, album_peak = ?
+            WHERE album_id = ?
+            ",
+            loudness.gain,
+            loudness.peak,
+            album_id
+        )
+        .execute(&mut *self.tran)
+        .await
+        .map_err(|e| DbError::DbError(format!("{e:?}")))
+    }
+
     async fn add_song(
         &mut self,
         path: &str,
         metadata: &Tag,
+        album_id: i64,
         file_size: i64,
         fingerprint: &str,
     ) -> Result<SqliteQueryResult, DbError> {
+        let (start_offset, end_offset) = offsets(metadata);
+        let artist = metadata.artist();
         sqlx::query!(
             "
         INSERT INTO song(
@@ -277,18 +936,31 @@ impl<'a> SyncDAL<'a> {
             bit_rate,
             file_size,
             album_art_path,
-            fingerprint
+            fingerprint,
+            track_gain,
+            track_peak,
+            album_gain,
+            album_peak,
+            release_date,
+            original_year,
+            composer,
+            comment,
+            bpm,
+            musicbrainz_recording_id,
+            musicbrainz_release_id,
+            musicbrainz_artist_id,
+            musicbrainz_release_group_id,
+            start_offset,
+            end_offset,
+            lyrics,
+            synced_lyrics
             )
             values
             (
                 ?, ?, ?, ?,
                 (SELECT artist_id FROM artist WHERE artist_name = ?),
                 ?,
-                (
-                    SELECT album_id FROM album al
-                    INNER JOIN artist ar ON ar.artist_id = al.artist_id
-                    WHERE al.album_name = ? AND ar.artist_name = ?
-                ),
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
                 ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
             )
             ON CONFLICT(song_path) DO UPDATE
@@ -298,21 +970,37 @@ impl<'a> SyncDAL<'a> {
             self.timestamp,
             self.timestamp,
             self.timestamp,
-            metadata.artists,
+            artist,
             metadata.title,
-            metadata.album,
-            metadata.album_artists,
+            album_id,
             metadata.track_number,
             metadata.disc_number,
             metadata.year,
-            0,
-            0,
+            metadata.month,
+            metadata.day,
             metadata.duration,
             metadata.sample_rate,
             metadata.bitrate,
             file_size,
-            "",
+            metadata.album_art,
             fingerprint,
+            metadata.replay_gain.track_gain,
+            metadata.replay_gain.track_peak,
+            metadata.replay_gain.album_gain,
+            metadata.replay_gain.album_peak,
+            metadata.release_date,
+            metadata.original_year,
+            metadata.composer,
+            metadata.comment,
+            metadata.bpm,
+            metadata.musicbrainz.recording_id,
+            metadata.musicbrainz.release_id,
+            metadata.musicbrainz.artist_id,
+            metadata.musicbrainz.release_group_id,
+            start_offset,
+            end_offset,
+            metadata.lyrics,
+            metadata.synced_lyrics,
             self.timestamp
         )
         .execute(&mut *self.tran)
@@ -324,51 +1012,94 @@ impl<'a> SyncDAL<'a> {
         &mut self,
         path: &str,
         metadata: &Tag,
+        album_id: i64,
         file_size: i64,
         fingerprint: &str,
     ) -> Result<SqliteQueryResult, DbError> {
+        let (start_offset, end_offset) = offsets(metadata);
+        let artist = metadata.artist();
         sqlx::query!(
             "
         UPDATE song
             SET modified_date = $2,
             artist_id = (SELECT artist_id FROM artist WHERE artist_name = $3),
             song_title = $4,
-            album_id = (SELECT album_id FROM album a
-                        INNER JOIN artist aa ON a.artist_id = aa.artist_id
-                        WHERE a.album_name = $5 AND aa.artist_name = $6),
-            track_number = $7,
-            disc_number = $8,
-            song_year = $9,
-            song_month = $10,
-            song_day = $11,
-            duration = $12,
-            sample_rate = $13,
-            bit_rate = $14,
-            file_size = $15,
-            album_art_path = $16,
-            fingerprint = $17
-        WHERE song_path = $1 AND fingerprint != $17;
+            album_id = $5,
+            track_number = $6,
+            disc_number = $7,
+            song_year = $8,
+            song_month = $9,
+            song_day = $10,
+            duration = $11,
+            sample_rate = $12,
+            bit_rate = $13,
+            file_size = $14,
+            album_art_path = $15,
+            fingerprint = $16,
+            track_gain = $17,
+            track_peak = $18,
+            album_gain = $19,
+            album_peak = $20,
+            release_date = $21,
+            original_year = $22,
+            composer = $23,
+            comment = $24,
+            bpm = $25,
+            musicbrainz_recording_id = $26,
+            musicbrainz_release_id = $27,
+            musicbrainz_artist_id = $28,
+            musicbrainz_release_group_id = $29,
+            start_offset = $30,
+            end_offset = $31,
+            lyrics = $32,
+            synced_lyrics = $33,
+            loudness_analyzed = 0,
+            acoustic_fingerprint = NULL
+        WHERE song_path = $1 AND fingerprint != $16;
         ",
             path,
             self.timestamp,
-            metadata.artists,
+            artist,
             metadata.title,
-            metadata.album,
-            metadata.album_artists,
+            album_id,
             metadata.track_number,
             metadata.disc_number,
             metadata.year,
-            0,
-            0,
+            metadata.month,
+            metadata.day,
             metadata.duration,
             metadata.sample_rate,
             metadata.bitrate,
             file_size,
-            "",
-            fingerprint
+            metadata.album_art,
+            fingerprint,
+            metadata.replay_gain.track_gain,
+            metadata.replay_gain.track_peak,
+            metadata.replay_gain.album_gain,
+            metadata.replay_gain.album_peak,
+            metadata.release_date,
+            metadata.original_year,
+            metadata.composer,
+            metadata.comment,
+            metadata.bpm,
+            metadata.musicbrainz.recording_id,
+            metadata.musicbrainz.release_id,
+            metadata.musicbrainz.artist_id,
+            metadata.musicbrainz.release_group_id,
+            start_offset,
+            end_offset,
+            metadata.lyrics,
+            metadata.synced_lyrics
         )
         .execute(&mut *self.tran)
         .await
         .map_err(|e| DbError::DbError(format!("{e:?}")))
     }
 }
+
+fn offsets(metadata: &Tag) -> (Option<i64>, Option<i64>) {
+    metadata
+        .range
+        .map(|r| (Some(r.start_millis), Some(r.end_millis)))
+        .unwrap_or_default()
+}
@@ -1,10 +1,16 @@
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::hash::{Hash, Hasher};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 
+use futures::StreamExt;
 use ignore::{WalkBuilder, WalkState};
 use itertools::Itertools;
+use lofty::file::TaggedFile;
 use lofty::probe::Probe;
 use regex::Regex;
 use sqlx::{Pool, Sqlite};
@@ -16,11 +22,19 @@ use tokio::task::{JoinHandle, spawn_blocking};
 use tracing::{error, info};
 use walkdir::WalkDir;
 
+use super::album_art;
+use super::cue::{AudioRange, CUE_EXT, CueSheet};
 use super::dir_read::DirRead;
-use super::sync_dal::SyncDAL;
+use super::file_filter::FileFilter;
+use super::fingerprint;
+use super::loudness::{self, album_loudness};
+use super::sync_dal::{FileStamp, SyncDAL};
+use super::sync_report::{SyncFileStatus, SyncReportFile};
+use super::sync_state::SyncSignal;
 use super::tag::Tag;
-use crate::consts::{ALLOWED_FILE_EXTS, MIN_WORDS};
+use crate::consts::MIN_WORDS;
 use crate::db_error::DbError;
+use crate::lyrics::reader::{read_lyrics, sidecar_path};
 use crate::path_util::clean_file_path;
 
 #[derive(Error, Debug, Clone)]
@@ -33,6 +47,8 @@ pub enum SyncError {
     IOError(String),
     #[error("Tag read error: {0}")]
     TagReadError(String),
+    #[error("Image error: {0}")]
+    ImageError(String),
 }
 
 trait SendSyncError {
@@ -48,10 +64,28 @@ impl SendSyncError for broadcast::Sender<Option<Result<f32, SyncError>>> {
     }
 }
 
+enum ScannedFile {
+    /// New or modified file that needs to be saved
+    Parsed {
+        metadata: Tag,
+        path_str: String,
+        path: PathBuf,
+        modified_date: Option<i64>,
+    },
+    /// File that hasn't changed since the last scan
+    Unchanged(String),
+    /// File that couldn't be read
+    Failed { path_str: String, error: SyncError },
+}
+
 pub(crate) struct SyncEngine {
     paths: Vec<String>,
     write_pool: Pool<Sqlite>,
-    mount: Option<String>,
+    mounts: Vec<String>,
+    art_cache_dir: Option<PathBuf>,
+    full_rescan: bool,
+    filter: Arc<FileFilter>,
+    signal: SyncSignal,
     tx: broadcast::Sender<Option<Result<f32, SyncError>>>,
 }
 
@@ -59,13 +93,21 @@ impl SyncEngine {
     pub(crate) fn new(
         paths: Vec<String>,
         write_pool: Pool<Sqlite>,
-        mount: Option<String>,
+        mounts: Vec<String>,
+        art_cache_dir: Option<PathBuf>,
+        full_rescan: bool,
+        filter: FileFilter,
+        signal: SyncSignal,
         tx: broadcast::Sender<Option<Result<f32, SyncError>>>,
     ) -> Self {
         Self {
             paths,
             write_pool,
-            mount,
+            mounts,
+            art_cache_dir,
+            full_rescan,
+            filter: Arc::new(filter),
+            signal,
             tx,
         }
     }
@@ -78,12 +120,25 @@ impl SyncEngine {
             return;
         }
 
+        // Files that haven't changed since the last scan don't need to be read again unless
+        // we're forcing a full rescan
+        let scanned_files = if self.full_rescan {
+            HashMap::new()
+        } else {
+            SyncDAL::get_scanned_files(&self.write_pool)
+                .await
+                .unwrap_or_else(|e| {
+                    self.tx.send_error(e.into());
+                    HashMap::new()
+                })
+        };
+
         let (tags_tx, tags_rx) = mpsc::channel(10000);
         let (dir_tx, dir_rx) = mpsc::channel(10000);
         let dir_tx_ = dir_tx.clone();
 
         let counter_task = self.dir_counter(dir_tx_);
-        let tags_task = self.tags_parser(tags_tx, dir_tx);
+        let tags_task = self.tags_parser(tags_tx, dir_tx, Arc::new(scanned_files));
         let db_task = self.db_updater(tags_rx);
 
         self.progress_loop(dir_rx).await;
@@ -118,9 +173,20 @@ impl SyncEngine {
 
     fn dir_counter(&self, dir_tx: Sender<DirRead>) -> JoinHandle<Result<(), SyncError>> {
         let paths = self.paths.clone();
+        let filter = self.filter.clone();
+        let mut signal = self.signal.clone();
         spawn_blocking::<_, Result<(), SyncError>>(move || {
             for path in paths {
-                for _ in WalkDir::new(path).into_iter() {
+                // Skip the same entries as the file walker so the progress adds up. The walker
+                // always visits the root path.
+                let entries = WalkDir::new(path).into_iter().filter_entry(|entry| {
+                    entry.depth() == 0
+                        || !filter.is_excluded(entry.path(), entry.file_type().is_dir())
+                });
+                for _ in entries {
+                    if !signal.blocking_wait_while_paused() {
+                        return Ok(());
+                    }
                     dir_tx.blocking_send(DirRead::Found).map_err(|e| {
                         SyncError::ThreadCommError(format!(
                             "Error sending directory found message: {e:?}"
@@ -134,12 +200,18 @@ impl SyncEngine {
 
     fn tags_parser(
         &self,
-        tags_tx: Sender<(Tag, String, PathBuf)>,
+        tags_tx: Sender<ScannedFile>,
         dir_tx: Sender<DirRead>,
+        scanned_files: Arc<HashMap<String, FileStamp>>,
     ) -> JoinHandle<()> {
         let mut walker_builder = WalkBuilder::new(&self.paths[0]);
         let num_cpus = num_cpus::get();
         walker_builder.threads(num_cpus).standard_filters(false);
+        let filter = self.filter.clone();
+        // Prune excluded folders so they don't need to be walked at all
+        walker_builder.filter_entry(move |entry| {
+            !filter.is_excluded(entry.path(), entry.file_type().is_some_and(|t| t.is_dir()))
+        });
 
         if self.paths.len() > 1 {
             for path in &self.paths[1..] {
@@ -147,14 +219,34 @@ impl SyncEngine {
             }
         }
         let walker = walker_builder.build_parallel();
-        let mount = self.mount.clone();
+        let mounts = self.mounts.clone();
+        let art_cache_dir = self.art_cache_dir.clone();
+        // Folder images are shared by every song in the folder, so only look them up once
+        let folder_art = Arc::new(Mutex::new(HashMap::new()));
+        // Modified time of each folder image, used to detect changed art without reading it
+        let folder_art_modified = Arc::new(Mutex::new(HashMap::new()));
+        // Audio files referenced by a cue sheet in each folder. These are synced as individual
+        // tracks when the cue sheet is parsed.
+        let cue_files = Arc::new(Mutex::new(HashMap::new()));
+        let filter = self.filter.clone();
+        let signal = self.signal.clone();
 
         spawn_blocking(move || {
             walker.run(|| {
                 let tags_tx = tags_tx.clone();
                 let dir_tx = dir_tx.clone();
-                let mount = mount.clone();
+                let mounts = mounts.clone();
+                let art_cache_dir = art_cache_dir.clone();
+                let folder_art = folder_art.clone();
+                let folder_art_modified = folder_art_modified.clone();
+                let cue_files = cue_files.clone();
+                let scanned_files = scanned_files.clone();
+                let filter = filter.clone();
+                let mut signal = signal.clone();
                 Box::new(move |result| {
+                    if !signal.blocking_wait_while_paused() {
+                        return WalkState::Quit;
+                    }
                     if dir_tx
                         .blocking_send(DirRead::Completed)
                         .tap_err(|e| error!("Error sending completed dir read: {e:?}"))
@@ -163,16 +255,48 @@ impl SyncEngine {
                         return WalkState::Quit;
                     }
 
-                    if let Ok(result) = result {
-                        let file_path = result.into_path();
-                        if file_path.is_file()
-                            && let Ok(Some(metadata)) = SyncEngine::parse_metadata(&file_path)
-                                .tap_err(|e| error!("Error parsing tag metadata: {e:?}"))
-                            && let Ok(file_path_str) = clean_file_path(&file_path, &mount)
-                            && tags_tx
-                                .blocking_send((metadata, file_path_str, file_path))
-                                .tap_err(|e| error!("Error sending tag: {e:?}"))
-                                .is_err()
+                    let songs = match result {
+                        Ok(entry) => {
+                            let file_path = entry.into_path();
+                            // Paths from the file watcher may point directly to an excluded file
+                            if !file_path.is_file() || filter.is_excluded(&file_path, false) {
+                                return WalkState::Continue;
+                            }
+                            SyncEngine::parse_file(
+                                &file_path,
+                                &mounts,
+                                art_cache_dir.as_deref(),
+                                &folder_art,
+                                &folder_art_modified,
+                                &cue_files,
+                                &scanned_files,
+                                &filter,
+                            )
+                            .unwrap_or_else(|error| {
+                                error!("Error parsing tag metadata: {error:?}");
+                                vec![ScannedFile::Failed {
+                                    path_str: report_path(&file_path, &mounts),
+                                    error,
+                                }]
+                            })
+                        }
+                        Err(e) => {
+                            error!("Error walking directory: {e:?}");
+                            // Errors that aren't tied to a specific entry don't have a path
+                            let path_str = walk_error_path(&e)
+                                .map(|path| report_path(path, &mounts))
+                                .unwrap_or_default();
+                            vec![ScannedFile::Failed {
+                                path_str,
+                                error: SyncError::IOError(e.to_string()),
+                            }]
+                        }
+                    };
+                    for song in songs {
+                        if tags_tx
+                            .blocking_send(song)
+                            .tap_err(|e| error!("Error sending tag: {e:?}"))
+                            .is_err()
                         {
                             return WalkState::Quit;
                         }
@@ -202,18 +326,84 @@ impl SyncEngine {
 
     fn db_updater(
         &self,
-        mut tags_rx: mpsc::Receiver<(Tag, String, PathBuf)>,
+        tags_rx: mpsc::Receiver<ScannedFile>,
     ) -> JoinHandle<Result<(), SyncError>> {
         let write_pool = self.write_pool.clone();
         let cleaned_paths = self
             .paths
             .iter()
-            .map(|p| clean_file_path(p, &self.mount))
+            .map(|p| clean_file_path(p, &self.mounts))
             .collect_vec();
+        let full_rescan = self.full_rescan;
+        let mut signal = self.signal.clone();
 
         tokio::spawn(async move {
-            let mut dal = SyncDAL::try_new(write_pool).await?;
-            while let Some((metadata, path_str, path)) = tags_rx.recv().await {
+            let dal = SyncDAL::try_new(write_pool.clone()).await?;
+            let started_date = dal.timestamp();
+            let mut report = Vec::new();
+            let result =
+                SyncEngine::update_db(dal, tags_rx, &cleaned_paths, &mut signal, &mut report).await;
+            if let Err(e) = &result {
+                // Changes since the last commit were rolled back, but the report is still saved
+                // so the failure shows up for the folders being synced
+                report.extend(
+                    cleaned_paths
+                        .iter()
+                        .flatten()
+                        .map(|path| SyncReportFile::failed(path.clone(), e)),
+                );
+            }
+            let cancelled = result.as_ref().copied().unwrap_or(signal.is_cancelled());
+            SyncDAL::save_sync_report(&write_pool, started_date, full_rescan, cancelled, &report)
+                .await?;
+            result.map(|_| ())
+        })
+    }
+
+    /// Saves the scanned files and returns whether the sync was cancelled
+    async fn update_db(
+        mut dal: SyncDAL<'_>,
+        mut tags_rx: mpsc::Receiver<ScannedFile>,
+        cleaned_paths: &[io::Result<String>],
+        signal: &mut SyncSignal,
+        report: &mut Vec<SyncReportFile>,
+    ) -> Result<bool, SyncError> {
+        let mut unanalyzed = Vec::new();
+        let mut unfingerprinted = Vec::new();
+        // Albums that songs were added to or moved out of, which need to be regrouped
+        let mut touched_albums = HashSet::new();
+        loop {
+            dal = SyncEngine::wait_if_paused(dal, signal).await?;
+            if signal.is_cancelled() {
+                info!("Sync cancelled, saving changes processed so far");
+                break;
+            }
+            let file = tokio::select! {
+                file = tags_rx.recv() => file,
+                // The file walkers stop sending while paused so check the state again
+                _ = signal.interrupted() => continue,
+            };
+            let Some(file) = file else {
+                break;
+            };
+            let (metadata, path_str, path, modified_date) = match file {
+                ScannedFile::Parsed {
+                    metadata,
+                    path_str,
+                    path,
+                    modified_date,
+                } => (metadata, path_str, path, modified_date),
+                ScannedFile::Unchanged(path_str) => {
+                    dal.mark_scanned(&path_str).await?;
+                    continue;
+                }
+                ScannedFile::Failed { path_str, error } => {
+                    report.push(SyncReportFile::failed(path_str, &error));
+                    continue;
+                }
+            };
+            // Errors for a single file shouldn't stop the rest of the sync
+            let synced: Result<_, SyncError> = async {
                 let mut hasher = DefaultHasher::new();
                 metadata.hash(&mut hasher);
 
@@ -228,34 +418,222 @@ impl SyncEngine {
                 file_size.hash(&mut hasher);
                 let fingerprint = hasher.finish().to_string();
 
-                dal.add_artist(&metadata.artists).await?;
-                if metadata.album_artists != metadata.artists {
-                    dal.add_artist(&metadata.album_artists).await?;
+                // Songs without an artist tag are still grouped under an empty artist
+                dal.add_artist(metadata.artist()).await?;
+                for (_, artists) in metadata.credits() {
+                    for artist in artists {
+                        dal.add_artist(&artist).await?;
+                    }
                 }
 
-                dal.add_album(&metadata.album, &metadata.album_artists)
-                    .await?;
+                let previous_album_id = dal.get_song_album(&path_str).await?;
+                let album_id = dal.add_album(&metadata).await?;
+                for genre in &metadata.genres {
+                    dal.add_genre(genre).await?;
+                }
 
-                dal.sync_song(&path_str, &metadata, file_size as i64, &fingerprint)
+                let status = dal
+                    .sync_song(
+                        &path_str,
+                        &metadata,
+                        album_id,
+                        file_size as i64,
+                        modified_date,
+                        &fingerprint,
+                    )
                     .await?;
+                let unfingerprinted = dal.get_unfingerprinted_song(&path_str).await?;
+                let unanalyzed = if metadata.replay_gain.track_gain.is_none() {
+                    dal.get_unanalyzed_song(&path_str).await?
+                } else {
+                    None
+                };
+                Ok((
+                    status,
+                    unfingerprinted,
+                    unanalyzed,
+                    [previous_album_id, Some(album_id)],
+                ))
             }
+            .await;
 
+            match synced {
+                Ok((status, unfingerprinted_id, unanalyzed_id, album_ids)) => {
+                    touched_albums.extend(album_ids.into_iter().flatten());
+                    if let Some(status) = status {
+                        report.push(SyncReportFile::new(path_str.clone(), status));
+                    }
+                    if let Some(song_id) = unfingerprinted_id {
+                        unfingerprinted.push((
+                            song_id,
+                            path_str.clone(),
+                            path.clone(),
+                            metadata.range,
+                        ));
+                    }
+                    if let Some(song_id) = unanalyzed_id {
+                        unanalyzed.push((song_id, path_str, path, metadata.range));
+                    }
+                }
+                Err(error) => {
+                    error!("Error syncing {path_str}: {error:?}");
+                    report.push(SyncReportFile::failed(path_str, &error));
+                }
+            }
+        }
+
+        // Stop the file walkers if we quit early
+        tags_rx.close();
+        // Before analyzing loudness so album gain is calculated with the final albums
+        dal.regroup_albums(&touched_albums).await?;
+
+        // Anything skipped after cancelling will be picked up on the next sync
+        if !signal.is_cancelled() {
+            dal = SyncEngine::analyze_loudness(dal, unanalyzed, signal, report).await?;
+        }
+        if !signal.is_cancelled() {
+            dal = SyncEngine::fingerprint_songs(dal, unfingerprinted, signal, report).await?;
+        }
+        // Files that weren't reached before cancelling would look like they were deleted
+        let cancelled = signal.is_cancelled();
+        if !cancelled {
             for path in cleaned_paths {
-                if let Ok(path) = path.tap_err(|e| error!("Error cleaning path: {e:?}")) {
-                    dal.update_missing_songs(path).await?;
+                if let Ok(path) = path
+                    .as_ref()
+                    .tap_err(|e| error!("Error cleaning path: {e:?}"))
+                {
+                    let deleted = dal.update_missing_songs(path.clone()).await?;
+                    report.extend(
+                        deleted
+                            .into_iter()
+                            .map(|path| SyncReportFile::new(path, SyncFileStatus::Deleted)),
+                    );
                 }
             }
+        }
 
-            dal.sync_spellfix().await?;
-            SyncEngine::add_search_aliases(&mut dal).await?;
-            dal.remove_empty_entries().await?;
+        dal.sync_spellfix().await?;
+        SyncEngine::add_search_aliases(&mut dal).await?;
+        dal.remove_empty_entries().await?;
 
-            info!("Committing changes");
-            dal.commit().await?;
-            info!("Finished committing");
+        info!("Committing changes");
+        dal.commit().await?;
+        info!("Finished committing");
 
-            Ok(())
-        })
+        Ok(cancelled)
+    }
+
+    async fn analyze_loudness<'a>(
+        mut dal: SyncDAL<'a>,
+        songs: Vec<(i64, String, PathBuf, Option<AudioRange>)>,
+        signal: &mut SyncSignal,
+        report: &mut Vec<SyncReportFile>,
+    ) -> Result<SyncDAL<'a>, DbError> {
+        let mut analyzed = Vec::new();
+        if !songs.is_empty() {
+            info!("Analyzing loudness for {} songs", songs.len());
+            // Decoding takes a while, so don't block other writers while it's running
+            let suspended = dal.suspend().await?;
+            // Decoding is CPU-bound so analyze files in parallel on the blocking pool
+            let mut results = futures::stream::iter(songs)
+                .map(|(song_id, path_str, path, range)| async move {
+                    let loudness =
+                        spawn_blocking(move || loudness::analyze_file(&path, range)).await;
+                    (song_id, path_str, loudness)
+                })
+                .buffer_unordered(num_cpus::get());
+
+            while let Some((song_id, path_str, loudness)) = results.next().await {
+                let loudness = loudness
+                    .map_err(|e| {
+                        SyncError::ThreadCommError(format!(
+                            "Error joining loudness analysis task: {e:?}"
+                        ))
+                    })
+                    .and_then(|loudness| loudness)
+                    .tap_err(|e| {
+                        error!("Error analyzing loudness: {e:?}");
+                        report.push(SyncReportFile::failed(path_str, e));
+                    })
+                    .ok();
+                analyzed.push((song_id, loudness));
+                if signal.is_paused() {
+                    signal.wait_while_paused().await;
+                }
+                if signal.is_cancelled() {
+                    break;
+                }
+            }
+            dal = suspended.resume().await?;
+        }
+        for (song_id, loudness) in analyzed {
+            dal.set_track_loudness(song_id, loudness).await?;
+        }
+        if signal.is_cancelled() {
+            return Ok(dal);
+        }
+
+        for (album_id, tracks) in dal.get_albums_missing_gain().await? {
+            if let Some(loudness) = album_loudness(&tracks) {
+                dal.set_album_loudness(album_id, loudness).await?;
+            }
+        }
+
+        Ok(dal)
+    }
+
+    async fn fingerprint_songs<'a>(
+        mut dal: SyncDAL<'a>,
+        songs: Vec<(i64, String, PathBuf, Option<AudioRange>)>,
+        signal: &mut SyncSignal,
+        report: &mut Vec<SyncReportFile>,
+    ) -> Result<SyncDAL<'a>, DbError> {
+        if !songs.is_empty() {
+            info!("Computing acoustic fingerprints for {} songs", songs.len());
+        }
+        let mut results = futures::stream::iter(songs)
+            .map(|(song_id, path_str, path, range)| async move {
+                let fingerprint =
+                    spawn_blocking(move || fingerprint::fingerprint_file(&path, range)).await;
+                (song_id, path_str, fingerprint)
+            })
+            .buffer_unordered(num_cpus::get());
+
+        while let Some((song_id, path_str, fingerprint)) = results.next().await {
+            let fingerprint = fingerprint
+                .map_err(|e| {
+                    SyncError::ThreadCommError(format!("Error joining fingerprint task: {e:?}"))
+                })
+                .and_then(|fingerprint| fingerprint)
+                .tap_err(|e| {
+                    error!("Error computing acoustic fingerprint: {e:?}");
+                    report.push(SyncReportFile::failed(path_str, e));
+                })
+                .ok()
+                .map(|fingerprint| fingerprint::to_bytes(&fingerprint));
+            dal.set_acoustic_fingerprint(song_id, fingerprint).await?;
+            dal = SyncEngine::wait_if_paused(dal, signal).await?;
+            if signal.is_cancelled() {
+                break;
+            }
+        }
+
+        Ok(dal)
+    }
+
+    /// Commits the pending changes while the sync is paused so the write connection isn't held
+    async fn wait_if_paused<'a>(
+        dal: SyncDAL<'a>,
+        signal: &mut SyncSignal,
+    ) -> Result<SyncDAL<'a>, DbError> {
+        if !signal.is_paused() {
+            return Ok(dal);
+        }
+        info!("Pausing sync");
+        let suspended = dal.suspend().await?;
+        signal.wait_while_paused().await;
+        info!("Resuming sync");
+        suspended.resume().await
     }
 
     async fn add_search_aliases(dal: &mut SyncDAL<'_>) -> Result<(), DbError> {
@@ -289,8 +667,154 @@ impl SyncEngine {
         Ok(())
     }
 
-    fn parse_metadata(file_path: &Path) -> Result<Option<Tag>, SyncError> {
-        let name = file_path.extension().unwrap_or_default();
+    fn parse_file(
+        file_path: &Path,
+        mounts: &[String],
+        art_cache_dir: Option<&Path>,
+        folder_art: &Mutex<HashMap<PathBuf, Option<String>>>,
+        folder_art_modified: &Mutex<HashMap<PathBuf, Option<i64>>>,
+        cue_files: &Mutex<HashMap<PathBuf, HashSet<OsString>>>,
+        scanned_files: &HashMap<String, FileStamp>,
+        filter: &FileFilter,
+    ) -> Result<Vec<ScannedFile>, SyncError> {
+        let clean_path = |path: &Path| {
+            clean_file_path(&path, mounts).map_err(|e| {
+                SyncError::IOError(format!("Error cleaning file path {path:?}: {e:?}"))
+            })
+        };
+        if file_path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(CUE_EXT))
+        {
+            // Cue sheets are cheap to parse and can change independently of the audio file so
+            // they're always read again
+            let sheet = CueSheet::read(file_path)?;
+            let dir = file_path.parent().unwrap_or(Path::new(""));
+            let mut songs = Vec::new();
+            for file in &sheet.files {
+                let Some(audio_path) = CueSheet::resolve_file(dir, &file.name, filter) else {
+                    let error = SyncError::IOError(format!(
+                        "Unable to find {:?} referenced by {file_path:?}",
+                        file.name
+                    ));
+                    error!("{error:?}");
+                    songs.push(ScannedFile::Failed {
+                        path_str: clean_path(file_path)?,
+                        error,
+                    });
+                    continue;
+                };
+                let Some(file_tag) =
+                    SyncEngine::parse_metadata(&audio_path, art_cache_dir, folder_art, filter)?
+                else {
+                    continue;
+                };
+                let audio_path_str = clean_path(&audio_path)?;
+                // Each track gets its own path so it can be played and updated independently
+                for tag in sheet.track_tags(file, &file_tag) {
+                    if let Some(range) = tag.range {
+                        let track_path = format!("{audio_path_str}{}", range.fragment());
+                        songs.push(ScannedFile::Parsed {
+                            metadata: tag,
+                            path_str: track_path,
+                            path: audio_path.clone(),
+                            modified_date: None,
+                        });
+                    }
+                }
+            }
+            return Ok(songs);
+        }
+        // Check the extension before looking up the previous scan so files with extensions that
+        // were removed from the config get treated as missing
+        if !filter.has_allowed_ext(file_path)
+            || SyncEngine::is_split_by_cue(file_path, cue_files, filter)
+        {
+            return Ok(Vec::new());
+        }
+
+        let path_str = clean_path(file_path)?;
+        let stamp = SyncEngine::file_stamp(file_path, folder_art_modified);
+        if stamp.is_some() && scanned_files.get(&path_str) == stamp.as_ref() {
+            return Ok(vec![ScannedFile::Unchanged(path_str)]);
+        }
+
+        match SyncEngine::parse_metadata(file_path, art_cache_dir, folder_art, filter)? {
+            Some(metadata) => Ok(vec![ScannedFile::Parsed {
+                metadata,
+                path_str,
+                path: file_path.to_path_buf(),
+                modified_date: stamp.map(|s| s.modified_date),
+            }]),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn file_stamp(
+        file_path: &Path,
+        folder_art_modified: &Mutex<HashMap<PathBuf, Option<i64>>>,
+    ) -> Option<FileStamp> {
+        let modified_millis = |path: &Path| {
+            path.metadata()
+                .ok()?
+                .modified()
+                .ok()?
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_millis() as i64)
+        };
+        let metadata = file_path.metadata().ok()?;
+        // Sidecar lyrics and folder images are stored with the song so editing them should
+        // trigger an update too
+        let lyrics_modified = modified_millis(&sidecar_path(file_path));
+        let art_modified = file_path.parent().and_then(|dir| {
+            if let Some(cached) = folder_art_modified.lock().unwrap().get(dir) {
+                return *cached;
+            }
+            let modified = album_art::folder_art_path(dir)
+                .ok()
+                .flatten()
+                .and_then(|path| modified_millis(&path));
+            folder_art_modified
+                .lock()
+                .unwrap()
+                .insert(dir.to_path_buf(), modified);
+            modified
+        });
+        Some(FileStamp {
+            modified_date: modified_millis(file_path)?
+                .max(lyrics_modified.unwrap_or_default())
+                .max(art_modified.unwrap_or_default()),
+            file_size: metadata.len() as i64,
+        })
+    }
+
+    fn is_split_by_cue(
+        file_path: &Path,
+        cue_files: &Mutex<HashMap<PathBuf, HashSet<OsString>>>,
+        filter: &FileFilter,
+    ) -> bool {
+        let (Some(dir), Some(name)) = (file_path.parent(), file_path.file_name()) else {
+            return false;
+        };
+        if let Some(referenced) = cue_files.lock().unwrap().get(dir) {
+            return referenced.contains(name);
+        }
+        let referenced = CueSheet::referenced_files(dir, filter);
+        let is_split = referenced.contains(name);
+        cue_files
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), referenced);
+        is_split
+    }
+
+    fn parse_metadata(
+        file_path: &Path,
+        art_cache_dir: Option<&Path>,
+        folder_art: &Mutex<HashMap<PathBuf, Option<String>>>,
+        filter: &FileFilter,
+    ) -> Result<Option<Tag>, SyncError> {
         let _size = file_path
             .metadata()
             .map_err(|e| {
@@ -300,8 +824,7 @@ impl SyncEngine {
             })?
             .len();
 
-        let name = &name.to_str().unwrap_or_default().to_lowercase()[..];
-        if ALLOWED_FILE_EXTS.contains(&name) {
+        if filter.has_allowed_ext(file_path) {
             let tagged_file = Probe::open(file_path)
                 .map_err(|e| {
                     SyncError::TagReadError(format!("Error opening file {file_path:?}: {e:?}"))
@@ -312,9 +835,63 @@ impl SyncEngine {
                         "Error reading tag from file {file_path:?}: {e:?}"
                     ))
                 })?;
-            return Ok(Some(tagged_file.into()));
+            let album_art = art_cache_dir.and_then(|art_cache_dir| {
+                SyncEngine::find_album_art(file_path, &tagged_file, art_cache_dir, folder_art)
+                    .tap_err(|e| error!("Error caching album art for {file_path:?}: {e:?}"))
+                    .ok()
+                    .flatten()
+            });
+            let mut tag: Tag = tagged_file.into();
+            tag.album_art = album_art;
+            let lyrics = read_lyrics(file_path, tag.lyrics.take());
+            tag.lyrics = lyrics.plain;
+            tag.synced_lyrics = lyrics.synced;
+            return Ok(Some(tag));
         }
 
         Ok(None)
     }
+
+    fn find_album_art(
+        file_path: &Path,
+        tagged_file: &TaggedFile,
+        art_cache_dir: &Path,
+        folder_art: &Mutex<HashMap<PathBuf, Option<String>>>,
+    ) -> Result<Option<String>, SyncError> {
+        if let Some(data) = album_art::embedded_art(tagged_file) {
+            return album_art::cache_art(art_cache_dir, &data).map(Some);
+        }
+        let Some(dir) = file_path.parent() else {
+            return Ok(None);
+        };
+        if let Some(cached) = folder_art.lock().unwrap().get(dir) {
+            return Ok(cached.clone());
+        }
+        let art = match album_art::folder_art(dir)? {
+            Some(data) => Some(album_art::cache_art(art_cache_dir, &data)?),
+            None => None,
+        };
+        folder_art
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), art.clone());
+        Ok(art)
+    }
+}
+
+/// Uses the raw path if it can't be cleaned so the failure still gets reported
+fn report_path(path: &Path, mounts: &[String]) -> String {
+    clean_file_path(&path, mounts).unwrap_or_else(|_| path.to_string_lossy().to_string())
+}
+
+fn walk_error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path.as_path()),
+        ignore::Error::Loop { child, .. } => Some(child.as_path()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        ignore::Error::Partial(errors) => errors.iter().find_map(walk_error_path),
+        _ => None,
+    }
 }
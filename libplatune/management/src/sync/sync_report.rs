@@ -0,0 +1,91 @@
+use std::str::FromStr;
+
+use strum::{Display, EnumString};
+
+use super::sync_engine::SyncError;
+use crate::db_error::DbError;
+use crate::path_util::PathMut;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SyncFileStatus {
+    Added,
+    Updated,
+    Deleted,
+    /// The file couldn't be read or analyzed so it wasn't fully synced
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncReportFile {
+    pub path: String,
+    pub status: SyncFileStatus,
+    /// Reason the file failed to sync
+    pub error: Option<String>,
+}
+
+impl SyncReportFile {
+    pub(crate) fn new(path: String, status: SyncFileStatus) -> Self {
+        Self {
+            path,
+            status,
+            error: None,
+        }
+    }
+
+    pub(crate) fn failed(path: String, error: &SyncError) -> Self {
+        Self {
+            path,
+            status: SyncFileStatus::Failed,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+impl PathMut for SyncReportFile {
+    fn get_path(&self) -> String {
+        self.path.to_owned()
+    }
+
+    fn update_path(&mut self, path: String) {
+        self.path = path
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncReport {
+    pub sync_report_id: i64,
+    /// Unix timestamp in seconds
+    pub started_date: i64,
+    /// Unix timestamp in seconds
+    pub finished_date: i64,
+    pub full_rescan: bool,
+    /// Deleted files aren't detected if the sync was cancelled
+    pub cancelled: bool,
+    pub added_count: i64,
+    pub updated_count: i64,
+    pub deleted_count: i64,
+    pub failed_count: i64,
+    /// Files included in the report, filtered by the requested statuses
+    pub files: Vec<SyncReportFile>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SyncReportFileRow {
+    pub(crate) file_path: String,
+    pub(crate) status: String,
+    pub(crate) error: Option<String>,
+}
+
+impl TryFrom<SyncReportFileRow> for SyncReportFile {
+    type Error = DbError;
+
+    fn try_from(row: SyncReportFileRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            path: row.file_path,
+            status: SyncFileStatus::from_str(&row.status)
+                .map_err(|e| DbError::DbError(format!("Invalid sync status {e:?}")))?,
+            error: row.error,
+        })
+    }
+}
@@ -0,0 +1,64 @@
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncState {
+    /// No sync has been started yet
+    #[default]
+    Idle,
+    Running,
+    /// Processing is stopped and the changes made so far are committed until the sync is resumed
+    Paused,
+    /// The sync was stopped early. Changes made before it was cancelled are kept.
+    Cancelled,
+    Finished,
+}
+
+/// Read side of the sync state used by the sync engine to check for pause and cancel requests
+#[derive(Clone)]
+pub(crate) struct SyncSignal {
+    state_rx: watch::Receiver<SyncState>,
+}
+
+impl SyncSignal {
+    pub(crate) fn new(state_rx: watch::Receiver<SyncState>) -> Self {
+        Self { state_rx }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        *self.state_rx.borrow() == SyncState::Paused
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        *self.state_rx.borrow() == SyncState::Cancelled
+    }
+
+    /// Waits until the sync is no longer paused. Returns `false` if the sync was cancelled.
+    pub(crate) async fn wait_while_paused(&mut self) -> bool {
+        match self
+            .state_rx
+            .wait_for(|state| *state != SyncState::Paused)
+            .await
+        {
+            Ok(state) => *state != SyncState::Cancelled,
+            // The controller is gone so there's nothing left to wait for
+            Err(_) => false,
+        }
+    }
+
+    /// Waits until the sync is paused or cancelled
+    pub(crate) async fn interrupted(&mut self) {
+        if self
+            .state_rx
+            .wait_for(|state| *state != SyncState::Running)
+            .await
+            .is_err()
+        {
+            // The state can't change anymore
+            std::future::pending::<()>().await;
+        }
+    }
+
+    pub(crate) fn blocking_wait_while_paused(&mut self) -> bool {
+        futures::executor::block_on(self.wait_while_paused())
+    }
+}
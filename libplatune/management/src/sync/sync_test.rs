@@ -1,12 +1,14 @@
 use std::fs::{self, File, create_dir, create_dir_all};
-use std::path::{Path, PathBuf};
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use futures::StreamExt;
 use itertools::Itertools;
 use lofty::config::WriteOptions;
+use lofty::file::AudioFile;
 use lofty::file::TaggedFileExt;
+use lofty::picture::{Picture, PictureType};
 use lofty::probe::Probe;
 use lofty::tag::{Accessor, ItemKey, TagExt};
 use normpath::PathExt;
@@ -16,7 +18,12 @@ use tempfile::TempDir;
 
 use crate::config::MemoryConfig;
 use crate::database::Database;
-use crate::manager::Manager;
+use crate::entry_type::EntryType;
+use crate::manager::{
+    ArtistRole, DEFAULT_MOUNT, FolderRules, Manager, ManagerError, SyncFileStatus, SyncReport,
+    SyncState,
+};
+use crate::sync::album_art;
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 pub async fn test_sync_empty() {
@@ -75,7 +82,7 @@ pub async fn test_sync_basic(use_mount: bool) {
 
     if use_mount {
         let mount = music_dir.parent().unwrap();
-        manager.register_drive(mount).await.unwrap();
+        manager.register_mount(DEFAULT_MOUNT, mount).await.unwrap();
     }
     let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
 
@@ -94,6 +101,129 @@ pub async fn test_sync_basic(use_mount: bool) {
     }
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_multiple_mounts() {
+    let nas_dir = TempDir::new().unwrap();
+    let disk_dir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let nas_folder = nas_dir.path().join("music");
+    let disk_folder = disk_dir.path().join("recordings");
+    create_dir_all(&nas_folder).unwrap();
+    create_dir_all(&disk_folder).unwrap();
+    fs::copy("../test_assets/test.mp3", nas_folder.join("test.mp3")).unwrap();
+    fs::copy("../test_assets/test2.mp3", disk_folder.join("test2.mp3")).unwrap();
+
+    manager.register_mount("nas", nas_dir.path()).await.unwrap();
+    manager
+        .register_mount("disk", disk_dir.path())
+        .await
+        .unwrap();
+    manager
+        .add_folders(vec![
+            nas_folder.to_str().unwrap(),
+            disk_folder.to_str().unwrap(),
+        ])
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let mounts = manager.get_registered_mounts().await.unwrap();
+    assert_eq!(
+        vec!["nas", "disk"],
+        mounts.iter().map(|m| &m.name[..]).collect_vec()
+    );
+    let song1 = manager
+        .get_song_by_path(nas_folder.join("test.mp3"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_normalized(
+        nas_folder.join("test.mp3").to_string_lossy().to_string(),
+        song1.path,
+    );
+    let song2 = manager
+        .get_song_by_path(disk_folder.join("test2.mp3"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_normalized(
+        disk_folder.join("test2.mp3").to_string_lossy().to_string(),
+        song2.path,
+    );
+
+    // The same relative folder can't be added for another mount
+    create_dir_all(disk_dir.path().join("music")).unwrap();
+    assert!(matches!(
+        manager
+            .add_folder(disk_dir.path().join("music").to_str().unwrap())
+            .await,
+        Err(ManagerError::MountConflict(_))
+    ));
+    // Folders that contain or are inside of a folder from another mount are ambiguous too
+    let nested_dir = disk_dir.path().join("music").join("live");
+    create_dir_all(&nested_dir).unwrap();
+    assert!(matches!(
+        manager.add_folder(nested_dir.to_str().unwrap()).await,
+        Err(ManagerError::MountConflict(_))
+    ));
+    assert!(matches!(
+        manager.add_folder(disk_dir.path().to_str().unwrap()).await,
+        Err(ManagerError::MountConflict(_))
+    ));
+    create_dir_all(nas_dir.path().join("other")).unwrap();
+    create_dir_all(disk_dir.path().join("other").join("live")).unwrap();
+    assert!(matches!(
+        manager
+            .add_folders(vec![
+                nas_dir.path().join("other").to_str().unwrap(),
+                disk_dir.path().join("other").join("live").to_str().unwrap(),
+            ])
+            .await,
+        Err(ManagerError::MountConflict(_))
+    ));
+
+    // Moving one mount leaves the other one alone
+    let new_disk_dir = TempDir::new().unwrap();
+    let new_disk_folder = new_disk_dir.path().join("recordings");
+    create_dir_all(&new_disk_folder).unwrap();
+    fs::copy(
+        "../test_assets/test2.mp3",
+        new_disk_folder.join("test2.mp3"),
+    )
+    .unwrap();
+    manager
+        .register_mount("disk", new_disk_dir.path())
+        .await
+        .unwrap();
+
+    assert_eq!(2, manager.get_registered_mounts().await.unwrap().len());
+    let folders = manager.get_all_folders().await.unwrap();
+    assert_eq!(2, folders.len());
+    assert_normalized(
+        format!("{}{MAIN_SEPARATOR}", nas_folder.to_string_lossy()),
+        folders[0].clone(),
+    );
+    assert_normalized(
+        format!("{}{MAIN_SEPARATOR}", new_disk_folder.to_string_lossy()),
+        folders[1].clone(),
+    );
+    assert!(
+        manager
+            .get_song_by_path(new_disk_folder.join("test2.mp3"))
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        manager
+            .get_song_by_path(nas_folder.join("test.mp3"))
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 pub async fn test_sync_multiple() {
     let tempdir = TempDir::new().unwrap();
@@ -307,6 +437,1190 @@ pub async fn test_sync_duplicate_album_name(do_update: bool) {
     assert_eq!("track2", song2_entry.song);
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_replay_gain_tags() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    {
+        let mut track = Probe::open(&song_path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        tag.insert_text(ItemKey::ReplayGainTrackGain, "-6.50 dB".to_owned());
+        tag.insert_text(ItemKey::ReplayGainTrackPeak, "0.988".to_owned());
+        tag.insert_text(ItemKey::ReplayGainAlbumGain, "-7.25 dB".to_owned());
+        tag.insert_text(ItemKey::ReplayGainAlbumPeak, "0.999".to_owned());
+        tag.save_to_path(&song_path, WriteOptions::new()).unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    let entry = manager.get_song_by_path(&song_path).await.unwrap().unwrap();
+    assert_eq!(Some(-6.5), entry.track_gain);
+    assert_eq!(Some(0.988), entry.track_peak);
+    assert_eq!(Some(-7.25), entry.album_gain);
+    assert_eq!(Some(0.999), entry.album_peak);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_extended_tags() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    {
+        let mut track = Probe::open(&song_path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        tag.set_genre("Post-Rock".to_owned());
+        tag.set_comment("a comment".to_owned());
+        tag.insert_text(ItemKey::Composer, "composer".to_owned());
+        tag.insert_text(ItemKey::Bpm, "120.4".to_owned());
+        tag.insert_text(ItemKey::RecordingDate, "2004-05-17".to_owned());
+        tag.insert_text(ItemKey::OriginalReleaseDate, "1999-01-01".to_owned());
+        tag.insert_text(ItemKey::MusicBrainzRecordingId, "recording".to_owned());
+        tag.insert_text(ItemKey::MusicBrainzReleaseId, "release".to_owned());
+        tag.insert_text(ItemKey::MusicBrainzArtistId, "artist".to_owned());
+        tag.insert_text(
+            ItemKey::MusicBrainzReleaseGroupId,
+            "release group".to_owned(),
+        );
+        tag.save_to_path(&song_path, WriteOptions::new()).unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    let entry = manager.get_song_by_path(&song_path).await.unwrap().unwrap();
+    assert_eq!(Some("Post-Rock".to_owned()), entry.genre);
+    assert_eq!(Some("composer".to_owned()), entry.composer);
+    assert_eq!(Some("a comment".to_owned()), entry.comment);
+    assert_eq!(Some(120), entry.bpm);
+    assert_eq!(Some("2004-05-17".to_owned()), entry.release_date);
+    assert_eq!(Some(1999), entry.original_year);
+    assert_eq!(Some("recording".to_owned()), entry.musicbrainz_recording_id);
+    assert_eq!(Some("release".to_owned()), entry.musicbrainz_release_id);
+    assert_eq!(Some("artist".to_owned()), entry.musicbrainz_artist_id);
+    assert_eq!(
+        Some("release group".to_owned()),
+        entry.musicbrainz_release_group_id
+    );
+
+    let genre = manager
+        .search("post rock", Default::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|r| matches!(r.entry_type, EntryType::Genre))
+        .unwrap();
+    assert_eq!("Genre", genre.description);
+    let songs = manager
+        .lookup(genre.correlation_ids, genre.entry_type)
+        .await
+        .unwrap();
+    assert_eq!(1, songs.len());
+    assert_eq!(entry.song_id, songs[0].song_id);
+
+    let composer = manager
+        .search("composer", Default::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|r| matches!(r.entry_type, EntryType::Composer))
+        .unwrap();
+    let songs = manager
+        .lookup(composer.correlation_ids, composer.entry_type)
+        .await
+        .unwrap();
+    assert_eq!(1, songs.len());
+    assert_eq!(entry.song_id, songs[0].song_id);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_computes_loudness() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let paths = vec![music_dir.join("test.mp3"), music_dir.join("test2.mp3")];
+    for path in &paths {
+        fs::copy("../test_assets/test.mp3", path).unwrap();
+        let mut track = Probe::open(path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        tag.set_album("album".to_owned());
+        tag.insert_text(ItemKey::AlbumArtist, "artist".to_owned());
+        tag.set_title(path.file_name().unwrap().to_string_lossy().to_string());
+        tag.save_to_path(path, WriteOptions::new()).unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    for path in paths {
+        let entry = manager.get_song_by_path(&path).await.unwrap().unwrap();
+        let track_gain = entry.track_gain.unwrap();
+        let track_peak = entry.track_peak.unwrap();
+        assert!(track_peak > 0.0);
+        // Both tracks are identical, so the album values should match the track values
+        assert!((entry.album_gain.unwrap() - track_gain).abs() < 0.01);
+        assert_eq!(Some(track_peak), entry.album_peak);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_updates_album_loudness() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+
+    let paths = vec![music_dir.join("test.mp3"), music_dir.join("test2.mp3")];
+    for path in &paths {
+        fs::copy(
+            Path::new("../test_assets").join(path.file_name().unwrap()),
+            path,
+        )
+        .unwrap();
+        let mut track = Probe::open(path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        tag.set_album("album".to_owned());
+        tag.insert_text(ItemKey::AlbumArtist, "artist".to_owned());
+        tag.set_title(path.file_name().unwrap().to_string_lossy().to_string());
+        tag.save_to_path(path, WriteOptions::new()).unwrap();
+
+        // Sync after each track is added so the album has to be recalculated
+        let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+        while (receiver.next().await).is_some() {}
+    }
+
+    let first = manager.get_song_by_path(&paths[0]).await.unwrap().unwrap();
+    let second = manager.get_song_by_path(&paths[1]).await.unwrap().unwrap();
+    assert!(first.album_gain.is_some());
+    assert_eq!(first.album_gain, second.album_gain);
+    assert_eq!(first.album_peak, second.album_peak);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_album_art() {
+    let tempdir = TempDir::new().unwrap();
+    let art_dir = tempdir.path().join("art");
+    let db = Database::connect_in_memory()
+        .await
+        .unwrap()
+        .with_art_cache_dir(&art_dir);
+    db.sync_database().await.unwrap();
+    let mut manager = Manager::new(&db, Arc::new(MemoryConfig::new_boxed()));
+
+    let music_dir = tempdir.path().join("configdir");
+    let folder_dir = music_dir.join("folder1");
+    let embedded_dir = music_dir.join("folder2");
+    create_dir_all(&folder_dir).unwrap();
+    create_dir_all(&embedded_dir).unwrap();
+
+    let folder_paths = vec![folder_dir.join("test.mp3"), folder_dir.join("test2.mp3")];
+    fs::copy("../test_assets/test.mp3", &folder_paths[0]).unwrap();
+    fs::copy("../test_assets/test2.mp3", &folder_paths[1]).unwrap();
+    fs::write(folder_dir.join("Cover.png"), png_bytes([255, 0, 0])).unwrap();
+
+    let embedded_path = embedded_dir.join("test3.mp3");
+    fs::copy("../test_assets/test3.mp3", &embedded_path).unwrap();
+    {
+        let mut track = Probe::open(&embedded_path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        let mut picture = Picture::from_reader(&mut &png_bytes([0, 0, 255])[..]).unwrap();
+        picture.set_pic_type(PictureType::CoverFront);
+        tag.push_picture(picture);
+        tag.save_to_path(&embedded_path, WriteOptions::new())
+            .unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    let mut art = vec![];
+    for path in folder_paths.iter().chain([&embedded_path]) {
+        let entry = manager.get_song_by_path(path).await.unwrap().unwrap();
+        art.push(entry.album_art.unwrap());
+    }
+    // Songs in the same folder should share the same cached image
+    assert_eq!(art[0], art[1]);
+    assert_ne!(art[0], art[2]);
+    for name in &art {
+        assert!(art_dir.join(name).exists());
+        assert!(
+            art_dir
+                .join(album_art::THUMBNAIL_DIR)
+                .join(album_art::thumbnail_name(name))
+                .exists()
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_folder_art_changes() {
+    let tempdir = TempDir::new().unwrap();
+    let art_dir = tempdir.path().join("art");
+    let db = Database::connect_in_memory()
+        .await
+        .unwrap()
+        .with_art_cache_dir(&art_dir);
+    db.sync_database().await.unwrap();
+    let mut manager = Manager::new(&db, Arc::new(MemoryConfig::new_boxed()));
+
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(&music_dir).unwrap();
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    let cover_path = music_dir.join("cover.png");
+    fs::write(&cover_path, png_bytes([255, 0, 0])).unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    let first = manager
+        .get_song_by_path(&song_path)
+        .await
+        .unwrap()
+        .unwrap()
+        .album_art
+        .unwrap();
+
+    // Only the image changes so the song itself looks untouched
+    let song_modified = song_path.metadata().unwrap().modified().unwrap();
+    fs::write(&cover_path, png_bytes([0, 255, 0])).unwrap();
+    File::options()
+        .write(true)
+        .open(&cover_path)
+        .unwrap()
+        .set_modified(song_modified + Duration::from_secs(10))
+        .unwrap();
+
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    let second = manager
+        .get_song_by_path(&song_path)
+        .await
+        .unwrap()
+        .unwrap()
+        .album_art
+        .unwrap();
+    assert_ne!(first, second);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_cue_sheet() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let album_path = music_dir.join("album.mp3");
+    fs::copy("../test_assets/test_stereo_44100.mp3", &album_path).unwrap();
+    let file_duration = Probe::open(&album_path)
+        .unwrap()
+        .read()
+        .unwrap()
+        .properties()
+        .duration()
+        .as_millis();
+    let cue_path = music_dir.join("album.cue");
+    // The referenced file has the wrong extension to make sure other audio files are checked
+    fs::write(
+        &cue_path,
+        r#"REM GENRE "Post-Rock"
+REM DATE 2004
+PERFORMER "cue artist"
+TITLE "cue album"
+FILE "album.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "first"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "second"
+    PERFORMER "guest artist"
+    INDEX 00 00:01:50
+    INDEX 01 00:02:00
+  TRACK 03 AUDIO
+    TITLE "third"
+    INDEX 01 00:04:37
+"#,
+    )
+    .unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    // The file itself shouldn't be added when it's split up by a cue sheet
+    assert!(
+        manager
+            .get_song_by_path(&album_path)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let album_path_str = normalize(&album_path.to_string_lossy().to_string());
+    let expected = [
+        ("first", "cue artist", 1, "0.000,2.000", 2000),
+        ("second", "guest artist", 2, "2.000,4.493", 2493),
+        (
+            "third",
+            "cue artist",
+            3,
+            &format!("4.493,{}.{:03}", file_duration / 1000, file_duration % 1000)[..],
+            file_duration as i64 - 4493,
+        ),
+    ];
+    let mut track_paths = vec![];
+    for (title, artist, track_number, range, duration) in expected {
+        let track_path = format!("{album_path_str}#t={range}");
+        let entry = manager
+            .get_song_by_path(&track_path)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(title, entry.song);
+        assert_eq!(artist, entry.artist);
+        assert_eq!("cue artist", entry.album_artist);
+        assert_eq!("cue album", entry.album);
+        assert_eq!(track_number, entry.track_number);
+        assert_eq!(duration, entry.duration_millis);
+        assert_eq!(Some("Post-Rock".to_owned()), entry.genre);
+        assert_eq!(track_path, entry.path);
+        track_paths.push(track_path);
+    }
+
+    // Removing the cue sheet should bring back the whole file
+    fs::remove_file(&cue_path).unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    let entry = manager
+        .get_song_by_path(&album_path)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(file_duration as i64, entry.duration_millis);
+    let deleted = manager
+        .get_deleted_songs()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|song| song.song_path)
+        .sorted()
+        .collect_vec();
+    assert_eq!(track_paths, deleted);
+}
+
+fn png_bytes(color: [u8; 3]) -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(400, 400, image::Rgb(color));
+    let mut bytes = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+    bytes
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_skips_unchanged_files() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir(&music_dir).unwrap();
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    set_title(&song_path, "aaaa");
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    // Change the tags without changing the size or modified date so the file looks untouched
+    let metadata = song_path.metadata().unwrap();
+    set_title(&song_path, "bbbb");
+    assert_eq!(metadata.len(), song_path.metadata().unwrap().len());
+    File::options()
+        .write(true)
+        .open(&song_path)
+        .unwrap()
+        .set_modified(metadata.modified().unwrap())
+        .unwrap();
+    // Wait for the scan timestamp to change so we can tell if the song was marked as missing
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let song = manager.get_song_by_path(&song_path).await.unwrap().unwrap();
+    assert_eq!("aaaa", song.song);
+    assert!(manager.get_deleted_songs().await.unwrap().is_empty());
+
+    let mut receiver = manager.full_rescan(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let song = manager.get_song_by_path(&song_path).await.unwrap().unwrap();
+    assert_eq!("bbbb", song.song);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_full_rescan_while_running() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir(&music_dir).unwrap();
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    set_title(&song_path, "aaaa");
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let metadata = song_path.metadata().unwrap();
+    set_title(&song_path, "bbbb");
+    File::options()
+        .write(true)
+        .open(&song_path)
+        .unwrap()
+        .set_modified(metadata.modified().unwrap())
+        .unwrap();
+
+    // Keep the incremental sync running so the full rescan has to wait for it
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    assert!(manager.pause_sync().await);
+    let mut rescan_receiver = manager.full_rescan(None, Box::pin(async {})).await.unwrap();
+    assert!(manager.resume_sync().await);
+    while receiver.next().await.is_some() {}
+    while rescan_receiver.next().await.is_some() {}
+
+    let song = manager.get_song_by_path(&song_path).await.unwrap().unwrap();
+    assert_eq!("bbbb", song.song);
+    let report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    assert!(report.full_rescan);
+}
+
+fn set_title(path: &Path, title: &str) {
+    let mut track = Probe::open(path).unwrap().read().unwrap();
+    let tag = track.primary_tag_mut().unwrap();
+    tag.set_title(title.to_owned());
+    tag.save_to_path(path, WriteOptions::new()).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_report() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir(&music_dir).unwrap();
+    let song1_path = music_dir.join("test.mp3");
+    let song2_path = music_dir.join("test2.mp3");
+    fs::copy("../test_assets/test.mp3", &song1_path).unwrap();
+    fs::copy("../test_assets/test2.mp3", &song2_path).unwrap();
+    fs::write(music_dir.join("broken.mp3"), "not an mp3 file").unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let first_report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    assert_eq!(
+        vec![
+            ("broken.mp3".to_owned(), SyncFileStatus::Failed),
+            ("test.mp3".to_owned(), SyncFileStatus::Added),
+            ("test2.mp3".to_owned(), SyncFileStatus::Added),
+        ],
+        report_files(&first_report)
+    );
+    assert!(first_report.files[0].error.is_some());
+    assert_eq!(
+        (2, 0, 0, 1),
+        (
+            first_report.added_count,
+            first_report.updated_count,
+            first_report.deleted_count,
+            first_report.failed_count
+        )
+    );
+
+    set_title(&song2_path, "new title");
+    fs::remove_file(&song1_path).unwrap();
+    // Deleted songs are detected based on the scan timestamp which has a granularity of seconds
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    assert_eq!(
+        vec![
+            ("broken.mp3".to_owned(), SyncFileStatus::Failed),
+            ("test.mp3".to_owned(), SyncFileStatus::Deleted),
+            ("test2.mp3".to_owned(), SyncFileStatus::Updated),
+        ],
+        report_files(&report)
+    );
+
+    let failed = manager
+        .get_sync_report(Some(report.sync_report_id), &[SyncFileStatus::Failed])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        vec![("broken.mp3".to_owned(), SyncFileStatus::Failed)],
+        report_files(&failed)
+    );
+    // Counts include every file even if they're filtered out
+    assert_eq!(1, failed.deleted_count);
+
+    let first = manager
+        .get_sync_report(Some(first_report.sync_report_id), &[])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(report_files(&first_report), report_files(&first));
+    assert!(
+        manager
+            .get_sync_report(Some(-1), &[])
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_report_analysis_failures() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir(&music_dir).unwrap();
+    fs::copy("../test_assets/test.mp3", music_dir.join("test.mp3")).unwrap();
+    // Silent files can be read but have no measurable loudness
+    fs::write(music_dir.join("silent.wav"), silent_wav(8000)).unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    let files = report_files(&report);
+    assert!(files.contains(&("silent.wav".to_owned(), SyncFileStatus::Added)));
+    assert!(files.contains(&("test.mp3".to_owned(), SyncFileStatus::Added)));
+    assert!(!files.contains(&("test.mp3".to_owned(), SyncFileStatus::Failed)));
+    assert!(report.files.iter().any(|f| {
+        f.status == SyncFileStatus::Failed
+            && f.path.ends_with("silent.wav")
+            && f.error.as_ref().is_some_and(|e| e.contains("loudness"))
+    }));
+}
+
+fn silent_wav(frames: u32) -> Vec<u8> {
+    let data_len = frames * 2;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono, 8kHz, 16 bit
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&8000u32.to_le_bytes());
+    bytes.extend_from_slice(&16000u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    bytes.resize(bytes.len() + data_len as usize, 0);
+    bytes
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_default_extensions() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir(&music_dir).unwrap();
+    fs::write(music_dir.join("silent.aiff"), silent_aiff(8000)).unwrap();
+    fs::write(music_dir.join("notes.txt"), "not audio").unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    let files = report_files(&report);
+    assert!(files.contains(&("silent.aiff".to_owned(), SyncFileStatus::Added)));
+    assert!(!files.iter().any(|(path, _)| path == "notes.txt"));
+}
+
+fn silent_aiff(frames: u32) -> Vec<u8> {
+    let data_len = frames * 2;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"FORM");
+    bytes.extend_from_slice(&(46 + data_len).to_be_bytes());
+    bytes.extend_from_slice(b"AIFFCOMM");
+    bytes.extend_from_slice(&18u32.to_be_bytes());
+    // Mono, 16 bit, 8kHz as an 80 bit float
+    bytes.extend_from_slice(&1u16.to_be_bytes());
+    bytes.extend_from_slice(&frames.to_be_bytes());
+    bytes.extend_from_slice(&16u16.to_be_bytes());
+    bytes.extend_from_slice(&[0x40, 0x0B, 0xFA, 0, 0, 0, 0, 0, 0, 0]);
+    bytes.extend_from_slice(b"SSND");
+    bytes.extend_from_slice(&(8 + data_len).to_be_bytes());
+    bytes.extend_from_slice(&0u32.to_be_bytes());
+    bytes.extend_from_slice(&0u32.to_be_bytes());
+    bytes.resize(bytes.len() + data_len as usize, 0);
+    bytes
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_pause_and_cancel() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir(&music_dir).unwrap();
+    let song1_path = music_dir.join("test.mp3");
+    let song2_path = music_dir.join("test2.mp3");
+    fs::copy("../test_assets/test.mp3", &song1_path).unwrap();
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    assert!(!manager.pause_sync().await);
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    assert_eq!(
+        SyncState::Finished,
+        *manager.subscribe_sync_state().await.borrow()
+    );
+
+    fs::remove_file(&song1_path).unwrap();
+    fs::copy("../test_assets/test2.mp3", &song2_path).unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // Pausing before the engine starts means nothing is processed until the sync is resumed
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    assert!(manager.pause_sync().await);
+    assert!(!manager.pause_sync().await);
+    assert_eq!(
+        SyncState::Paused,
+        *manager.subscribe_sync_state().await.borrow()
+    );
+    assert!(manager.cancel_sync().await);
+    assert!(!manager.resume_sync().await);
+    while receiver.next().await.is_some() {}
+
+    let report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    assert!(report.cancelled);
+    assert!(report_files(&report).is_empty());
+    assert_eq!(
+        SyncState::Cancelled,
+        *manager.subscribe_sync_state().await.borrow()
+    );
+    // Missing files aren't marked as deleted when the sync is cancelled
+    assert!(manager.get_deleted_songs().await.unwrap().is_empty());
+    assert!(
+        manager
+            .get_song_by_path(&song2_path)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    assert!(manager.pause_sync().await);
+    assert!(manager.resume_sync().await);
+    while receiver.next().await.is_some() {}
+
+    let report = manager.get_sync_report(None, &[]).await.unwrap().unwrap();
+    assert!(!report.cancelled);
+    assert_eq!(
+        vec![
+            ("test.mp3".to_owned(), SyncFileStatus::Deleted),
+            ("test2.mp3".to_owned(), SyncFileStatus::Added),
+        ],
+        report_files(&report)
+    );
+    assert!(!manager.cancel_sync().await);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_remove_folders() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let folder1 = tempdir.path().join("folder1");
+    let folder2 = tempdir.path().join("folder2");
+    create_dir(&folder1).unwrap();
+    create_dir(&folder2).unwrap();
+    let song1_path = folder1.join("test.mp3");
+    let song2_path = folder2.join("test2.mp3");
+    let song3_path = folder2.join("test3.mp3");
+    fs::copy("../test_assets/test.mp3", &song1_path).unwrap();
+    fs::copy("../test_assets/test2.mp3", &song2_path).unwrap();
+    fs::copy("../test_assets/test3.mp3", &song3_path).unwrap();
+    set_artist_album(&song1_path, "artist1", "album1");
+    set_artist_album(&song2_path, "artist2", "album2");
+    set_artist_album(&song3_path, "artist1", "album1");
+
+    let folder1 = folder1.to_str().unwrap();
+    let folder2 = folder2.to_str().unwrap();
+    // Folders are stored with a trailing separator
+    let folder1_expected = format!("{folder1}{MAIN_SEPARATOR}");
+    let folder2_expected = format!("{folder2}{MAIN_SEPARATOR}");
+    manager.add_folders(vec![folder1, folder2]).await.unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+
+    let removed = manager.remove_folders(vec![folder1], false).await.unwrap();
+    assert_eq!(vec![folder1_expected.clone()], removed.folders);
+    assert_eq!(
+        vec![song1_path.to_str().unwrap().to_owned()],
+        removed.songs.into_iter().map(|s| s.song_path).collect_vec()
+    );
+    // Marked songs are still in the library so nothing else is removed
+    assert!(removed.albums.is_empty());
+    assert!(removed.artists.is_empty());
+    assert_eq!(
+        vec![folder2_expected.clone()],
+        manager.get_all_folders().await.unwrap()
+    );
+    assert_eq!(1, manager.get_deleted_songs().await.unwrap().len());
+
+    let removed = manager.remove_folders(vec![folder1], false).await.unwrap();
+    assert!(removed.folders.is_empty());
+    assert!(removed.songs.is_empty());
+
+    // Adding the folder back restores the marked songs
+    manager.add_folder(folder1).await.unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    assert!(manager.get_deleted_songs().await.unwrap().is_empty());
+
+    let removed = manager
+        .remove_folders(vec![folder1, folder2, "/not/a/folder"], true)
+        .await
+        .unwrap();
+    assert_eq!(vec![folder1_expected, folder2_expected], removed.folders);
+    assert_eq!(
+        vec![&song1_path, &song2_path, &song3_path]
+            .into_iter()
+            .map(|p| p.to_str().unwrap().to_owned())
+            .collect_vec(),
+        removed
+            .songs
+            .into_iter()
+            .map(|s| s.song_path)
+            .sorted()
+            .collect_vec()
+    );
+    assert_eq!(
+        vec!["album1", "album2"],
+        removed.albums.into_iter().sorted().collect_vec()
+    );
+    assert_eq!(
+        vec!["artist1", "artist2"],
+        removed.artists.into_iter().sorted().collect_vec()
+    );
+    assert!(
+        manager
+            .get_song_by_path(&song2_path)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(manager.get_deleted_songs().await.unwrap().is_empty());
+    assert!(manager.get_all_folders().await.unwrap().is_empty());
+}
+
+fn set_artist_album(path: &Path, artist: &str, album: &str) {
+    let mut track = Probe::open(path).unwrap().read().unwrap();
+    let tag = track.primary_tag_mut().unwrap();
+    tag.set_artist(artist.to_owned());
+    tag.set_album(album.to_owned());
+    tag.remove_key(ItemKey::AlbumArtist);
+    tag.save_to_path(path, WriteOptions::new()).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_folder_rules() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    let podcast_dir = music_dir.join("inner").join("Podcasts");
+    create_dir_all(&podcast_dir).unwrap();
+    let song_path = music_dir.join("test.mp3");
+    let podcast_path = podcast_dir.join("test2.mp3");
+    let sample_path = music_dir.join("inner").join("test3.sample.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    fs::copy("../test_assets/test2.mp3", &podcast_path).unwrap();
+    fs::copy("../test_assets/test3.mp3", &sample_path).unwrap();
+
+    let folder = music_dir.to_str().unwrap();
+    manager.add_folder(folder).await.unwrap();
+    assert_eq!(
+        FolderRules::default(),
+        manager.get_folder_rules(folder).await.unwrap()
+    );
+    let rules = FolderRules {
+        include: vec![],
+        exclude: vec!["**/Podcasts/**".to_owned(), "*.sample.mp3".to_owned()],
+    };
+    manager
+        .set_folder_rules(folder, rules.clone())
+        .await
+        .unwrap();
+    assert_eq!(rules, manager.get_folder_rules(folder).await.unwrap());
+
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    assert!(
+        manager
+            .get_song_by_path(&song_path)
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        manager
+            .get_song_by_path(&podcast_path)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        manager
+            .get_song_by_path(&sample_path)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Syncing an excluded path directly shouldn't add it either
+    let mut receiver = manager
+        .sync(
+            Some(vec![podcast_path.to_string_lossy().to_string()]),
+            Box::pin(async {}),
+        )
+        .await
+        .unwrap();
+    while receiver.next().await.is_some() {}
+    assert!(
+        manager
+            .get_song_by_path(&podcast_path)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    assert!(matches!(
+        manager
+            .set_folder_rules(
+                folder,
+                FolderRules {
+                    include: vec!["a{".to_owned()],
+                    exclude: vec![],
+                }
+            )
+            .await,
+        Err(ManagerError::InvalidPattern(_))
+    ));
+    assert!(matches!(
+        manager
+            .set_folder_rules("/not/a/folder", FolderRules::default())
+            .await,
+        Err(ManagerError::FolderNotFound(_))
+    ));
+
+    // Files with extensions that are no longer configured are treated as missing
+    manager
+        .set_file_extensions(vec![".FLAC", "m4a", ""])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["flac".to_owned(), "m4a".to_owned()],
+        manager.get_file_extensions().await.unwrap()
+    );
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    assert_eq!(1, manager.get_deleted_songs().await.unwrap().len());
+
+    manager.set_file_extensions(vec![]).await.unwrap();
+    assert!(
+        manager
+            .get_file_extensions()
+            .await
+            .unwrap()
+            .contains(&"mp3".to_owned())
+    );
+
+    // Formats that can't be played would only show up as failed files
+    assert!(matches!(
+        manager.set_file_extensions(vec!["flac", "WMA"]).await,
+        Err(ManagerError::UnsupportedExtension(ext)) if ext == "wma"
+    ));
+    assert!(
+        manager
+            .get_file_extensions()
+            .await
+            .unwrap()
+            .contains(&"mp3".to_owned())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_artist_credits() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let song_path = music_dir.join("test.mp3");
+    let song_path2 = music_dir.join("test2.mp3");
+    fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+    fs::copy("../test_assets/test2.mp3", &song_path2).unwrap();
+    set_artist_album(&song_path, "A / B", "album");
+    set_artist_album(&song_path2, "B", "album 2");
+    set_title(&song_path, "Song (feat. C)");
+    {
+        let mut track = Probe::open(&song_path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        tag.insert_text(ItemKey::Composer, "D; E".to_owned());
+        tag.save_to_path(&song_path, WriteOptions::new()).unwrap();
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    let entry = manager.get_song_by_path(&song_path).await.unwrap().unwrap();
+    assert_eq!("A", entry.artist);
+    assert_eq!("A", entry.album_artist);
+
+    let credits = manager.get_song_artists(entry.song_id).await.unwrap();
+    assert_eq!(
+        vec![
+            ("A", ArtistRole::Primary),
+            ("B", ArtistRole::Primary),
+            ("C", ArtistRole::Featured),
+            ("A", ArtistRole::AlbumArtist),
+            ("B", ArtistRole::AlbumArtist),
+            ("D", ArtistRole::Composer),
+            ("E", ArtistRole::Composer),
+        ],
+        credits
+            .iter()
+            .map(|c| (c.artist_name.as_str(), c.role))
+            .collect_vec()
+    );
+
+    // Songs are found from any of their credited artists, not just the first one
+    let artist_id = |name: &str| {
+        credits
+            .iter()
+            .find(|c| c.artist_name == name)
+            .unwrap()
+            .artist_id
+    };
+    let songs = manager
+        .lookup(vec![artist_id("B")], EntryType::Artist)
+        .await
+        .unwrap();
+    assert_eq!(2, songs.len());
+    let songs = manager
+        .lookup(vec![artist_id("C")], EntryType::Artist)
+        .await
+        .unwrap();
+    assert_eq!(1, songs.len());
+    assert_eq!(entry.song_id, songs[0].song_id);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_sync_album_identity() {
+    let tempdir = TempDir::new().unwrap();
+    let (_, mut manager) = setup().await;
+    let music_dir = tempdir.path().join("configdir");
+    create_dir_all(music_dir.clone()).unwrap();
+
+    let release_id = "0b4a5fb5-4f38-4ed6-b7a1-0b27c3f1a5a4";
+    let songs = [
+        ("artist", "album (Disc 1)", "2000", false, None),
+        ("artist", "album (Disc 2)", "2000", false, None),
+        ("artist", "album", "2010", false, None),
+        ("artist1", "hits", "1971", true, None),
+        ("artist2", "hits", "1985", true, None),
+        ("artist3", "live", "1990", false, Some(release_id)),
+        ("artist3", "live", "1991", false, Some(release_id)),
+        ("artist3", "live", "", false, None),
+    ];
+    let mut paths = Vec::new();
+    for (i, (artist, album, year, compilation, release_id)) in songs.into_iter().enumerate() {
+        let song_path = music_dir.join(format!("test{i}.mp3"));
+        fs::copy("../test_assets/test.mp3", &song_path).unwrap();
+        set_artist_album(&song_path, artist, album);
+        let mut track = Probe::open(&song_path).unwrap().read().unwrap();
+        let tag = track.primary_tag_mut().unwrap();
+        tag.remove_key(ItemKey::DiscNumber);
+        tag.remove_key(ItemKey::Year);
+        tag.remove_key(ItemKey::RecordingDate);
+        if !year.is_empty() {
+            tag.insert_text(ItemKey::RecordingDate, year.to_owned());
+        }
+        if compilation {
+            tag.insert_text(ItemKey::FlagCompilation, "1".to_owned());
+        }
+        if let Some(release_id) = release_id {
+            tag.insert_text(ItemKey::MusicBrainzReleaseId, release_id.to_owned());
+        }
+        tag.save_to_path(&song_path, WriteOptions::new()).unwrap();
+        paths.push(song_path);
+    }
+
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while (receiver.next().await).is_some() {}
+
+    // Discs of the same release are grouped together and releases from different years are not
+    let entry = manager.get_song_by_path(&paths[1]).await.unwrap().unwrap();
+    assert_eq!("album", entry.album);
+    let albums = manager
+        .albums_by_album_artists(vec![album_artist_id(&manager, entry.song_id).await])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![("album", Some(2000)), ("album", Some(2010))],
+        albums
+            .iter()
+            .map(|a| (a.album.as_str(), a.year))
+            .collect_vec()
+    );
+    let songs = manager
+        .lookup(vec![albums[0].album_id], EntryType::Album)
+        .await
+        .unwrap();
+    assert_eq!(2, songs.len());
+
+    // Compilations stay together even though every track has a different artist
+    let entry = manager.get_song_by_path(&paths[3]).await.unwrap().unwrap();
+    assert_eq!("Various Artists", entry.album_artist);
+    let albums = manager
+        .albums_by_album_artists(vec![album_artist_id(&manager, entry.song_id).await])
+        .await
+        .unwrap();
+    assert_eq!(1, albums.len());
+    assert!(albums[0].is_compilation);
+    let songs = manager
+        .lookup(vec![albums[0].album_id], EntryType::Album)
+        .await
+        .unwrap();
+    assert_eq!(2, songs.len());
+
+    // A matching release ID is decisive and tracks without one join the only release they match
+    let entry = manager.get_song_by_path(&paths[5]).await.unwrap().unwrap();
+    let albums = manager
+        .albums_by_album_artists(vec![album_artist_id(&manager, entry.song_id).await])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![("live", Some(1990))],
+        albums
+            .iter()
+            .map(|a| (a.album.as_str(), a.year))
+            .collect_vec()
+    );
+    let songs = manager
+        .lookup(vec![albums[0].album_id], EntryType::Album)
+        .await
+        .unwrap();
+    assert_eq!(3, songs.len());
+}
+
+async fn album_artist_id(manager: &Manager, song_id: i64) -> i64 {
+    manager
+        .get_song_artists(song_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|a| a.role == ArtistRole::AlbumArtist)
+        .unwrap()
+        .artist_id
+}
+
+fn report_files(report: &SyncReport) -> Vec<(String, SyncFileStatus)> {
+    report
+        .files
+        .iter()
+        .map(|f| {
+            (
+                Path::new(&f.path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                f.status,
+            )
+        })
+        .collect()
+}
+
 async fn setup() -> (Database, Manager) {
     let db = Database::connect_in_memory().await.unwrap();
     db.sync_database().await.unwrap();
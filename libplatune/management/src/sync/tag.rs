@@ -1,19 +1,171 @@
+use std::hash::{Hash, Hasher};
+
 use itertools::Itertools;
 use lofty::file::{AudioFile, TaggedFile, TaggedFileExt};
-use lofty::tag::{Accessor, ItemKey};
+use lofty::tag::{Accessor, ItemKey, Tag as LoftyTag};
+
+use super::album_identity::{compilation_artists, split_disc};
+use super::artist_credit::{ArtistCredits, ArtistRole, split_artists};
+use super::cue::AudioRange;
+use super::gain_value::parse_gain_value;
 
-#[derive(Debug, Hash, Default)]
+#[derive(Debug, Hash, Default, Clone)]
 pub(crate) struct Tag {
     pub(crate) title: String,
-    pub(crate) album_artists: String,
+    pub(crate) album_artists: Vec<String>,
     pub(crate) album: String,
-    pub(crate) artists: String,
+    /// Album is a compilation grouped under "Various Artists"
+    pub(crate) compilation: bool,
+    pub(crate) artists: Vec<String>,
+    /// Artists credited with "feat." in the artist tag or the title
+    pub(crate) featured_artists: Vec<String>,
     pub(crate) track_number: u32,
     pub(crate) disc_number: u32,
     pub(crate) year: u32,
+    pub(crate) month: u32,
+    pub(crate) day: u32,
+    /// Release date in ISO 8601 format, only as precise as the tag itself
+    pub(crate) release_date: Option<String>,
+    pub(crate) original_year: Option<u32>,
+    /// Every genre in the tag, the first one is the main genre
+    pub(crate) genres: Vec<String>,
+    pub(crate) composer: Option<String>,
+    pub(crate) comment: Option<String>,
+    pub(crate) bpm: Option<u32>,
+    pub(crate) musicbrainz: MusicBrainzIds,
     pub(crate) duration: i64,
     pub(crate) sample_rate: u32,
     pub(crate) bitrate: u32,
+    pub(crate) replay_gain: ReplayGain,
+    /// Name of the cached album art image
+    pub(crate) album_art: Option<String>,
+    pub(crate) lyrics: Option<String>,
+    /// Timed lyrics in the LRC format
+    pub(crate) synced_lyrics: Option<String>,
+    /// Part of the file that belongs to this song if the file is split up by a cue sheet
+    pub(crate) range: Option<AudioRange>,
+}
+
+impl Tag {
+    /// Main artist of the song
+    pub(crate) fn artist(&self) -> &str {
+        self.artists.first().map(String::as_str).unwrap_or_default()
+    }
+
+    /// Artist the album is grouped under
+    pub(crate) fn album_artist(&self) -> &str {
+        self.album_artists
+            .first()
+            .map(String::as_str)
+            .unwrap_or_else(|| self.artist())
+    }
+
+    /// Every artist credited on the song, grouped by their role
+    pub(crate) fn credits(&self) -> Vec<(ArtistRole, Vec<String>)> {
+        let composers = self
+            .composer
+            .as_deref()
+            .map(|composer| split_artists([composer]))
+            .unwrap_or_default();
+        vec![
+            (ArtistRole::Primary, self.artists.clone()),
+            (ArtistRole::Featured, self.featured_artists.clone()),
+            (ArtistRole::AlbumArtist, self.album_artists.clone()),
+            (ArtistRole::Composer, composers),
+        ]
+    }
+}
+
+#[derive(Debug, Hash, Default, Clone, PartialEq, Eq)]
+pub(crate) struct MusicBrainzIds {
+    pub(crate) recording_id: Option<String>,
+    pub(crate) release_id: Option<String>,
+    pub(crate) artist_id: Option<String>,
+    pub(crate) release_group_id: Option<String>,
+}
+
+impl MusicBrainzIds {
+    fn from_tag(tag: &LoftyTag) -> Self {
+        Self {
+            recording_id: get_non_empty(tag, ItemKey::MusicBrainzRecordingId),
+            release_id: get_non_empty(tag, ItemKey::MusicBrainzReleaseId),
+            artist_id: get_non_empty(tag, ItemKey::MusicBrainzArtistId),
+            release_group_id: get_non_empty(tag, ItemKey::MusicBrainzReleaseGroupId),
+        }
+    }
+}
+
+fn get_non_empty(tag: &LoftyTag, key: ItemKey) -> Option<String> {
+    tag.get_string(key)
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+/// Splits genre tags into separate genres.
+/// Some formats only support a single value so multiple genres get joined with a semicolon.
+pub(crate) fn split_genres<'a>(values: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    values
+        .into_iter()
+        .flat_map(|value| value.split(';'))
+        .map(str::trim)
+        .filter(|genre| !genre.is_empty())
+        .map(ToOwned::to_owned)
+        .unique_by(|genre| genre.to_lowercase())
+        .collect()
+}
+
+fn parse_bpm(tag: &LoftyTag) -> Option<u32> {
+    // Some taggers write fractional values like "120.50"
+    let value: f64 = tag.get_string(ItemKey::Bpm)?.trim().parse().ok()?;
+    (value.is_finite() && value > 0.0).then(|| value.round() as u32)
+}
+
+fn parse_compilation(tag: &LoftyTag) -> bool {
+    tag.get_string(ItemKey::FlagCompilation)
+        .is_some_and(|flag| matches!(flag.trim(), "1" | "true"))
+}
+
+fn parse_original_year(tag: &LoftyTag) -> Option<u32> {
+    // Original dates can be a full date or just the year, so only the year is kept
+    let value = tag.get_string(ItemKey::OriginalReleaseDate)?.trim();
+    value.get(..4)?.parse().ok().filter(|year| *year > 0)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct ReplayGain {
+    pub(crate) track_gain: Option<f64>,
+    pub(crate) track_peak: Option<f64>,
+    pub(crate) album_gain: Option<f64>,
+    pub(crate) album_peak: Option<f64>,
+}
+
+impl ReplayGain {
+    fn from_tag(tag: &LoftyTag) -> Self {
+        Self {
+            track_gain: parse_gain_tag(tag, ItemKey::ReplayGainTrackGain),
+            track_peak: parse_gain_tag(tag, ItemKey::ReplayGainTrackPeak),
+            album_gain: parse_gain_tag(tag, ItemKey::ReplayGainAlbumGain),
+            album_peak: parse_gain_tag(tag, ItemKey::ReplayGainAlbumPeak),
+        }
+    }
+}
+
+impl Hash for ReplayGain {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for value in [
+            self.track_gain,
+            self.track_peak,
+            self.album_gain,
+            self.album_peak,
+        ] {
+            value.map(f64::to_bits).hash(state);
+        }
+    }
+}
+
+fn parse_gain_tag(tag: &LoftyTag, key: ItemKey) -> Option<f64> {
+    tag.get_string(key).and_then(parse_gain_value)
 }
 
 impl From<TaggedFile> for Tag {
@@ -28,23 +180,55 @@ impl From<TaggedFile> for Tag {
         let props = tagged_file.properties();
         match tag {
             Some(tag) => {
-                // Ensure we remove duplicate artist/album artist info
-                let artists = tag.get_strings(ItemKey::TrackArtist).unique().join("/");
-                let mut album_artists = tag.get_strings(ItemKey::AlbumArtist).unique().join("/");
+                let title = tag.title().unwrap_or_default().into_owned();
+                let tagged_album_artists = split_artists(tag.get_strings(ItemKey::AlbumArtist));
+                // Full names of every artist on the track, used to keep names like
+                // "Earth, Wind & Fire" together when they're featured
+                let known_artists = split_artists(tag.get_strings(ItemKey::TrackArtists))
+                    .into_iter()
+                    .chain(tagged_album_artists.iter().cloned())
+                    .collect_vec();
+                let credits =
+                    ArtistCredits::parse(tag.get_strings(ItemKey::TrackArtist), &known_artists)
+                        .with_title(&title, &known_artists);
+                let (mut album_artists, compilation) =
+                    compilation_artists(tagged_album_artists, parse_compilation(tag));
                 if album_artists.is_empty() {
-                    album_artists.clone_from(&artists);
+                    album_artists.clone_from(&credits.primary);
                 }
+                let (album, album_disc) = split_disc(&tag.album().unwrap_or_default());
+                let date = tag.date();
+                let release_date = date.map(|d| match (d.month, d.day) {
+                    (Some(month), Some(day)) => format!("{:04}-{month:02}-{day:02}", d.year),
+                    (Some(month), None) => format!("{:04}-{month:02}", d.year),
+                    _ => format!("{:04}", d.year),
+                });
                 Tag {
-                    title: tag.title().unwrap_or_default().into_owned(),
-                    artists,
-                    album: tag.album().unwrap_or_default().into_owned(),
+                    title,
+                    artists: credits.primary,
+                    featured_artists: credits.featured,
+                    album,
+                    compilation,
                     track_number: tag.track().unwrap_or(1),
-                    disc_number: tag.disk().unwrap_or(1),
-                    // TODO: Support full date
-                    year: tag.date().map(|d| d.year as u32).unwrap_or(0),
+                    disc_number: tag.disk().or(album_disc).unwrap_or(1),
+                    year: date.map(|d| d.year as u32).unwrap_or(0),
+                    month: date.and_then(|d| d.month).unwrap_or(0) as u32,
+                    day: date.and_then(|d| d.day).unwrap_or(0) as u32,
+                    release_date,
+                    original_year: parse_original_year(tag),
+                    genres: split_genres(tag.get_strings(ItemKey::Genre)),
+                    composer: get_non_empty(tag, ItemKey::Composer),
+                    comment: get_non_empty(tag, ItemKey::Comment),
+                    bpm: parse_bpm(tag),
+                    musicbrainz: MusicBrainzIds::from_tag(tag),
                     duration: props.duration().as_millis() as i64,
                     sample_rate: props.sample_rate().unwrap_or(0),
                     bitrate: props.audio_bitrate().unwrap_or(0),
+                    replay_gain: ReplayGain::from_tag(tag),
+                    album_art: None,
+                    lyrics: get_non_empty(tag, ItemKey::Lyrics),
+                    synced_lyrics: None,
+                    range: None,
                     album_artists,
                 }
             }
@@ -0,0 +1,109 @@
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use futures::StreamExt;
+use itertools::Itertools;
+use lofty::config::WriteOptions;
+use lofty::file::TaggedFileExt;
+use lofty::probe::Probe;
+use lofty::tag::{Accessor, ItemKey, TagExt};
+
+use crate::config::MemoryConfig;
+use crate::database::Database;
+use crate::manager::Manager;
+
+/// Tags written to a copy of the test file. Empty values are removed from the tag.
+pub(crate) struct TestSong {
+    pub(crate) title: &'static str,
+    pub(crate) artist: &'static str,
+    pub(crate) album_artist: &'static str,
+    pub(crate) album: &'static str,
+    pub(crate) genre: &'static str,
+    pub(crate) year: u32,
+}
+
+/// Folder in the temp dir that test libraries are synced from
+pub(crate) fn music_dir(tempdir: &Path) -> PathBuf {
+    tempdir.join("music")
+}
+
+/// Copies each test asset to its path relative to the music folder.
+/// Files are given as `(path, asset)` pairs and the copied paths are returned in the same order.
+pub(crate) fn copy_files(tempdir: &Path, files: &[(&str, &str)]) -> Vec<PathBuf> {
+    let music_dir = music_dir(tempdir);
+    files
+        .iter()
+        .map(|(dest, source)| {
+            let path = music_dir.join(dest);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            fs::copy(Path::new("../test_assets").join(source), &path).unwrap();
+            path
+        })
+        .collect()
+}
+
+/// Syncs the files in the music folder into a new in-memory library
+pub(crate) async fn sync_library(tempdir: &Path) -> Manager {
+    let db = Database::connect_in_memory().await.unwrap();
+    db.sync_database().await.unwrap();
+    let config = Arc::new(MemoryConfig::new_boxed());
+    let mut manager = Manager::new(&db, config);
+
+    let music_dir = music_dir(tempdir);
+    create_dir_all(&music_dir).unwrap();
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    sync(&mut manager).await;
+
+    manager
+}
+
+pub(crate) async fn sync(manager: &mut Manager) {
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+}
+
+/// Copies the test assets into the temp dir and syncs them into a new in-memory library.
+/// Returns the manager along with the path of each file.
+pub(crate) async fn setup_files(tempdir: &Path, files: &[(&str, &str)]) -> (Manager, Vec<PathBuf>) {
+    let paths = copy_files(tempdir, files);
+    (sync_library(tempdir).await, paths)
+}
+
+/// Writes each song into the temp dir and syncs them into a new in-memory library.
+/// Returns the manager along with the path of each song.
+pub(crate) async fn setup_library(tempdir: &Path, songs: &[TestSong]) -> (Manager, Vec<PathBuf>) {
+    let names = (0..songs.len())
+        .map(|i| format!("test{i}.mp3"))
+        .collect_vec();
+    let files = names
+        .iter()
+        .map(|name| (name.as_str(), "test.mp3"))
+        .collect_vec();
+    let song_paths = copy_files(tempdir, &files);
+    for (song, song_path) in songs.iter().zip(&song_paths) {
+        let mut t = Probe::open(song_path).unwrap().read().unwrap();
+        let tag = t.primary_tag_mut().unwrap();
+        tag.set_title(song.title.to_owned());
+        tag.set_artist(song.artist.to_owned());
+        tag.insert_text(ItemKey::AlbumArtist, song.album_artist.to_owned());
+        tag.set_album(song.album.to_owned());
+        if song.genre.is_empty() {
+            tag.remove_genre();
+        } else {
+            tag.set_genre(song.genre.to_owned());
+        }
+        tag.remove_key(ItemKey::Year);
+        if song.year == 0 {
+            tag.remove_key(ItemKey::RecordingDate);
+        } else {
+            tag.insert_text(ItemKey::RecordingDate, song.year.to_string());
+        }
+        tag.save_to_path(song_path, WriteOptions::new()).unwrap();
+    }
+
+    (sync_library(tempdir).await, song_paths)
+}
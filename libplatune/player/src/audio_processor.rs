@@ -8,10 +8,18 @@ use flume::TryRecvError;
 use tap::TapFallible;
 use tracing::{error, info, warn};
 
+use crate::crossfade::{FadingSource, equal_power_gain};
 use crate::dto::decoder_command::DecoderCommand;
 use crate::dto::decoder_response::DecoderResponse;
 use crate::dto::processor_error::ProcessorError;
-use crate::platune_player::{Metadata, PlayerEvent, SeekMode};
+use crate::dto::track_range::TrackRange;
+use crate::equalizer::Equalizer;
+use crate::gain_value::parse_gain_value;
+#[cfg(test)]
+use crate::output_tap::{self, OutputEvent};
+use crate::platune_player::{
+    EqualizerPreset, LyricLine, Metadata, PlayerEvent, ReplayGain, ReplayGainMode, SeekMode,
+};
 use crate::two_way_channel::TwoWayReceiver;
 
 pub(crate) struct AudioProcessor<'a, H: Host> {
@@ -22,6 +30,17 @@ pub(crate) struct AudioProcessor<'a, H: Host> {
     event_tx: &'a tokio::sync::broadcast::Sender<PlayerEvent>,
     input_metadata: Option<Metadata>,
     metadata_init: bool,
+    volume: f32,
+    crossfade: Duration,
+    fade_in: bool,
+    fade_gain: f32,
+    replay_gain: Option<ReplayGain>,
+    replay_gain_mode: ReplayGainMode,
+    equalizer: Equalizer,
+    range: Option<TrackRange>,
+    lyrics: Vec<LyricLine>,
+    lyric_index: Option<usize>,
+    fading: Option<FadingSource>,
 }
 
 pub(crate) enum InputResult {
@@ -51,33 +70,119 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
         cmd_rx: &'a mut TwoWayReceiver<DecoderCommand, DecoderResponse>,
         event_tx: &'a tokio::sync::broadcast::Sender<PlayerEvent>,
         input_metadata: Metadata,
+        wait_for_init: bool,
     ) -> Result<Self, ProcessorError> {
-        match cmd_rx.recv() {
-            Ok(DecoderCommand::WaitForInitialization) => {
-                info!("Notifying decoder started");
-            }
-            Ok(cmd) => {
-                error!("Got unexpected command {cmd:?}");
-            }
-            Err(e) => {
-                error!("Error receiving initialization message {e:?}");
+        if wait_for_init {
+            match cmd_rx.recv() {
+                Ok(DecoderCommand::WaitForInitialization) => {
+                    info!("Notifying decoder started");
+                }
+                Ok(cmd) => {
+                    error!("Got unexpected command {cmd:?}");
+                }
+                Err(e) => {
+                    error!("Error receiving initialization message {e:?}");
+                }
             }
-        }
 
-        cmd_rx
-            .respond(DecoderResponse::InitializationSucceeded)
-            .map_err(|e| ProcessorError::CommunicationError(format!("{e:?}")))
-            .tap_err(|e| error!("Error sending decoder initialization succeeded: {e:?}"))?;
+            cmd_rx
+                .respond(DecoderResponse::InitializationSucceeded)
+                .map_err(|e| ProcessorError::CommunicationError(format!("{e:?}")))
+                .tap_err(|e| error!("Error sending decoder initialization succeeded: {e:?}"))?;
+        }
 
-        Ok(Self {
+        let mut processor = Self {
             decoder,
             manager,
             cmd_rx,
             event_tx,
-            input_metadata: Some(input_metadata),
+            input_metadata: None,
             metadata_init: false,
             last_sent_position: Duration::ZERO,
-        })
+            volume: 1.0,
+            crossfade: Duration::ZERO,
+            fade_in: false,
+            fade_gain: 1.0,
+            replay_gain: None,
+            replay_gain_mode: ReplayGainMode::Off,
+            equalizer: Equalizer::new(None),
+            range: None,
+            lyrics: Vec::new(),
+            lyric_index: None,
+            fading: None,
+        };
+        processor.init_source(input_metadata);
+        Ok(processor)
+    }
+
+    /// Switches to the next decoder while keeping the output and filter state intact so the
+    /// new source continues directly from where the previous one ended.
+    /// Returns the previous decoder.
+    pub(crate) fn load(&mut self, decoder: Decoder<f32>, input_metadata: Metadata) -> Decoder<f32> {
+        let previous = std::mem::replace(&mut self.decoder, decoder);
+        self.init_source(input_metadata);
+        previous
+    }
+
+    /// Whether the current track is close enough to the end that the next one should start
+    /// fading in over it
+    pub(crate) fn crossfade_ready(&self) -> bool {
+        if self.crossfade.is_zero() || self.fading.is_some() {
+            return false;
+        }
+        self.remaining()
+            .is_some_and(|remaining| !remaining.is_zero() && remaining <= self.crossfade)
+    }
+
+    /// Starts the next track while the rest of the current one fades out underneath it
+    pub(crate) fn crossfade_into(&mut self, decoder: Decoder<f32>, input_metadata: Metadata) {
+        let remaining = self.remaining().unwrap_or_default();
+        // The fade is applied while mixing, so only keep the track's own volume
+        let volume = self.volume * self.normalization_gain();
+        let mut previous = self.load(decoder, input_metadata);
+        previous.set_volume(volume);
+        self.fading = Some(FadingSource::new(previous, remaining, self.crossfade));
+    }
+
+    fn remaining(&self) -> Option<Duration> {
+        // Live streams don't have a known duration so there's nothing to fade out of
+        self.duration()
+            .map(|duration| duration.saturating_sub(self.current_position().position))
+    }
+
+    fn init_source(&mut self, input_metadata: Metadata) {
+        self.replay_gain = input_metadata.replay_gain;
+        let mut lyrics = input_metadata.lyrics.clone().unwrap_or_default();
+        lyrics.sort_by_key(|line| line.start);
+        self.lyrics = lyrics;
+        self.lyric_index = None;
+        self.input_metadata = Some(input_metadata);
+        self.metadata_init = false;
+        self.last_sent_position = Duration::ZERO;
+        self.fade_in = false;
+        self.fade_gain = 1.0;
+        self.range = None;
+        if self.replay_gain.is_none() {
+            // Fall back to the gain tags in the file if they weren't supplied with the track
+            self.replay_gain = self
+                .decoder
+                .metadata()
+                .skip_to_latest()
+                .cloned()
+                .and_then(|latest| self.extract_metadata(&latest).replay_gain);
+        }
+        #[cfg(test)]
+        output_tap::record(OutputEvent::SourceStarted);
+    }
+
+    /// Gives access to the output and command channel while the processor holds onto them
+    pub(crate) fn output_parts(
+        &mut self,
+    ) -> (
+        &mut AudioManager<f32, H>,
+        &mut TwoWayReceiver<DecoderCommand, DecoderResponse>,
+    ) {
+        (&mut *self.manager, &mut *self.cmd_rx)
     }
 
     fn process_input(&mut self) -> Result<InputResult, ProcessorError> {
@@ -103,12 +208,16 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
                         return Ok(InputResult::Stop);
                     }
                     DecoderCommand::Seek(time, mode) => {
-                        let current_time = self.decoder.current_position();
+                        let current_time = self.current_position();
                         let seek_time = match mode {
                             SeekMode::Absolute => time,
                             SeekMode::Forward => current_time.position + time,
-                            SeekMode::Backward => current_time.position - time,
+                            SeekMode::Backward => current_time.position.saturating_sub(time),
                         };
+                        let seek_time = self.range_start() + seek_time;
+                        // Don't fade in again if we seek back to the start of the track
+                        self.fade_in = false;
+                        self.fading = None;
                         let seek_response = match self.decoder.seek(seek_time) {
                             Ok(seeked_to) => Ok(seeked_to.actual_ts),
                             Err(e) => Err(e.to_string()),
@@ -120,6 +229,8 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
                             .tap_err(|e| error!("Unable to send seek result: {e:?}"))?;
                     }
                     DecoderCommand::Pause => {
+                        // Cut off the previous track so it doesn't keep playing while paused
+                        self.fading = None;
                         self.decoder.pause();
                         self.manager.pause();
 
@@ -129,16 +240,43 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
                             .tap_err(|e| error!("Error sending stopped response: {e:?}"))?;
                     }
                     DecoderCommand::SetVolume(volume) => {
+                        self.volume = volume;
                         self.manager.set_volume(volume);
-                        self.decoder.set_volume(volume);
+                        self.update_volume();
 
                         self.cmd_rx
                             .respond(DecoderResponse::Received)
                             .map_err(|e| ProcessorError::CommunicationError(format!("{e:?}")))
                             .tap_err(|e| error!("Error sending set volume response: {e:?}"))?;
                     }
+                    DecoderCommand::SetCrossfade(crossfade) => {
+                        self.crossfade = crossfade;
+
+                        self.cmd_rx
+                            .respond(DecoderResponse::Received)
+                            .map_err(|e| ProcessorError::CommunicationError(format!("{e:?}")))
+                            .tap_err(|e| error!("Error sending set crossfade response: {e:?}"))?;
+                    }
+                    DecoderCommand::SetReplayGainMode(mode) => {
+                        self.set_replay_gain_mode(mode);
+
+                        self.cmd_rx
+                            .respond(DecoderResponse::Received)
+                            .map_err(|e| ProcessorError::CommunicationError(format!("{e:?}")))
+                            .tap_err(|e| {
+                                error!("Error sending set replay gain mode response: {e:?}")
+                            })?;
+                    }
+                    DecoderCommand::SetEqualizer(preset) => {
+                        self.equalizer.set_preset(preset);
+
+                        self.cmd_rx
+                            .respond(DecoderResponse::Received)
+                            .map_err(|e| ProcessorError::CommunicationError(format!("{e:?}")))
+                            .tap_err(|e| error!("Error sending set equalizer response: {e:?}"))?;
+                    }
                     DecoderCommand::GetCurrentPosition => {
-                        let time = self.decoder.current_position();
+                        let time = self.current_position();
 
                         self.cmd_rx
                             .respond(DecoderResponse::CurrentPositionResponse(time))
@@ -161,7 +299,7 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
                 info!("Completed decoder command");
             }
             Err(TryRecvError::Empty) => {
-                let position = self.decoder.current_position();
+                let position = self.current_position();
                 // if position.position < last_send_time, we just seeked backwards
                 if position.position < self.last_sent_position
                     || position.position - self.last_sent_position >= Duration::from_secs(10)
@@ -171,6 +309,7 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
                         .unwrap_or_default();
                     self.last_sent_position = position.position;
                 }
+                self.send_lyric_line(position.position);
             }
             Err(TryRecvError::Disconnected) => {
                 info!("Decoder command sender has disconnected");
@@ -181,8 +320,94 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
         Ok(InputResult::Continue)
     }
 
+    fn send_lyric_line(&mut self, position: Duration) {
+        if self.lyrics.is_empty() {
+            return;
+        }
+        // The current line is the last one that started before the current position
+        let index = self
+            .lyrics
+            .partition_point(|line| line.start <= position)
+            .checked_sub(1);
+        if index != self.lyric_index {
+            self.lyric_index = index;
+            if let Some(index) = index {
+                self.event_tx
+                    .send(PlayerEvent::LyricLine(self.lyrics[index].clone()))
+                    .unwrap_or_default();
+            }
+        }
+    }
+
+    pub(crate) fn start_paused(&mut self, position: Duration) {
+        self.decoder.pause();
+        self.manager.pause();
+        if !position.is_zero() {
+            let _ = self
+                .decoder
+                .seek(self.range_start() + position)
+                .inspect_err(|e| warn!("Error seeking to start position: {e}"));
+        }
+    }
+
+    /// Restricts playback to part of the file. Positions and seeks are relative to the start of
+    /// the range.
+    pub(crate) fn set_range(&mut self, range: Option<TrackRange>) {
+        self.range = range;
+        if let Some(range) = range
+            && !range.start.is_zero()
+        {
+            let _ = self
+                .decoder
+                .seek(range.start)
+                .inspect_err(|e| warn!("Error seeking to start of range: {e}"));
+        }
+    }
+
+    fn range_start(&self) -> Duration {
+        self.range.map(|r| r.start).unwrap_or_default()
+    }
+
+    fn current_position(&self) -> CurrentPosition {
+        let mut position = self.decoder.current_position();
+        position.position = position.position.saturating_sub(self.range_start());
+        position
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        match self.range {
+            Some(range) => range.duration(self.decoder.duration()),
+            None => self.decoder.duration(),
+        }
+    }
+
+    fn reached_range_end(&self) -> bool {
+        self.range
+            .and_then(|r| r.end)
+            .is_some_and(|end| self.decoder.current_position().position >= end)
+    }
+
+    pub(crate) fn set_crossfade(&mut self, volume: f32, crossfade: Duration, fade_in: bool) {
+        self.volume = volume;
+        self.crossfade = crossfade;
+        self.fade_in = fade_in;
+    }
+
+    pub(crate) fn set_replay_gain_mode(&mut self, mode: ReplayGainMode) {
+        self.replay_gain_mode = mode;
+        self.update_volume();
+    }
+
+    pub(crate) fn set_equalizer(&mut self, preset: Option<EqualizerPreset>) {
+        self.equalizer.set_preset(preset);
+    }
+
+    pub(crate) fn volume(&self) -> f32 {
+        self.volume
+    }
+
     pub(crate) fn position(&self) -> CurrentPosition {
-        self.decoder.current_position()
+        self.current_position()
     }
 
     pub(crate) fn next(&mut self) -> Result<(InputResult, DecoderResult), ProcessorError> {
@@ -191,13 +416,110 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
             Ok(InputResult::Stop) => return Ok((InputResult::Stop, DecoderResult::Finished)),
             Err(e) => return Err(e),
         };
-        let res = self
-            .manager
-            .write(&mut self.decoder)
-            .map_err(ProcessorError::WriteOutputError)?;
+        if self.reached_range_end() {
+            return Ok((InputResult::Continue, DecoderResult::Finished));
+        }
+        #[cfg(test)]
+        let start = self.decoder.current_position().position;
+        let res = if self.equalizer.is_active() || self.fading.is_some() {
+            let equalizer = &mut self.equalizer;
+            let fading = &mut self.fading;
+            self.manager
+                .write_filtered(&mut self.decoder, |samples, channels, sample_rate| {
+                    if let Some(fading) = fading {
+                        #[cfg_attr(not(test), allow(unused_variables))]
+                        let overlap = fading.mix_into(samples, channels, sample_rate);
+                        #[cfg(test)]
+                        if overlap > 0 {
+                            output_tap::record(OutputEvent::Overlap(Duration::from_secs_f64(
+                                overlap as f64 / sample_rate as f64,
+                            )));
+                        }
+                    }
+                    equalizer.process(samples, channels, sample_rate);
+                })
+        } else {
+            self.manager.write(&mut self.decoder)
+        }
+        .map_err(ProcessorError::WriteOutputError)?;
+        #[cfg(test)]
+        output_tap::record(OutputEvent::Written(
+            self.decoder
+                .current_position()
+                .position
+                .saturating_sub(start),
+        ));
+        if self.fading.as_ref().is_some_and(FadingSource::is_finished) {
+            self.fading = None;
+        }
+        self.apply_crossfade();
         Ok((InputResult::Continue, res))
     }
 
+    fn apply_crossfade(&mut self) {
+        let gain = self.crossfade_gain();
+        if gain != self.fade_gain {
+            self.fade_gain = gain;
+            self.update_volume();
+        }
+    }
+
+    fn update_volume(&mut self) {
+        self.decoder
+            .set_volume(self.volume * self.fade_gain * self.normalization_gain());
+    }
+
+    fn normalization_gain(&self) -> f32 {
+        let Some(replay_gain) = self.replay_gain else {
+            return 1.0;
+        };
+        // Use the values for the other mode if the preferred ones are missing
+        let (gain, peak) = match self.replay_gain_mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (
+                replay_gain.track_gain.or(replay_gain.album_gain),
+                replay_gain.track_peak.or(replay_gain.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                replay_gain.album_gain.or(replay_gain.track_gain),
+                replay_gain.album_peak.or(replay_gain.track_peak),
+            ),
+        };
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+        let mut factor = 10f64.powf(gain / 20.0);
+        // Don't boost the signal past the point where the loudest sample would clip
+        if let Some(peak) = peak
+            && peak > 0.0
+        {
+            factor = factor.min(1.0 / peak);
+        }
+        factor as f32
+    }
+
+    fn crossfade_gain(&mut self) -> f32 {
+        if self.crossfade.is_zero() {
+            return 1.0;
+        }
+        let position = self.current_position().position;
+        let mut gain = 1.0;
+        if self.fade_in {
+            if position < self.crossfade {
+                gain = equal_power_gain(position, self.crossfade);
+            } else {
+                self.fade_in = false;
+            }
+        }
+        // Fade out at the end if there's no next track to crossfade into
+        if let Some(remaining) = self.remaining()
+            && remaining < self.crossfade
+        {
+            gain = gain.min(equal_power_gain(remaining, self.crossfade));
+        }
+        gain
+    }
+
     pub(crate) fn reset(&mut self) {
         // Reset may fail on Windows on the first try if the device was unplugged
         let _ = self
@@ -258,7 +580,28 @@ impl<'a, H: Host> AudioProcessor<'a, H> {
             track_number: find_tag!(std_tags, StandardTag::TrackNumber)
                 .map(|t| t.parse().ok())
                 .flatten(),
-            duration: self.decoder.duration(),
+            duration: self.duration(),
+            replay_gain: extract_replay_gain(&std_tags),
+            album_art_url: None,
+            lyrics: None,
         }
     }
 }
+
+fn extract_replay_gain(std_tags: &[&StandardTag]) -> Option<ReplayGain> {
+    let replay_gain = ReplayGain {
+        track_gain: find_tag!(std_tags, StandardTag::ReplayGainTrackGain)
+            .as_deref()
+            .and_then(parse_gain_value),
+        track_peak: find_tag!(std_tags, StandardTag::ReplayGainTrackPeak)
+            .as_deref()
+            .and_then(parse_gain_value),
+        album_gain: find_tag!(std_tags, StandardTag::ReplayGainAlbumGain)
+            .as_deref()
+            .and_then(parse_gain_value),
+        album_peak: find_tag!(std_tags, StandardTag::ReplayGainAlbumPeak)
+            .as_deref()
+            .and_then(parse_gain_value),
+    };
+    (replay_gain != ReplayGain::default()).then_some(replay_gain)
+}
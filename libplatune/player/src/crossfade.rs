@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use decal::decoder::Decoder;
+use tracing::warn;
+
+// Equal-power curve so the perceived loudness stays constant across the transition
+pub(crate) fn equal_power_gain(elapsed: Duration, total: Duration) -> f32 {
+    let progress = (elapsed.as_secs_f32() / total.as_secs_f32()).clamp(0.0, 1.0);
+    (progress * std::f32::consts::FRAC_PI_2).sin()
+}
+
+/// The end of the previous track, which keeps playing underneath the start of the next one
+/// until it fades out completely
+pub(crate) struct FadingSource {
+    decoder: Decoder<f32>,
+    samples: VecDeque<f32>,
+    // Fractional frame offset into the buffered samples, used for resampling to the output rate
+    frame_offset: f64,
+    remaining: Duration,
+    fade: Duration,
+    finished: bool,
+}
+
+impl FadingSource {
+    pub(crate) fn new(decoder: Decoder<f32>, remaining: Duration, fade: Duration) -> Self {
+        Self {
+            decoder,
+            samples: VecDeque::new(),
+            frame_offset: 0.0,
+            remaining,
+            fade,
+            finished: false,
+        }
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Adds the faded out samples to the output of the incoming track.
+    /// Returns the number of frames where both tracks were audible.
+    pub(crate) fn mix_into(
+        &mut self,
+        output: &mut [f32],
+        channels: usize,
+        sample_rate: u32,
+    ) -> usize {
+        if channels == 0 || sample_rate == 0 {
+            return 0;
+        }
+        let step = self.decoder.sample_rate() as f64 / sample_rate as f64;
+        let frame_time = Duration::from_secs_f64(1.0 / sample_rate as f64);
+        let mut overlapping = 0;
+        for frame in output.chunks_exact_mut(channels) {
+            if self.remaining.is_zero() || !self.fill((self.frame_offset as usize + 2) * channels) {
+                self.finished = true;
+                break;
+            }
+            let gain = equal_power_gain(self.remaining, self.fade);
+            let index = self.frame_offset as usize;
+            let fraction = (self.frame_offset - index as f64) as f32;
+            let mut audible = false;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let current = self.samples[index * channels + channel];
+                let next = self.samples[(index + 1) * channels + channel];
+                let faded = (current + (next - current) * fraction) * gain;
+                audible |= *sample != 0.0 && faded != 0.0;
+                *sample += faded;
+            }
+            if audible {
+                overlapping += 1;
+            }
+            self.remaining = self.remaining.saturating_sub(frame_time);
+            self.frame_offset += step;
+            let consumed = (self.frame_offset as usize).min(self.samples.len() / channels);
+            self.samples.drain(..consumed * channels);
+            self.frame_offset -= consumed as f64;
+        }
+        overlapping
+    }
+
+    fn fill(&mut self, len: usize) -> bool {
+        while self.samples.len() < len {
+            match self.decoder.next() {
+                Ok(Some(samples)) => self.samples.extend(samples),
+                Ok(None) => return false,
+                Err(e) => {
+                    warn!("Error decoding the end of the previous track: {e:?}");
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
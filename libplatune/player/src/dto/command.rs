@@ -1,6 +1,9 @@
 use std::fmt::Debug;
 use std::time::Duration;
 
+use super::equalizer_preset::EqualizerPreset;
+use super::repeat_mode::RepeatMode;
+use super::replay_gain_mode::ReplayGainMode;
 use super::track::{Metadata, Track};
 use crate::platune_player::SeekMode;
 
@@ -12,6 +15,14 @@ pub(crate) enum Command {
     Seek(Duration, SeekMode),
     SetVolume(f32),
     SetDeviceName(Option<String>),
+    SetRepeatMode(RepeatMode),
+    SetShuffle(bool),
+    SetCrossfade(Duration),
+    SetReplayGainMode(ReplayGainMode),
+    GetEqualizerPresets,
+    SaveEqualizerPreset(EqualizerPreset),
+    DeleteEqualizerPreset(String),
+    SetEqualizerPreset(Option<String>),
     Pause,
     Resume,
     Toggle,
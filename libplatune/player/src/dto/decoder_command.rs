@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use crate::platune_player::SeekMode;
+use crate::platune_player::{EqualizerPreset, ReplayGainMode, SeekMode};
 
 #[derive(Clone, Debug)]
 pub(crate) enum DecoderCommand {
@@ -10,6 +10,9 @@ pub(crate) enum DecoderCommand {
     Play,
     Stop,
     SetVolume(f32),
+    SetCrossfade(Duration),
+    SetReplayGainMode(ReplayGainMode),
+    SetEqualizer(Option<EqualizerPreset>),
     GetCurrentPosition,
     Reset,
 }
@@ -0,0 +1,83 @@
+use std::fs::{self, create_dir_all};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::equalizer_preset::{EqualizerPreset, built_in_presets};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct EqualizerConfig {
+    pub(crate) active_preset: Option<String>,
+    // Only user presets are saved, the built-in ones are always available
+    pub(crate) presets: Vec<EqualizerPreset>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EqualizerPresets {
+    pub presets: Vec<EqualizerPreset>,
+    pub built_in: Vec<EqualizerPreset>,
+    pub active_preset: Option<String>,
+}
+
+impl EqualizerConfig {
+    pub(crate) fn read(path: &Path) -> io::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, serde_json::to_string(self)?)?;
+        fs::rename(temp_path, path)
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<EqualizerPreset> {
+        built_in_presets()
+            .into_iter()
+            .chain(self.presets.iter().cloned())
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    pub(crate) fn active(&self) -> Option<EqualizerPreset> {
+        self.active_preset
+            .as_deref()
+            .and_then(|name| self.find(name))
+    }
+
+    pub(crate) fn save_preset(&mut self, preset: EqualizerPreset) {
+        match self
+            .presets
+            .iter_mut()
+            .find(|p| p.name.eq_ignore_ascii_case(&preset.name))
+        {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub(crate) fn delete_preset(&mut self, name: &str) {
+        self.presets.retain(|p| !p.name.eq_ignore_ascii_case(name));
+        if self
+            .active_preset
+            .as_deref()
+            .is_some_and(|active| active.eq_ignore_ascii_case(name))
+        {
+            self.active_preset = None;
+        }
+    }
+
+    pub(crate) fn to_presets(&self) -> EqualizerPresets {
+        EqualizerPresets {
+            presets: self.presets.clone(),
+            built_in: built_in_presets(),
+            active_preset: self.active_preset.clone(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EqualizerBand {
+    pub filter_type: FilterType,
+    /// Center or corner frequency in Hz
+    pub frequency: f32,
+    /// Gain in dB
+    pub gain: f32,
+    pub q: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EqualizerPreset {
+    pub name: String,
+    /// Gain in dB applied before the bands, used to leave headroom for boosted frequencies
+    pub preamp: f32,
+    pub bands: Vec<EqualizerBand>,
+}
+
+const MAX_GAIN: f32 = 24.0;
+
+impl EqualizerPreset {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Preset name cannot be empty".to_owned());
+        }
+        if is_built_in(&self.name) {
+            return Err(format!("{} is a built-in preset", self.name));
+        }
+        if !self.preamp.is_finite() || self.preamp.abs() > MAX_GAIN {
+            return Err(format!(
+                "Preamp must be between -{MAX_GAIN} and {MAX_GAIN} dB"
+            ));
+        }
+        for band in &self.bands {
+            // The upper bound is checked against the sample rate during processing
+            if !band.frequency.is_finite() || band.frequency <= 0.0 {
+                return Err(format!("Invalid band frequency {}", band.frequency));
+            }
+            if !band.gain.is_finite() || band.gain.abs() > MAX_GAIN {
+                return Err(format!(
+                    "Band gain must be between -{MAX_GAIN} and {MAX_GAIN} dB"
+                ));
+            }
+            if !band.q.is_finite() || band.q <= 0.0 {
+                return Err(format!("Invalid band Q {}", band.q));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn is_built_in(name: &str) -> bool {
+    built_in_presets()
+        .iter()
+        .any(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+pub(crate) fn built_in_presets() -> Vec<EqualizerPreset> {
+    vec![
+        EqualizerPreset {
+            name: "Flat".to_owned(),
+            preamp: 0.0,
+            bands: vec![],
+        },
+        EqualizerPreset {
+            name: "Bass Boost".to_owned(),
+            preamp: -6.0,
+            bands: vec![EqualizerBand {
+                filter_type: FilterType::LowShelf,
+                frequency: 100.0,
+                gain: 6.0,
+                q: 0.707,
+            }],
+        },
+        EqualizerPreset {
+            name: "Treble Boost".to_owned(),
+            preamp: -6.0,
+            bands: vec![EqualizerBand {
+                filter_type: FilterType::HighShelf,
+                frequency: 8000.0,
+                gain: 6.0,
+                q: 0.707,
+            }],
+        },
+        EqualizerPreset {
+            name: "Vocal".to_owned(),
+            preamp: -4.0,
+            bands: vec![
+                EqualizerBand {
+                    filter_type: FilterType::Peaking,
+                    frequency: 250.0,
+                    gain: -2.0,
+                    q: 1.0,
+                },
+                EqualizerBand {
+                    filter_type: FilterType::Peaking,
+                    frequency: 2500.0,
+                    gain: 4.0,
+                    q: 1.0,
+                },
+            ],
+        },
+    ]
+}
@@ -2,10 +2,17 @@ pub(crate) mod audio_status;
 pub(crate) mod command;
 pub(crate) mod decoder_command;
 pub(crate) mod decoder_response;
+pub(crate) mod equalizer_config;
+pub(crate) mod equalizer_preset;
+pub(crate) mod played_track;
 pub(crate) mod player_event;
 pub(crate) mod player_response;
 pub(crate) mod player_state;
 pub(crate) mod player_status;
 pub(crate) mod processor_error;
 pub(crate) mod queue_source;
+pub(crate) mod repeat_mode;
+pub(crate) mod replay_gain_mode;
+pub(crate) mod saved_state;
 pub(crate) mod track;
+pub(crate) mod track_range;
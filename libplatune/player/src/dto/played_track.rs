@@ -0,0 +1,13 @@
+use std::time::Duration;
+
+use super::track::Metadata;
+
+#[derive(Clone, Debug)]
+pub struct PlayedTrack {
+    pub url: String,
+    pub metadata: Option<Metadata>,
+    /// Time spent actually playing the track, excluding any time spent paused
+    pub listened: Duration,
+    /// Whether playback moved on before the track finished
+    pub skipped: bool,
+}
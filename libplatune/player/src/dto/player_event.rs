@@ -3,7 +3,9 @@ use std::time::Duration;
 use decal::decoder::CurrentPosition;
 use strum::Display;
 
+use super::played_track::PlayedTrack;
 use super::player_state::PlayerState;
+use super::track::LyricLine;
 
 #[derive(Clone, Debug, Display)]
 pub enum PlayerEvent {
@@ -14,7 +16,12 @@ pub enum PlayerEvent {
     Resume(PlayerState),
     TrackChanged(PlayerState),
     SetVolume(PlayerState),
+    SetRepeatMode(PlayerState),
+    SetShuffle(PlayerState),
     Seek(PlayerState, Duration),
     QueueEnded(PlayerState),
     Position(CurrentPosition),
+    /// Sent along with position updates whenever a new line of the current track's lyrics starts
+    LyricLine(LyricLine),
+    TrackPlayed(PlayedTrack),
 }
@@ -1,6 +1,8 @@
+use super::equalizer_config::EqualizerPresets;
 use super::player_status::TrackStatus;
 
 #[derive(Clone, Debug)]
 pub(crate) enum PlayerResponse {
     StatusResponse(TrackStatus),
+    EqualizerResponse(EqualizerPresets),
 }
@@ -1,4 +1,5 @@
 use super::audio_status::AudioStatus;
+use super::repeat_mode::RepeatMode;
 use super::track::Metadata;
 use crate::resolver::TrackInput;
 
@@ -9,6 +10,8 @@ pub struct PlayerState {
     pub metadata: Option<Metadata>,
     pub queue_position: usize,
     pub status: AudioStatus,
+    pub repeat_mode: RepeatMode,
+    pub shuffle: bool,
 }
 
 impl PlayerState {
@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use decal::decoder::Source;
 
+use super::equalizer_preset::EqualizerPreset;
 use super::track::Metadata;
+use super::track_range::TrackRange;
 use crate::settings::Settings;
 
 #[derive(Debug)]
@@ -10,4 +14,8 @@ pub(crate) struct QueueSource {
     pub(crate) volume: Option<f32>,
     pub(crate) settings: Settings,
     pub(crate) has_content_length: bool,
+    pub(crate) start_paused_at: Option<Duration>,
+    pub(crate) wait_for_init: bool,
+    pub(crate) equalizer: Option<EqualizerPreset>,
+    pub(crate) range: Option<TrackRange>,
 }
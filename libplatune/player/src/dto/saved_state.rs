@@ -0,0 +1,36 @@
+use std::fs::{self, create_dir_all};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::track::Track;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SavedState {
+    pub(crate) queue: Vec<Track>,
+    pub(crate) queue_position: usize,
+    pub(crate) volume: f32,
+    pub(crate) position: Option<Duration>,
+}
+
+impl SavedState {
+    pub(crate) fn read(path: &Path) -> io::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        // Write to a temp file first so a crash mid-write doesn't leave a truncated snapshot
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, serde_json::to_string(self)?)?;
+        fs::rename(temp_path, path)
+    }
+}
@@ -1,12 +1,14 @@
 use std::time::Duration;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Track {
     pub url: String,
     pub metadata: Option<Metadata>,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Metadata {
     pub artist: Option<String>,
     pub album_artist: Option<String>,
@@ -14,4 +16,24 @@ pub struct Metadata {
     pub song: Option<String>,
     pub track_number: Option<u32>,
     pub duration: Option<Duration>,
+    pub replay_gain: Option<ReplayGain>,
+    pub album_art_url: Option<String>,
+    /// Timed lyrics that are sent as lyric line events during playback
+    pub lyrics: Option<Vec<LyricLine>>,
+}
+
+/// Gain adjustments in dB and peaks as linear sample amplitudes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricLine {
+    /// Offset from the start of the track
+    pub start: Duration,
+    pub text: String,
 }
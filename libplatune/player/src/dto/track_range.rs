@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+/// The part of a file to play, like a single track from an album that's stored as one file.
+/// Ranges are passed as a media fragment on the track URL, e.g. `/music/album.flac#t=10.5,250`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackRange {
+    pub start: Duration,
+    /// Plays until the end of the file if not set
+    pub end: Option<Duration>,
+}
+
+impl TrackRange {
+    /// Splits the range off of the URL, returning the URL without the fragment
+    pub(crate) fn from_url(url: &str) -> (&str, Option<Self>) {
+        match url
+            .rsplit_once('#')
+            .and_then(|(base, fragment)| Some((base, Self::parse_fragment(fragment)?)))
+        {
+            Some((base, range)) => (base, Some(range)),
+            None => (url, None),
+        }
+    }
+
+    fn parse_fragment(fragment: &str) -> Option<Self> {
+        let value = fragment.strip_prefix("t=")?;
+        let value = value.strip_prefix("npt:").unwrap_or(value);
+        let (start, end) = match value.split_once(',') {
+            Some((start, end)) => (start, Some(parse_seconds(end)?)),
+            None => (value, None),
+        };
+        let start = if start.is_empty() {
+            Duration::ZERO
+        } else {
+            parse_seconds(start)?
+        };
+        if let Some(end) = end
+            && end <= start
+        {
+            return None;
+        }
+        Some(Self { start, end })
+    }
+
+    pub(crate) fn fragment(&self) -> String {
+        match self.end {
+            Some(end) => format!("#t={},{}", format_seconds(self.start), format_seconds(end)),
+            None => format!("#t={}", format_seconds(self.start)),
+        }
+    }
+
+    pub(crate) fn duration(&self, file_duration: Option<Duration>) -> Option<Duration> {
+        self.end
+            .or(file_duration)
+            .map(|end| end.saturating_sub(self.start))
+    }
+}
+
+fn parse_seconds(value: &str) -> Option<Duration> {
+    let seconds: f64 = value.trim().parse().ok()?;
+    // Offsets are only kept to millisecond precision so they can be written back out unchanged
+    (seconds.is_finite() && seconds >= 0.0)
+        .then(|| Duration::from_millis((seconds * 1000.0).round() as u64))
+}
+
+fn format_seconds(time: Duration) -> String {
+    format!("{}.{:03}", time.as_secs(), time.subsec_millis())
+}
@@ -0,0 +1,159 @@
+use std::f32::consts::PI;
+
+use crate::platune_player::{EqualizerBand, EqualizerPreset, FilterType};
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Coefficients {
+    // Biquad coefficients from the RBJ audio EQ cookbook
+    fn new(band: &EqualizerBand, sample_rate: u32) -> Self {
+        let nyquist = sample_rate as f32 / 2.0;
+        let frequency = band.frequency.min(nyquist * 0.99);
+        let a = 10f32.powf(band.gain / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * band.q);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.filter_type {
+            FilterType::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos_w0,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos_w0,
+                1.0 - alpha / a,
+            ),
+            FilterType::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0),
+                a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0),
+                (a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha,
+            ),
+            FilterType::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0),
+                a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos_w0),
+                (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Biquad {
+    coefficients: Coefficients,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn process(&mut self, input: f32) -> f32 {
+        // Transposed direct form II
+        let c = &self.coefficients;
+        let output = c.b0 * input + self.z1;
+        self.z1 = c.b1 * input - c.a1 * output + self.z2;
+        self.z2 = c.b2 * input - c.a2 * output;
+        output
+    }
+}
+
+pub(crate) struct Equalizer {
+    preset: Option<EqualizerPreset>,
+    sample_rate: u32,
+    channels: usize,
+    preamp: f32,
+    // One filter per band per channel since each channel needs its own filter state
+    filters: Vec<Vec<Biquad>>,
+}
+
+impl Equalizer {
+    pub(crate) fn new(preset: Option<EqualizerPreset>) -> Self {
+        let mut equalizer = Self {
+            preset: None,
+            sample_rate: 0,
+            channels: 0,
+            preamp: 1.0,
+            filters: Vec::new(),
+        };
+        equalizer.set_preset(preset);
+        equalizer
+    }
+
+    pub(crate) fn set_preset(&mut self, preset: Option<EqualizerPreset>) {
+        // Keep the current filter state so there's no discontinuity between tracks
+        if preset == self.preset {
+            return;
+        }
+        self.preamp = preset
+            .as_ref()
+            .map(|p| 10f32.powf(p.preamp / 20.0))
+            .unwrap_or(1.0);
+        self.preset = preset;
+        // Force the filters to be rebuilt on the next buffer
+        self.sample_rate = 0;
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.preset.is_some()
+    }
+
+    pub(crate) fn process(&mut self, samples: &mut [f32], channels: usize, sample_rate: u32) {
+        let Some(preset) = &self.preset else {
+            return;
+        };
+        if channels == 0 || sample_rate == 0 {
+            return;
+        }
+        if sample_rate != self.sample_rate || channels != self.channels {
+            self.filters = preset
+                .bands
+                .iter()
+                .map(|band| {
+                    vec![
+                        Biquad {
+                            coefficients: Coefficients::new(band, sample_rate),
+                            ..Default::default()
+                        };
+                        channels
+                    ]
+                })
+                .collect();
+            self.sample_rate = sample_rate;
+            self.channels = channels;
+        }
+
+        for frame in samples.chunks_exact_mut(channels) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let mut value = *sample * self.preamp;
+                for band in &mut self.filters {
+                    value = band[channel].process(value);
+                }
+                *sample = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./equalizer_test.rs"]
+mod equalizer_test;
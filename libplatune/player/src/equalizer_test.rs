@@ -0,0 +1,98 @@
+use std::f32::consts::PI;
+
+use super::Equalizer;
+use crate::platune_player::{EqualizerBand, EqualizerPreset, FilterType};
+
+const SAMPLE_RATE: u32 = 48000;
+
+fn sine(frequency: f32, channels: usize) -> Vec<f32> {
+    (0..SAMPLE_RATE as usize)
+        .flat_map(|i| {
+            let value = (2.0 * PI * frequency * i as f32 / SAMPLE_RATE as f32).sin() * 0.1;
+            std::iter::repeat_n(value, channels)
+        })
+        .collect()
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+fn peaking_preset(gain: f32) -> EqualizerPreset {
+    EqualizerPreset {
+        name: "test".to_owned(),
+        preamp: 0.0,
+        bands: vec![EqualizerBand {
+            filter_type: FilterType::Peaking,
+            frequency: 1000.0,
+            gain,
+            q: 1.0,
+        }],
+    }
+}
+
+#[test]
+fn test_inactive_passthrough() {
+    let mut equalizer = Equalizer::new(None);
+    let original = sine(1000.0, 2);
+    let mut samples = original.clone();
+    equalizer.process(&mut samples, 2, SAMPLE_RATE);
+
+    assert!(!equalizer.is_active());
+    assert_eq!(original, samples);
+}
+
+#[test]
+fn test_peaking_boost() {
+    let mut equalizer = Equalizer::new(Some(peaking_preset(12.0)));
+    let original = sine(1000.0, 2);
+    let mut samples = original.clone();
+    equalizer.process(&mut samples, 2, SAMPLE_RATE);
+
+    // Skip the beginning while the filter settles
+    let half = samples.len() / 2;
+    let ratio = rms(&samples[half..]) / rms(&original[half..]);
+    let expected = 10f32.powf(12.0 / 20.0);
+    assert!(
+        (ratio - expected).abs() / expected < 0.05,
+        "ratio was {ratio}"
+    );
+}
+
+#[test]
+fn test_peaking_leaves_distant_frequencies() {
+    let mut equalizer = Equalizer::new(Some(peaking_preset(12.0)));
+    let original = sine(50.0, 1);
+    let mut samples = original.clone();
+    equalizer.process(&mut samples, 1, SAMPLE_RATE);
+
+    let half = samples.len() / 2;
+    let ratio = rms(&samples[half..]) / rms(&original[half..]);
+    assert!((ratio - 1.0).abs() < 0.1, "ratio was {ratio}");
+}
+
+#[test]
+fn test_preamp() {
+    let mut equalizer = Equalizer::new(Some(EqualizerPreset {
+        name: "test".to_owned(),
+        preamp: -6.0,
+        bands: vec![],
+    }));
+    let original = sine(1000.0, 2);
+    let mut samples = original.clone();
+    equalizer.process(&mut samples, 2, SAMPLE_RATE);
+
+    let ratio = rms(&samples) / rms(&original);
+    assert!((ratio - 10f32.powf(-6.0 / 20.0)).abs() < 0.001);
+}
+
+#[test]
+fn test_validate_built_in_name() {
+    let preset = EqualizerPreset {
+        name: "bass boost".to_owned(),
+        ..peaking_preset(3.0)
+    };
+    assert!(preset.validate().is_err());
+    assert!(peaking_preset(3.0).validate().is_ok());
+    assert!(peaking_preset(48.0).validate().is_err());
+}
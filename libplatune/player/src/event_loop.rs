@@ -1,10 +1,11 @@
 use std::time::Duration;
 
-use decal::decoder::{DecoderResult, DecoderSettings, ResamplerSettings};
+use decal::decoder::{Decoder, DecoderResult, DecoderSettings, ResamplerSettings};
 use decal::output::{Host, OutputBuilder, OutputSettings, WriteBlockingError};
 use decal::{AudioManager, ResetError, WriteOutputError};
 use flume::{Receiver, TryRecvError};
 use tap::TapFallible;
+use tokio::time::MissedTickBehavior;
 use tracing::{error, info};
 
 use crate::audio_processor::{AudioProcessor, InputResult};
@@ -14,13 +15,16 @@ use crate::dto::decoder_response::DecoderResponse;
 use crate::dto::player_response::PlayerResponse;
 use crate::dto::processor_error::ProcessorError;
 use crate::dto::queue_source::QueueSource;
-use crate::platune_player::PlayerEvent;
+use crate::dto::track_range::TrackRange;
+#[cfg(test)]
+use crate::output_tap::{self, OutputEvent};
+use crate::platune_player::{EqualizerPreset, Metadata, PlayerEvent, ReplayGainMode};
 use crate::player::Player;
 use crate::two_way_channel::{TwoWayReceiver, TwoWaySender};
 
 pub(crate) fn decode_loop<H: Host>(
     queue_rx: Receiver<QueueSource>,
-    volume: f32,
+    mut volume: f32,
     mut cmd_rx: TwoWayReceiver<DecoderCommand, DecoderResponse>,
     player_cmd_tx: TwoWaySender<Command, PlayerResponse>,
     event_tx: tokio::sync::broadcast::Sender<PlayerEvent>,
@@ -51,29 +55,31 @@ pub(crate) fn decode_loop<H: Host>(
     };
     manager.set_volume(volume);
 
+    // Source that was received while the previous one was playing, but needs to be started from
+    // scratch
+    let mut pending_source = None;
     loop {
-        let (decoder, metadata, has_content_length) = match queue_rx.try_recv() {
+        let next_source = match pending_source.take() {
+            Some(queue_source) => Ok(queue_source),
+            None => queue_rx.try_recv(),
+        };
+        let (decoder, source_info) = match next_source {
             Ok(queue_source) => {
                 info!("Got source on initial attempt");
-                init_source(&mut manager, &queue_source);
-                if let Ok(decoder) = manager
-                    .init_decoder(queue_source.source, DecoderSettings::new())
-                    .tap_err(|e| handle_decoder_failure(e, &mut cmd_rx))
-                {
-                    (
-                        decoder,
-                        queue_source.metadata,
-                        queue_source.has_content_length,
-                    )
-                } else {
-                    continue;
+                match init_decoder(
+                    &mut manager,
+                    queue_source,
+                    &mut volume,
+                    &mut cmd_rx,
+                    &player_cmd_tx,
+                ) {
+                    Some(initialized) => initialized,
+                    None => continue,
                 }
             }
             Err(TryRecvError::Empty) => {
                 info!("No sources on initial attempt, waiting");
-                let _ = manager
-                    .flush()
-                    .tap_err(|e| error!("Error flushing output: {e:?}"));
+                flush_output(&mut manager);
                 manager.pause();
                 match queue_rx.recv() {
                     Ok(queue_source) => {
@@ -82,18 +88,15 @@ pub(crate) fn decode_loop<H: Host>(
                         let _ = manager
                             .reset_output()
                             .tap_err(|e| error!("error resetting output: {e:?}"));
-                        init_source(&mut manager, &queue_source);
-                        if let Ok(decoder) = manager
-                            .init_decoder(queue_source.source, DecoderSettings::new())
-                            .tap_err(|e| handle_decoder_failure(e, &mut cmd_rx))
-                        {
-                            (
-                                decoder,
-                                queue_source.metadata,
-                                queue_source.has_content_length,
-                            )
-                        } else {
-                            continue;
+                        match init_decoder(
+                            &mut manager,
+                            queue_source,
+                            &mut volume,
+                            &mut cmd_rx,
+                            &player_cmd_tx,
+                        ) {
+                            Some(initialized) => initialized,
+                            None => continue,
                         }
                     }
                     Err(_) => {
@@ -108,10 +111,18 @@ pub(crate) fn decode_loop<H: Host>(
             }
         };
         info!("Creating processor");
-        if let Ok(mut processor) =
-            AudioProcessor::new(&mut manager, decoder, &mut cmd_rx, &event_tx, metadata)
-                .inspect_err(|e| error!("Error creating processor: {e}"))
+        if let Ok(mut processor) = AudioProcessor::new(
+            &mut manager,
+            decoder,
+            &mut cmd_rx,
+            &event_tx,
+            source_info.metadata.clone(),
+            source_info.wait_for_init,
+        )
+        .inspect_err(|e| error!("Error creating processor: {e}"))
         {
+            configure_processor(&mut processor, &source_info, volume);
+            let mut has_content_length = source_info.has_content_length;
             let mut send_time = true;
             let mut is_first_packet = true;
             loop {
@@ -119,9 +130,8 @@ pub(crate) fn decode_loop<H: Host>(
                     Ok((InputResult::Stop, _)) => {
                         // Don't send Command::Ended when we explicitly requested to stop
                         // because we don't want to initialize the next track
-                        let _ = manager
-                            .flush()
-                            .inspect_err(|e| error!("error flushing: {e:?}"));
+                        let (manager, _) = processor.output_parts();
+                        flush_output(manager);
                         break;
                     }
                     Ok((_, DecoderResult::Unfinished)) => {
@@ -144,6 +154,28 @@ pub(crate) fn decode_loop<H: Host>(
                                 .send(Command::Metadata(metadata))
                                 .inspect_err(|e| error!("Unable to send command: {e:?}"));
                         }
+                        if processor.crossfade_ready() {
+                            if let Some((decoder, source_info)) = init_next_decoder(
+                                &mut processor,
+                                &queue_rx,
+                                &mut pending_source,
+                                &mut volume,
+                                &player_cmd_tx,
+                            ) {
+                                info!("Crossfading into the next track");
+                                player_cmd_tx
+                                    .send(Command::Ended)
+                                    .tap_err(|e| error!("Unable to send ended command: {e:?}"))
+                                    .ok();
+                                processor.crossfade_into(decoder, source_info.metadata.clone());
+                                configure_processor(&mut processor, &source_info, volume);
+                                has_content_length = source_info.has_content_length;
+                                send_time = true;
+                                is_first_packet = true;
+                            } else if pending_source.is_some() {
+                                break;
+                            }
+                        }
                     }
                     Ok((_, DecoderResult::Finished)) => {
                         info!("Sending ended event");
@@ -151,7 +183,22 @@ pub(crate) fn decode_loop<H: Host>(
                             .send(Command::Ended)
                             .tap_err(|e| error!("Unable to send ended command: {e:?}"))
                             .ok();
-                        break;
+                        // Continue directly into the next track if it's already queued up so the
+                        // output doesn't stop between them
+                        let Some((decoder, source_info)) = init_next_decoder(
+                            &mut processor,
+                            &queue_rx,
+                            &mut pending_source,
+                            &mut volume,
+                            &player_cmd_tx,
+                        ) else {
+                            break;
+                        };
+                        processor.load(decoder, source_info.metadata.clone());
+                        configure_processor(&mut processor, &source_info, volume);
+                        has_content_length = source_info.has_content_length;
+                        send_time = true;
+                        is_first_packet = true;
                     }
                     Err(ProcessorError::WriteOutputError(
                         WriteOutputError::WriteBlockingError(WriteBlockingError::OutputStalled),
@@ -179,10 +226,109 @@ pub(crate) fn decode_loop<H: Host>(
                     }
                 }
             }
+            volume = processor.volume();
         }
     }
 }
 
+/// Initializes the next queued source if it's meant to follow the current one
+fn init_next_decoder<H: Host>(
+    processor: &mut AudioProcessor<'_, H>,
+    queue_rx: &Receiver<QueueSource>,
+    pending_source: &mut Option<QueueSource>,
+    volume: &mut f32,
+    player_cmd_tx: &TwoWaySender<Command, PlayerResponse>,
+) -> Option<(Decoder<f32>, SourceInfo)> {
+    let queue_source = match queue_rx.try_recv() {
+        Ok(queue_source) if !queue_source.wait_for_init => queue_source,
+        Ok(queue_source) => {
+            // Sources that were started explicitly need to go through the full initialization
+            *pending_source = Some(queue_source);
+            return None;
+        }
+        Err(_) => return None,
+    };
+    *volume = processor.volume();
+    let (manager, cmd_rx) = processor.output_parts();
+    init_decoder(manager, queue_source, volume, cmd_rx, player_cmd_tx)
+}
+
+fn configure_processor<H: Host>(
+    processor: &mut AudioProcessor<'_, H>,
+    source_info: &SourceInfo,
+    volume: f32,
+) {
+    processor.set_range(source_info.range);
+    if let Some(position) = source_info.start_paused_at {
+        processor.start_paused(position);
+    }
+    // Tracks that follow the previous one without a handshake are the ones that should
+    // fade in from the end of the last track
+    processor.set_crossfade(volume, source_info.crossfade, !source_info.wait_for_init);
+    processor.set_replay_gain_mode(source_info.replay_gain_mode);
+    processor.set_equalizer(source_info.equalizer.clone());
+}
+
+fn flush_output<H: Host>(manager: &mut AudioManager<f32, H>) {
+    let _ = manager
+        .flush()
+        .inspect_err(|e| error!("error flushing: {e:?}"));
+    #[cfg(test)]
+    output_tap::record(OutputEvent::Flushed);
+}
+
+struct SourceInfo {
+    metadata: Metadata,
+    has_content_length: bool,
+    start_paused_at: Option<Duration>,
+    wait_for_init: bool,
+    crossfade: Duration,
+    replay_gain_mode: ReplayGainMode,
+    equalizer: Option<EqualizerPreset>,
+    range: Option<TrackRange>,
+}
+
+fn init_decoder<H: Host>(
+    manager: &mut AudioManager<f32, H>,
+    queue_source: QueueSource,
+    volume: &mut f32,
+    cmd_rx: &mut TwoWayReceiver<DecoderCommand, DecoderResponse>,
+    player_cmd_tx: &TwoWaySender<Command, PlayerResponse>,
+) -> Option<(Decoder<f32>, SourceInfo)> {
+    init_source(manager, &queue_source);
+    if let Some(new_volume) = queue_source.volume {
+        *volume = new_volume;
+    }
+    let QueueSource {
+        source,
+        metadata,
+        settings,
+        has_content_length,
+        start_paused_at,
+        wait_for_init,
+        equalizer,
+        range,
+        ..
+    } = queue_source;
+    let decoder = manager
+        .init_decoder(source, decoder_settings())
+        .tap_err(|e| handle_decoder_failure(e, wait_for_init, cmd_rx, player_cmd_tx))
+        .ok()?;
+    Some((
+        decoder,
+        SourceInfo {
+            metadata,
+            has_content_length,
+            start_paused_at,
+            wait_for_init,
+            crossfade: settings.crossfade,
+            replay_gain_mode: settings.replay_gain_mode,
+            equalizer,
+            range,
+        },
+    ))
+}
+
 fn init_source<H: Host>(manager: &mut AudioManager<f32, H>, queue_source: &QueueSource) {
     info!("got source {queue_source:?}");
     if let Some(volume) = queue_source.volume {
@@ -193,11 +339,30 @@ fn init_source<H: Host>(manager: &mut AudioManager<f32, H>, queue_source: &Queue
     });
 }
 
+fn decoder_settings() -> DecoderSettings {
+    // Trim encoder delay and padding so consecutive tracks line up without any added silence
+    DecoderSettings {
+        enable_gapless: true,
+        ..DecoderSettings::new()
+    }
+}
+
 fn handle_decoder_failure(
     err: &ResetError,
+    wait_for_init: bool,
     cmd_rx: &mut TwoWayReceiver<DecoderCommand, DecoderResponse>,
+    player_cmd_tx: &TwoWaySender<Command, PlayerResponse>,
 ) {
     error!("error initializing decoder: {err:?}");
+    if !wait_for_init {
+        // The player isn't waiting on this source, so treat it like it finished immediately to
+        // move on to the next track
+        player_cmd_tx
+            .send(Command::Ended)
+            .tap_err(|e| error!("Unable to send ended command: {e:?}"))
+            .ok();
+        return;
+    }
     match cmd_rx.recv() {
         Ok(DecoderCommand::WaitForInitialization) => {
             info!("Received initialization request");
@@ -215,15 +380,57 @@ fn handle_decoder_failure(
         .tap_err(|e| error!("Error sending decoder initialization succeeded: {e:?}"));
 }
 
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
 pub(crate) async fn main_loop(
     mut receiver: TwoWayReceiver<Command, PlayerResponse>,
     mut player: Player,
 ) -> Result<(), String> {
+    if let Err(e) = player.restore_state().await {
+        error!("Error restoring player state: {e:?}");
+    }
+    let mut save_interval = tokio::time::interval(STATE_SAVE_INTERVAL);
+    save_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
     info!("waiting for command");
     // TODO send something to tell clients to clear their state on server restart
-    while let Ok(next_command) = receiver.recv_async().await {
+    loop {
+        let next_command = tokio::select! {
+            next_command = receiver.recv_async() => match next_command {
+                Ok(next_command) => next_command,
+                Err(_) => break,
+            },
+            // The rest of the saved queue is restored one track at a time so other commands can
+            // be handled in between
+            _ = std::future::ready(()), if player.is_restoring() => {
+                player.restore_next_track().await;
+                continue;
+            }
+            _ = save_interval.tick() => {
+                // Keep the saved playback position reasonably fresh in case we don't shut down
+                // cleanly
+                if player.is_playing() {
+                    player.save_state().await;
+                }
+                continue;
+            }
+        };
         let cmd_str = format!("{next_command:?}");
         info!("Got command {cmd_str}");
+        let save_state = matches!(
+            next_command,
+            Command::SetQueue(_)
+                | Command::AddToQueue(_)
+                | Command::Seek(_, _)
+                | Command::SetVolume(_)
+                | Command::Pause
+                | Command::Resume
+                | Command::Toggle
+                | Command::Stop
+                | Command::Ended
+                | Command::Next
+                | Command::Previous
+        );
         match next_command {
             Command::SetQueue(songs) => {
                 player.set_queue(songs).await?;
@@ -273,6 +480,33 @@ pub(crate) async fn main_loop(
                     error!("Error sending player status: {e:?}");
                 }
             }
+            Command::SetRepeatMode(repeat_mode) => {
+                player.set_repeat_mode(repeat_mode).await;
+            }
+            Command::SetShuffle(shuffle) => {
+                player.set_shuffle(shuffle).await;
+            }
+            Command::SetCrossfade(crossfade) => {
+                player.set_crossfade(crossfade).await?;
+            }
+            Command::SetReplayGainMode(mode) => {
+                player.set_replay_gain_mode(mode).await?;
+            }
+            Command::GetEqualizerPresets => {
+                let presets = player.get_equalizer_presets();
+                if let Err(e) = receiver.respond(PlayerResponse::EqualizerResponse(presets)) {
+                    error!("Error sending equalizer presets: {e:?}");
+                }
+            }
+            Command::SaveEqualizerPreset(preset) => {
+                player.save_equalizer_preset(preset).await?;
+            }
+            Command::DeleteEqualizerPreset(name) => {
+                player.delete_equalizer_preset(&name).await?;
+            }
+            Command::SetEqualizerPreset(name) => {
+                player.set_equalizer_preset(name).await?;
+            }
             Command::SetDeviceName(name) => {
                 player.set_device_name(name).await?;
             }
@@ -280,9 +514,13 @@ pub(crate) async fn main_loop(
                 player.reset().await?;
             }
             Command::Shutdown => {
+                player.shutdown().await?;
                 return Ok(());
             }
         }
+        if save_state {
+            player.save_state().await;
+        }
         info!("Completed command {cmd_str:?}");
     }
     info!("Request loop completed");
@@ -0,0 +1,12 @@
+// This is also used by libplatune-management so gain tags are read the same way when syncing
+// and during playback
+
+/// Parses a ReplayGain tag value. Gains are stored like "-6.52 dB" and peaks are plain numbers.
+pub(crate) fn parse_gain_value(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse().ok().filter(|v: &f64| v.is_finite())
+}
@@ -1,6 +1,11 @@
 mod audio_processor;
+mod crossfade;
 mod dto;
+mod equalizer;
 mod event_loop;
+mod gain_value;
+#[cfg(test)]
+mod output_tap;
 mod player;
 mod resolver;
 mod settings;
@@ -25,12 +30,20 @@ pub mod platune_player {
     use crate::dto::command::Command;
     use crate::dto::decoder_command::DecoderCommand;
     use crate::dto::decoder_response::DecoderResponse;
+    pub use crate::dto::equalizer_config::EqualizerPresets;
+    pub use crate::dto::equalizer_preset::{EqualizerBand, EqualizerPreset, FilterType};
+    pub use crate::dto::played_track::PlayedTrack;
     pub use crate::dto::player_event::PlayerEvent;
     use crate::dto::player_response::PlayerResponse;
     pub use crate::dto::player_state::PlayerState;
     pub use crate::dto::player_status::PlayerStatus;
-    pub use crate::dto::track::{Metadata, Track};
+    pub use crate::dto::repeat_mode::RepeatMode;
+    pub use crate::dto::replay_gain_mode::ReplayGainMode;
+    pub use crate::dto::track::{LyricLine, Metadata, ReplayGain, Track};
+    pub use crate::dto::track_range::TrackRange;
     use crate::event_loop::{decode_loop, main_loop};
+    #[cfg(test)]
+    use crate::output_tap::OutputTap;
     use crate::player::Player;
     pub use crate::settings::Settings;
     use crate::two_way_channel::{TwoWaySender, two_way_channel};
@@ -59,6 +72,18 @@ pub mod platune_player {
     }
 
     impl<H: Host + Send + 'static> PlatunePlayer<H> {
+        #[cfg(test)]
+        pub(crate) fn with_output_tap(
+            audio_backend: H,
+            settings: Settings,
+            output_tap: OutputTap,
+        ) -> Self {
+            OutputTap::set_current(Some(output_tap));
+            let player = Self::new(audio_backend, settings);
+            OutputTap::set_current(None);
+            player
+        }
+
         pub fn new(audio_backend: H, settings: Settings) -> Self {
             Self::clean_temp_files();
 
@@ -88,7 +113,11 @@ pub mod platune_player {
                 }
             };
             let host_id = audio_backend.id();
+            #[cfg(test)]
+            let output_tap = OutputTap::current();
             let decoder_fn = || {
+                #[cfg(test)]
+                OutputTap::set_current(output_tap);
                 decode_loop::<H>(queue_rx_, 1.0, decoder_rx, cmd_tx_, event_tx__, host_id);
             };
 
@@ -174,6 +203,7 @@ pub mod platune_player {
                 .await
             {
                 Ok(PlayerResponse::StatusResponse(track_status)) => track_status,
+                Ok(_) => unreachable!("Should only receive StatusResponse"),
                 Err(e) => return Err(PlayerError(format!("{e:?}"))),
             };
 
@@ -203,6 +233,55 @@ pub mod platune_player {
             }
         }
 
+        pub async fn get_equalizer_presets(&self) -> Result<EqualizerPresets, PlayerError> {
+            match self
+                .cmd_sender
+                .get_response(Command::GetEqualizerPresets)
+                .await
+            {
+                Ok(PlayerResponse::EqualizerResponse(presets)) => Ok(presets),
+                Ok(_) => unreachable!("Should only receive EqualizerResponse"),
+                Err(e) => Err(PlayerError(format!("{e:?}"))),
+            }
+        }
+
+        pub async fn save_equalizer_preset(
+            &self,
+            preset: EqualizerPreset,
+        ) -> Result<(), PlayerError> {
+            preset.validate().map_err(PlayerError)?;
+            self.cmd_sender
+                .send_async(Command::SaveEqualizerPreset(preset))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
+        pub async fn delete_equalizer_preset(&self, name: String) -> Result<(), PlayerError> {
+            self.cmd_sender
+                .send_async(Command::DeleteEqualizerPreset(name))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
+        /// Activates the preset with the given name. Passing `None` disables the equalizer.
+        pub async fn set_equalizer_preset(&self, name: Option<String>) -> Result<(), PlayerError> {
+            if let Some(name) = &name {
+                let presets = self.get_equalizer_presets().await?;
+                if !presets
+                    .presets
+                    .iter()
+                    .chain(presets.built_in.iter())
+                    .any(|p| p.name.eq_ignore_ascii_case(name))
+                {
+                    return Err(PlayerError(format!("Equalizer preset {name} not found")));
+                }
+            }
+            self.cmd_sender
+                .send_async(Command::SetEqualizerPreset(name))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
         pub async fn stop(&self) -> Result<(), PlayerError> {
             info!("sending stop command");
             self.cmd_sender
@@ -238,6 +317,34 @@ pub mod platune_player {
                 .map_err(|e| PlayerError(format!("{e:?}")))
         }
 
+        pub async fn set_repeat_mode(&self, repeat_mode: RepeatMode) -> Result<(), PlayerError> {
+            self.cmd_sender
+                .send_async(Command::SetRepeatMode(repeat_mode))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
+        pub async fn set_shuffle(&self, shuffle: bool) -> Result<(), PlayerError> {
+            self.cmd_sender
+                .send_async(Command::SetShuffle(shuffle))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
+        pub async fn set_crossfade(&self, crossfade: Duration) -> Result<(), PlayerError> {
+            self.cmd_sender
+                .send_async(Command::SetCrossfade(crossfade))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
+        pub async fn set_replay_gain_mode(&self, mode: ReplayGainMode) -> Result<(), PlayerError> {
+            self.cmd_sender
+                .send_async(Command::SetReplayGainMode(mode))
+                .await
+                .map_err(|e| PlayerError(format!("{e:?}")))
+        }
+
         pub async fn next(&self) -> Result<(), PlayerError> {
             self.cmd_sender
                 .send_async(Command::Next)
@@ -254,11 +361,7 @@ pub mod platune_player {
 
         pub async fn join(self) -> Result<(), PlayerError> {
             info!("Joining player instance");
-            self.cmd_sender
-                .send_async(Command::Stop)
-                .await
-                .map_err(|e| PlayerError(format!("{e:?}")))?;
-            info!("Sent stop command");
+            // The main loop saves the player state and stops playback before shutting down
             self.cmd_sender
                 .send_async(Command::Shutdown)
                 .await
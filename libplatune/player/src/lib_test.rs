@@ -1,3 +1,467 @@
+use std::collections::BTreeMap;
+use std::env::current_dir;
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+use crate::MockHost;
+use crate::dto::saved_state::SavedState;
+use crate::output_tap::{OutputEvent, OutputTap};
+use crate::platune_player::{
+    AudioStatus, LyricLine, Metadata, PlatunePlayer, PlayerEvent, RepeatMode, Settings, Track,
+};
+
+fn get_track(song: &str) -> Track {
+    let path = current_dir()
+        .unwrap()
+        .join("..")
+        .join("test_assets")
+        .join(song);
+    Track {
+        url: path.to_string_lossy().to_string(),
+        metadata: None,
+    }
+}
+
+async fn next_event(receiver: &mut broadcast::Receiver<PlayerEvent>) -> PlayerEvent {
+    timeout(Duration::from_secs(30), receiver.recv())
+        .await
+        .expect("timed out waiting for player event")
+        .expect("player event channel closed")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_gapless_transition() {
+    let output_tap = OutputTap::default();
+    let player = PlatunePlayer::with_output_tap(
+        MockHost::default(),
+        Settings::default(),
+        output_tap.clone(),
+    );
+    let mut receiver = player.subscribe();
+    player
+        .set_queue(vec![get_track("test.mp3"), get_track("test2.mp3")])
+        .await
+        .unwrap();
+
+    let mut durations = BTreeMap::new();
+    loop {
+        match next_event(&mut receiver).await {
+            PlayerEvent::TrackChanged(state) => {
+                if let Some(duration) = state.metadata.and_then(|m| m.duration) {
+                    durations.insert(state.queue_position, duration);
+                }
+            }
+            PlayerEvent::QueueEnded(_) => break,
+            _ => {}
+        }
+    }
+    player.join().await.unwrap();
+    assert_eq!(2, durations.len());
+
+    // Split the rendered output into the audio written for each source
+    let mut rendered: Vec<Duration> = vec![];
+    let mut flushed_between = false;
+    for event in output_tap.events() {
+        match event {
+            OutputEvent::SourceStarted => rendered.push(Duration::ZERO),
+            OutputEvent::Written(duration) => *rendered.last_mut().unwrap() += duration,
+            OutputEvent::Flushed => {
+                if rendered.len() == 1 {
+                    flushed_between = true;
+                }
+            }
+        }
+    }
+    // The second track should be written directly after the first one without the output being
+    // stopped in between, and neither track should be padded with extra audio
+    assert!(!flushed_between, "output was flushed between tracks");
+    assert_eq!(2, rendered.len());
+    for (rendered, duration) in rendered.iter().zip(durations.values()) {
+        assert!(
+            rendered.abs_diff(*duration) <= Duration::from_millis(100),
+            "rendered {rendered:?} of audio for a {duration:?} track"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_crossfade_overlap() {
+    let crossfade = Duration::from_secs(1);
+    let output_tap = OutputTap::default();
+    let player = PlatunePlayer::with_output_tap(
+        MockHost::default(),
+        Settings {
+            crossfade,
+            ..Default::default()
+        },
+        output_tap.clone(),
+    );
+    let mut receiver = player.subscribe();
+    player
+        .set_queue(vec![get_track("test.mp3"), get_track("test2.mp3")])
+        .await
+        .unwrap();
+
+    loop {
+        if let PlayerEvent::QueueEnded(_) = next_event(&mut receiver).await {
+            break;
+        }
+    }
+    player.join().await.unwrap();
+
+    let mut sources = 0;
+    let mut overlap = Duration::ZERO;
+    for event in output_tap.events() {
+        match event {
+            OutputEvent::SourceStarted => sources += 1,
+            OutputEvent::Written(_) => {}
+            OutputEvent::Overlap(duration) => {
+                // The previous track can only overlap with the one that follows it
+                assert_eq!(2, sources);
+                overlap += duration;
+            }
+            OutputEvent::Flushed => {
+                assert_ne!(1, sources, "output was flushed during the crossfade");
+            }
+        }
+    }
+    // Both tracks should be audible at the same time instead of fading through silence
+    assert!(
+        overlap >= crossfade / 2,
+        "tracks only overlapped for {overlap:?}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_track_range() {
+    let output_tap = OutputTap::default();
+    let player = PlatunePlayer::with_output_tap(
+        MockHost::default(),
+        Settings::default(),
+        output_tap.clone(),
+    );
+    let mut receiver = player.subscribe();
+    let track = get_track("test2.mp3");
+    let with_range = |fragment: &str| Track {
+        url: format!("{}{fragment}", track.url),
+        metadata: None,
+    };
+    player
+        .set_queue(vec![
+            track.clone(),
+            with_range("#t=3"),
+            with_range("#t=1,2.5"),
+        ])
+        .await
+        .unwrap();
+
+    let mut durations = BTreeMap::new();
+    loop {
+        match next_event(&mut receiver).await {
+            PlayerEvent::TrackChanged(state) => {
+                if let Some(duration) = state.metadata.and_then(|m| m.duration) {
+                    durations.insert(state.queue_position, duration);
+                }
+            }
+            PlayerEvent::QueueEnded(_) => break,
+            _ => {}
+        }
+    }
+    player.join().await.unwrap();
+    let file_duration = durations[&0];
+
+    let mut rendered: Vec<Duration> = vec![];
+    for event in output_tap.events() {
+        match event {
+            OutputEvent::SourceStarted => rendered.push(Duration::ZERO),
+            OutputEvent::Written(duration) => *rendered.last_mut().unwrap() += duration,
+            _ => {}
+        }
+    }
+    // A range without an end plays from its start until the end of the file, so only the part
+    // before the start offset should be skipped
+    let expected = [
+        file_duration,
+        file_duration - Duration::from_secs(3),
+        Duration::from_millis(1500),
+    ];
+    // Reported durations should only cover the range
+    assert_eq!(
+        expected.to_vec(),
+        durations.into_values().collect::<Vec<_>>()
+    );
+    assert_eq!(expected.len(), rendered.len());
+    for (rendered, expected) in rendered.iter().zip(expected) {
+        assert!(
+            rendered.abs_diff(expected) <= Duration::from_millis(100),
+            "rendered {rendered:?} of audio, expected {expected:?}"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_track_played() {
+    let player = PlatunePlayer::new(MockHost::default(), Settings::default());
+    let mut receiver = player.subscribe();
+    player
+        .set_queue(vec![get_track("test.mp3"), get_track("test2.mp3")])
+        .await
+        .unwrap();
+
+    loop {
+        if let PlayerEvent::StartQueue(_) = next_event(&mut receiver).await {
+            break;
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    player.next().await.unwrap();
+
+    let mut played = vec![];
+    loop {
+        match next_event(&mut receiver).await {
+            PlayerEvent::TrackPlayed(track) => played.push(track),
+            PlayerEvent::QueueEnded(_) => break,
+            _ => {}
+        }
+    }
+
+    assert_eq!(2, played.len());
+    assert!(played[0].url.ends_with("test.mp3"));
+    assert!(played[0].skipped);
+    assert!(played[0].listened >= Duration::from_millis(100));
+    assert!(played[1].url.ends_with("test2.mp3"));
+    assert!(!played[1].skipped);
+
+    player.join().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_lyric_lines() {
+    let player = PlatunePlayer::new(MockHost::default(), Settings::default());
+    let mut receiver = player.subscribe();
+    let line = |start_millis, text: &str| LyricLine {
+        start: Duration::from_millis(start_millis),
+        text: text.to_owned(),
+    };
+    let mut track = get_track("test2.mp3");
+    track.metadata = Some(Metadata {
+        // Lines should be sorted before playback starts
+        lyrics: Some(vec![line(2500, "second"), line(500, "first")]),
+        ..Default::default()
+    });
+    player.set_queue(vec![track]).await.unwrap();
+
+    let mut lines = vec![];
+    loop {
+        match next_event(&mut receiver).await {
+            PlayerEvent::LyricLine(line) => lines.push(line),
+            PlayerEvent::QueueEnded(_) => break,
+            _ => {}
+        }
+    }
+
+    assert_eq!(vec![line(500, "first"), line(2500, "second")], lines);
+
+    player.join().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_restore_saved_queue() {
+    let state_path =
+        std::env::temp_dir().join(format!("platune_state_test_{}.json", std::process::id()));
+    let settings = Settings {
+        state_path: Some(state_path.clone()),
+        ..Default::default()
+    };
+    let mut first = get_track("test.mp3");
+    // The queued URL should be saved instead of the path it resolves to
+    first.url = format!("file://{}", first.url);
+    let queue = vec![first, get_track("test2.mp3")];
+
+    let player = PlatunePlayer::new(MockHost::default(), settings.clone());
+    let mut receiver = player.subscribe();
+    player.set_queue(queue.clone()).await.unwrap();
+    player.next().await.unwrap();
+    loop {
+        if let PlayerEvent::TrackChanged(state) = next_event(&mut receiver).await
+            && state.queue_position == 1
+        {
+            break;
+        }
+    }
+    player.join().await.unwrap();
+
+    let saved = SavedState::read(&state_path).unwrap().unwrap();
+    assert_eq!(
+        queue.iter().map(|t| t.url.as_str()).collect::<Vec<_>>(),
+        saved
+            .queue
+            .iter()
+            .map(|t| t.url.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, saved.queue_position);
+
+    // Status requests are handled once the current track has been restored, the rest of the
+    // queue is filled in afterwards
+    let player = PlatunePlayer::new(MockHost::default(), settings);
+    let status = player.get_current_status().await.unwrap();
+    assert_eq!(AudioStatus::Paused, status.track_status.status);
+    let state = status.track_status.state;
+    assert!(state.queue()[state.queue_position].ends_with("test2.mp3"));
+    let state = timeout(Duration::from_secs(10), async {
+        loop {
+            let status = player.get_current_status().await.unwrap();
+            if status.track_status.state.queue().len() == 2 {
+                return status.track_status.state;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("saved queue was not restored");
+    assert_eq!(1, state.queue_position);
+
+    player.join().await.unwrap();
+    let _ = std::fs::remove_file(state_path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_restore_missing_last_track() {
+    let state_path = std::env::temp_dir().join(format!(
+        "platune_missing_state_test_{}.json",
+        std::process::id()
+    ));
+    let settings = Settings {
+        state_path: Some(state_path.clone()),
+        ..Default::default()
+    };
+    let mut missing = get_track("test.mp3");
+    missing.url = missing.url.replace("test.mp3", "missing.mp3");
+    SavedState {
+        queue: vec![get_track("test.mp3"), missing],
+        queue_position: 1,
+        volume: 1.0,
+        position: Some(Duration::from_secs(1)),
+    }
+    .write(&state_path)
+    .unwrap();
+
+    // The closest available track should be loaded instead of pointing past the end of the queue
+    let player = PlatunePlayer::new(MockHost::default(), settings);
+    let status = player.get_current_status().await.unwrap();
+    assert_eq!(AudioStatus::Paused, status.track_status.status);
+    assert_eq!(0, status.track_status.state.queue_position);
+    assert_eq!(1, status.track_status.state.queue().len());
+
+    player.join().await.unwrap();
+    let _ = std::fs::remove_file(state_path);
+}
+
+async fn track_positions(
+    receiver: &mut broadcast::Receiver<PlayerEvent>,
+    count: usize,
+) -> Vec<usize> {
+    let mut positions: Vec<usize> = vec![];
+    while positions.len() < count {
+        if let PlayerEvent::TrackChanged(state) = next_event(receiver).await
+            && positions.last() != Some(&state.queue_position)
+        {
+            positions.push(state.queue_position);
+        }
+    }
+    positions
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_repeat_all() {
+    let player = PlatunePlayer::new(MockHost::default(), Settings::default());
+    let mut receiver = player.subscribe();
+    player.set_repeat_mode(RepeatMode::All).await.unwrap();
+    player
+        .set_queue(vec![get_track("test.mp3"), get_track("test2.mp3")])
+        .await
+        .unwrap();
+
+    // The queue should start over from the beginning after the last track
+    assert_eq!(vec![0, 1, 0], track_positions(&mut receiver, 3).await);
+
+    player.join().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_repeat_one() {
+    let player = PlatunePlayer::new(MockHost::default(), Settings::default());
+    let mut receiver = player.subscribe();
+    player.set_repeat_mode(RepeatMode::One).await.unwrap();
+    player
+        .set_queue(vec![get_track("test.mp3"), get_track("test2.mp3")])
+        .await
+        .unwrap();
+
+    let mut played = vec![];
+    while played.len() < 2 {
+        if let PlayerEvent::TrackPlayed(track) = next_event(&mut receiver).await {
+            played.push(track);
+        }
+    }
+    assert!(
+        played
+            .iter()
+            .all(|p| p.url.ends_with("test.mp3") && !p.skipped)
+    );
+    let status = player.get_current_status().await.unwrap();
+    assert_eq!(0, status.track_status.state.queue_position);
+
+    // Skipping should still move past the repeated track
+    player.next().await.unwrap();
+    let status = player.get_current_status().await.unwrap();
+    assert_eq!(1, status.track_status.state.queue_position);
+
+    player.join().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_shuffle_keeps_current_track() {
+    let player = PlatunePlayer::new(MockHost::default(), Settings::default());
+    let queue = vec![
+        get_track("test.mp3"),
+        get_track("test2.mp3"),
+        get_track("test.mp3"),
+        get_track("test3.mp3"),
+    ];
+    player.set_queue(queue).await.unwrap();
+    player.next().await.unwrap();
+    player.next().await.unwrap();
+
+    player.set_shuffle(true).await.unwrap();
+    let state = player
+        .get_current_status()
+        .await
+        .unwrap()
+        .track_status
+        .state;
+    assert!(state.shuffle);
+    assert_eq!(0, state.queue_position);
+    assert_eq!(4, state.queue().len());
+    assert!(state.queue()[0].ends_with("test.mp3"));
+
+    // The current track is a duplicate of the first one, but its own position should be restored
+    player.set_shuffle(false).await.unwrap();
+    let state = player
+        .get_current_status()
+        .await
+        .unwrap()
+        .track_status
+        .state;
+    assert!(!state.shuffle);
+    assert_eq!(2, state.queue_position);
+
+    player.join().await.unwrap();
+}
+
 // use crate::mock_output::*;
 // use crate::settings::Settings;
 // use assert_matches::*;
@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum OutputEvent {
+    SourceStarted,
+    // Audio from the current source that was written to the output
+    Written(Duration),
+    // Audio where the previous track was still audible underneath the current one
+    Overlap(Duration),
+    Flushed,
+}
+
+/// Records what gets written to the output device so the rendered audio can be inspected
+/// without relying on timing
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OutputTap(Arc<Mutex<Vec<OutputEvent>>>);
+
+thread_local! {
+    static OUTPUT_TAP: RefCell<Option<OutputTap>> = const { RefCell::new(None) };
+}
+
+impl OutputTap {
+    /// Sets the tap that events on the current thread are recorded to. Players pass the tap from
+    /// the thread that created them on to their decoder thread.
+    pub(crate) fn set_current(tap: Option<OutputTap>) {
+        OUTPUT_TAP.set(tap);
+    }
+
+    pub(crate) fn current() -> Option<OutputTap> {
+        OUTPUT_TAP.with_borrow(Clone::clone)
+    }
+
+    pub(crate) fn events(&self) -> Vec<OutputEvent> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn push(&self, event: OutputEvent) {
+        let mut events = self.0.lock().unwrap();
+        // Merge consecutive writes so the log doesn't grow with every packet
+        match (events.last_mut(), &event) {
+            (Some(OutputEvent::Written(total)), OutputEvent::Written(duration))
+            | (Some(OutputEvent::Overlap(total)), OutputEvent::Overlap(duration)) => {
+                *total += *duration;
+            }
+            _ => events.push(event),
+        }
+    }
+}
+
+/// Records the event to the tap for the current thread, if there is one
+pub(crate) fn record(event: OutputEvent) {
+    OUTPUT_TAP.with_borrow(|tap| {
+        if let Some(tap) = tap {
+            tap.push(event);
+        }
+    });
+}
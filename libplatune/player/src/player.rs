@@ -1,9 +1,10 @@
 use std::collections::VecDeque;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use eyre::bail;
 use flume::{Receiver, Sender};
+use rand::seq::SliceRandom;
 use stream_download::registry::{Input, Registry};
 use tap::TapFallible;
 use tokio::sync::broadcast;
@@ -14,12 +15,19 @@ use crate::dto::audio_status::AudioStatus;
 use crate::dto::command::Command;
 use crate::dto::decoder_command::DecoderCommand;
 use crate::dto::decoder_response::DecoderResponse;
+use crate::dto::equalizer_config::{EqualizerConfig, EqualizerPresets};
+use crate::dto::equalizer_preset::EqualizerPreset;
+use crate::dto::played_track::PlayedTrack;
 use crate::dto::player_event::PlayerEvent;
 use crate::dto::player_response::PlayerResponse;
 use crate::dto::player_state::PlayerState;
 use crate::dto::player_status::TrackStatus;
 use crate::dto::queue_source::QueueSource;
+use crate::dto::repeat_mode::RepeatMode;
+use crate::dto::replay_gain_mode::ReplayGainMode;
+use crate::dto::saved_state::SavedState;
 use crate::dto::track::{Metadata, Track};
+use crate::dto::track_range::TrackRange;
 use crate::platune_player::SeekMode;
 use crate::resolver::{
     DefaultUrlResolver, FileSourceResolver, HttpSourceResolver, MetadataSource, TrackInput,
@@ -36,6 +44,11 @@ enum AppendError {
 
 pub(crate) struct Player {
     state: PlayerState,
+    // Queue in its original order, used to restore the order when shuffle is turned off
+    original_queue: Vec<TrackInput>,
+    // Index in the original queue of each track in the current queue. The queue can contain the
+    // same track more than once, so this is needed to find the original position again.
+    queue_order: Vec<usize>,
     event_tx: broadcast::Sender<PlayerEvent>,
     queued_count: usize,
     queue_tx: Sender<QueueSource>,
@@ -43,10 +56,18 @@ pub(crate) struct Player {
     cmd_sender: TwoWaySender<DecoderCommand, DecoderResponse>,
     settings: Settings,
     pending_volume: Option<f32>,
+    pending_start_paused_at: Option<Duration>,
+    equalizer: EqualizerConfig,
+    // Start of the current uninterrupted stretch of playback for the current track
+    listen_start: Option<Instant>,
+    listened: Duration,
     device_name: Option<String>,
     url_resolver: Registry<eyre::Result<Vec<Input>>>,
     source_resolver: Registry<eyre::Result<(MetadataSource, CancellationToken)>>,
     stream_cancellation_tokens: VecDeque<CancellationToken>,
+    // Saved tracks around the restored one that still need to be resolved
+    restore_previous: Vec<Track>,
+    restore_next: VecDeque<Track>,
 }
 
 impl Player {
@@ -59,6 +80,16 @@ impl Player {
         settings: Settings,
         device_name: Option<String>,
     ) -> Self {
+        let equalizer = settings
+            .equalizer_path
+            .as_deref()
+            .and_then(|path| {
+                EqualizerConfig::read(path)
+                    .tap_err(|e| error!("Error reading equalizer presets: {e:?}"))
+                    .ok()
+                    .flatten()
+            })
+            .unwrap_or_default();
         Self {
             event_tx: event_tx.clone(),
             state: PlayerState {
@@ -67,15 +98,25 @@ impl Player {
                 queue_position: 0,
                 status: AudioStatus::Stopped,
                 metadata: None,
+                repeat_mode: RepeatMode::Off,
+                shuffle: false,
             },
+            original_queue: vec![],
+            queue_order: vec![],
             queued_count: 0,
             queue_tx,
             queue_rx,
             cmd_sender,
             settings,
             pending_volume: None,
+            pending_start_paused_at: None,
+            equalizer,
+            listen_start: None,
+            listened: Duration::ZERO,
             device_name,
             stream_cancellation_tokens: VecDeque::new(),
+            restore_previous: Vec::new(),
+            restore_next: VecDeque::new(),
             url_resolver: Registry::new()
                 .entry(YtDlpUrlResolver::new())
                 .entry(DefaultUrlResolver::new()),
@@ -102,7 +143,14 @@ impl Player {
         Some(reader)
     }
 
-    async fn append_file(&mut self, input: TrackInput) -> Result<(), AppendError> {
+    // Sources that are started explicitly need to wait for the player to confirm initialization.
+    // Sources that follow the current track start as soon as the previous one finishes so there's
+    // no gap between them.
+    async fn append_file(
+        &mut self,
+        input: TrackInput,
+        wait_for_init: bool,
+    ) -> Result<(), AppendError> {
         match self.get_source(input.input.clone()).await {
             Some(source) => {
                 info!("Sending source {input:?}");
@@ -113,6 +161,10 @@ impl Player {
                         has_content_length: source.has_content_length,
                         settings: self.settings.clone(),
                         volume: self.pending_volume.take(),
+                        start_paused_at: self.pending_start_paused_at.take(),
+                        wait_for_init,
+                        equalizer: self.equalizer.active(),
+                        range: input.range,
                         // Metadata precedence:
                         // 1. Info supplied by the user
                         // 2. Extracted from the source
@@ -147,58 +199,75 @@ impl Player {
     }
 
     fn remove_from_queue(&mut self, input: &TrackInput) {
-        let queue = self.state.queue.clone();
-        self.state.queue = queue.into_iter().filter(|q| q != input).collect();
+        let removed = self
+            .original_queue
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| *q == input)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        self.queue_order = self
+            .queue_order
+            .iter()
+            .copied()
+            .filter(|i| !removed.contains(i))
+            .map(|i| i - removed.iter().filter(|r| **r < i).count())
+            .collect();
+        self.state.queue.retain(|q| q != input);
+        self.original_queue.retain(|q| q != input);
+    }
+
+    fn set_original_queue(&mut self, queue: Vec<TrackInput>) {
+        self.queue_order = (0..queue.len()).collect();
+        self.original_queue = queue;
+    }
+
+    fn ordered_queue(&self) -> Vec<TrackInput> {
+        self.queue_order
+            .iter()
+            .map(|i| self.original_queue[*i].clone())
+            .collect()
     }
 
     async fn start(&mut self) -> Result<(), Option<AppendError>> {
-        let mut success: Result<(), Option<AppendError>> = Err(None);
         // Keep trying until a valid source is found or we reach the end of the queue
-        while success.is_err() {
-            match self.get_current() {
-                Some(input) => {
-                    match self.append_file(input.clone()).await {
-                        Ok(_) => {
-                            success = Ok(());
-                        }
-                        Err(AppendError::SendFailed) => return Err(Some(AppendError::SendFailed)),
-                        Err(AppendError::SourceUnavailable) => {}
-                    }
-
-                    if let Some(path) = self.get_next() {
-                        match self.append_file(path).await {
-                            Ok(_) => {
-                                success = Ok(());
-                            }
-                            Err(AppendError::SendFailed) => {
-                                return Err(Some(AppendError::SendFailed));
-                            }
-                            Err(AppendError::SourceUnavailable) => {}
-                        }
-                    }
-                }
-                None => return Err(None),
+        loop {
+            let Some(input) = self.get_current() else {
+                return Err(None);
+            };
+            match self.append_file(input.clone(), true).await {
+                Ok(_) => {}
+                Err(AppendError::SendFailed) => return Err(Some(AppendError::SendFailed)),
+                // Unavailable sources are removed from the queue so the current position now
+                // points to the next track
+                Err(AppendError::SourceUnavailable) => continue,
             }
 
             info!("Waiting for decoder after starting");
-            if success.is_ok() {
-                let decoder_result = self.wait_for_decoder().await;
-                if decoder_result == DecoderResponse::InitializationFailed {
-                    warn!("received initialization failed message");
-                    success = Err(Some(AppendError::SourceUnavailable));
-                    let input = self.get_current().expect("current track missing");
-                    self.stream_cancellation_tokens.pop_front();
-                    self.queued_count -= 1;
-
-                    self.remove_from_queue(&input);
-                    continue;
-                }
+            let decoder_result = self.wait_for_decoder().await;
+            if decoder_result == DecoderResponse::InitializationFailed {
+                warn!("received initialization failed message");
+                self.stream_cancellation_tokens.pop_front();
+                self.queued_count -= 1;
 
-                self.state.status = AudioStatus::Playing;
+                self.remove_from_queue(&input);
+                continue;
+            }
+
+            self.state.status = AudioStatus::Playing;
+            self.begin_listen();
+            break;
+        }
+
+        // Queue up the next track ahead of time so it can start as soon as the current one ends
+        if let Some(next) = self.get_next() {
+            match self.append_file(next, false).await {
+                Err(AppendError::SendFailed) => return Err(Some(AppendError::SendFailed)),
+                Ok(_) | Err(AppendError::SourceUnavailable) => {}
             }
         }
 
-        success
+        Ok(())
     }
 
     async fn wait_for_decoder(&self) -> DecoderResponse {
@@ -241,6 +310,7 @@ impl Player {
             .tap_err(|e| error!("Error sending play command {e:?}"))?;
 
         self.state.status = AudioStatus::Playing;
+        self.resume_listen();
         self.event_tx
             .send(PlayerEvent::Resume(self.state.clone()))
             .unwrap_or_default();
@@ -260,6 +330,7 @@ impl Player {
             .tap_err(|e| error!("Error sending pause command {e:?}"))?;
 
         self.state.status = AudioStatus::Paused;
+        self.pause_listen();
         self.event_tx
             .send(PlayerEvent::Pause(self.state.clone()))
             .unwrap_or_default();
@@ -323,9 +394,11 @@ impl Player {
     }
 
     pub(crate) async fn stop(&mut self) -> Result<(), String> {
+        self.end_listen(true);
         self.reset_queue().await?;
         self.state.queue_position = 0;
         self.state.queue = vec![];
+        self.set_original_queue(vec![]);
         self.state.metadata = None;
         self.queued_count = 0;
         self.event_tx
@@ -335,6 +408,175 @@ impl Player {
         Ok(())
     }
 
+    pub(crate) async fn shutdown(&mut self) -> Result<(), String> {
+        // Save before stopping so the snapshot still contains the queue
+        self.save_state().await;
+        self.stop().await
+    }
+
+    pub(crate) fn is_playing(&self) -> bool {
+        self.state.status == AudioStatus::Playing
+    }
+
+    pub(crate) async fn save_state(&self) {
+        let Some(state_path) = &self.settings.state_path else {
+            return;
+        };
+        // Tracks that haven't been restored yet are kept so they aren't lost if we shut down
+        // before they're resolved
+        let queue = self.state.queue.iter().map(|q| Track {
+            url: q.saved_url(),
+            metadata: q.metadata.clone(),
+        });
+        let saved_state = SavedState {
+            queue: self
+                .restore_previous
+                .iter()
+                .cloned()
+                .chain(queue)
+                .chain(self.restore_next.iter().cloned())
+                .collect(),
+            queue_position: self.restore_previous.len() + self.state.queue_position,
+            volume: self.state.volume,
+            position: self.current_position().await,
+        };
+        let _ = saved_state
+            .write(state_path)
+            .inspect_err(|e| error!("Error saving player state: {e:?}"));
+    }
+
+    pub(crate) async fn restore_state(&mut self) -> Result<(), String> {
+        let Some(state_path) = self.settings.state_path.clone() else {
+            return Ok(());
+        };
+        let saved_state = match SavedState::read(&state_path) {
+            Ok(Some(saved_state)) => saved_state,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                warn!("Error reading saved player state: {e:?}");
+                return Ok(());
+            }
+        };
+        info!("Restoring saved player state");
+
+        self.state.volume = saved_state.volume;
+        self.pending_volume = Some(saved_state.volume);
+
+        let queue = saved_state.queue;
+        if queue.is_empty() {
+            return Ok(());
+        }
+        let saved_position = saved_state.queue_position.min(queue.len() - 1);
+        // Only the current track is restored here so commands aren't blocked while the whole
+        // queue is resolved. If it's no longer available, the closest track after it is used
+        // instead, or the closest one before it if it was at the end of the queue.
+        let mut restored = None;
+        for i in (saved_position..queue.len()).chain((0..saved_position).rev()) {
+            let Ok(items) = self
+                .find_urls(queue[i].clone())
+                .await
+                .tap_err(|e| error!("error finding urls: {e:?}"))
+            else {
+                continue;
+            };
+            // The saved playback position only applies to the saved track
+            let position = saved_state.position.filter(|_| i == saved_position);
+            self.set_original_queue(items.clone());
+            self.pending_start_paused_at = Some(position.unwrap_or_default());
+            self.set_queue_internal(items, 0).await?;
+            self.pending_start_paused_at = None;
+            // Sources that fail to load are removed from the queue when starting it
+            if self.get_current().is_some() {
+                restored = Some(i);
+                break;
+            }
+        }
+        let Some(restored) = restored else {
+            return Ok(());
+        };
+
+        // Tracks that were already tried while looking for the current one are skipped
+        let (previous, next) = if restored >= saved_position {
+            (0..saved_position, restored + 1..queue.len())
+        } else {
+            (0..restored, 0..0)
+        };
+        self.restore_previous = queue[previous].to_vec();
+        self.restore_next = queue[next].iter().cloned().collect();
+        self.event_tx
+            .send(PlayerEvent::StartQueue(self.state.clone()))
+            .unwrap_or_default();
+        // The decoder should already be paused, but a track may have been skipped if it failed
+        // to initialize
+        self.pause().await
+    }
+
+    pub(crate) fn is_restoring(&self) -> bool {
+        !self.restore_previous.is_empty() || !self.restore_next.is_empty()
+    }
+
+    /// Resolves one of the saved tracks that weren't restored on startup and adds it back to the
+    /// queue around the current track
+    pub(crate) async fn restore_next_track(&mut self) {
+        let (track, previous) = match self.restore_next.pop_front() {
+            Some(track) => (track, false),
+            None => match self.restore_previous.pop() {
+                Some(track) => (track, true),
+                None => return,
+            },
+        };
+        let Ok(items) = self
+            .find_urls(track)
+            .await
+            .tap_err(|e| error!("error finding urls: {e:?}"))
+        else {
+            return;
+        };
+
+        if previous {
+            // Tracks before the current one are restored starting with the closest one
+            for item in items.into_iter().rev() {
+                self.state.queue.insert(0, item.clone());
+                self.original_queue.insert(0, item);
+                for i in &mut self.queue_order {
+                    *i += 1;
+                }
+                self.queue_order.insert(0, 0);
+                self.state.queue_position += 1;
+            }
+        } else {
+            for item in items {
+                self.push_to_queue(item).await;
+            }
+        }
+        self.event_tx
+            .send(PlayerEvent::QueueUpdated(self.state.clone()))
+            .unwrap_or_default();
+    }
+
+    async fn current_position(&self) -> Option<Duration> {
+        if self.state.status == AudioStatus::Stopped {
+            return None;
+        }
+        match self
+            .cmd_sender
+            .get_response(DecoderCommand::GetCurrentPosition)
+            .await
+        {
+            Ok(DecoderResponse::CurrentPositionResponse(current_position)) => {
+                Some(current_position.position)
+            }
+            Ok(response) => {
+                error!("Got unexpected decoder response: {response:?}");
+                None
+            }
+            Err(e) => {
+                error!("Error getting current position: {e:?}");
+                None
+            }
+        }
+    }
+
     pub(crate) fn get_current_status(&self) -> TrackStatus {
         TrackStatus {
             status: self.state.status.clone(),
@@ -368,11 +610,11 @@ impl Player {
         self.queued_count -= 1;
         self.stream_cancellation_tokens.pop_front();
         info!("Queued count {}", self.queued_count);
+        self.end_listen(false);
 
-        if self.state.queue_position < self.state.queue.len() - 1 {
-            info!("Waiting for decoder after ended event");
-            self.wait_for_decoder().await;
-            self.state.queue_position += 1;
+        if let Some(next_position) = self.next_position() {
+            self.state.queue_position = next_position;
+            self.begin_listen();
             info!(
                 "Incrementing position. New position: {}",
                 self.state.queue_position
@@ -387,7 +629,42 @@ impl Player {
         }
 
         if let Some(file) = self.get_next() {
-            self.append_file(file).await.unwrap_or_default();
+            self.append_file(file, false).await.unwrap_or_default();
+        }
+    }
+
+    fn begin_listen(&mut self) {
+        self.listen_start = Some(Instant::now());
+        self.listened = Duration::ZERO;
+    }
+
+    fn pause_listen(&mut self) {
+        if let Some(listen_start) = self.listen_start.take() {
+            self.listened += listen_start.elapsed();
+        }
+    }
+
+    fn resume_listen(&mut self) {
+        if self.listen_start.is_none() {
+            self.listen_start = Some(Instant::now());
+        }
+    }
+
+    fn end_listen(&mut self, skipped: bool) {
+        self.pause_listen();
+        let listened = std::mem::take(&mut self.listened);
+        if listened.is_zero() {
+            return;
+        }
+        if let Some(current) = self.get_current() {
+            self.event_tx
+                .send(PlayerEvent::TrackPlayed(PlayedTrack {
+                    url: current.url(),
+                    metadata: current.metadata,
+                    listened,
+                    skipped,
+                }))
+                .unwrap_or_default();
         }
     }
 
@@ -406,15 +683,20 @@ impl Player {
     }
 
     async fn find_urls(&mut self, item: Track) -> eyre::Result<Vec<TrackInput>> {
-        let Some(items) = self.url_resolver.find_match(&item.url).await else {
+        let (url, range) = TrackRange::from_url(&item.url);
+        let Some(items) = self.url_resolver.find_match(url).await else {
             bail!("no resolver found for {}", item.url);
         };
         let items = items?;
+        // Playlists expand into multiple tracks, so those have to be saved individually
+        let queued_url = (items.len() == 1).then_some(item.url);
         Ok(items
             .into_iter()
             .map(|i| TrackInput {
                 input: i,
                 metadata: item.metadata.clone(),
+                range,
+                queued_url: queued_url.clone(),
             })
             .collect())
     }
@@ -432,7 +714,11 @@ impl Player {
             }
         }
 
-        self.set_queue_internal(new_queue, 0).await?;
+        self.set_original_queue(new_queue);
+        if self.state.shuffle {
+            self.queue_order.shuffle(&mut rand::rng());
+        }
+        self.set_queue_internal(self.ordered_queue(), 0).await?;
         self.event_tx
             .send(PlayerEvent::StartQueue(self.state.clone()))
             .unwrap_or_default();
@@ -445,6 +731,10 @@ impl Player {
         queue: Vec<TrackInput>,
         start_position: usize,
     ) -> Result<(), String> {
+        self.end_listen(true);
+        // Saved tracks that haven't been restored yet belong to the queue being replaced
+        self.restore_previous.clear();
+        self.restore_next.clear();
         // Don't need to send stop signal if no sources are playing
         if self.queued_count > 0 {
             self.reset_queue().await?;
@@ -478,18 +768,13 @@ impl Player {
     async fn add_one_to_queue(&mut self, song: TrackInput) -> Result<(), String> {
         // Queue is not currently running, need to start it
         if self.queued_count == 0 {
+            self.set_original_queue(vec![song.clone()]);
             self.set_queue_internal(vec![song], 0).await?;
             self.event_tx
                 .send(PlayerEvent::StartQueue(self.state.clone()))
                 .unwrap_or_default();
         } else {
-            self.state.queue.push(song.clone());
-            // Special case: if we started with only one song, then the new song will never get
-            // triggered by the ended event so we need to add it here explicitly
-            if self.queued_count == 1 {
-                self.append_file(song).await.unwrap_or_default();
-            }
-
+            self.push_to_queue(song).await;
             self.event_tx
                 .send(PlayerEvent::QueueUpdated(self.state.clone()))
                 .unwrap_or_default();
@@ -498,12 +783,208 @@ impl Player {
         Ok(())
     }
 
+    async fn push_to_queue(&mut self, song: TrackInput) {
+        let was_last = self.state.queue_position + 1 == self.state.queue.len();
+        self.state.queue.push(song.clone());
+        self.queue_order.push(self.original_queue.len());
+        self.original_queue.push(song);
+        // Special case: if we're on the last song, then the new song will never get
+        // triggered by the ended event so we need to add it here explicitly
+        if was_last && self.queued_count > 0 {
+            self.requeue_next().await;
+        }
+    }
+
     fn get_current(&self) -> Option<TrackInput> {
         self.get_position(self.state.queue_position)
     }
 
     fn get_next(&self) -> Option<TrackInput> {
-        self.get_position(self.state.queue_position + 1)
+        self.next_position().and_then(|p| self.get_position(p))
+    }
+
+    // Position to move to once the current track ends
+    fn next_position(&self) -> Option<usize> {
+        match self.state.repeat_mode {
+            RepeatMode::One if self.state.queue_position < self.state.queue.len() => {
+                Some(self.state.queue_position)
+            }
+            _ => self.skip_position(),
+        }
+    }
+
+    // Position to move to when skipping forward. Repeat-one is ignored here so the user can
+    // still skip past the repeated track.
+    fn skip_position(&self) -> Option<usize> {
+        let next_position = self.state.queue_position + 1;
+        if next_position < self.state.queue.len() {
+            Some(next_position)
+        } else if self.state.repeat_mode == RepeatMode::All && !self.state.queue.is_empty() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn previous_position(&self) -> Option<usize> {
+        if self.state.queue_position > 0 {
+            Some(self.state.queue_position - 1)
+        } else if self.state.repeat_mode == RepeatMode::All && !self.state.queue.is_empty() {
+            Some(self.state.queue.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    async fn requeue_next(&mut self) {
+        if self.queued_count == 0 {
+            return;
+        }
+        // The decoder already has the current track, so anything still in the queue is the
+        // prefetched next track, which may no longer be correct
+        self.drain_pending();
+        if let Some(next) = self.get_next() {
+            self.append_file(next, false).await.unwrap_or_default();
+        }
+    }
+
+    fn drain_pending(&mut self) {
+        let drained = self.queue_rx.drain().count();
+        self.queued_count -= drained;
+        for _ in 0..drained {
+            if let Some(token) = self.stream_cancellation_tokens.pop_back() {
+                token.cancel();
+            }
+        }
+    }
+
+    pub(crate) async fn set_repeat_mode(&mut self, repeat_mode: RepeatMode) {
+        self.state.repeat_mode = repeat_mode;
+        self.requeue_next().await;
+        self.event_tx
+            .send(PlayerEvent::SetRepeatMode(self.state.clone()))
+            .unwrap_or_default();
+    }
+
+    pub(crate) async fn set_shuffle(&mut self, shuffle: bool) {
+        if shuffle == self.state.shuffle {
+            return;
+        }
+        self.state.shuffle = shuffle;
+        let current = self.queue_order.get(self.state.queue_position).copied();
+        if shuffle {
+            // Keep the current track playing and shuffle everything else after it
+            let mut remaining = (0..self.original_queue.len())
+                .filter(|i| Some(*i) != current)
+                .collect::<Vec<_>>();
+            remaining.shuffle(&mut rand::rng());
+            self.queue_order = current.into_iter().chain(remaining).collect();
+            self.state.queue_position = 0;
+        } else {
+            self.queue_order = (0..self.original_queue.len()).collect();
+            self.state.queue_position = current.unwrap_or_default();
+        }
+        self.state.queue = self.ordered_queue();
+        self.requeue_next().await;
+        self.event_tx
+            .send(PlayerEvent::SetShuffle(self.state.clone()))
+            .unwrap_or_default();
+    }
+
+    pub(crate) async fn set_crossfade(&mut self, crossfade: Duration) -> Result<(), String> {
+        self.settings.crossfade = crossfade;
+        if self.state.status != AudioStatus::Stopped {
+            self.cmd_sender
+                .get_response(DecoderCommand::SetCrossfade(crossfade))
+                .await
+                .tap_err(|e| error!("Error sending set crossfade command {e:?}"))?;
+            // The prefetched track was created with the old settings
+            self.requeue_next().await;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn set_replay_gain_mode(
+        &mut self,
+        mode: ReplayGainMode,
+    ) -> Result<(), String> {
+        self.settings.replay_gain_mode = mode;
+        if self.state.status != AudioStatus::Stopped {
+            self.cmd_sender
+                .get_response(DecoderCommand::SetReplayGainMode(mode))
+                .await
+                .tap_err(|e| error!("Error sending set replay gain mode command {e:?}"))?;
+            self.requeue_next().await;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_equalizer_presets(&self) -> EqualizerPresets {
+        self.equalizer.to_presets()
+    }
+
+    pub(crate) async fn save_equalizer_preset(
+        &mut self,
+        preset: EqualizerPreset,
+    ) -> Result<(), String> {
+        let name = preset.name.clone();
+        self.equalizer.save_preset(preset);
+        self.save_equalizer();
+        // Apply the changes immediately if the preset is currently in use
+        if self
+            .equalizer
+            .active_preset
+            .as_deref()
+            .is_some_and(|active| active.eq_ignore_ascii_case(&name))
+        {
+            self.apply_equalizer().await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn delete_equalizer_preset(&mut self, name: &str) -> Result<(), String> {
+        let was_active = self.equalizer.active_preset.is_some();
+        self.equalizer.delete_preset(name);
+        self.save_equalizer();
+        if was_active && self.equalizer.active_preset.is_none() {
+            self.apply_equalizer().await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn set_equalizer_preset(
+        &mut self,
+        name: Option<String>,
+    ) -> Result<(), String> {
+        if let Some(name) = &name
+            && self.equalizer.find(name).is_none()
+        {
+            warn!("Equalizer preset {name} not found");
+            return Ok(());
+        }
+        self.equalizer.active_preset = name;
+        self.save_equalizer();
+        self.apply_equalizer().await
+    }
+
+    async fn apply_equalizer(&mut self) -> Result<(), String> {
+        if self.state.status != AudioStatus::Stopped {
+            self.cmd_sender
+                .get_response(DecoderCommand::SetEqualizer(self.equalizer.active()))
+                .await
+                .tap_err(|e| error!("Error sending set equalizer command {e:?}"))?;
+            self.requeue_next().await;
+        }
+        Ok(())
+    }
+
+    fn save_equalizer(&self) {
+        if let Some(path) = &self.settings.equalizer_path {
+            let _ = self
+                .equalizer
+                .write(path)
+                .inspect_err(|e| error!("Error saving equalizer presets: {e:?}"));
+        }
     }
 
     fn get_position(&self, position: usize) -> Option<TrackInput> {
@@ -511,14 +992,13 @@ impl Player {
     }
 
     pub(crate) async fn go_next(&mut self) -> Result<(), String> {
-        let queue_len = self.state.queue.len();
-        // need to check for length > 0 first because an unsigned value of 0 - 1 panics
-        if queue_len > 0 && self.state.queue_position < queue_len - 1 {
+        if let Some(next_position) = self.skip_position() {
             info!(
                 "Current position: {}, Going to next track.",
                 self.state.queue_position
             );
-            self.state.queue_position += 1;
+            self.end_listen(true);
+            self.state.queue_position = next_position;
             self.reset_queue().await?;
             if self.start().await.is_ok() {
                 self.event_tx
@@ -536,12 +1016,13 @@ impl Player {
     }
 
     pub(crate) async fn go_previous(&mut self) -> Result<(), String> {
-        if self.state.queue_position > 0 {
+        if let Some(previous_position) = self.previous_position() {
             info!(
                 "Current position: {}, Going to previous track.",
                 self.state.queue_position
             );
-            self.state.queue_position -= 1;
+            self.end_listen(true);
+            self.state.queue_position = previous_position;
             self.reset_queue().await?;
             if self.start().await.is_ok() {
                 self.event_tx
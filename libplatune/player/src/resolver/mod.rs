@@ -7,6 +7,7 @@ pub(crate) use url::*;
 pub(crate) use yt_dlp::*;
 
 use crate::dto::track::Metadata;
+use crate::dto::track_range::TrackRange;
 
 #[derive(Debug)]
 pub(crate) struct MetadataSource {
@@ -15,10 +16,29 @@ pub(crate) struct MetadataSource {
     pub(crate) has_content_length: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TrackInput {
     pub input: Input,
     pub metadata: Option<Metadata>,
+    pub range: Option<TrackRange>,
+    /// URL that was queued if it resolved to this track alone. Resolvers can return temporary
+    /// stream URLs, so this is what gets resolved again after a restart.
+    pub queued_url: Option<String>,
+}
+
+impl TrackInput {
+    /// The resolved track URL, including the range
+    pub fn url(&self) -> String {
+        match self.range {
+            Some(range) => format!("{}{}", self.input, range.fragment()),
+            None => self.input.to_string(),
+        }
+    }
+
+    /// URL to save so the track can be resolved again later
+    pub fn saved_url(&self) -> String {
+        self.queued_url.clone().unwrap_or_else(|| self.url())
+    }
 }
 
 // live streams have fixed transfer rates so we'll limit prefetch to 2 seconds
@@ -379,6 +379,9 @@ impl RegistryEntry<Result<(MetadataSource, CancellationToken)>> for YtDlpSourceR
                 .duration
                 .and_then(|d| d.as_f64())
                 .map(|d| Duration::from_secs(d as u64)),
+            replay_gain: None,
+            album_art_url: video.thumbnail,
+            lyrics: None,
         };
         // We always pipe the output into FFMPEG instead of reading directly from yt-dlp's output
         // stream because yt-dlp still outputs the video stream which can cause format
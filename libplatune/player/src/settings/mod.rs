@@ -1,12 +1,33 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::platune_player::ReplayGainMode;
+
 #[derive(Clone, Debug)]
 pub struct Settings {
     pub resample_chunk_size: usize,
+    /// Location of the queue snapshot used to restore the player after a restart.
+    /// State is not persisted if this is not set.
+    pub state_path: Option<PathBuf>,
+    /// Length of the transition between consecutive tracks.
+    /// The ending track fades out over this duration while the next track fades in on top of it.
+    /// Crossfading is disabled if this is zero.
+    pub crossfade: Duration,
+    /// Which ReplayGain values to use for loudness normalization.
+    pub replay_gain_mode: ReplayGainMode,
+    /// Location of the saved equalizer presets and the active preset.
+    /// Presets are not persisted if this is not set.
+    pub equalizer_path: Option<PathBuf>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             resample_chunk_size: 1024,
+            state_path: None,
+            crossfade: Duration::ZERO,
+            replay_gain_mode: ReplayGainMode::Off,
+            equalizer_path: None,
         }
     }
 }
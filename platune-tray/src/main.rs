@@ -348,13 +348,20 @@ async fn metadata_updater(mut controls: MediaControls) {
                                 #[cfg(target_os = "linux")]
                                 controls.set_volume(state.volume as f64).unwrap();
                             }
-                            Event::Seek | Event::Position | Event::QueueUpdated => {}
+                            Event::Seek
+                            | Event::Position
+                            | Event::QueueUpdated
+                            | Event::SetRepeatMode
+                            | Event::SetShuffle
+                            | Event::TrackPlayed
+                            | Event::LyricLine => {}
                         }
                     }
                     EventPayload::SeekData(seek) => {
                         progress = Duration::from_millis(seek.seek_millis);
                         last_progress = Instant::now();
                     }
+                    EventPayload::PlayedTrack(_) | EventPayload::LyricLine(_) => {}
                 }
             }
             Ok(Some(Err(err))) => {
@@ -405,7 +412,7 @@ async fn set_metadata(
                     title: metadata.song.as_deref().unwrap_or_default().into(),
                     album: metadata.album.as_deref().unwrap_or_default().into(),
                     artist: metadata.artist.as_deref().unwrap_or_default().into(),
-                    cover_url: None,
+                    cover_url: metadata.album_art_url.as_deref(),
                     duration: metadata.duration.map(|d| d.try_into().unwrap()),
                 })
                 .unwrap();
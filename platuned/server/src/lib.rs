@@ -9,6 +9,8 @@ use daemon_slayer::core::{BoxedError, Label};
 const DEFAULT_MAIN_SERVER_PORT: usize = 50051;
 const DEFAULT_FILE_SERVER_PORT: usize = 50050;
 const DEFAULT_IPC_NAME: &str = "platuned";
+/// Route on the file server that serves cached album art
+pub const ALBUM_ART_ROUTE: &str = "_art";
 
 pub fn main_server_port() -> Result<usize, ParseIntError> {
     Ok(match env::var("PLATUNE_SERVER_PORT") {
@@ -2,6 +2,8 @@ use std::env;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
+#[cfg(all(feature = "player", feature = "management"))]
+use std::time::SystemTime;
 
 use background_service::ServiceContext;
 use daemon_slayer::core::notify::AsyncNotification;
@@ -12,7 +14,7 @@ use futures::StreamExt;
 #[cfg(feature = "management")]
 use libplatune_management::config::FileConfig;
 #[cfg(feature = "management")]
-use libplatune_management::config::config_dir;
+use libplatune_management::config::{cache_dir, config_dir};
 #[cfg(feature = "management")]
 use libplatune_management::database::Database;
 #[cfg(feature = "management")]
@@ -21,10 +23,16 @@ use libplatune_management::file_watch_manager::FileWatchManager;
 use libplatune_management::manager::Manager;
 #[cfg(feature = "player")]
 use libplatune_player::CpalHost;
+#[cfg(all(feature = "player", feature = "management"))]
+use libplatune_player::Host;
 #[cfg(feature = "player")]
 use libplatune_player::platune_player::PlatunePlayer;
 #[cfg(feature = "player")]
 use libplatune_player::platune_player::PlayerEvent;
+#[cfg(feature = "player")]
+use libplatune_player::platune_player::Settings;
+#[cfg(feature = "management")]
+use platuned::ALBUM_ART_ROUTE;
 use platuned::{file_server_port, ipc_server_name, main_server_port, service_label};
 use tipsy::{IntoIpcPath, ServerId};
 use tokio_util::sync::CancellationToken;
@@ -65,7 +73,14 @@ impl Services {
         let manager = init_manager().await?;
         Ok(Self {
             #[cfg(feature = "player")]
-            player: Arc::new(PlatunePlayer::new(Default::default(), Default::default())),
+            player: Arc::new(PlatunePlayer::new(
+                Default::default(),
+                Settings {
+                    state_path: Some(config_dir()?.join("player_state.json")),
+                    equalizer_path: Some(config_dir()?.join("equalizer.json")),
+                    ..Default::default()
+                },
+            )),
             #[cfg(feature = "management")]
             manager: FileWatchManager::new(manager, Duration::from_millis(500), move || {
                 Box::pin(async move {
@@ -96,6 +111,8 @@ pub async fn run_all(shutdown_rx: BroadcastEventStore<Signal>) -> Result<()> {
 
     #[cfg(feature = "player")]
     show_notifications(&services.player);
+    #[cfg(all(feature = "player", feature = "management"))]
+    record_history(&services.player, services.manager.clone());
 
     let manager = background_service::Manager::new(
         CancellationToken::new(),
@@ -142,10 +159,14 @@ pub async fn run_all(shutdown_rx: BroadcastEventStore<Signal>) -> Result<()> {
     }));
     #[cfg(feature = "management")]
     {
-        let folders = services.manager.read().await.get_all_folders().await?;
+        let library = services.manager.read().await;
+        let folders = library.get_all_folders().await?;
+        let art_cache_dir = library.art_cache_dir().map(|dir| dir.to_path_buf());
+        drop(library);
         if !folders.is_empty() {
             context.spawn(("file_service", |context: ServiceContext| async move {
-                run_file_service(folders, context.cancellation_token().clone()).await?;
+                run_file_service(folders, art_cache_dir, context.cancellation_token().clone())
+                    .await?;
                 Ok(())
             }));
         }
@@ -192,9 +213,60 @@ fn show_notifications(player: &Arc<PlatunePlayer<CpalHost>>) {
     });
 }
 
+#[cfg(all(feature = "player", feature = "management"))]
+fn record_history<H: Host + Send + 'static>(player: &PlatunePlayer<H>, manager: FileWatchManager) {
+    let mut player_rx = player.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let played = match player_rx.recv().await {
+                Ok(PlayerEvent::TrackPlayed(played)) => played,
+                Ok(_) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                    warn!("history receiver lagged");
+                    continue;
+                }
+                Err(_) => break,
+            };
+            let Some(path) = played_path(&played.url) else {
+                continue;
+            };
+            let played_date = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+            let _ = manager
+                .read()
+                .await
+                .record_play(
+                    path,
+                    played_date,
+                    played.listened.as_millis() as i64,
+                    played.skipped,
+                )
+                .await
+                .inspect_err(|e| warn!("Error recording play history: {e:?}"));
+        }
+    });
+}
+
+/// The player resolves local files to plain paths, but file URLs are accepted as well
+#[cfg(all(feature = "player", feature = "management"))]
+fn played_path(url: &str) -> Option<String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(
+            urlencoding::decode(path)
+                .map(|p| p.to_string())
+                .unwrap_or_else(|_| path.to_owned()),
+        );
+    }
+    // Only local files can be matched against the library
+    (!url.contains("://")).then(|| url.to_owned())
+}
+
 #[cfg(feature = "management")]
 async fn run_file_service(
     folders: Vec<String>,
+    art_cache_dir: Option<std::path::PathBuf>,
     cancellation_token: CancellationToken,
 ) -> Result<()> {
     let addr: SocketAddr = format!("0.0.0.0:{}", file_server_port()?)
@@ -202,7 +274,9 @@ async fn run_file_service(
         .expect("failed to parse address");
     info!("Running file server on {addr}");
     let mut app = axum::Router::new();
-    let root_path = "/";
+    if let Some(art_cache_dir) = art_cache_dir {
+        app = app.nest_service(&format!("/{ALBUM_ART_ROUTE}"), ServeDir::new(art_cache_dir));
+    }
     match &folders[..] {
         [] => {}
         [folder] => {
@@ -216,7 +290,7 @@ async fn run_file_service(
             for folder in rest {
                 serve_dir = serve_dir.fallback(ServeDir::new(folder));
             }
-            app = app.nest_service(root_path, serve_dir);
+            app = app.fallback_service(serve_dir);
         }
     }
 
@@ -238,7 +312,9 @@ async fn init_manager() -> Result<Manager> {
         .replace("sqlite://", "");
 
     info!("Connecting to database {path:?}");
-    let db = Database::connect(path, true).await?;
+    let db = Database::connect(path, true)
+        .await?
+        .with_art_cache_dir(cache_dir()?.join("album_art"));
     db.sync_database()
         .await
         .wrap_err("Error migrating database")?;
@@ -333,3 +409,7 @@ async fn run_server(
 
     server_result.wrap_err("Error running server")
 }
+
+#[cfg(all(test, feature = "player", feature = "management"))]
+#[path = "./server_test.rs"]
+mod server_test;
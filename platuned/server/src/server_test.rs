@@ -0,0 +1,89 @@
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libplatune_management::config::MemoryConfig;
+use libplatune_management::database::Database;
+use libplatune_management::file_watch_manager::FileWatchManager;
+use libplatune_management::manager::Manager;
+use libplatune_player::MockHost;
+use libplatune_player::platune_player::{PlatunePlayer, PlayerEvent, Settings, Track};
+use tokio::time::timeout;
+
+use super::record_history;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_record_history() {
+    let music_dir = std::env::temp_dir().join(format!("platuned_history_{}", std::process::id()));
+    fs::create_dir_all(&music_dir).unwrap();
+    let song_path = music_dir.join("test.mp3");
+    fs::copy("../../libplatune/test_assets/test.mp3", &song_path).unwrap();
+
+    let db = Database::connect_in_memory().await.unwrap();
+    db.sync_database().await.unwrap();
+    let mut manager = Manager::new(&db, Arc::new(MemoryConfig::new_boxed()));
+    manager
+        .add_folder(music_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut receiver = manager.sync(None, Box::pin(async {})).await.unwrap();
+    while receiver.next().await.is_some() {}
+    let manager = FileWatchManager::new(manager, Duration::from_millis(100), || Box::pin(async {}))
+        .await
+        .unwrap();
+
+    let player = PlatunePlayer::new(MockHost::default(), Settings::default());
+    record_history(&player, manager.clone());
+    let mut events = player.subscribe();
+    let path = song_path.to_string_lossy().to_string();
+    // Local files get resolved to plain paths, but queued file URLs should be matched too
+    player
+        .set_queue(vec![
+            Track {
+                url: path.clone(),
+                metadata: None,
+            },
+            Track {
+                url: format!("file://{path}"),
+                metadata: None,
+            },
+        ])
+        .await
+        .unwrap();
+    loop {
+        let event = timeout(Duration::from_secs(30), events.recv())
+            .await
+            .expect("timed out waiting for player event")
+            .unwrap();
+        if let PlayerEvent::QueueEnded(_) = event {
+            break;
+        }
+    }
+
+    // History is written in the background after the event is received
+    let history = timeout(Duration::from_secs(10), async {
+        loop {
+            let history = manager
+                .read()
+                .await
+                .get_history(0, i64::MAX, 10)
+                .await
+                .unwrap();
+            if history.len() == 2 {
+                return history;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("play history was not recorded");
+
+    for entry in history {
+        assert!(entry.path.ends_with("test.mp3"), "{}", entry.path);
+        assert!(!entry.skipped);
+    }
+
+    player.join().await.unwrap();
+    let _ = fs::remove_dir_all(music_dir);
+}
@@ -1,13 +1,15 @@
 use std::env;
 use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
 use std::pin::Pin;
 use std::time::Duration;
 
 use futures::{Stream, StreamExt};
 use libplatune_management::file_watch_manager::FileWatchManager;
-use libplatune_management::manager::{Manager, SearchOptions};
+use libplatune_management::manager::{Manager, SearchError, SearchOptions};
+use libplatune_management::sync::album_art::{THUMBNAIL_DIR, thumbnail_name};
 use libplatune_management::{database, manager};
-use platuned::file_server_port;
+use platuned::{ALBUM_ART_ROUTE, file_server_port};
 use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{RwLockReadGuard, mpsc};
 use tokio_util::future::FutureExt;
@@ -46,30 +48,45 @@ enum ConnectionType {
 }
 
 #[allow(clippy::result_large_err)]
-fn map_lookup_entry(
-    entry: database::LookupEntry,
-    connection_type: &ConnectionType,
-) -> Result<LookupEntry, Status> {
-    let path = match connection_type {
-        ConnectionType::Local => format!("file://{}", entry.path),
+fn map_path(path: &str, connection_type: &ConnectionType) -> Result<String, Status> {
+    match connection_type {
+        ConnectionType::Local => Ok(format!("file://{path}")),
         ConnectionType::Remote {
             folders,
             local_addr,
         } => {
-            let folder = match folders.iter().find(|f| entry.path.starts_with(*f)) {
+            let folder = match folders.iter().find(|f| path.starts_with(*f)) {
                 Some(folder) => folder,
                 None => {
                     return Err(format_error(format!(
-                        "Unable to find folder for path {}",
-                        entry.path
+                        "Unable to find folder for path {path}"
                     )));
                 }
             };
-            entry.path.replacen(folder, local_addr, 1)
+            Ok(path.replacen(folder, local_addr, 1))
         }
+    }
+}
+
+fn map_album_art_url(file_name: &str, connection_type: &ConnectionType) -> Option<String> {
+    // Art is always served from the file server, even for local connections
+    let base_url = match connection_type {
+        ConnectionType::Local => format!("http://localhost:{}/", file_server_port().ok()?),
+        ConnectionType::Remote { local_addr, .. } => local_addr.to_owned(),
     };
+    Some(format!("{base_url}{ALBUM_ART_ROUTE}/{file_name}"))
+}
+
+#[allow(clippy::result_large_err)]
+fn map_lookup_entry(
+    entry: database::LookupEntry,
+    connection_type: &ConnectionType,
+) -> Result<LookupEntry, Status> {
+    let path = map_path(&entry.path, connection_type)?;
+    let album_art = entry.album_art.filter(|a| !a.is_empty());
 
     Ok(LookupEntry {
+        song_id: entry.song_id,
         artist: entry.artist,
         album_artist: entry.album_artist,
         album: entry.album,
@@ -79,9 +96,373 @@ fn map_lookup_entry(
         duration: Duration::from_millis(entry.duration_millis as u64)
             .try_into()
             .ok(),
+        replay_gain: Some(ReplayGain {
+            track_gain: entry.track_gain,
+            track_peak: entry.track_peak,
+            album_gain: entry.album_gain,
+            album_peak: entry.album_peak,
+        }),
+        album_art_url: album_art
+            .as_deref()
+            .and_then(|a| map_album_art_url(a, connection_type)),
+        album_art_thumbnail_url: album_art.as_deref().and_then(|a| {
+            map_album_art_url(
+                &format!("{THUMBNAIL_DIR}/{}", thumbnail_name(a)),
+                connection_type,
+            )
+        }),
+        genre: entry.genre,
+        composer: entry.composer,
+        comment: entry.comment,
+        bpm: entry.bpm,
+        release_date: entry.release_date,
+        original_year: entry.original_year,
+        musicbrainz_ids: Some(MusicBrainzIds {
+            recording_id: entry.musicbrainz_recording_id,
+            release_id: entry.musicbrainz_release_id,
+            artist_id: entry.musicbrainz_artist_id,
+            release_group_id: entry.musicbrainz_release_group_id,
+        }),
+    })
+}
+
+const DEFAULT_STATS_LIMIT: i64 = 50;
+
+fn map_date_range(request: DateRangeRequest) -> (i64, i64, i64) {
+    (
+        request.start.unwrap_or(0),
+        request.end.unwrap_or(i64::MAX),
+        request.limit.unwrap_or(DEFAULT_STATS_LIMIT),
+    )
+}
+
+fn map_play_stat(stat: database::PlayStat) -> PlayStat {
+    PlayStat {
+        id: stat.id,
+        name: stat.name,
+        artist: stat.artist,
+        play_count: stat.play_count,
+        listened: Duration::from_millis(stat.listened_millis as u64)
+            .try_into()
+            .ok(),
+    }
+}
+
+fn map_query_error(error: manager::QueryError) -> QueryError {
+    let kind = match &error {
+        manager::QueryError::MissingValue { .. } => QueryErrorKind::MissingValue,
+        manager::QueryError::InvalidYear { .. } => QueryErrorKind::InvalidYear,
+        manager::QueryError::UnterminatedQuote { .. } => QueryErrorKind::UnterminatedQuote,
+        manager::QueryError::MissingOperand { .. } => QueryErrorKind::MissingOperand,
+    };
+    QueryError {
+        kind: kind.into(),
+        message: error.to_string(),
+        position: error.position() as u32,
+    }
+}
+
+fn map_browse_filter(filter: Option<BrowseFilter>) -> manager::BrowseFilter {
+    let filter = filter.unwrap_or_default();
+    manager::BrowseFilter {
+        genre_ids: filter.genre_ids,
+        year: filter.year,
+        decade: filter.decade,
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn map_browse_request(request: BrowseRequest) -> Result<manager::BrowseOptions, Status> {
+    let sort = match BrowseSort::try_from(request.sort)
+        .map_err(|_| Status::invalid_argument("Invalid browse sort"))?
+    {
+        BrowseSort::Name => manager::BrowseSort::Name,
+        BrowseSort::Year => manager::BrowseSort::Year,
+        BrowseSort::DateAdded => manager::BrowseSort::DateAdded,
+    };
+    let defaults = manager::BrowseOptions::default();
+    Ok(manager::BrowseOptions {
+        sort,
+        descending: request.descending,
+        filter: map_browse_filter(request.filter),
+        offset: request.offset.max(0),
+        limit: request.limit.unwrap_or(defaults.limit),
+    })
+}
+
+fn map_artist_summaries(
+    page: manager::BrowsePage<manager::ArtistSummary>,
+) -> ArtistSummaryResponse {
+    ArtistSummaryResponse {
+        entries: page
+            .entries
+            .into_iter()
+            .map(|a| ArtistSummary {
+                artist_id: a.artist_id,
+                artist: a.artist,
+                album_count: a.album_count,
+                song_count: a.song_count,
+                year: a.year,
+                created_date: a.created_date,
+            })
+            .collect(),
+        total: page.total,
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn map_smart_playlist_definition(
+    definition: Option<SmartPlaylistDefinition>,
+) -> Result<manager::SmartPlaylistDefinition, Status> {
+    let definition =
+        definition.ok_or_else(|| Status::invalid_argument("Missing smart playlist definition"))?;
+    let rules: Result<Vec<_>, Status> = definition
+        .rules
+        .into_iter()
+        .map(|rule| {
+            let field = match SmartPlaylistField::try_from(rule.field)
+                .map_err(|_| Status::invalid_argument("Invalid smart playlist field"))?
+            {
+                SmartPlaylistField::Title => manager::RuleField::Title,
+                SmartPlaylistField::Artist => manager::RuleField::Artist,
+                SmartPlaylistField::AlbumArtist => manager::RuleField::AlbumArtist,
+                SmartPlaylistField::Album => manager::RuleField::Album,
+                SmartPlaylistField::Genre => manager::RuleField::Genre,
+                SmartPlaylistField::Composer => manager::RuleField::Composer,
+                SmartPlaylistField::Comment => manager::RuleField::Comment,
+                SmartPlaylistField::Path => manager::RuleField::Path,
+                SmartPlaylistField::Year => manager::RuleField::Year,
+                SmartPlaylistField::Bpm => manager::RuleField::Bpm,
+                SmartPlaylistField::Duration => manager::RuleField::Duration,
+                SmartPlaylistField::PlayCount => manager::RuleField::PlayCount,
+                SmartPlaylistField::SkipCount => manager::RuleField::SkipCount,
+                SmartPlaylistField::LastPlayed => manager::RuleField::LastPlayed,
+                SmartPlaylistField::DateAdded => manager::RuleField::DateAdded,
+            };
+            let operator = match SmartPlaylistOperator::try_from(rule.operator)
+                .map_err(|_| Status::invalid_argument("Invalid smart playlist operator"))?
+            {
+                SmartPlaylistOperator::Is => manager::RuleOperator::Is,
+                SmartPlaylistOperator::IsNot => manager::RuleOperator::IsNot,
+                SmartPlaylistOperator::Contains => manager::RuleOperator::Contains,
+                SmartPlaylistOperator::NotContains => manager::RuleOperator::NotContains,
+                SmartPlaylistOperator::StartsWith => manager::RuleOperator::StartsWith,
+                SmartPlaylistOperator::EndsWith => manager::RuleOperator::EndsWith,
+                SmartPlaylistOperator::GreaterThan => manager::RuleOperator::GreaterThan,
+                SmartPlaylistOperator::LessThan => manager::RuleOperator::LessThan,
+                SmartPlaylistOperator::InLast => manager::RuleOperator::InLast,
+                SmartPlaylistOperator::NotInLast => manager::RuleOperator::NotInLast,
+            };
+            Ok(manager::SmartPlaylistRule::new(field, operator, rule.value))
+        })
+        .collect();
+    let sort = match SmartPlaylistSort::try_from(definition.sort)
+        .map_err(|_| Status::invalid_argument("Invalid smart playlist sort"))?
+    {
+        SmartPlaylistSort::Random => manager::SmartPlaylistSort::Random,
+        SmartPlaylistSort::Title => manager::SmartPlaylistSort::Title,
+        SmartPlaylistSort::Artist => manager::SmartPlaylistSort::Artist,
+        SmartPlaylistSort::Album => manager::SmartPlaylistSort::Album,
+        SmartPlaylistSort::Year => manager::SmartPlaylistSort::Year,
+        SmartPlaylistSort::DateAdded => manager::SmartPlaylistSort::DateAdded,
+        SmartPlaylistSort::PlayCount => manager::SmartPlaylistSort::PlayCount,
+        SmartPlaylistSort::LastPlayed => manager::SmartPlaylistSort::LastPlayed,
+    };
+
+    Ok(manager::SmartPlaylistDefinition {
+        name: definition.name,
+        rules: rules?,
+        match_all: !definition.match_any,
+        sort,
+        descending: definition.descending,
+        limit: definition.limit,
     })
 }
 
+fn map_smart_playlist(playlist: manager::SmartPlaylist) -> SmartPlaylistEntry {
+    let definition = playlist.definition;
+    let rules = definition
+        .rules
+        .into_iter()
+        .map(|rule| {
+            let field = match rule.field {
+                manager::RuleField::Title => SmartPlaylistField::Title,
+                manager::RuleField::Artist => SmartPlaylistField::Artist,
+                manager::RuleField::AlbumArtist => SmartPlaylistField::AlbumArtist,
+                manager::RuleField::Album => SmartPlaylistField::Album,
+                manager::RuleField::Genre => SmartPlaylistField::Genre,
+                manager::RuleField::Composer => SmartPlaylistField::Composer,
+                manager::RuleField::Comment => SmartPlaylistField::Comment,
+                manager::RuleField::Path => SmartPlaylistField::Path,
+                manager::RuleField::Year => SmartPlaylistField::Year,
+                manager::RuleField::Bpm => SmartPlaylistField::Bpm,
+                manager::RuleField::Duration => SmartPlaylistField::Duration,
+                manager::RuleField::PlayCount => SmartPlaylistField::PlayCount,
+                manager::RuleField::SkipCount => SmartPlaylistField::SkipCount,
+                manager::RuleField::LastPlayed => SmartPlaylistField::LastPlayed,
+                manager::RuleField::DateAdded => SmartPlaylistField::DateAdded,
+            };
+            let operator = match rule.operator {
+                manager::RuleOperator::Is => SmartPlaylistOperator::Is,
+                manager::RuleOperator::IsNot => SmartPlaylistOperator::IsNot,
+                manager::RuleOperator::Contains => SmartPlaylistOperator::Contains,
+                manager::RuleOperator::NotContains => SmartPlaylistOperator::NotContains,
+                manager::RuleOperator::StartsWith => SmartPlaylistOperator::StartsWith,
+                manager::RuleOperator::EndsWith => SmartPlaylistOperator::EndsWith,
+                manager::RuleOperator::GreaterThan => SmartPlaylistOperator::GreaterThan,
+                manager::RuleOperator::LessThan => SmartPlaylistOperator::LessThan,
+                manager::RuleOperator::InLast => SmartPlaylistOperator::InLast,
+                manager::RuleOperator::NotInLast => SmartPlaylistOperator::NotInLast,
+            };
+            SmartPlaylistRule {
+                field: field.into(),
+                operator: operator.into(),
+                value: rule.value,
+            }
+        })
+        .collect();
+    let sort = match definition.sort {
+        manager::SmartPlaylistSort::Random => SmartPlaylistSort::Random,
+        manager::SmartPlaylistSort::Title => SmartPlaylistSort::Title,
+        manager::SmartPlaylistSort::Artist => SmartPlaylistSort::Artist,
+        manager::SmartPlaylistSort::Album => SmartPlaylistSort::Album,
+        manager::SmartPlaylistSort::Year => SmartPlaylistSort::Year,
+        manager::SmartPlaylistSort::DateAdded => SmartPlaylistSort::DateAdded,
+        manager::SmartPlaylistSort::PlayCount => SmartPlaylistSort::PlayCount,
+        manager::SmartPlaylistSort::LastPlayed => SmartPlaylistSort::LastPlayed,
+    };
+
+    SmartPlaylistEntry {
+        smart_playlist_id: playlist.smart_playlist_id,
+        definition: Some(SmartPlaylistDefinition {
+            name: definition.name,
+            rules,
+            match_any: !definition.match_all,
+            sort: sort.into(),
+            descending: definition.descending,
+            limit: definition.limit,
+        }),
+        song_count: playlist.song_count,
+        evaluated_date: playlist.evaluated_date,
+    }
+}
+
+fn map_smart_playlist_error(context: &str, error: manager::SmartPlaylistError) -> Status {
+    match error {
+        manager::SmartPlaylistError::DbError(e) => format_error(format!("{context} {e:?}")),
+        e @ manager::SmartPlaylistError::NotFound(_) => Status::not_found(e.to_string()),
+        e => Status::invalid_argument(e.to_string()),
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn map_duplicate_group(
+    group: manager::DuplicateGroup,
+    connection_type: &ConnectionType,
+) -> Result<DuplicateGroup, Status> {
+    let songs: Result<Vec<_>, _> = group
+        .songs
+        .into_iter()
+        .map(|s| {
+            Ok(DuplicateSong {
+                song_id: s.song_id,
+                path: map_path(&s.path, connection_type)?,
+                song: s.song,
+                artist: s.artist,
+                album: s.album,
+                duration: Duration::from_millis(s.duration_millis as u64)
+                    .try_into()
+                    .ok(),
+                bit_rate: s.bit_rate,
+                sample_rate: s.sample_rate,
+                file_size: s.file_size,
+                format: s.format,
+            })
+        })
+        .collect();
+
+    Ok(DuplicateGroup { songs: songs? })
+}
+
+fn map_manager_error(context: &str, error: manager::ManagerError) -> Status {
+    match error {
+        e @ manager::ManagerError::FolderNotFound(_) => Status::not_found(e.to_string()),
+        e @ manager::ManagerError::InvalidPattern(_) => Status::invalid_argument(e.to_string()),
+        e @ manager::ManagerError::UnsupportedExtension(_) => {
+            Status::invalid_argument(e.to_string())
+        }
+        e @ manager::ManagerError::MountConflict(_) => Status::already_exists(e.to_string()),
+        e => format_error(format!("{context} {e:?}")),
+    }
+}
+
+fn map_db_error(context: &str, error: manager::DbError) -> Status {
+    match error {
+        e @ manager::DbError::NotFound(_) => Status::not_found(e.to_string()),
+        e => format_error(format!("{context} {e:?}")),
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn map_playlist_format(format: i32) -> Result<manager::PlaylistFormat, Status> {
+    Ok(
+        match PlaylistFormat::try_from(format)
+            .map_err(|_| Status::invalid_argument("Invalid playlist format"))?
+        {
+            PlaylistFormat::M3u8 => manager::PlaylistFormat::M3u8,
+            PlaylistFormat::M3u => manager::PlaylistFormat::M3u,
+            PlaylistFormat::Pls => manager::PlaylistFormat::Pls,
+            PlaylistFormat::Xspf => manager::PlaylistFormat::Xspf,
+        },
+    )
+}
+
+fn map_sync_file_status(status: manager::SyncFileStatus) -> SyncFileStatus {
+    match status {
+        manager::SyncFileStatus::Added => SyncFileStatus::Added,
+        manager::SyncFileStatus::Updated => SyncFileStatus::Updated,
+        manager::SyncFileStatus::Deleted => SyncFileStatus::Deleted,
+        manager::SyncFileStatus::Failed => SyncFileStatus::Failed,
+    }
+}
+
+fn map_artist_role(role: manager::ArtistRole) -> ArtistRole {
+    match role {
+        manager::ArtistRole::Primary => ArtistRole::Primary,
+        manager::ArtistRole::Featured => ArtistRole::Featured,
+        manager::ArtistRole::AlbumArtist => ArtistRole::AlbumArtist,
+        manager::ArtistRole::Composer => ArtistRole::Composer,
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn map_sync_file_statuses(statuses: Vec<i32>) -> Result<Vec<manager::SyncFileStatus>, Status> {
+    statuses
+        .into_iter()
+        .map(|status| {
+            Ok(
+                match SyncFileStatus::try_from(status)
+                    .map_err(|_| Status::invalid_argument("Invalid sync file status"))?
+                {
+                    SyncFileStatus::Added => manager::SyncFileStatus::Added,
+                    SyncFileStatus::Updated => manager::SyncFileStatus::Updated,
+                    SyncFileStatus::Deleted => manager::SyncFileStatus::Deleted,
+                    SyncFileStatus::Failed => manager::SyncFileStatus::Failed,
+                },
+            )
+        })
+        .collect()
+}
+
+fn map_playlist_file_error(context: &str, error: manager::PlaylistFileError) -> Status {
+    match error {
+        manager::PlaylistFileError::DbError(e) => format_error(format!("{context} {e:?}")),
+        e @ manager::PlaylistFileError::NotFound(_) => Status::not_found(e.to_string()),
+        e @ manager::PlaylistFileError::ReadError(_) => format_error(format!("{context} {e:?}")),
+        e => Status::invalid_argument(e.to_string()),
+    }
+}
+
 async fn get_connection_type<T>(
     request: &Request<T>,
     manager: &RwLockReadGuard<'_, Manager>,
@@ -164,6 +545,37 @@ impl Management for ManagementImpl {
         }
     }
 
+    async fn start_full_rescan(&self, _: Request<()>) -> Result<Response<()>, Status> {
+        match self.manager.start_full_rescan().await {
+            Ok(_) => Ok(Response::new(())),
+            Err(e) => Err(format_error(e.to_string())),
+        }
+    }
+
+    async fn pause_sync(&self, _: Request<()>) -> Result<Response<()>, Status> {
+        if self.manager.read().await.pause_sync().await {
+            Ok(Response::new(()))
+        } else {
+            Err(Status::failed_precondition("No sync is running"))
+        }
+    }
+
+    async fn resume_sync(&self, _: Request<()>) -> Result<Response<()>, Status> {
+        if self.manager.read().await.resume_sync().await {
+            Ok(Response::new(()))
+        } else {
+            Err(Status::failed_precondition("No sync is paused"))
+        }
+    }
+
+    async fn cancel_sync(&self, _: Request<()>) -> Result<Response<()>, Status> {
+        if self.manager.read().await.cancel_sync().await {
+            Ok(Response::new(()))
+        } else {
+            Err(Status::failed_precondition("No sync is in progress"))
+        }
+    }
+
     type SubscribeEventsStream =
         Pin<Box<dyn Stream<Item = Result<Progress, Status>> + Send + Sync + 'static>>;
 
@@ -187,6 +599,8 @@ impl Management for ManagementImpl {
                                 job: val.job,
                                 percentage: val.percentage,
                                 finished: val.finished,
+                                paused: val.paused,
+                                cancelled: val.cancelled,
                             }))
                             .await
                             .is_err()
@@ -223,7 +637,7 @@ impl Management for ManagementImpl {
             )
             .await
         {
-            return Err(format_error(format!("Error adding folders {e:?}")));
+            return Err(map_manager_error("Error adding folders", e));
         };
         Ok(Response::new(()))
     }
@@ -238,15 +652,110 @@ impl Management for ManagementImpl {
         Ok(Response::new(FoldersMessage { folders }))
     }
 
+    async fn remove_folders(
+        &self,
+        request: Request<RemoveFoldersRequest>,
+    ) -> Result<Response<RemoveFoldersResponse>, Status> {
+        let request = request.into_inner();
+        let removed = match self
+            .manager
+            .remove_folders(
+                request.folders.iter().map(|s| &s[..]).collect(),
+                request.purge,
+            )
+            .await
+        {
+            Ok(removed) => removed,
+            Err(e) => return Err(format_error(format!("Error removing folders {e:?}"))),
+        };
+        Ok(Response::new(RemoveFoldersResponse {
+            folders: removed.folders,
+            songs: removed
+                .songs
+                .into_iter()
+                .map(|d| DeletedResult {
+                    path: d.song_path,
+                    id: d.song_id,
+                })
+                .collect(),
+            albums: removed.albums,
+            artists: removed.artists,
+        }))
+    }
+
+    async fn get_folder_rules(
+        &self,
+        request: Request<PathMessage>,
+    ) -> Result<Response<FolderRulesMessage>, Status> {
+        let folder = request.into_inner().path;
+        let rules = self
+            .manager
+            .read()
+            .await
+            .get_folder_rules(&folder)
+            .await
+            .map_err(|e| map_manager_error("Error getting folder rules", e))?;
+        Ok(Response::new(FolderRulesMessage {
+            folder,
+            include: rules.include,
+            exclude: rules.exclude,
+        }))
+    }
+
+    async fn set_folder_rules(
+        &self,
+        request: Request<FolderRulesMessage>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .read()
+            .await
+            .set_folder_rules(
+                &request.folder,
+                manager::FolderRules {
+                    include: request.include,
+                    exclude: request.exclude,
+                },
+            )
+            .await
+            .map_err(|e| map_manager_error("Error setting folder rules", e))?;
+        Ok(Response::new(()))
+    }
+
+    async fn get_file_extensions(
+        &self,
+        _: Request<()>,
+    ) -> Result<Response<FileExtensionsMessage>, Status> {
+        match self.manager.read().await.get_file_extensions().await {
+            Ok(extensions) => Ok(Response::new(FileExtensionsMessage { extensions })),
+            Err(e) => Err(format_error(format!("Error getting file extensions {e:?}"))),
+        }
+    }
+
+    async fn set_file_extensions(
+        &self,
+        request: Request<FileExtensionsMessage>,
+    ) -> Result<Response<()>, Status> {
+        let extensions = request.into_inner().extensions;
+        self.manager
+            .read()
+            .await
+            .set_file_extensions(extensions.iter().map(|s| &s[..]).collect())
+            .await
+            .map_err(|e| map_manager_error("Error setting file extensions", e))?;
+        Ok(Response::new(()))
+    }
+
     async fn register_mount(
         &self,
         request: Request<RegisteredMountMessage>,
     ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
         match self
             .manager
             .write()
             .await
-            .register_drive(&request.into_inner().mount)
+            .register_mount(&request.name, &request.mount)
             .await
         {
             Ok(()) => Ok(Response::new(())),
@@ -261,9 +770,28 @@ impl Management for ManagementImpl {
         let mount = self.manager.read().await.get_registered_mount().await;
         Ok(Response::new(RegisteredMountMessage {
             mount: mount.unwrap_or_default(),
+            name: manager::DEFAULT_MOUNT.to_owned(),
         }))
     }
 
+    async fn get_registered_mounts(
+        &self,
+        _: Request<()>,
+    ) -> Result<Response<RegisteredMountsResponse>, Status> {
+        match self.manager.read().await.get_registered_mounts().await {
+            Ok(mounts) => Ok(Response::new(RegisteredMountsResponse {
+                mounts: mounts
+                    .into_iter()
+                    .map(|m| RegisteredMountMessage {
+                        mount: m.path,
+                        name: m.name,
+                    })
+                    .collect(),
+            })),
+            Err(e) => Err(format_error(format!("Error getting mounts {e:?}"))),
+        }
+    }
+
     async fn get_albums_by_album_artists(
         &self,
         request: Request<IdMessage>,
@@ -284,6 +812,8 @@ impl Management for ManagementImpl {
                     album_id: a.album_id,
                     album_artist: a.album_artist,
                     album_artist_id: a.album_artist_id,
+                    year: a.year,
+                    is_compilation: a.is_compilation,
                 })
                 .collect(),
         }))
@@ -303,6 +833,8 @@ impl Management for ManagementImpl {
                     EntryType::Song => manager::EntryType::Song,
                     EntryType::Album => manager::EntryType::Album,
                     EntryType::Artist => manager::EntryType::Artist,
+                    EntryType::Genre => manager::EntryType::Genre,
+                    EntryType::Composer => manager::EntryType::Composer,
                 },
             )
             .await
@@ -366,6 +898,13 @@ impl Management for ManagementImpl {
             tokio_stream::wrappers::ReceiverStream::new(rx).map(|r| {
                 let search_results = match r {
                     Ok(results) => results,
+                    // Invalid queries are expected while typing so they shouldn't end the stream
+                    Err(SearchError::InvalidQuery(e)) => {
+                        return Ok(SearchResponse {
+                            results: vec![],
+                            error: Some(map_query_error(e)),
+                        });
+                    }
                     Err(e) => {
                         return Err(format_error(format!("Error sending search request {e:?}")));
                     }
@@ -379,13 +918,18 @@ impl Management for ManagementImpl {
                             manager::EntryType::Song => EntryType::Song,
                             manager::EntryType::Artist => EntryType::Artist,
                             manager::EntryType::Album => EntryType::Album,
+                            manager::EntryType::Genre => EntryType::Genre,
+                            manager::EntryType::Composer => EntryType::Composer,
                         })
                         .into(),
                         artist: res.artist,
                         correlation_ids: res.correlation_ids,
                     })
                     .collect();
-                Ok(SearchResponse { results })
+                Ok(SearchResponse {
+                    results,
+                    error: None,
+                })
             })
         })))
     }
@@ -417,6 +961,640 @@ impl Management for ManagementImpl {
         Ok(Response::new(()))
     }
 
+    async fn create_playlist(
+        &self,
+        request: Request<CreatePlaylistRequest>,
+    ) -> Result<Response<CreatePlaylistResponse>, Status> {
+        let request = request.into_inner();
+        let playlist_id = self
+            .manager
+            .write()
+            .await
+            .create_playlist(&request.name)
+            .await
+            .map_err(|e| format_error(format!("Error creating playlist {e:?}")))?;
+
+        Ok(Response::new(CreatePlaylistResponse { playlist_id }))
+    }
+
+    async fn rename_playlist(
+        &self,
+        request: Request<RenamePlaylistRequest>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .write()
+            .await
+            .rename_playlist(request.playlist_id, &request.name)
+            .await
+            .map_err(|e| map_db_error("Error renaming playlist", e))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn delete_playlists(&self, request: Request<IdMessage>) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .write()
+            .await
+            .delete_playlists(request.ids)
+            .await
+            .map_err(|e| map_db_error("Error deleting playlists", e))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn append_to_playlist(
+        &self,
+        request: Request<AppendToPlaylistRequest>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .write()
+            .await
+            .append_to_playlist(request.playlist_id, request.song_ids)
+            .await
+            .map_err(|e| map_db_error("Error adding to playlist", e))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn reorder_playlist(
+        &self,
+        request: Request<ReorderPlaylistRequest>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .write()
+            .await
+            .reorder_playlist(
+                request.playlist_id,
+                request.from_position,
+                request.to_position,
+            )
+            .await
+            .map_err(|e| map_db_error("Error reordering playlist", e))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn remove_from_playlist(
+        &self,
+        request: Request<RemoveFromPlaylistRequest>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .write()
+            .await
+            .remove_from_playlist(request.playlist_id, request.positions)
+            .await
+            .map_err(|e| map_db_error("Error removing from playlist", e))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn get_all_playlists(
+        &self,
+        _: Request<()>,
+    ) -> Result<Response<PlaylistsResponse>, Status> {
+        let playlists = self
+            .manager
+            .read()
+            .await
+            .get_all_playlists()
+            .await
+            .map_err(|e| format_error(format!("Error getting playlists {e:?}")))?;
+
+        Ok(Response::new(PlaylistsResponse {
+            playlists: playlists
+                .into_iter()
+                .map(|p| PlaylistEntry {
+                    playlist_id: p.playlist_id,
+                    name: p.playlist_name,
+                    song_count: p.song_count,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_playlist_songs(
+        &self,
+        request: Request<PlaylistIdMessage>,
+    ) -> Result<Response<LookupResponse>, Status> {
+        let manager = self.manager.read().await;
+        let connection_type = get_connection_type(&request, &manager).await?;
+        let request = request.into_inner();
+        let songs = manager
+            .get_playlist_songs(request.playlist_id)
+            .await
+            .map_err(|e| map_db_error("Error getting playlist songs", e))?;
+
+        let entries: Result<Vec<_>, _> = songs
+            .into_iter()
+            .map(|e| map_lookup_entry(e, &connection_type))
+            .collect();
+
+        Ok(Response::new(LookupResponse { entries: entries? }))
+    }
+
+    async fn get_history(
+        &self,
+        request: Request<DateRangeRequest>,
+    ) -> Result<Response<HistoryResponse>, Status> {
+        let manager = self.manager.read().await;
+        let connection_type = get_connection_type(&request, &manager).await?;
+        let (start, end, limit) = map_date_range(request.into_inner());
+        let history = manager
+            .get_history(start, end, limit)
+            .await
+            .map_err(|e| format_error(format!("Error getting history {e:?}")))?;
+
+        let entries: Result<Vec<_>, _> = history
+            .into_iter()
+            .map(|h| {
+                Ok(HistoryEntry {
+                    history_id: h.song_history_id,
+                    song_id: h.song_id,
+                    played_date: h.played_date,
+                    listened: Duration::from_millis(h.listened_millis as u64)
+                        .try_into()
+                        .ok(),
+                    skipped: h.skipped,
+                    song: h.song,
+                    artist: h.artist,
+                    album: h.album,
+                    path: map_path(&h.path, &connection_type)?,
+                })
+            })
+            .collect();
+
+        Ok(Response::new(HistoryResponse { entries: entries? }))
+    }
+
+    async fn get_top_songs(
+        &self,
+        request: Request<DateRangeRequest>,
+    ) -> Result<Response<PlayStatsResponse>, Status> {
+        let (start, end, limit) = map_date_range(request.into_inner());
+        let stats = self
+            .manager
+            .read()
+            .await
+            .get_top_songs(start, end, limit)
+            .await
+            .map_err(|e| format_error(format!("Error getting top songs {e:?}")))?;
+
+        Ok(Response::new(PlayStatsResponse {
+            stats: stats.into_iter().map(map_play_stat).collect(),
+        }))
+    }
+
+    async fn get_top_albums(
+        &self,
+        request: Request<DateRangeRequest>,
+    ) -> Result<Response<PlayStatsResponse>, Status> {
+        let (start, end, limit) = map_date_range(request.into_inner());
+        let stats = self
+            .manager
+            .read()
+            .await
+            .get_top_albums(start, end, limit)
+            .await
+            .map_err(|e| format_error(format!("Error getting top albums {e:?}")))?;
+
+        Ok(Response::new(PlayStatsResponse {
+            stats: stats.into_iter().map(map_play_stat).collect(),
+        }))
+    }
+
+    async fn get_top_artists(
+        &self,
+        request: Request<DateRangeRequest>,
+    ) -> Result<Response<PlayStatsResponse>, Status> {
+        let (start, end, limit) = map_date_range(request.into_inner());
+        let stats = self
+            .manager
+            .read()
+            .await
+            .get_top_artists(start, end, limit)
+            .await
+            .map_err(|e| format_error(format!("Error getting top artists {e:?}")))?;
+
+        Ok(Response::new(PlayStatsResponse {
+            stats: stats.into_iter().map(map_play_stat).collect(),
+        }))
+    }
+
+    async fn get_play_counts(
+        &self,
+        request: Request<IdMessage>,
+    ) -> Result<Response<PlayCountsResponse>, Status> {
+        let counts = self
+            .manager
+            .read()
+            .await
+            .get_play_counts(request.into_inner().ids)
+            .await
+            .map_err(|e| format_error(format!("Error getting play counts {e:?}")))?;
+
+        Ok(Response::new(PlayCountsResponse {
+            counts: counts
+                .into_iter()
+                .map(|c| SongPlayCount {
+                    song_id: c.song_id,
+                    play_count: c.play_count,
+                    skip_count: c.skip_count,
+                    last_played: c.last_played,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn browse_artists(
+        &self,
+        request: Request<BrowseRequest>,
+    ) -> Result<Response<ArtistSummaryResponse>, Status> {
+        let options = map_browse_request(request.into_inner())?;
+        let page = self
+            .manager
+            .read()
+            .await
+            .browse_artists(&options)
+            .await
+            .map_err(|e| format_error(format!("Error browsing artists {e:?}")))?;
+
+        Ok(Response::new(map_artist_summaries(page)))
+    }
+
+    async fn browse_album_artists(
+        &self,
+        request: Request<BrowseRequest>,
+    ) -> Result<Response<ArtistSummaryResponse>, Status> {
+        let options = map_browse_request(request.into_inner())?;
+        let page = self
+            .manager
+            .read()
+            .await
+            .browse_album_artists(&options)
+            .await
+            .map_err(|e| format_error(format!("Error browsing album artists {e:?}")))?;
+
+        Ok(Response::new(map_artist_summaries(page)))
+    }
+
+    async fn browse_albums(
+        &self,
+        request: Request<BrowseRequest>,
+    ) -> Result<Response<AlbumSummaryResponse>, Status> {
+        let options = map_browse_request(request.into_inner())?;
+        let page = self
+            .manager
+            .read()
+            .await
+            .browse_albums(&options)
+            .await
+            .map_err(|e| format_error(format!("Error browsing albums {e:?}")))?;
+
+        Ok(Response::new(AlbumSummaryResponse {
+            entries: page
+                .entries
+                .into_iter()
+                .map(|a| AlbumSummary {
+                    album_id: a.album_id,
+                    album: a.album,
+                    album_artist_id: a.album_artist_id,
+                    album_artist: a.album_artist,
+                    song_count: a.song_count,
+                    year: a.year,
+                    created_date: a.created_date,
+                })
+                .collect(),
+            total: page.total,
+        }))
+    }
+
+    async fn browse_genres(
+        &self,
+        request: Request<BrowseFilter>,
+    ) -> Result<Response<GenreSummaryResponse>, Status> {
+        let filter = map_browse_filter(Some(request.into_inner()));
+        let genres = self
+            .manager
+            .read()
+            .await
+            .browse_genres(&filter)
+            .await
+            .map_err(|e| format_error(format!("Error browsing genres {e:?}")))?;
+
+        Ok(Response::new(GenreSummaryResponse {
+            entries: genres
+                .into_iter()
+                .map(|g| GenreSummary {
+                    genre_id: g.genre_id,
+                    genre: g.genre,
+                    album_count: g.album_count,
+                    song_count: g.song_count,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn browse_years(
+        &self,
+        request: Request<BrowseYearsRequest>,
+    ) -> Result<Response<YearSummaryResponse>, Status> {
+        let request = request.into_inner();
+        let filter = map_browse_filter(request.filter);
+        let years = self
+            .manager
+            .read()
+            .await
+            .browse_years(&filter, request.by_decade)
+            .await
+            .map_err(|e| format_error(format!("Error browsing years {e:?}")))?;
+
+        Ok(Response::new(YearSummaryResponse {
+            entries: years
+                .into_iter()
+                .map(|y| YearSummary {
+                    year: y.year,
+                    album_count: y.album_count,
+                    song_count: y.song_count,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn create_smart_playlist(
+        &self,
+        request: Request<CreateSmartPlaylistRequest>,
+    ) -> Result<Response<CreateSmartPlaylistResponse>, Status> {
+        let definition = map_smart_playlist_definition(request.into_inner().definition)?;
+        let smart_playlist_id = self
+            .manager
+            .write()
+            .await
+            .create_smart_playlist(&definition)
+            .await
+            .map_err(|e| map_smart_playlist_error("Error creating smart playlist", e))?;
+
+        Ok(Response::new(CreateSmartPlaylistResponse {
+            smart_playlist_id,
+        }))
+    }
+
+    async fn update_smart_playlist(
+        &self,
+        request: Request<UpdateSmartPlaylistRequest>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        let definition = map_smart_playlist_definition(request.definition)?;
+        self.manager
+            .write()
+            .await
+            .update_smart_playlist(request.smart_playlist_id, &definition)
+            .await
+            .map_err(|e| map_smart_playlist_error("Error updating smart playlist", e))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn delete_smart_playlists(
+        &self,
+        request: Request<IdMessage>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        self.manager
+            .write()
+            .await
+            .delete_smart_playlists(request.ids)
+            .await
+            .map_err(|e| format_error(format!("Error deleting smart playlists {e:?}")))?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn get_all_smart_playlists(
+        &self,
+        _: Request<()>,
+    ) -> Result<Response<SmartPlaylistsResponse>, Status> {
+        let smart_playlists = self
+            .manager
+            .read()
+            .await
+            .get_all_smart_playlists()
+            .await
+            .map_err(|e| format_error(format!("Error getting smart playlists {e:?}")))?;
+
+        Ok(Response::new(SmartPlaylistsResponse {
+            smart_playlists: smart_playlists
+                .into_iter()
+                .map(map_smart_playlist)
+                .collect(),
+        }))
+    }
+
+    async fn evaluate_smart_playlist(
+        &self,
+        request: Request<SmartPlaylistIdMessage>,
+    ) -> Result<Response<LookupResponse>, Status> {
+        let manager = self.manager.read().await;
+        let connection_type = get_connection_type(&request, &manager).await?;
+        let request = request.into_inner();
+        let songs = manager
+            .evaluate_smart_playlist(request.smart_playlist_id)
+            .await
+            .map_err(|e| map_smart_playlist_error("Error evaluating smart playlist", e))?;
+
+        let entries: Result<Vec<_>, _> = songs
+            .into_iter()
+            .map(|e| map_lookup_entry(e, &connection_type))
+            .collect();
+
+        Ok(Response::new(LookupResponse { entries: entries? }))
+    }
+
+    async fn get_smart_playlist_songs(
+        &self,
+        request: Request<SmartPlaylistIdMessage>,
+    ) -> Result<Response<LookupResponse>, Status> {
+        let manager = self.manager.read().await;
+        let connection_type = get_connection_type(&request, &manager).await?;
+        let request = request.into_inner();
+        let songs = manager
+            .get_smart_playlist_songs(request.smart_playlist_id)
+            .await
+            .map_err(|e| format_error(format!("Error getting smart playlist songs {e:?}")))?;
+
+        let entries: Result<Vec<_>, _> = songs
+            .into_iter()
+            .map(|e| map_lookup_entry(e, &connection_type))
+            .collect();
+
+        Ok(Response::new(LookupResponse { entries: entries? }))
+    }
+
+    async fn get_duplicates(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<DuplicatesResponse>, Status> {
+        let manager = self.manager.read().await;
+        let connection_type = get_connection_type(&request, &manager).await?;
+        let groups = manager
+            .get_duplicates()
+            .await
+            .map_err(|e| format_error(format!("Error getting duplicates {e:?}")))?;
+
+        let groups: Result<Vec<_>, _> = groups
+            .into_iter()
+            .map(|g| map_duplicate_group(g, &connection_type))
+            .collect();
+
+        Ok(Response::new(DuplicatesResponse { groups: groups? }))
+    }
+
+    async fn import_playlist(
+        &self,
+        request: Request<ImportPlaylistRequest>,
+    ) -> Result<Response<ImportPlaylistResponse>, Status> {
+        let request = request.into_inner();
+        let res = self
+            .manager
+            .write()
+            .await
+            .import_playlist(&request.path, request.name.as_deref())
+            .await
+            .map_err(|e| map_playlist_file_error("Error importing playlist", e))?;
+
+        Ok(Response::new(ImportPlaylistResponse {
+            playlist_id: res.playlist_id,
+            playlist_name: res.playlist_name,
+            imported_count: res.imported_count as i64,
+            unresolved: res.unresolved,
+        }))
+    }
+
+    async fn export_playlist(
+        &self,
+        request: Request<ExportPlaylistRequest>,
+    ) -> Result<Response<ExportPlaylistResponse>, Status> {
+        let request = request.into_inner();
+        let format = map_playlist_format(request.format)?;
+        let relative_to = request.relative_to.as_ref().map(Path::new);
+        let manager = self.manager.read().await;
+        let content = match request.source {
+            Some(export_playlist_request::Source::PlaylistId(playlist_id)) => {
+                manager
+                    .export_playlist(playlist_id, format, relative_to)
+                    .await
+            }
+            Some(export_playlist_request::Source::SongIds(song_ids)) => {
+                manager
+                    .export_songs(&request.name, song_ids.ids, format, relative_to)
+                    .await
+            }
+            Some(export_playlist_request::Source::Paths(paths)) => {
+                manager
+                    .export_paths(&request.name, paths.paths, format, relative_to)
+                    .await
+            }
+            None => return Err(Status::invalid_argument("Missing export source")),
+        }
+        .map_err(|e| map_playlist_file_error("Error exporting playlist", e))?;
+
+        Ok(Response::new(ExportPlaylistResponse { content }))
+    }
+
+    async fn get_lyrics(
+        &self,
+        request: Request<SongIdMessage>,
+    ) -> Result<Response<LyricsResponse>, Status> {
+        let song_id = request.into_inner().song_id;
+        let lyrics = self
+            .manager
+            .read()
+            .await
+            .get_lyrics(song_id)
+            .await
+            .map_err(|e| format_error(format!("Error getting lyrics {e:?}")))?
+            .ok_or_else(|| Status::not_found(format!("Song {song_id} not found")))?;
+
+        Ok(Response::new(LyricsResponse {
+            plain: lyrics.plain,
+            synced: lyrics
+                .synced
+                .into_iter()
+                .map(|line| LyricLine {
+                    start: Duration::from_millis(line.start_millis as u64)
+                        .try_into()
+                        .ok(),
+                    text: line.text,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_sync_report(
+        &self,
+        request: Request<SyncReportRequest>,
+    ) -> Result<Response<SyncReportResponse>, Status> {
+        let request = request.into_inner();
+        let statuses = map_sync_file_statuses(request.statuses)?;
+        let report = self
+            .manager
+            .read()
+            .await
+            .get_sync_report(request.sync_report_id, &statuses)
+            .await
+            .map_err(|e| format_error(format!("Error getting sync report {e:?}")))?
+            .ok_or_else(|| Status::not_found("Sync report not found"))?;
+
+        Ok(Response::new(SyncReportResponse {
+            sync_report_id: report.sync_report_id,
+            started_date: report.started_date,
+            finished_date: report.finished_date,
+            full_rescan: report.full_rescan,
+            cancelled: report.cancelled,
+            added_count: report.added_count,
+            updated_count: report.updated_count,
+            deleted_count: report.deleted_count,
+            failed_count: report.failed_count,
+            files: report
+                .files
+                .into_iter()
+                .map(|file| SyncReportFile {
+                    path: file.path,
+                    status: map_sync_file_status(file.status).into(),
+                    error: file.error,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_song_artists(
+        &self,
+        request: Request<SongIdMessage>,
+    ) -> Result<Response<SongArtistsResponse>, Status> {
+        let artists = self
+            .manager
+            .read()
+            .await
+            .get_song_artists(request.into_inner().song_id)
+            .await
+            .map_err(|e| format_error(format!("Error getting song artists {e:?}")))?;
+
+        Ok(Response::new(SongArtistsResponse {
+            artists: artists
+                .into_iter()
+                .map(|artist| SongArtist {
+                    artist_id: artist.artist_id,
+                    artist_name: artist.artist_name,
+                    role: map_artist_role(artist.role).into(),
+                })
+                .collect(),
+        }))
+    }
+
     async fn get_song_by_path(
         &self,
         request: Request<PathMessage>,
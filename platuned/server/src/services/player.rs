@@ -46,6 +46,8 @@ fn get_event_response(event: Event, state: PlayerState) -> Result<EventResponse,
             volume: state.volume,
             status: map_audio_status(state.status).into(),
             metadata: state.metadata.map(map_player_metadata),
+            repeat_mode: map_repeat_mode(state.repeat_mode).into(),
+            shuffle: state.shuffle,
         })),
     })
 }
@@ -59,6 +61,8 @@ fn map_response(msg: PlayerEvent) -> Result<EventResponse, Status> {
         PlayerEvent::TrackChanged(state) => get_event_response(Event::TrackChanged, state),
         PlayerEvent::StartQueue(state) => get_event_response(Event::StartQueue, state),
         PlayerEvent::QueueUpdated(state) => get_event_response(Event::QueueUpdated, state),
+        PlayerEvent::SetRepeatMode(state) => get_event_response(Event::SetRepeatMode, state),
+        PlayerEvent::SetShuffle(state) => get_event_response(Event::SetShuffle, state),
         PlayerEvent::Seek(state, time) => Ok(EventResponse {
             event: Event::Seek.into(),
             event_payload: Some(EventPayload::SeekData(SeekResponse {
@@ -68,6 +72,8 @@ fn map_response(msg: PlayerEvent) -> Result<EventResponse, Status> {
                     volume: state.volume,
                     status: map_audio_status(state.status).into(),
                     metadata: None,
+                    repeat_mode: map_repeat_mode(state.repeat_mode).into(),
+                    shuffle: state.shuffle,
                 }),
                 seek_millis: time.as_millis() as u64,
             })),
@@ -92,6 +98,24 @@ fn map_response(msg: PlayerEvent) -> Result<EventResponse, Status> {
                     .map_or(Ok(None), |r| r.map(Some))?,
             })),
         }),
+        PlayerEvent::TrackPlayed(played) => Ok(EventResponse {
+            event: Event::TrackPlayed.into(),
+            event_payload: Some(EventPayload::PlayedTrack(PlayedTrack {
+                url: played.url,
+                metadata: played.metadata.map(map_player_metadata),
+                listened: Some(
+                    played
+                        .listened
+                        .try_into()
+                        .map_err(|e| format_error(format!("Error converting duration: {e:?}")))?,
+                ),
+                skipped: played.skipped,
+            })),
+        }),
+        PlayerEvent::LyricLine(line) => Ok(EventResponse {
+            event: Event::LyricLine.into(),
+            event_payload: Some(EventPayload::LyricLine(map_player_lyric_line(line))),
+        }),
         _ => unreachable!("Encountered unhandled event {:?}", msg.to_string()),
     }
 }
@@ -104,6 +128,14 @@ fn map_audio_status(status: AudioStatus) -> crate::rpc::v1::PlayerStatus {
     }
 }
 
+fn map_repeat_mode(repeat_mode: platune_player::RepeatMode) -> RepeatMode {
+    match repeat_mode {
+        platune_player::RepeatMode::Off => RepeatMode::Off,
+        platune_player::RepeatMode::One => RepeatMode::One,
+        platune_player::RepeatMode::All => RepeatMode::All,
+    }
+}
+
 fn map_queue_request(request: QueueRequest) -> Vec<platune_player::Track> {
     request
         .queue
@@ -123,6 +155,26 @@ fn map_queue_metadata(metadata: Metadata) -> platune_player::Metadata {
         song: metadata.song,
         track_number: metadata.track_number.map(|t| t as u32),
         duration: metadata.duration.map(|d| d.try_into().unwrap()),
+        replay_gain: metadata.replay_gain.map(|r| platune_player::ReplayGain {
+            track_gain: r.track_gain,
+            track_peak: r.track_peak,
+            album_gain: r.album_gain,
+            album_peak: r.album_peak,
+        }),
+        album_art_url: metadata.album_art_url,
+        lyrics: (!metadata.lyrics.is_empty()).then(|| {
+            metadata
+                .lyrics
+                .into_iter()
+                .map(|line| platune_player::LyricLine {
+                    start: line
+                        .start
+                        .map(|s| s.try_into().unwrap())
+                        .unwrap_or_default(),
+                    text: line.text,
+                })
+                .collect()
+        }),
     }
 }
 
@@ -134,6 +186,69 @@ fn map_player_metadata(metadata: platune_player::Metadata) -> Metadata {
         song: metadata.song,
         track_number: metadata.track_number.map(|t| t as i64),
         duration: metadata.duration.map(|d| d.try_into().unwrap()),
+        replay_gain: metadata.replay_gain.map(|r| ReplayGain {
+            track_gain: r.track_gain,
+            track_peak: r.track_peak,
+            album_gain: r.album_gain,
+            album_peak: r.album_peak,
+        }),
+        album_art_url: metadata.album_art_url,
+        lyrics: metadata
+            .lyrics
+            .unwrap_or_default()
+            .into_iter()
+            .map(map_player_lyric_line)
+            .collect(),
+    }
+}
+
+fn map_player_lyric_line(line: platune_player::LyricLine) -> LyricLine {
+    LyricLine {
+        start: line.start.try_into().ok(),
+        text: line.text,
+    }
+}
+
+fn map_equalizer_preset(preset: platune_player::EqualizerPreset) -> EqualizerPreset {
+    EqualizerPreset {
+        name: preset.name,
+        preamp: preset.preamp,
+        bands: preset
+            .bands
+            .into_iter()
+            .map(|band| EqualizerBand {
+                filter_type: match band.filter_type {
+                    platune_player::FilterType::Peaking => FilterType::Peaking,
+                    platune_player::FilterType::LowShelf => FilterType::LowShelf,
+                    platune_player::FilterType::HighShelf => FilterType::HighShelf,
+                }
+                .into(),
+                frequency: band.frequency,
+                gain: band.gain,
+                q: band.q,
+            })
+            .collect(),
+    }
+}
+
+fn map_equalizer_preset_request(preset: EqualizerPreset) -> platune_player::EqualizerPreset {
+    platune_player::EqualizerPreset {
+        name: preset.name,
+        preamp: preset.preamp,
+        bands: preset
+            .bands
+            .into_iter()
+            .map(|band| platune_player::EqualizerBand {
+                filter_type: match band.filter_type() {
+                    FilterType::Peaking => platune_player::FilterType::Peaking,
+                    FilterType::LowShelf => platune_player::FilterType::LowShelf,
+                    FilterType::HighShelf => platune_player::FilterType::HighShelf,
+                },
+                frequency: band.frequency,
+                gain: band.gain,
+                q: band.q,
+            })
+            .collect(),
     }
 }
 
@@ -266,6 +381,8 @@ impl Player for PlayerImpl {
                 volume: status.track_status.state.volume,
                 status: map_audio_status(status.track_status.status).into(),
                 metadata: status.track_status.state.metadata.map(map_player_metadata),
+                repeat_mode: map_repeat_mode(status.track_status.state.repeat_mode).into(),
+                shuffle: status.track_status.state.shuffle,
             }),
         }))
     }
@@ -293,6 +410,121 @@ impl Player for PlayerImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_repeat_mode(
+        &self,
+        request: Request<SetRepeatModeRequest>,
+    ) -> Result<Response<()>, Status> {
+        let repeat_mode = match request.into_inner().repeat_mode() {
+            RepeatMode::Off => platune_player::RepeatMode::Off,
+            RepeatMode::One => platune_player::RepeatMode::One,
+            RepeatMode::All => platune_player::RepeatMode::All,
+        };
+        self.player
+            .set_repeat_mode(repeat_mode)
+            .await
+            .map_err(|e| format_error(format!("Error setting repeat mode: {e:?}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn set_shuffle(
+        &self,
+        request: Request<SetShuffleRequest>,
+    ) -> Result<Response<()>, Status> {
+        self.player
+            .set_shuffle(request.into_inner().shuffle)
+            .await
+            .map_err(|e| format_error(format!("Error setting shuffle: {e:?}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn set_crossfade(
+        &self,
+        request: Request<SetCrossfadeRequest>,
+    ) -> Result<Response<()>, Status> {
+        // A missing duration disables crossfading
+        let crossfade = request.into_inner().crossfade.unwrap_or_default();
+        let crossfade = Duration::try_from(crossfade)
+            .map_err(|e| Status::invalid_argument(format!("Invalid crossfade duration: {e}")))?;
+        self.player
+            .set_crossfade(crossfade)
+            .await
+            .map_err(|e| format_error(format!("Error setting crossfade: {e:?}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn set_replay_gain_mode(
+        &self,
+        request: Request<SetReplayGainModeRequest>,
+    ) -> Result<Response<()>, Status> {
+        let mode = match request.into_inner().mode() {
+            ReplayGainMode::Off => platune_player::ReplayGainMode::Off,
+            ReplayGainMode::Track => platune_player::ReplayGainMode::Track,
+            ReplayGainMode::Album => platune_player::ReplayGainMode::Album,
+        };
+        self.player
+            .set_replay_gain_mode(mode)
+            .await
+            .map_err(|e| format_error(format!("Error setting replay gain mode: {e:?}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn get_equalizer_presets(
+        &self,
+        _: Request<()>,
+    ) -> Result<Response<EqualizerPresetsResponse>, Status> {
+        let presets = self
+            .player
+            .get_equalizer_presets()
+            .await
+            .map_err(|e| format_error(format!("Error getting equalizer presets: {e:?}")))?;
+        Ok(Response::new(EqualizerPresetsResponse {
+            presets: presets
+                .presets
+                .into_iter()
+                .map(map_equalizer_preset)
+                .collect(),
+            built_in: presets
+                .built_in
+                .into_iter()
+                .map(map_equalizer_preset)
+                .collect(),
+            active_preset: presets.active_preset,
+        }))
+    }
+
+    async fn save_equalizer_preset(
+        &self,
+        request: Request<EqualizerPreset>,
+    ) -> Result<Response<()>, Status> {
+        self.player
+            .save_equalizer_preset(map_equalizer_preset_request(request.into_inner()))
+            .await
+            .map_err(|e| Status::invalid_argument(format!("Error saving equalizer preset: {e}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn delete_equalizer_preset(
+        &self,
+        request: Request<EqualizerPresetName>,
+    ) -> Result<Response<()>, Status> {
+        self.player
+            .delete_equalizer_preset(request.into_inner().name)
+            .await
+            .map_err(|e| format_error(format!("Error deleting equalizer preset: {e:?}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn set_equalizer_preset(
+        &self,
+        request: Request<SetEqualizerPresetRequest>,
+    ) -> Result<Response<()>, Status> {
+        self.player
+            .set_equalizer_preset(request.into_inner().name)
+            .await
+            .map_err(|e| format_error(format!("Error setting equalizer preset: {e:?}")))?;
+        Ok(Response::new(()))
+    }
+
     type SubscribeEventsStream =
         Pin<Box<dyn futures::Stream<Item = Result<EventResponse, Status>> + Send + Sync + 'static>>;
 